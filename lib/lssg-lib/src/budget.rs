@@ -0,0 +1,377 @@
+//! Size budgets for pages and the whole site, set with a `[budget]` table on the root page
+//!
+//! ```toml
+//! [budget]
+//! strict = true
+//! page = { html = "50 KB", css = "30 KB", images = "1 MB", total = "1.5 MB" }
+//! site = { total = "20 MB" }
+//! ```
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use log::{info, warn};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use serde_extensions::Overwrite;
+
+use crate::{
+    lssg_error::LssgError,
+    renderer::util::format_size,
+    sitetree::{SiteId, SiteNodeKind, SiteTree},
+};
+
+/// Parse a size with an optional unit (eg. "50 KB", "1.5MiB" or "2000")
+pub(crate) fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let multiplier = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "kb" => 1e3,
+        "mb" => 1e6,
+        "gb" => 1e9,
+        "kib" => 1024.0,
+        "mib" => 1024.0 * 1024.0,
+        "gib" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * multiplier) as u64)
+}
+
+fn deserialize_size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+    match Size::deserialize(deserializer)? {
+        Size::Bytes(bytes) => Ok(Some(bytes)),
+        Size::Text(text) => parse_size(&text)
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid size {text:?}"))),
+    }
+}
+
+/// Maximum size in bytes of each kind of file, sizes are a number of bytes or text with a unit
+/// (eg. "50 KB")
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Budget {
+    #[serde(deserialize_with = "deserialize_size")]
+    html: Option<u64>,
+    #[serde(deserialize_with = "deserialize_size")]
+    css: Option<u64>,
+    #[serde(deserialize_with = "deserialize_size")]
+    js: Option<u64>,
+    #[serde(deserialize_with = "deserialize_size")]
+    images: Option<u64>,
+    /// All files together
+    #[serde(deserialize_with = "deserialize_size")]
+    total: Option<u64>,
+}
+
+impl Budget {
+    /// Limits of `self` overwritten by the limits that are set in `other`
+    fn merge(&self, other: &Budget) -> Budget {
+        Budget {
+            html: other.html.or(self.html),
+            css: other.css.or(self.css),
+            js: other.js.or(self.js),
+            images: other.images.or(self.images),
+            total: other.total.or(self.total),
+        }
+    }
+
+    /// Every limit with the kind of files it applies to, None for all files
+    fn limits(&self) -> [(&'static str, Option<u64>, Option<FileKind>); 5] {
+        [
+            ("html", self.html, Some(FileKind::Html)),
+            ("css", self.css, Some(FileKind::Css)),
+            ("js", self.js, Some(FileKind::Js)),
+            ("images", self.images, Some(FileKind::Image)),
+            ("total", self.total, None),
+        ]
+    }
+
+    fn is_empty(&self) -> bool {
+        self.limits().iter().all(|(_, limit, _)| limit.is_none())
+    }
+}
+
+#[derive(Debug, Default, Overwrite)]
+pub struct BudgetOptions {
+    /// Fail the build instead of warning when a budget is exceeded
+    strict: bool,
+    /// Limits of every page including the stylesheets, scripts and images it loads, can be
+    /// changed per page with `[budget.page]`
+    page: Budget,
+    /// Limits of all files of the site together
+    site: Budget,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FileKind {
+    Html,
+    Css,
+    Js,
+    Image,
+    Other,
+}
+
+impl FileKind {
+    fn from_path(path: &Path) -> FileKind {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        match extension.as_str() {
+            "html" | "htm" => FileKind::Html,
+            "css" => FileKind::Css,
+            "js" | "mjs" => FileKind::Js,
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "avif" | "svg" | "ico" | "bmp" => {
+                FileKind::Image
+            }
+            _ => FileKind::Other,
+        }
+    }
+}
+
+struct File {
+    path: PathBuf,
+    size: u64,
+    kind: FileKind,
+}
+
+/// Check `files` against `budget`, returns a message for every exceeded limit listing the
+/// heaviest files
+fn exceeded(budget: &Budget, files: &[File], output_directory: &Path) -> Vec<String> {
+    let mut messages = vec![];
+    for (name, limit, kind) in budget.limits() {
+        let Some(limit) = limit else {
+            continue;
+        };
+        let mut matching: Vec<&File> = files
+            .iter()
+            .filter(|f| kind.is_none() || Some(f.kind) == kind)
+            .collect();
+        let size: u64 = matching.iter().map(|f| f.size).sum();
+        if size <= limit {
+            continue;
+        }
+        matching.sort_by_key(|f| std::cmp::Reverse(f.size));
+        let heaviest: Vec<String> = matching
+            .iter()
+            .take(3)
+            .map(|f| {
+                let path = f.path.strip_prefix(output_directory).unwrap_or(&f.path);
+                format!("{} ({})", path.display(), format_size(f.size))
+            })
+            .collect();
+        messages.push(format!(
+            "{name} is {}, over its budget of {}, heaviest: {}",
+            format_size(size),
+            format_size(limit),
+            heaviest.join(", ")
+        ));
+    }
+    messages
+}
+
+/// Resolve `url` used in the html file at `html_path` to a file in `output_directory`, None for
+/// external urls
+pub(crate) fn resolve(
+    url: &str,
+    html_path: &Path,
+    output_directory: &Path,
+    url_prefix: &str,
+) -> Option<PathBuf> {
+    if url.contains(':') || url.starts_with("//") || url.starts_with('#') {
+        return None;
+    }
+    let url = url.split(['?', '#']).next()?;
+    let (base, url) = match url.strip_prefix('/') {
+        Some(url) => {
+            let url = url
+                .strip_prefix(url_prefix.trim_start_matches('/'))
+                .unwrap_or(url);
+            (output_directory.to_path_buf(), url)
+        }
+        None => (html_path.parent()?.to_path_buf(), url),
+    };
+    let mut path = base;
+    for component in Path::new(url).components() {
+        match component {
+            Component::ParentDir => {
+                path.pop();
+            }
+            Component::Normal(part) => path.push(part),
+            _ => {}
+        }
+    }
+    // pages are written as folders with an index.html
+    if path.is_dir() {
+        path.push("index.html");
+    }
+    match path.starts_with(output_directory) && path.is_file() {
+        true => Some(path),
+        false => None,
+    }
+}
+
+/// The html file at `html_path` and every local stylesheet, script and image it loads
+fn page_files(html_path: &Path, output_directory: &Path, url_prefix: &str) -> Vec<File> {
+    let Ok(html) = fs::read_to_string(html_path) else {
+        return vec![];
+    };
+    let tag = Regex::new(r"(?is)<(link|script|img|source)\s[^>]*>").unwrap();
+    let attribute = Regex::new(r#"(?is)([\w-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+
+    let mut paths = vec![html_path.to_path_buf()];
+    for tag in tag.find_iter(&html) {
+        let attribute = |name: &str| {
+            attribute.captures_iter(tag.as_str()).find_map(|c| {
+                (c[1].eq_ignore_ascii_case(name))
+                    .then(|| c.get(2).or(c.get(3)).map(|v| v.as_str().to_string()))
+                    .flatten()
+            })
+        };
+        let url = match attribute("rel") {
+            Some(rel) if rel.split_whitespace().any(|r| r == "stylesheet") => attribute("href"),
+            _ => attribute("src").or_else(|| {
+                // first candidate of a srcset
+                attribute("srcset").and_then(|s| s.split_whitespace().next().map(|s| s.to_string()))
+            }),
+        };
+        if let Some(path) =
+            url.and_then(|url| resolve(&url, html_path, output_directory, url_prefix))
+        {
+            paths.push(path);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    paths
+        .into_iter()
+        .filter(|p| seen.insert(p.clone()))
+        .filter_map(|path| {
+            let size = fs::metadata(&path).ok()?.len();
+            let kind = FileKind::from_path(&path);
+            Some(File { path, size, kind })
+        })
+        .collect()
+}
+
+/// All files in `directory`
+fn site_files(directory: &Path) -> Result<Vec<File>, LssgError> {
+    let mut files = vec![];
+    let mut queue = vec![directory.to_path_buf()];
+    while let Some(directory) = queue.pop() {
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            let metadata = fs::metadata(&path)?;
+            if metadata.is_dir() {
+                queue.push(path);
+            } else {
+                let kind = FileKind::from_path(&path);
+                files.push(File {
+                    path,
+                    size: metadata.len(),
+                    kind,
+                });
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Check the written `pages` (site id and html file) and all files in `output_directory` against
+/// the budgets of the root page, returns an error in strict mode when a budget is exceeded
+pub fn check(
+    site_tree: &SiteTree,
+    output_directory: &Path,
+    pages: &[(SiteId, PathBuf)],
+) -> Result<(), LssgError> {
+    let budget_table = |id: SiteId| match &site_tree[id].kind {
+        SiteNodeKind::Page(page) => page.attributes().and_then(|a| a.get("budget")).cloned(),
+        _ => None,
+    };
+    let Some(table) = budget_table(site_tree.root()) else {
+        return Ok(());
+    };
+    let mut options = BudgetOptions::default();
+    if let Err(e) = options.overwrite(table) {
+        warn!("Failed to parse [budget] options: {e}");
+        return Ok(());
+    }
+
+    let mut messages = vec![];
+    for (id, html_path) in pages {
+        let budget = match budget_table(*id).and_then(|t| t.get("page").cloned()) {
+            Some(table) => match Budget::deserialize(table) {
+                Ok(budget) => options.page.merge(&budget),
+                Err(e) => {
+                    warn!(
+                        "Failed to parse [budget.page] of {}: {e}",
+                        site_tree.path(*id)
+                    );
+                    options.page.clone()
+                }
+            },
+            None => options.page.clone(),
+        };
+        if budget.is_empty() {
+            continue;
+        }
+        let files = page_files(html_path, output_directory, site_tree.url_prefix());
+        for message in exceeded(&budget, &files, output_directory) {
+            messages.push(format!("Page {}: {message}", site_tree.path(*id)));
+        }
+    }
+    if !options.site.is_empty() {
+        let files = site_files(output_directory)?;
+        for message in exceeded(&options.site, &files, output_directory) {
+            messages.push(format!("Site: {message}"));
+        }
+    }
+
+    if messages.is_empty() {
+        info!("All size budgets met");
+        return Ok(());
+    }
+    for message in &messages {
+        warn!("{message}");
+    }
+    if options.strict {
+        return Err(LssgError::strict(format!(
+            "{} size budget(s) exceeded",
+            messages.len()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_size;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("2000"), Some(2000));
+        assert_eq!(parse_size("50 KB"), Some(50_000));
+        assert_eq!(parse_size("1.5MB"), Some(1_500_000));
+        assert_eq!(parse_size("1 KiB"), Some(1024));
+        assert_eq!(parse_size("1 parsec"), None);
+    }
+}
@@ -0,0 +1,254 @@
+use std::path::PathBuf;
+
+use crate::{
+    lssg_error::LssgError,
+    renderer::{
+        BlogModule, BlogrollModule, CardModule, DefaultModule, DocsModule, EpubModule,
+        EventsModule, ExternalModule, HeadersModule, NotebookModule, OutputMode, RendererModule,
+        SitemapModule, UpdatesModule,
+    },
+    sitetree::Input,
+    Lssg,
+};
+
+/// Assembles a [`Lssg`] from typed options, without having to know which modules exist and in
+/// which order they have to be added
+///
+/// ```no_run
+/// use lssg_lib::{sitetree::Input, LssgBuilder};
+///
+/// let input = Input::from_string("./content/index.md").unwrap();
+/// let mut lssg = LssgBuilder::new(input)
+///     .output("./build")
+///     .title("My site")
+///     .base_url("https://example.com")
+///     .enable_blog()
+///     .enable_sitemap()
+///     .build()
+///     .unwrap();
+/// lssg.render().unwrap();
+/// ```
+///
+/// Options set here are defaults for the root page, attributes of the root page itself take
+/// precedence
+pub struct LssgBuilder {
+    input: Input,
+    output_directory: Option<PathBuf>,
+    output_mode: OutputMode,
+    incremental: bool,
+    attributes: toml::Table,
+    external: bool,
+    blog: bool,
+    blogroll: bool,
+    cards: bool,
+    epub: bool,
+    events: bool,
+    notebooks: bool,
+    updates: bool,
+    #[cfg(feature = "pdf")]
+    pdf: bool,
+    docs: bool,
+    headers: bool,
+    sitemap: bool,
+    modules: Vec<Box<dyn RendererModule>>,
+}
+
+impl LssgBuilder {
+    /// Start from the markdown `input` of the root page, only the default module is enabled.
+    /// Enabled modules are still configured by the attributes of pages (eg. `[blog] root = true`)
+    pub fn new(input: Input) -> LssgBuilder {
+        LssgBuilder {
+            input,
+            output_directory: None,
+            output_mode: OutputMode::default(),
+            incremental: false,
+            attributes: toml::Table::new(),
+            external: false,
+            blog: false,
+            blogroll: false,
+            cards: false,
+            epub: false,
+            events: false,
+            notebooks: false,
+            updates: false,
+            #[cfg(feature = "pdf")]
+            pdf: false,
+            docs: false,
+            headers: false,
+            sitemap: false,
+            modules: vec![],
+        }
+    }
+
+    /// Directory to write the site into
+    pub fn output(mut self, output_directory: impl Into<PathBuf>) -> Self {
+        self.output_directory = Some(output_directory.into());
+        self
+    }
+
+    /// Write pages as html (default) or xhtml
+    pub fn output_mode(mut self, output_mode: OutputMode) -> Self {
+        self.output_mode = output_mode;
+        self
+    }
+
+    /// Only replace the files in the output directory that changed, see
+    /// [`Lssg::set_incremental`]
+    pub fn incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    /// Title of the site
+    pub fn title(self, title: impl Into<String>) -> Self {
+        self.attribute("title", title.into())
+    }
+
+    /// Absolute url the site is hosted at (eg. "https://example.com")
+    pub fn base_url(self, base_url: impl Into<String>) -> Self {
+        self.attribute("base_url", base_url.into())
+    }
+
+    /// Language of the site (eg. "en")
+    pub fn language(self, language: impl Into<String>) -> Self {
+        self.attribute("language", language.into())
+    }
+
+    /// Set any option of the root page, like the attributes at the top of a page (eg.
+    /// `.attribute("docs", true)`)
+    pub fn attribute(mut self, key: impl Into<String>, value: impl Into<toml::Value>) -> Self {
+        self.attributes.insert(key.into(), value.into());
+        self
+    }
+
+    /// Show icons for links to known websites
+    pub fn enable_external(mut self) -> Self {
+        self.external = true;
+        self
+    }
+
+    pub fn enable_blog(mut self) -> Self {
+        self.blog = true;
+        self
+    }
+
+    pub fn enable_blogroll(mut self) -> Self {
+        self.blogroll = true;
+        self
+    }
+
+    /// Render marked links as preview cards
+    pub fn enable_cards(mut self) -> Self {
+        self.cards = true;
+        self
+    }
+
+    pub fn enable_epub(mut self) -> Self {
+        self.epub = true;
+        self
+    }
+
+    pub fn enable_events(mut self) -> Self {
+        self.events = true;
+        self
+    }
+
+    /// Render links to Jupyter notebooks
+    pub fn enable_notebooks(mut self) -> Self {
+        self.notebooks = true;
+        self
+    }
+
+    /// List recently updated pages on a generated page (`[updates] page = "whats-new"`) or at the
+    /// end of pages (`[updates] widget = 5`)
+    pub fn enable_updates(mut self) -> Self {
+        self.updates = true;
+        self
+    }
+
+    #[cfg(feature = "pdf")]
+    pub fn enable_pdf(mut self) -> Self {
+        self.pdf = true;
+        self
+    }
+
+    /// Docs mode is turned on with `.attribute("docs", true)` or by the root page
+    pub fn enable_docs(mut self) -> Self {
+        self.docs = true;
+        self
+    }
+
+    /// Write `_headers` or `vercel.json` for static hosts when the root page has `[headers]`
+    pub fn enable_headers(mut self) -> Self {
+        self.headers = true;
+        self
+    }
+
+    pub fn enable_sitemap(mut self) -> Self {
+        self.sitemap = true;
+        self
+    }
+
+    /// Add a custom module, these render before the default module
+    pub fn module(mut self, module: impl RendererModule + 'static) -> Self {
+        self.modules.push(Box::new(module));
+        self
+    }
+
+    pub fn build(self) -> Result<Lssg, LssgError> {
+        let output_directory = self
+            .output_directory
+            .ok_or(LssgError::io("no output directory given"))?;
+        let mut lssg = Lssg::new(self.input, output_directory);
+        lssg.set_output_mode(self.output_mode);
+        lssg.set_incremental(self.incremental);
+        lssg.attributes = self.attributes;
+
+        let renderer = &mut lssg.renderer;
+        if self.external {
+            renderer.add_module(ExternalModule::new());
+        }
+        if self.blog {
+            renderer.add_module(BlogModule::new());
+        }
+        if self.blogroll {
+            renderer.add_module(BlogrollModule::new());
+        }
+        if self.cards {
+            renderer.add_module(CardModule::new());
+        }
+        if self.epub {
+            renderer.add_module(EpubModule::new());
+        }
+        if self.events {
+            renderer.add_module(EventsModule::new());
+        }
+        if self.notebooks {
+            renderer.add_module(NotebookModule::new());
+        }
+        // the updates page has to exist before the default module is initialized
+        if self.updates {
+            renderer.add_module(UpdatesModule::new());
+        }
+        #[cfg(feature = "pdf")]
+        if self.pdf {
+            renderer.add_module(crate::renderer::PdfModule::new());
+        }
+        for module in self.modules {
+            renderer.add_boxed_module(module);
+        }
+        renderer.add_module(DefaultModule::new());
+        // runs after the default module has put the content in `<main>`
+        if self.docs {
+            renderer.add_module(DocsModule::new());
+        }
+        // appends to the `_headers` file of the default module
+        if self.headers {
+            renderer.add_module(HeadersModule::new());
+        }
+        if self.sitemap {
+            renderer.add_module(SitemapModule::new());
+        }
+        Ok(lssg)
+    }
+}
@@ -0,0 +1,140 @@
+//! Build cache shared by everything that wants to keep fetched or generated data between builds
+//!
+//! Entries are stored as `{directory}/{namespace}/{sha256 of key}`, their modification time is
+//! updated on every use so [`gc`] removes the least recently used entries first.
+use std::{
+    cmp::Reverse,
+    fs::{self, File},
+    io::{self, Read},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock,
+    },
+    time::{Duration, SystemTime},
+};
+
+use log::{debug, info};
+
+use crate::{lssg_error::LssgError, sitetree::content_hash};
+
+/// Default location of the cache relative to the working directory
+pub const DEFAULT_CACHE_DIRECTORY: &str = ".lssg-cache";
+/// Entries not used for this long are removed by [`gc`]
+pub const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Least recently used entries are removed by [`gc`] until the cache is smaller than this
+pub const MAX_SIZE: u64 = 512 * 1024 * 1024;
+
+static DIRECTORY: RwLock<Option<PathBuf>> = RwLock::new(None);
+/// Makes the names of temporary files written by [`put`] unique within this process
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Set the location of the cache, defaults to [`DEFAULT_CACHE_DIRECTORY`]
+pub fn set_directory(directory: PathBuf) {
+    *DIRECTORY.write().unwrap() = Some(directory);
+}
+
+pub fn directory() -> PathBuf {
+    DIRECTORY
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_CACHE_DIRECTORY))
+}
+
+/// Path of the entry for `key` in `namespace`, does not have to exist
+pub fn path(namespace: &str, key: &str) -> Result<PathBuf, LssgError> {
+    Ok(directory()
+        .join(namespace)
+        .join(content_hash(key.as_bytes())?))
+}
+
+/// Get an entry and mark it as used
+pub fn get(namespace: &str, key: &str) -> Result<Option<File>, LssgError> {
+    let path = path(namespace, key)?;
+    match File::options().read(true).write(true).open(&path) {
+        Ok(file) => {
+            file.set_modified(SystemTime::now())?;
+            Ok(Some(file))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn put(namespace: &str, key: &str, content: &[u8]) -> Result<(), LssgError> {
+    let path = path(namespace, key)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // write to a temporary file first so concurrent readers never see partial entries, unique
+    // per writer so concurrent writers of the same entry don't write to the same file
+    let count = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let temp = path.with_extension(format!("{}-{count}.tmp", std::process::id()));
+    fs::write(&temp, content)?;
+    fs::rename(temp, path)?;
+    Ok(())
+}
+
+/// Read `readable` completely while storing a copy of it in the cache
+pub fn put_readable(
+    namespace: &str,
+    key: &str,
+    mut readable: impl Read,
+) -> Result<Vec<u8>, LssgError> {
+    let mut content = vec![];
+    readable.read_to_end(&mut content)?;
+    put(namespace, key, &content)?;
+    Ok(content)
+}
+
+/// Remove entries older than [`MAX_AGE`] and the least recently used entries until the cache is
+/// smaller than [`MAX_SIZE`]
+pub fn gc() -> Result<(), LssgError> {
+    let directory = directory();
+    if !directory.exists() {
+        return Ok(());
+    }
+    let mut entries = vec![];
+    for namespace in fs::read_dir(&directory)? {
+        let namespace = namespace?;
+        if !namespace.file_type()?.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(namespace.path())? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            entries.push((entry.path(), metadata.modified()?, metadata.len()));
+        }
+    }
+
+    // most recently used first
+    entries.sort_by_key(|(_, modified, _)| Reverse(*modified));
+    let now = SystemTime::now();
+    let mut size = 0;
+    let mut removed = 0;
+    for (path, modified, length) in entries {
+        let age = now.duration_since(modified).unwrap_or_default();
+        if age > MAX_AGE || size + length > MAX_SIZE {
+            debug!("Removing {path:?} from cache");
+            fs::remove_file(path)?;
+            removed += 1;
+            continue;
+        }
+        size += length;
+    }
+    if removed > 0 {
+        info!("Removed {removed} entries from cache");
+    }
+    Ok(())
+}
+
+/// Remove the whole cache
+pub fn clean() -> Result<(), LssgError> {
+    let directory = directory();
+    if directory.exists() {
+        info!("Removing {directory:?}");
+        fs::remove_dir_all(directory)?;
+    }
+    Ok(())
+}
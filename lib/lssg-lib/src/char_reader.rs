@@ -283,12 +283,13 @@ impl<R: Read> CharReader<R> {
 
     /// stop consuming by pattern, if eof returns whatever is captured
     pub fn consume_until_match_inclusive(&mut self, pattern: &str) -> Result<String, ParseError> {
-        let mut result = self.consume_string(pattern.len())?;
-        if result.len() < pattern.len() {
+        let pattern_length = pattern.chars().count();
+        let mut result = self.consume_string(pattern_length)?;
+        if result.chars().count() < pattern_length {
             return Ok(result);
         }
         loop {
-            if &result[result.len() - pattern.len()..] == pattern {
+            if result.ends_with(pattern) {
                 break;
             }
             match self.consume_char()? {
@@ -387,5 +388,13 @@ Very important test"
             "some str".to_string()
         );
         assert_eq!(reader.consume_string(8).unwrap(), "ing".to_string());
+
+        let input = "Привет\nмир";
+        let mut reader = CharReader::new(input.as_bytes());
+        assert_eq!(
+            reader.consume_until_match_inclusive("\n").unwrap(),
+            "Привет\n".to_string()
+        );
+        assert_eq!(reader.consume_string(3).unwrap(), "мир".to_string());
     }
 }
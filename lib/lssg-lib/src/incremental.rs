@@ -0,0 +1,213 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, create_dir_all, remove_dir, remove_file, rename},
+    io,
+    path::Path,
+};
+
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{lssg_error::LssgError, sitetree::content_hash};
+
+/// Name of the file in the output directory with the hash of every file written by lssg
+pub const MANIFEST: &str = ".lssg-manifest.json";
+
+/// Hashes of every file in a build and the sources of its pages
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Manifest {
+    /// relative path (eg. "blog/index.html") to content hash of the file
+    pub files: BTreeMap<String, String>,
+    /// relative path of the html of a page to the content hash of its markdown source
+    #[serde(default)]
+    pub sources: BTreeMap<String, String>,
+}
+
+/// Hash of a file, for symlinks the hash of their target
+fn hash(path: &Path) -> Result<String, LssgError> {
+    if fs::symlink_metadata(path)?.is_symlink() {
+        let target = fs::read_link(path)?;
+        return content_hash(format!("symlink:{}", target.to_string_lossy()).as_bytes());
+    }
+    content_hash(fs::File::open(path)?)
+}
+
+/// Path of `path` in the manifest of `directory` (eg. "blog/index.html"), None if `path` is not in
+/// `directory`
+pub(crate) fn manifest_path(directory: &Path, path: &Path) -> Option<String> {
+    let rel_path = path.strip_prefix(directory).ok()?;
+    Some(
+        rel_path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// Hash all files in `directory`, files in `known` are not read again
+fn hash_files(
+    directory: &Path,
+    known: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, LssgError> {
+    let mut files = BTreeMap::new();
+    let mut queue = vec![directory.to_path_buf()];
+    while let Some(current) = queue.pop() {
+        for entry in fs::read_dir(current)? {
+            let path = entry?.path();
+            if fs::symlink_metadata(&path)?.is_dir() {
+                queue.push(path);
+                continue;
+            }
+            let rel_path = manifest_path(directory, &path).expect("path is in directory");
+            let hash = match known.get(&rel_path) {
+                Some(hash) => hash.clone(),
+                None => hash(&path)?,
+            };
+            files.insert(rel_path, hash);
+        }
+    }
+    Ok(files)
+}
+
+fn read_manifest(output_directory: &Path) -> Manifest {
+    let path = output_directory.join(MANIFEST);
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Manifest::default();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        warn!("Ignoring invalid {path:?}: {e}");
+        Manifest::default()
+    })
+}
+
+/// Move the files of a fresh build in `build_directory` into `output_directory`, only replacing
+/// the files whose content changed and removing the files of the previous build that are no
+/// longer written. Files in `output_directory` that weren't written by lssg are kept. `known`
+/// has the hashes of files in `build_directory` that are already known and the page sources.
+pub fn sync(
+    build_directory: &Path,
+    output_directory: &Path,
+    known: &Manifest,
+) -> Result<(), LssgError> {
+    let previous = read_manifest(output_directory);
+    let current = Manifest {
+        files: hash_files(build_directory, &known.files)?,
+        sources: known.sources.clone(),
+    };
+    let changed_sources = current
+        .sources
+        .iter()
+        .filter(|(rel_path, hash)| previous.sources.get(*rel_path) != Some(*hash))
+        .count();
+
+    let mut written = 0;
+    for (rel_path, hash) in &current.files {
+        let path = output_directory.join(rel_path);
+        let exists = fs::symlink_metadata(&path).is_ok();
+        if exists && previous.files.get(rel_path) == Some(hash) {
+            continue;
+        }
+        debug!("Updating {path:?}");
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        if exists {
+            remove_file(&path)?;
+        }
+        rename(build_directory.join(rel_path), &path)?;
+        written += 1;
+    }
+
+    let mut removed = 0;
+    for rel_path in previous
+        .files
+        .keys()
+        .filter(|p| !current.files.contains_key(*p))
+    {
+        let path = output_directory.join(rel_path);
+        debug!("Removing {path:?}");
+        match remove_file(&path) {
+            Ok(()) => removed += 1,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        // remove folders that became empty
+        let mut parent = path.parent();
+        while let Some(folder) = parent.filter(|p| *p != output_directory) {
+            if remove_dir(folder).is_err() {
+                break;
+            }
+            parent = folder.parent();
+        }
+    }
+
+    fs::write(
+        output_directory.join(MANIFEST),
+        serde_json::to_string_pretty(&current).map_err(io::Error::from)?,
+    )?;
+    info!(
+        "{written} files written, {} unchanged, {removed} removed, {changed_sources} pages with a changed source",
+        current.files.len() - written
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync() {
+        let root = std::env::temp_dir().join(format!("lssg-incremental-{}", std::process::id()));
+        let build = root.join("build");
+        let output = root.join("output");
+        let _ = fs::remove_dir_all(&root);
+
+        let build_files = |files: &[(&str, &str)]| {
+            create_dir_all(&build).unwrap();
+            for (path, content) in files {
+                let path = build.join(path);
+                create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, content).unwrap();
+            }
+        };
+
+        build_files(&[("index.html", "a"), ("old/index.html", "b")]);
+        create_dir_all(&output).unwrap();
+        fs::write(output.join("CNAME"), "example.com").unwrap();
+        sync(&build, &output, &Manifest::default()).unwrap();
+        assert_eq!(
+            fs::read_to_string(output.join("old/index.html")).unwrap(),
+            "b"
+        );
+
+        fs::remove_dir_all(&build).unwrap();
+        build_files(&[("index.html", "a"), ("new.css", "c")]);
+        let modified = fs::metadata(output.join("index.html"))
+            .unwrap()
+            .modified()
+            .unwrap();
+        let known = Manifest {
+            files: BTreeMap::new(),
+            sources: BTreeMap::from([("index.html".to_string(), "source".to_string())]),
+        };
+        sync(&build, &output, &known).unwrap();
+        // unchanged files are left alone
+        assert_eq!(
+            fs::metadata(output.join("index.html"))
+                .unwrap()
+                .modified()
+                .unwrap(),
+            modified
+        );
+        assert_eq!(fs::read_to_string(output.join("new.css")).unwrap(), "c");
+        assert!(!output.join("old").exists());
+        // files not written by lssg are kept
+        assert!(output.join("CNAME").exists());
+        // page sources are kept in the manifest
+        assert_eq!(read_manifest(&output).sources, known.sources);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
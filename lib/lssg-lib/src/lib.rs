@@ -1,32 +1,91 @@
+pub mod cache;
 pub mod char_reader;
 pub mod lmarkdown;
 pub mod parse_error;
+pub mod progress;
 pub mod renderer;
 pub mod sitetree;
 
+mod budget;
+mod builder;
+pub use builder::LssgBuilder;
+mod incremental;
 pub mod lssg_error;
 mod path_extension;
 mod tree;
 
 use std::{
-    fs::{create_dir, create_dir_all, remove_dir_all, write},
-    path::PathBuf,
+    fs::{create_dir, create_dir_all, remove_dir_all, rename, write},
+    path::{Path, PathBuf},
+    sync::Mutex,
 };
 
-use log::info;
+use lmarkdown::Token;
+use log::{debug, error, info, warn};
 use lssg_error::LssgError;
+use progress::{Phase, Progress};
 use renderer::{Renderer, RendererModule};
 use sitetree::Input;
 
 use crate::{
+    incremental::{manifest_path, Manifest},
     path_extension::PathExtension,
-    sitetree::{Relation, SiteNodeKind, SiteTree},
+    sitetree::{content_hash, for_each_concurrent, Resource, SiteId, SiteNodeKind, SiteTree},
+    tree::Walk,
 };
 
+/// Page to render with [`Lssg::render_page`]
+#[derive(Debug, Clone)]
+pub enum PageRef {
+    Id(SiteId),
+    /// Absolute path of the page in the site (eg. "/blog/first-post") or the path of its
+    /// markdown file
+    Path(String),
+}
+impl From<SiteId> for PageRef {
+    fn from(id: SiteId) -> Self {
+        PageRef::Id(id)
+    }
+}
+impl From<&str> for PageRef {
+    fn from(path: &str) -> Self {
+        PageRef::Path(path.to_string())
+    }
+}
+impl From<String> for PageRef {
+    fn from(path: String) -> Self {
+        PageRef::Path(path)
+    }
+}
+impl PageRef {
+    fn find(&self, site_tree: &SiteTree) -> Option<SiteId> {
+        match self {
+            PageRef::Id(id) => Some(*id).filter(|id| *id < site_tree.len()),
+            PageRef::Path(path) => site_tree.page_by_path(path).or_else(|| {
+                let canonical = Input::from_string(path).ok()?.canonical()?;
+                (0..site_tree.len()).find(|id| {
+                    site_tree[*id].kind.is_page()
+                        && site_tree
+                            .get_input(*id)
+                            .and_then(|i| i.canonical())
+                            .as_ref()
+                            == Some(&canonical)
+                })
+            }),
+        }
+    }
+}
+
 pub struct Lssg {
     input: Input,
     output_directory: PathBuf,
     renderer: Renderer,
+    /// Default attributes of the root page
+    attributes: toml::Table,
+    /// SiteTree with initialized modules, kept between calls to `render_page`
+    site_tree: Option<SiteTree>,
+    /// Only replace the files in the output directory that changed
+    incremental: bool,
 }
 
 impl Lssg {
@@ -36,6 +95,9 @@ impl Lssg {
             input,
             output_directory,
             renderer,
+            attributes: toml::Table::new(),
+            site_tree: None,
+            incremental: false,
         }
     }
 
@@ -43,80 +105,299 @@ impl Lssg {
         self.renderer.add_module(module)
     }
 
-    pub fn render(&mut self) -> Result<(), LssgError> {
+    /// Write pages as html (default) or xhtml, see [`Renderer::set_output_mode`]
+    pub fn set_output_mode(&mut self, output_mode: renderer::OutputMode) {
+        self.renderer.set_output_mode(output_mode)
+    }
+
+    /// Keep the output directory and only replace the files that changed since the last build,
+    /// instead of replacing the whole directory. Files that are no longer part of the site are
+    /// removed, files that weren't written by lssg are kept.
+    pub fn set_incremental(&mut self, incremental: bool) {
+        self.incremental = incremental;
+    }
+
+    /// Discover all pages and resources and initialize the modules, without writing anything
+    fn prepare(&mut self) -> Result<SiteTree, LssgError> {
         info!("Generating SiteTree");
         let mut site_tree = SiteTree::from_input(self.input.clone())?;
+        self.apply_attributes(&mut site_tree);
 
         self.renderer.init(&mut site_tree);
-        info!("SiteTree:\n{site_tree}");
+        debug!("SiteTree:\n{site_tree}");
+
+        // update resources to stylesheet sitenode path
+        site_tree.update_stylesheet_resources();
 
         // site_tree.minify();
 
         self.renderer.after_init(&mut site_tree);
+        Ok(site_tree)
+    }
+
+    /// The SiteTree as it will be rendered, generated on first use
+    pub fn site_tree(&mut self) -> Result<&SiteTree, LssgError> {
+        if self.site_tree.is_none() {
+            self.site_tree = Some(self.prepare()?);
+        }
+        Ok(self
+            .site_tree
+            .as_ref()
+            .expect("site tree was just generated"))
+    }
+
+    /// Render a single page to html without writing any output, eg. for previewing a page
+    pub fn render_page(&mut self, page: impl Into<PageRef>) -> Result<String, LssgError> {
+        let page = page.into();
+        self.site_tree()?;
+        let site_tree = self
+            .site_tree
+            .as_ref()
+            .expect("site tree was just generated");
+        let id = page
+            .find(site_tree)
+            .filter(|id| site_tree[*id].kind.is_page())
+            .ok_or(LssgError::render(format!("{page:?} is not a page")))?;
+        self.renderer.render(site_tree, id)
+    }
+
+    pub fn render(&mut self) -> Result<(), LssgError> {
+        let mut site_tree = match self.site_tree.take() {
+            Some(site_tree) => site_tree,
+            None => self.prepare()?,
+        };
+
+        // write into a sibling directory first so a failed render never leaves a broken output
+        let output_directory =
+            std::path::absolute(&self.output_directory)?.canonicalize_nonexistent_path();
+        let temp_directory = sibling_directory(&output_directory, "tmp")?;
+        if temp_directory.exists() {
+            remove_dir_all(&temp_directory)?;
+        }
+        debug!("Creating {temp_directory:?}");
+        create_dir_all(&temp_directory)?;
+
+        let hashes = match self.write(&mut site_tree, &temp_directory) {
+            Ok(hashes) => hashes,
+            Err(e) => {
+                error!("Failed to write output, leaving {output_directory:?} untouched");
+                if let Err(e) = remove_dir_all(&temp_directory) {
+                    warn!("Failed to remove {temp_directory:?}: {e}");
+                }
+                return Err(e);
+            }
+        };
+
+        if self.incremental {
+            create_dir_all(&output_directory)?;
+            let result = incremental::sync(&temp_directory, &output_directory, &hashes);
+            if let Err(e) = remove_dir_all(&temp_directory) {
+                warn!("Failed to remove {temp_directory:?}: {e}");
+            }
+            result?;
+        } else {
+            swap_directories(&temp_directory, &output_directory)?;
+        }
+
+        info!("All files written to {output_directory:?}");
+
+        if let Err(e) = cache::gc() {
+            warn!("Failed to clean up cache: {e}");
+        }
+
+        Ok(())
+    }
 
-        if self.output_directory.exists() {
-            info!(
-                "Removing {:?}",
-                self.output_directory.canonicalize_nonexistent_path()
+    /// Add `attributes` to the root page, without overwriting the ones it already has
+    fn apply_attributes(&self, site_tree: &mut SiteTree) {
+        if self.attributes.is_empty() {
+            return;
+        }
+        let root = site_tree.root();
+        let SiteNodeKind::Page(page) = &mut site_tree[root].kind else {
+            return;
+        };
+        if page.attributes().is_none() {
+            page.tokens_mut().insert(
+                0,
+                Token::Attributes {
+                    table: toml::Table::new(),
+                },
             );
-            remove_dir_all(&self.output_directory)?;
         }
-        info!(
-            "Creating {:?}",
-            self.output_directory.canonicalize_nonexistent_path()
-        );
-        create_dir_all(&self.output_directory)?;
+        let table = page.attributes_mut().expect("attributes were just added");
+        for (key, value) in &self.attributes {
+            table.entry(key).or_insert_with(|| value.clone());
+        }
+    }
 
-        let mut queue: Vec<usize> = vec![site_tree.root()];
-        while let Some(site_id) = queue.pop() {
-            queue.append(&mut site_tree[site_id].children.clone());
+    /// Write all nodes of `site_tree` to `output_directory`, returns the hashes of the written
+    /// files that are known without reading them again and the page sources for incremental
+    /// builds
+    fn write(
+        &mut self,
+        site_tree: &mut SiteTree,
+        output_directory: &Path,
+    ) -> Result<Manifest, LssgError> {
+        let mut hashes = Manifest::default();
+        let mut walk = Walk::new(&*site_tree);
+        // remote resources are written concurrently once everything else is written
+        let mut remote_resources = vec![];
+        // html file of every page, checked against the size budgets
+        let mut pages = vec![];
+        let page_count = (0..site_tree.len())
+            .filter(|id| site_tree[*id].kind.is_page())
+            .count();
+        let progress = Progress::start(Phase::Rendering, page_count);
+        while let Some(site_id) = walk.next(&*site_tree) {
+            // site tree paths are url paths, join them part by part to get a platform path
             let rel_path = site_tree.rel_path(site_tree.root(), site_id);
-            let path = self
-                .output_directory
-                .join(rel_path)
+            let path = rel_path
+                .split('/')
+                .fold(output_directory.to_path_buf(), |path, part| path.join(part))
                 .canonicalize_nonexistent_path();
+            let symlink_target = site_tree.preserved_symlink(site_id);
             match &mut site_tree[site_id].kind {
                 SiteNodeKind::Stylesheet(stylesheet) => {
-                    let mut stylesheet = stylesheet.clone();
-
-                    // update resources to stylesheet sitenode path
-                    for link in site_tree.links_from(site_id) {
-                        if let Relation::Discovered { raw_path } = &link.relation {
-                            let updated_resource = site_tree.rel_path(
-                                site_tree[site_id]
-                                    .parent
-                                    .expect("stylesheet must have parent"),
-                                link.to,
-                            );
-                            stylesheet.update_resource(raw_path, &updated_resource);
-                        }
-                    }
-
                     stylesheet.write(&path)?;
                 }
-                SiteNodeKind::Resource(resource) => {
-                    resource.write(&path)?;
-                }
+                SiteNodeKind::Resource(resource) => match &symlink_target {
+                    Some(target) => {
+                        debug!("Creating symlink {path:?} to {target:?}");
+                        symlink(target, &path)?;
+                    }
+                    None if resource.is_remote() => remote_resources.push((site_id, path)),
+                    None => {
+                        resource.write(&path)?;
+                        if self.incremental {
+                            if let Some(rel_path) = manifest_path(output_directory, &path) {
+                                hashes.files.insert(rel_path, resource.hash()?);
+                            }
+                        }
+                    }
+                },
                 SiteNodeKind::Folder => {
-                    info!("Creating folder {path:?}",);
+                    debug!("Creating folder {path:?}",);
                     create_dir(path)?;
                 }
                 SiteNodeKind::Page { .. } => {
-                    let html = self.renderer.render(&site_tree, site_id)?;
-                    create_dir_all(&path)?;
-                    let html_output_path = path.join("index.html").canonicalize_nonexistent_path();
+                    let html = self.renderer.render(site_tree, site_id)?;
+                    // hosts serve `/404.html` for unknown paths so keep the names of error pages
+                    let html_output_path = match site_tree.error_page_file(site_id) {
+                        Some(file) => output_directory.join(file),
+                        None => {
+                            create_dir_all(&path)?;
+                            path.join("index.html")
+                        }
+                    }
+                    .canonicalize_nonexistent_path();
 
-                    info!(
+                    debug!(
                         "Writing to {:?}",
                         (&html_output_path).canonicalize_nonexistent_path()
                     );
-                    write(html_output_path, html)?;
+                    write(&html_output_path, &html)?;
+                    if self.incremental {
+                        if let (Some(rel_path), SiteNodeKind::Page(page)) = (
+                            manifest_path(output_directory, &html_output_path),
+                            &site_tree[site_id].kind,
+                        ) {
+                            hashes
+                                .files
+                                .insert(rel_path.clone(), content_hash(html.as_bytes())?);
+                            hashes.sources.insert(rel_path, page.hash().to_string());
+                        }
+                    }
+                    pages.push((site_id, html_output_path));
+                    progress.advance();
                 }
             }
         }
+        drop(progress);
 
-        info!("All files written");
+        self.write_remote_resources(site_tree, remote_resources)?;
 
-        Ok(())
+        self.renderer.finish(site_tree, output_directory)?;
+
+        budget::check(site_tree, output_directory, &pages)?;
+        Ok(hashes)
+    }
+
+    fn write_remote_resources(
+        &self,
+        site_tree: &SiteTree,
+        resources: Vec<(SiteId, PathBuf)>,
+    ) -> Result<(), LssgError> {
+        if resources.is_empty() {
+            return Ok(());
+        }
+        let errors = Mutex::new(vec![]);
+        let progress = Progress::start(Phase::Fetching, resources.len());
+        for_each_concurrent(
+            resources
+                .into_iter()
+                .filter_map(|(id, path)| match &site_tree[id].kind {
+                    SiteNodeKind::Resource(resource) => Some((resource, path)),
+                    _ => None,
+                })
+                .collect(),
+            |(resource, _)| match resource {
+                Resource::Fetched { input, .. } => input,
+                Resource::Static { .. } => unreachable!("only remote resources are queued"),
+            },
+            |(resource, path)| {
+                if let Err(e) = resource.write(&path) {
+                    errors.lock().unwrap().push(e);
+                }
+                progress.advance();
+            },
+        );
+        match errors.into_inner().unwrap().into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, path: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, path: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(target, path)
+}
+
+/// Directory next to `directory` (eg. "/srv/.public.lssg-tmp"), on the same filesystem so it can
+/// be renamed atomically
+fn sibling_directory(directory: &Path, suffix: &str) -> Result<PathBuf, LssgError> {
+    let name = directory.filename_from_path()?;
+    let parent = directory.parent().ok_or(LssgError::io(format!(
+        "{directory:?} does not have a parent"
+    )))?;
+    Ok(parent.join(format!(".{name}.lssg-{suffix}")))
+}
+
+/// Replace `output_directory` with `temp_directory`, restoring the old output if that fails
+fn swap_directories(temp_directory: &Path, output_directory: &Path) -> Result<(), LssgError> {
+    if !output_directory.exists() {
+        debug!("Moving {temp_directory:?} to {output_directory:?}");
+        rename(temp_directory, output_directory)?;
+        return Ok(());
+    }
+
+    let old_directory = sibling_directory(output_directory, "old")?;
+    if old_directory.exists() {
+        remove_dir_all(&old_directory)?;
+    }
+    rename(output_directory, &old_directory)?;
+    debug!("Moving {temp_directory:?} to {output_directory:?}");
+    if let Err(e) = rename(temp_directory, output_directory) {
+        rename(&old_directory, output_directory)?;
+        return Err(e.into());
     }
+    debug!("Removing {old_directory:?}");
+    remove_dir_all(&old_directory)?;
+    Ok(())
 }
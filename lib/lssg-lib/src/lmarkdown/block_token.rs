@@ -1,13 +1,14 @@
 use std::io::Read;
 
 use log::warn;
-use virtual_dom::Html;
+use virtual_dom::{is_raw_text_element, Html};
 
 use crate::{char_reader::CharReader, parse_error::ParseError};
 
 use super::{
     html::{html_comment, html_element},
-    sanitize_text, Token,
+    inline_token::is_footnote_label,
+    sanitize_text, Alignment, Token,
 };
 
 /// https://spec.commonmark.org/0.30/#blocks-and-inlines
@@ -76,19 +77,22 @@ fn from_reader(
     }
 
     if let Some((tag, attributes, content)) = html_element(reader)? {
-        let tokens = if let Some(content) = content {
-            let mut reader = CharReader::<&[u8]>::from_string(&content);
-            // NOTE: html allows for block tokens inside of it
-            read_block_tokens(&mut reader)?
-                .into_iter()
-                .flat_map(|t| match t {
-                    // HACK: dissallowing paragraphs in html so transforming them to inline element Text
-                    Token::Paragraph { text, .. } => vec![Token::Text { text }],
-                    _ => vec![t],
-                })
-                .collect()
-        } else {
-            vec![]
+        let tokens = match content {
+            // scripts and styles are not markdown
+            Some(content) if is_raw_text_element(&tag) => vec![Token::Text { text: content }],
+            Some(content) => {
+                let mut reader = CharReader::<&[u8]>::from_string(&content);
+                // NOTE: html allows for block tokens inside of it
+                read_block_tokens(&mut reader)?
+                    .into_iter()
+                    .flat_map(|t| match t {
+                        // HACK: dissallowing paragraphs in html so transforming them to inline element Text
+                        Token::Paragraph { text, .. } => vec![Token::Text { text }],
+                        _ => vec![t],
+                    })
+                    .collect()
+            }
+            None => vec![],
         };
 
         return Ok(Some(Token::Html {
@@ -98,6 +102,14 @@ fn from_reader(
         }));
     }
 
+    if let Some(footnote) = footnote_definition(reader)? {
+        return Ok(Some(footnote));
+    }
+
+    if let Some(table) = table(reader)? {
+        return Ok(Some(table));
+    }
+
     if let Some(setext) = setext_heading(reader, tokens)? {
         return Ok(Some(setext));
     }
@@ -324,10 +336,31 @@ fn list_item_text(
     let t = read_block_tokens(&mut reader)?;
     Ok(t)
 }
+/// Remove the task list marker (`[ ]` or `[x]`) from the start of a list item, returns if the
+/// task is checked
+/// https://github.github.com/gfm/#task-list-items-extension-
+fn task_list_marker(tokens: &mut [Token]) -> Option<bool> {
+    let Some(Token::Paragraph { text, .. }) = tokens.first_mut() else {
+        return None;
+    };
+    let checked = match text.get(0..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let rest = &text[3..];
+    if !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    *text = rest.trim_start_matches([' ', '\t']).to_string();
+    Some(checked)
+}
+
 // TODO implement all specs (check for same usage of bullet enc.)
 /// https://spec.commonmark.org/0.30/#list-items
 pub fn bullet_list(reader: &mut CharReader<impl Read>) -> Result<Option<Token>, ParseError> {
     let mut items = vec![];
+    let mut checked = vec![];
 
     while let Some(pos) = detect_char_with_ident(reader, |c| c == '-' || c == '+' || c == '*')? {
         // by default n=1
@@ -349,7 +382,8 @@ pub fn bullet_list(reader: &mut CharReader<impl Read>) -> Result<Option<Token>,
 
         let ident = 1 + pos + n;
 
-        let tokens = list_item_text(reader, ident)?;
+        let mut tokens = list_item_text(reader, ident)?;
+        checked.push(task_list_marker(&mut tokens));
         items.push(tokens)
     }
 
@@ -357,7 +391,7 @@ pub fn bullet_list(reader: &mut CharReader<impl Read>) -> Result<Option<Token>,
         return Ok(None);
     }
 
-    return Ok(Some(Token::BulletList { items }));
+    return Ok(Some(Token::BulletList { items, checked }));
 }
 // TODO implement all specs (check for same usage of bullet enc.)
 /// https://spec.commonmark.org/0.30/#list-items
@@ -440,18 +474,159 @@ pub fn heading(reader: &mut CharReader<impl Read>) -> Result<Option<Token>, Pars
                 .skip(depth as usize + 1)
                 .collect(),
         );
-        let tokens = read_block_tokens(&mut CharReader::new(text.as_bytes()))?;
-
+        // inline tokens are read from `text` by the lexer
         Ok(Some(Token::Heading {
             depth,
             text,
-            tokens,
+            tokens: vec![],
         }))
     } else {
         Ok(None)
     }
 }
 
+/// https://github.github.com/gfm/#footnotes (eg. `[^1]: text`), following lines indented by 4
+/// spaces are part of the footnote
+pub fn footnote_definition(
+    reader: &mut CharReader<impl Read>,
+) -> Result<Option<Token>, ParseError> {
+    let line = reader.peek_line()?;
+    let Some(rest) = line
+        .strip_prefix("   ")
+        .or(line.strip_prefix("  "))
+        .or(line.strip_prefix(' '))
+        .unwrap_or(&line)
+        .strip_prefix("[^")
+    else {
+        return Ok(None);
+    };
+    let Some((label, first_line)) = rest.split_once("]:") else {
+        return Ok(None);
+    };
+    if !is_footnote_label(label) {
+        return Ok(None);
+    }
+    let label = label.to_string();
+    let mut content = first_line.trim_start().to_string();
+    reader.consume_until_inclusive(|c| c == '\n')?;
+
+    loop {
+        let line = reader.peek_line()?;
+        if let Some(line) = line.strip_prefix("    ") {
+            content.push('\n');
+            content.push_str(line);
+        } else if line.trim().is_empty() {
+            // blank lines are part of the footnote if it continues after them
+            let next = reader.peek_line_from(line.chars().count() + 1)?;
+            if !next.starts_with("    ") {
+                break;
+            }
+            content.push('\n');
+        } else {
+            break;
+        }
+        reader.consume_until_inclusive(|c| c == '\n')?;
+    }
+
+    let tokens = read_block_tokens(&mut CharReader::new(content.as_bytes()))?;
+    Ok(Some(Token::FootnoteDefinition { label, tokens }))
+}
+
+/// Split a table row into its trimmed cells, the leading and trailing pipe are optional and
+/// `\|` is a pipe inside of a cell
+fn table_cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let mut cells = vec![];
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                chars.next();
+                cell.push('|');
+            }
+            '|' => cells.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+    if !cell.trim().is_empty() {
+        cells.push(cell);
+    }
+    cells.into_iter().map(|c| c.trim().to_string()).collect()
+}
+
+/// Alignment of every column from a delimiter row (eg. `| :-- | :-: | --: |`)
+fn table_alignments(line: &str) -> Option<Vec<Option<Alignment>>> {
+    if !line.contains('|') {
+        return None;
+    }
+    table_cells(line)
+        .iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Some(Alignment::Center),
+                (true, false) => Some(Alignment::Left),
+                (false, true) => Some(Alignment::Right),
+                (false, false) => None,
+            })
+        })
+        .collect()
+}
+
+/// Check if `line` starts a block that ends a table
+fn interrupts_table(line: &str) -> bool {
+    let line = line.trim_start();
+    line.is_empty()
+        || line.starts_with('>')
+        || line.starts_with("# ")
+        || line.starts_with("```")
+        || line.starts_with("~~~")
+}
+
+/// https://github.github.com/gfm/#tables-extension-
+pub fn table(reader: &mut CharReader<impl Read>) -> Result<Option<Token>, ParseError> {
+    let header_line = reader.peek_line()?;
+    if header_line.starts_with("    ") || !header_line.contains('|') {
+        return Ok(None);
+    }
+    let delimiter_line = reader.peek_line_from(header_line.chars().count() + 1)?;
+    let Some(align) = table_alignments(&delimiter_line) else {
+        return Ok(None);
+    };
+    let header = table_cells(&header_line);
+    // header has to have as many cells as there are columns
+    if align.is_empty() || header.len() != align.len() {
+        return Ok(None);
+    }
+    reader.consume_until_inclusive(|c| c == '\n')?;
+    reader.consume_until_inclusive(|c| c == '\n')?;
+
+    let cell = |text: String| vec![Token::Text { text }];
+    let mut rows = vec![];
+    loop {
+        let line = reader.peek_line()?;
+        if interrupts_table(&line) {
+            break;
+        }
+        reader.consume_until_inclusive(|c| c == '\n')?;
+        // missing cells are empty and excess cells are ignored
+        let mut row: Vec<Vec<Token>> = table_cells(&line).into_iter().map(cell).collect();
+        row.resize(align.len(), cell(String::new()));
+        rows.push(row);
+    }
+
+    Ok(Some(Token::Table {
+        align,
+        header: header.into_iter().map(cell).collect(),
+        rows,
+    }))
+}
+
 // https://spec.commonmark.org/0.30/#block-quotes
 pub fn blockquote(reader: &mut CharReader<impl Read>) -> Result<Option<Token>, ParseError> {
     let mut lines = vec![];
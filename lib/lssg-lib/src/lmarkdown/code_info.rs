@@ -0,0 +1,138 @@
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use super::Token;
+
+/// Structured form of the info string of a fenced code block
+/// (eg. "rust,filename=main.rs,hl=3-5")
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CodeInfo {
+    pub language: Option<String>,
+    pub filename: Option<String>,
+    /// Lines to highlight, starting at 1
+    pub highlight: Vec<RangeInclusive<usize>>,
+    /// Show line numbers ("linenos" or "linenos=false"), None when not specified
+    pub line_numbers: Option<bool>,
+    /// Any other `key=value` or bare `key` annotations
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// Parse "3" or "3-5"
+fn parse_range(text: &str) -> Option<RangeInclusive<usize>> {
+    match text.split_once('-') {
+        Some((start, end)) => Some(start.trim().parse().ok()?..=end.trim().parse().ok()?),
+        None => {
+            let line = text.trim().parse().ok()?;
+            Some(line..=line)
+        }
+    }
+}
+
+impl CodeInfo {
+    /// Parse annotations separated by commas or spaces, the first bare word is the language
+    pub fn parse(info: &str) -> CodeInfo {
+        let mut code_info = CodeInfo::default();
+        let mut last_key = None;
+        for part in info.split([',', ' ']).filter(|p| !p.is_empty()) {
+            let (key, value) = match part.split_once('=') {
+                Some((key, value)) => (key, value),
+                None => {
+                    // continuation of a list of lines (eg. "hl=1,3-5")
+                    if last_key == Some("hl") {
+                        if let Some(range) = parse_range(part) {
+                            code_info.highlight.push(range);
+                            continue;
+                        }
+                    }
+                    if matches!(part, "linenos" | "line_numbers") {
+                        code_info.line_numbers = Some(true);
+                    } else if code_info.language.is_none() && code_info.attributes.is_empty() {
+                        code_info.language = Some(part.to_string());
+                    } else {
+                        code_info.attributes.insert(part.to_string(), String::new());
+                    }
+                    last_key = None;
+                    continue;
+                }
+            };
+            match key {
+                "filename" | "file" | "title" => {
+                    code_info.filename = Some(value.trim_matches('"').to_string())
+                }
+                "hl" | "highlight" => code_info.highlight.extend(parse_range(value)),
+                "linenos" | "line_numbers" => code_info.line_numbers = value.parse().ok(),
+                _ => {
+                    code_info
+                        .attributes
+                        .insert(key.to_string(), value.to_string());
+                }
+            }
+            last_key = match key {
+                "hl" | "highlight" => Some("hl"),
+                _ => None,
+            };
+        }
+        code_info
+    }
+
+    /// If lines starting with `+` or `-` are additions and removals: for the "diff" language,
+    /// "diff-<language>" (eg. "diff-rust") or a bare `diff` annotation
+    pub fn is_diff(&self) -> bool {
+        self.attributes.contains_key("diff")
+            || self
+                .language
+                .as_deref()
+                .is_some_and(|l| l == "diff" || l.starts_with("diff-"))
+    }
+
+    /// Language of the code itself, without a "diff-" prefix
+    pub fn code_language(&self) -> Option<&str> {
+        let language = self.language.as_deref()?;
+        Some(language.strip_prefix("diff-").unwrap_or(language))
+    }
+
+    pub fn is_highlighted(&self, line: usize) -> bool {
+        self.highlight.iter().any(|range| range.contains(&line))
+    }
+}
+
+impl Token {
+    /// Structured info of a fenced code block, None for other tokens
+    pub fn code_info(&self) -> Option<CodeInfo> {
+        match self {
+            Token::Code {
+                info: Some(info), ..
+            } => Some(CodeInfo::parse(info)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CodeInfo;
+
+    #[test]
+    fn test_parse() {
+        let info = CodeInfo::parse("rust,filename=main.rs,hl=3-5,7 linenos");
+        assert_eq!(info.language.as_deref(), Some("rust"));
+        assert_eq!(info.filename.as_deref(), Some("main.rs"));
+        assert_eq!(info.highlight, vec![3..=5, 7..=7]);
+        assert_eq!(info.line_numbers, Some(true));
+        assert!(info.is_highlighted(4) && !info.is_highlighted(6));
+
+        assert_eq!(
+            CodeInfo::parse("sh linenos=false").line_numbers,
+            Some(false)
+        );
+        let info = CodeInfo::parse("diff-rust");
+        assert!(info.is_diff());
+        assert_eq!(info.code_language(), Some("rust"));
+        assert!(CodeInfo::parse("rust diff").is_diff());
+        assert!(!CodeInfo::parse("diffy").is_diff());
+        assert_eq!(CodeInfo::parse("").language, None);
+        assert_eq!(
+            CodeInfo::parse("python").language.as_deref(),
+            Some("python")
+        );
+    }
+}
@@ -1,13 +1,13 @@
-use std::{collections::HashMap, io::Read};
+use std::io::Read;
 
-use virtual_dom::Html;
+use virtual_dom::{Atom, Attributes, Html};
 
 use crate::{char_reader::CharReader, parse_error::ParseError};
 
 /// from virtual_dom::html
-fn attributes(start_tag_content: &str) -> Result<HashMap<String, String>, ParseError> {
+fn attributes(start_tag_content: &str) -> Result<Attributes, ParseError> {
     let chars: Vec<char> = start_tag_content.chars().collect();
-    let mut attributes = HashMap::new();
+    let mut attributes = Attributes::new();
     let mut key = String::new();
     let mut value = String::new();
     let mut in_value = false;
@@ -16,8 +16,8 @@ fn attributes(start_tag_content: &str) -> Result<HashMap<String, String>, ParseE
         match chars[i] {
             ' ' if in_value == false => {
                 if key.len() > 0 {
-                    attributes.insert(key, value);
-                    key = String::new();
+                    attributes.insert(Atom::from(&key), value);
+                    key.clear();
                     value = String::new();
                     in_value = false;
                 }
@@ -41,7 +41,7 @@ fn attributes(start_tag_content: &str) -> Result<HashMap<String, String>, ParseE
         i += 1;
     }
     if key.len() > 0 {
-        attributes.insert(key, value);
+        attributes.insert(Atom::from(key), value);
     }
 
     Ok(attributes)
@@ -50,18 +50,12 @@ fn attributes(start_tag_content: &str) -> Result<HashMap<String, String>, ParseE
 /// from virtual_dom::html
 pub fn html_element(
     reader: &mut CharReader<impl Read>,
-) -> Result<Option<(String, HashMap<String, String>, Option<String>)>, ParseError> {
+) -> Result<Option<(Atom, Attributes, Option<String>)>, ParseError> {
     if let Some('<') = reader.peek_char(0)? {
         if let Some(start_tag) = reader.peek_until_exclusive_from(1, |c| c == '>')? {
             // get html tag
-            let mut tag = String::new();
-            for c in start_tag.chars() {
-                match c {
-                    ' ' => break,
-                    '\n' => break,
-                    _ => tag.push(c),
-                }
-            }
+            let tag_end = start_tag.find([' ', '\n']).unwrap_or(start_tag.len());
+            let tag = Atom::from(&start_tag[..tag_end]);
 
             if start_tag.ends_with("/") && is_void_element(&tag) {
                 // <{start_tag}/>
@@ -1,11 +1,22 @@
 use std::io::Read;
 
-use virtual_dom::Html;
+use virtual_dom::{is_raw_text_element, Html};
 
 use crate::{char_reader::CharReader, parse_error::ParseError};
 
 use super::{html::html_comment, html::html_element, sanitize_text, Token};
 
+/// Target of a wiki link (`[[Page Name]]`), these are kept as href of their link until the SiteTree
+/// resolves them to a page
+pub fn wiki_link_target(href: &str) -> Option<&str> {
+    href.strip_prefix("[[")?.strip_suffix("]]")
+}
+
+/// Footnote labels can't be empty or contain whitespace or brackets
+pub fn is_footnote_label(label: &str) -> bool {
+    !label.is_empty() && !label.contains(|c: char| c.is_whitespace() || c == '[' || c == ']')
+}
+
 pub fn read_inline_tokens(reader: &mut CharReader<impl Read>) -> Result<Vec<Token>, ParseError> {
     let mut tokens = vec![];
     'outer: while let Some(c) = reader.peek_char(0)? {
@@ -19,11 +30,16 @@ pub fn read_inline_tokens(reader: &mut CharReader<impl Read>) -> Result<Vec<Toke
 
             // html
             if let Some((tag, attributes, content)) = html_element(reader)? {
-                let content_tokens = if let Some(content) = content {
-                    let content = sanitize_text(content);
-                    read_inline_tokens(&mut CharReader::new(content.as_bytes()))?
-                } else {
-                    vec![]
+                let content_tokens = match content {
+                    // scripts and styles are not markdown
+                    Some(content) if is_raw_text_element(&tag) => {
+                        vec![Token::Text { text: content }]
+                    }
+                    Some(content) => {
+                        let content = sanitize_text(content);
+                        read_inline_tokens(&mut CharReader::new(content.as_bytes()))?
+                    }
+                    None => vec![],
                 };
 
                 tokens.push(Token::Html {
@@ -52,7 +68,7 @@ pub fn read_inline_tokens(reader: &mut CharReader<impl Read>) -> Result<Vec<Toke
                 }
                 if valid {
                     reader.consume(1)?;
-                    let text = reader.consume_string(link.len())?;
+                    let text = reader.consume_string(link.chars().count())?;
                     reader.consume(1)?;
                     tokens.push(Token::Link {
                         tokens: vec![Token::Text { text }],
@@ -108,26 +124,28 @@ pub fn read_inline_tokens(reader: &mut CharReader<impl Read>) -> Result<Vec<Toke
         if c == '!' {
             if let Some('[') = reader.peek_char(1)? {
                 if let Some(raw_text) = reader.peek_until_inclusive_from(2, |c| c == ']')? {
-                    let href_start = 2 + raw_text.len();
+                    let href_start = 2 + raw_text.chars().count();
                     if let Some('(') = reader.peek_char(href_start)? {
                         if let Some(raw_href) =
                             reader.peek_until_inclusive_from(href_start + 1, |c| c == ')')?
                         {
                             reader.consume(2)?;
-                            let text = reader.consume_string(raw_text.len() - 1)?;
+                            let text = reader.consume_string(raw_text.chars().count() - 1)?;
                             reader.consume(2)?;
-                            let src = reader.consume_string(raw_href.len() - 1)?;
-                            let src = sanitize_text(src);
+                            let src = reader.consume_string(raw_href.chars().count() - 1)?;
+                            let mut src = sanitize_text(src);
 
                             // https://spec.commonmark.org/0.30/#link-title
                             let title = if let Some(start_title) = src.find(" ") {
-                                let title = &src[start_title..src.len()];
+                                let title = &src[start_title + 1..src.len()];
 
                                 if ((title.starts_with("\"") && title.ends_with("\""))
                                     || (title.starts_with("\'") && title.ends_with("\'")))
                                     && title.len() >= 2
                                 {
-                                    Some(title.to_string())
+                                    let title = title[1..title.len() - 1].to_string();
+                                    src = (&src[0..start_title]).into();
+                                    Some(title)
                                 } else {
                                     None
                                 }
@@ -149,6 +167,40 @@ pub fn read_inline_tokens(reader: &mut CharReader<impl Read>) -> Result<Vec<Toke
             }
         }
 
+        // footnote references: [^label]
+        if c == '[' && reader.peek_char(1)? == Some('^') {
+            if let Some(raw) = reader.peek_until_inclusive_from(2, |c| c == ']')? {
+                let label = &raw[..raw.len() - 1];
+                if is_footnote_label(label) {
+                    reader.consume(2 + raw.chars().count())?;
+                    tokens.push(Token::FootnoteReference {
+                        label: label.to_string(),
+                    });
+                    continue;
+                }
+            }
+        }
+
+        // wiki links: [[Page Name]] or [[Page Name|text]]
+        if c == '[' && reader.peek_char(1)? == Some('[') {
+            if let Some(raw) = reader.peek_until_match_inclusive_from(2, "]]")? {
+                let inner = &raw[..raw.len() - 2];
+                if !inner.trim().is_empty() && !inner.contains(['\n', '[', ']']) {
+                    reader.consume(2 + raw.chars().count())?;
+                    let (target, text) = match inner.split_once('|') {
+                        Some((target, text)) => (target.trim(), text.trim()),
+                        None => (inner.trim(), inner.trim()),
+                    };
+                    tokens.push(Token::Link {
+                        tokens: vec![Token::Text { text: text.into() }],
+                        href: format!("[[{target}]]"),
+                        title: None,
+                    });
+                    continue;
+                }
+            }
+        }
+
         // links: https://spec.commonmark.org/0.30/#links
         if c == '[' {
             let mut indent = 1;
@@ -177,7 +229,7 @@ pub fn read_inline_tokens(reader: &mut CharReader<impl Read>) -> Result<Vec<Toke
                         reader.consume(1)?;
                         let text = reader.consume_string(i - 2)?;
                         reader.consume(2)?;
-                        let mut href = reader.consume_string(raw_href.len() - 1)?;
+                        let mut href = reader.consume_string(raw_href.chars().count() - 1)?;
                         reader.consume(1)?;
                         let text = sanitize_text(text);
                         let text = read_inline_tokens(&mut CharReader::new(text.as_bytes()))?;
@@ -211,12 +263,28 @@ pub fn read_inline_tokens(reader: &mut CharReader<impl Read>) -> Result<Vec<Toke
             }
         }
 
+        // strikethrough: https://github.github.com/gfm/#strikethrough-extension-
+        // runs of more than two tildes are text
+        let after_tilde =
+            matches!(tokens.last(), Some(Token::Text { text }) if text.ends_with('~'));
+        if c == '~' && reader.peek_char(1)? == Some('~') && !after_tilde {
+            if let Some(raw) = reader.peek_until_match_inclusive_from(2, "~~")? {
+                let text = &raw[..raw.len() - 2];
+                if !text.is_empty() && !text.starts_with('~') {
+                    let text = text.to_string();
+                    reader.consume(4 + text.chars().count())?;
+                    tokens.push(Token::Strikethrough { text });
+                    continue;
+                }
+            }
+        }
+
         // emphasis: https://spec.commonmark.org/0.30/#emphasis-and-strong-emphasis
         if c == '*' {
             if let Some('*') = reader.peek_char(1)? {
                 if let Some(text) = reader.peek_until_match_inclusive_from(2, "**")? {
                     reader.consume(2)?;
-                    let text = reader.consume_string(text.len() - 2)?;
+                    let text = reader.consume_string(text.chars().count() - 2)?;
                     reader.consume(2)?;
                     tokens.push(Token::Bold { text });
                     continue;
@@ -224,7 +292,7 @@ pub fn read_inline_tokens(reader: &mut CharReader<impl Read>) -> Result<Vec<Toke
             }
             if let Some(text) = reader.peek_until_inclusive_from(1, |c| c == '*')? {
                 reader.consume(1)?;
-                let text = reader.consume_string(text.len() - 1)?;
+                let text = reader.consume_string(text.chars().count() - 1)?;
                 reader.consume(1)?;
                 tokens.push(Token::Emphasis { text });
                 continue;
@@ -1,4 +1,6 @@
-use std::{collections::HashMap, io::Read};
+use std::io::Read;
+
+use virtual_dom::{is_raw_text_element, Atom, Attributes};
 
 use crate::{
     char_reader::CharReader,
@@ -27,6 +29,8 @@ pub fn read_tokens(reader: &mut CharReader<impl Read>) -> Result<Vec<Token>, Par
 /// parse text inside of block tokens to inline tokens
 fn parse_block_token_text(block_token: &mut Token) -> Result<(), ParseError> {
     match block_token {
+        // scripts and styles are kept as text
+        Token::Html { tag, .. } if is_raw_text_element(tag) => {}
         // Html is special because it can contains any kind of token
         Token::Html { tokens, .. } => {
             *tokens = tokens
@@ -46,7 +50,7 @@ fn parse_block_token_text(block_token: &mut Token) -> Result<(), ParseError> {
                 .flatten()
                 .collect();
         }
-        Token::BlockQuote { tokens, .. } => {
+        Token::BlockQuote { tokens, .. } | Token::FootnoteDefinition { tokens, .. } => {
             for t in tokens.iter_mut() {
                 parse_block_token_text(t)?;
             }
@@ -62,6 +66,15 @@ fn parse_block_token_text(block_token: &mut Token) -> Result<(), ParseError> {
             let mut reader = CharReader::new(text.as_bytes());
             *tokens = read_inline_tokens(&mut reader)?;
         }
+        // cells are kept as text by the block parser
+        Token::Table { header, rows, .. } => {
+            for cell in header.iter_mut().chain(rows.iter_mut().flatten()) {
+                if let [Token::Text { text }] = cell.as_slice() {
+                    let mut reader = CharReader::new(text.as_bytes());
+                    *cell = read_inline_tokens(&mut reader)?;
+                }
+            }
+        }
         Token::Code { .. } | Token::Attributes { .. } | Token::Comment { .. } => {}
         _ => {
             return Err(ParseError::invalid(
@@ -73,6 +86,24 @@ fn parse_block_token_text(block_token: &mut Token) -> Result<(), ParseError> {
     return Ok(());
 }
 
+/// Alignment of a table column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+impl Alignment {
+    /// Value for the css `text-align` property
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Alignment::Left => "left",
+            Alignment::Center => "center",
+            Alignment::Right => "right",
+        }
+    }
+}
+
 /// https://github.com/markedjs/marked/blob/master/src/Tokenizer.js
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
@@ -81,6 +112,9 @@ pub enum Token {
     },
     BulletList {
         items: Vec<Vec<Token>>,
+        /// Task list state of every item, `- [x]` is checked and `- [ ]` unchecked
+        /// https://github.github.com/gfm/#task-list-items-extension-
+        checked: Vec<Option<bool>>,
     },
     OrderedList {
         items: Vec<Vec<Token>>,
@@ -93,8 +127,8 @@ pub enum Token {
     },
     Html {
         tokens: Vec<Token>,
-        tag: String,
-        attributes: HashMap<String, String>,
+        tag: Atom,
+        attributes: Attributes,
     },
     Paragraph {
         text: String,
@@ -103,6 +137,20 @@ pub enum Token {
     BlockQuote {
         tokens: Vec<Token>,
     },
+    /// https://github.github.com/gfm/#tables-extension-
+    Table {
+        /// Alignment of every column
+        align: Vec<Option<Alignment>>,
+        /// Cells of the header row
+        header: Vec<Vec<Token>>,
+        /// Cells of the body rows, every row has as many cells as the header
+        rows: Vec<Vec<Vec<Token>>>,
+    },
+    /// https://github.github.com/gfm/#footnotes (eg. `[^1]: Footnote text`)
+    FootnoteDefinition {
+        label: String,
+        tokens: Vec<Token>,
+    },
     Code {
         info: Option<String>,
         text: String,
@@ -113,6 +161,10 @@ pub enum Token {
     Emphasis {
         text: String,
     },
+    /// https://github.github.com/gfm/#strikethrough-extension- (eg. `~~text~~`)
+    Strikethrough {
+        text: String,
+    },
     /// https://spec.commonmark.org/0.30/#images
     Image {
         /// alt, recommended to convert tokens to text
@@ -130,6 +182,10 @@ pub enum Token {
     Text {
         text: String,
     },
+    /// Reference to a footnote (eg. `[^1]`)
+    FootnoteReference {
+        label: String,
+    },
     Comment {
         raw: String,
     },
@@ -141,19 +197,10 @@ pub enum Token {
 }
 
 impl Token {
+    /// Direct children of the token, None if it has none
     pub fn get_tokens(&self) -> Option<Vec<&Token>> {
-        match self {
-            Token::Heading { tokens, .. }
-            | Token::Paragraph { tokens, .. }
-            | Token::Link { tokens, .. }
-            | Token::Image { tokens, .. }
-            | Token::Html { tokens, .. } => Some(tokens.iter().collect()),
-            Token::BulletList { items, .. } | Token::OrderedList { items, .. } => {
-                let tokens = items.iter().flatten().collect();
-                Some(tokens)
-            }
-            _ => None,
-        }
+        let tokens = self.children();
+        (!tokens.is_empty()).then_some(tokens)
     }
 
     pub fn to_text(&self) -> Option<String> {
@@ -168,7 +215,7 @@ impl Token {
         }
         Some(
             match self {
-                Token::Bold { text, .. } => text,
+                Token::Bold { text, .. } | Token::Strikethrough { text } => text,
                 Token::Text { text, .. } => text,
                 Token::SoftBreak { .. } => " ",
                 _ => return None,
@@ -186,6 +233,8 @@ impl Token {
             | Token::Html { .. }
             | Token::Paragraph { .. }
             | Token::BlockQuote { .. }
+            | Token::Table { .. }
+            | Token::FootnoteDefinition { .. }
             | Token::Code { .. } => true,
             _ => false,
         }
@@ -3,11 +3,18 @@ use std::io::Read;
 use crate::{char_reader::CharReader, parse_error::ParseError};
 
 mod block_token;
+mod code_info;
 mod html;
 mod inline_token;
 mod lexer;
 mod tokenizer;
+mod visit;
+mod writer;
+pub use code_info::CodeInfo;
+pub use inline_token::wiki_link_target;
 pub use lexer::*;
+pub use visit::{visit_tokens, walk_tokens};
+pub use writer::{is_equivalent, to_markdown, to_markdown_wrapped};
 
 /// Remove any tailing new line or starting and ending spaces
 fn sanitize_text(text: String) -> String {
@@ -32,16 +39,18 @@ pub fn parse_lmarkdown(input: impl Read) -> Result<Vec<Token>, ParseError> {
 
 #[cfg(test)]
 mod tests {
-    use std::{collections::HashMap, io::Cursor, io::Read};
+    use std::{io::Cursor, io::Read};
+
+    use virtual_dom::{Atom, Attributes};
 
     use toml::Table;
 
-    use super::{parse_lmarkdown, Token};
+    use super::{parse_lmarkdown, Alignment, Token};
 
     /// Utility function to convert iteratables into attributes hashmap
-    fn to_attributes<I: IntoIterator<Item = (impl Into<String>, impl Into<String>)>>(
+    fn to_attributes<I: IntoIterator<Item = (impl Into<Atom>, impl Into<String>)>>(
         arr: I,
-    ) -> HashMap<String, String> {
+    ) -> Attributes {
         arr.into_iter().map(|(k, v)| (k.into(), v.into())).collect()
     }
 
@@ -175,7 +184,7 @@ another comment
             },
             Token::Html {
                 tag: "div".into(),
-                attributes: HashMap::new(),
+                attributes: Attributes::new(),
                 tokens: vec![
                     Token::Link {
                         tokens: vec![],
@@ -186,7 +195,7 @@ another comment
                     Token::Link {
                         tokens: vec![Token::Html {
                             tag: "b".into(),
-                            attributes: HashMap::new(),
+                            attributes: Attributes::new(),
                             tokens: vec![Token::Text {
                                 text: "bold".into(),
                             }],
@@ -214,6 +223,33 @@ another comment
         assert_eq!(expected, tokens);
     }
 
+    #[test]
+    fn test_raw_text_html() {
+        let script = "\nif (a < b) { x = \"*not emphasis*\" }\n# not a heading\n";
+        let input = format!("<script>{script}</script>\n\nInline <style>a > b {{}}</style>");
+        let expected = vec![
+            Token::Html {
+                tag: "script".into(),
+                attributes: Attributes::new(),
+                tokens: vec![text(script)],
+            },
+            Token::Paragraph {
+                text: "Inline <style>a > b {}</style>".into(),
+                tokens: vec![
+                    text("Inline "),
+                    Token::Html {
+                        tag: "style".into(),
+                        attributes: Attributes::new(),
+                        tokens: vec![text("a > b {}")],
+                    },
+                ],
+            },
+        ];
+
+        let tokens = parse_lmarkdown(input.as_bytes()).unwrap();
+        assert_eq!(expected, tokens);
+    }
+
     #[test]
     fn test_setext_heading() {
         let input = r#"Foo *bar*
@@ -259,6 +295,48 @@ Foo *bar*
                 tokens: vec![text("one"), Token::SoftBreak, text("two")],
                 text: "one\ntwo\n".into(),
             }]],
+            checked: vec![None],
+        }];
+
+        let reader: Box<dyn Read> = Box::new(Cursor::new(input));
+        let tokens = parse_lmarkdown(reader).unwrap();
+        assert_eq!(expected, tokens);
+    }
+
+    /// Examples from https://github.github.com/gfm/#task-list-items-extension-
+    #[test]
+    fn test_task_list() {
+        let input = "- [ ] foo\n- [x] bar\n- [X]baz\n- [ ]\n";
+        let item = |t: &str, raw: &str| {
+            vec![Token::Paragraph {
+                tokens: vec![text(t)],
+                text: raw.into(),
+            }]
+        };
+        let expected = vec![Token::BulletList {
+            items: vec![
+                item("foo", "foo\n"),
+                item("bar", "bar\n"),
+                item("[X]baz", "[X]baz\n"),
+                item("[ ]", "[ ]\n"),
+            ],
+            checked: vec![Some(false), Some(true), None, None],
+        }];
+
+        let reader: Box<dyn Read> = Box::new(Cursor::new(input));
+        let tokens = parse_lmarkdown(reader).unwrap();
+        assert_eq!(expected, tokens);
+    }
+
+    #[test]
+    fn test_strikethrough() {
+        let input = "~~Hi~~ Hello, ~there~ ~~~world~~!";
+        let expected = vec![Token::Paragraph {
+            tokens: vec![
+                Token::Strikethrough { text: "Hi".into() },
+                text(" Hello, ~there~ ~~~world~~!"),
+            ],
+            text: input.into(),
         }];
 
         let reader: Box<dyn Read> = Box::new(Cursor::new(input));
@@ -378,6 +456,23 @@ indented code block"
         assert_eq!(expected, tokens);
     }
 
+    #[test]
+    fn test_image_title() {
+        let input = r#"![alt](./image.png "A title")"#;
+        let expected = vec![Token::Paragraph {
+            text: input.into(),
+            tokens: vec![Token::Image {
+                tokens: vec![text("alt")],
+                src: "./image.png".into(),
+                title: Some("A title".into()),
+            }],
+        }];
+
+        let reader: Box<dyn Read> = Box::new(Cursor::new(input));
+        let tokens = parse_lmarkdown(reader).unwrap();
+        assert_eq!(expected, tokens);
+    }
+
     #[test]
     fn test_hard_line_break() {
         let input = r#"foo  
@@ -418,4 +513,221 @@ baz"#;
         let tokens = parse_lmarkdown(reader).unwrap();
         assert_eq!(expected, tokens);
     }
+
+    /// Table body or header row with a text token for every non empty cell
+    fn row(cells: &[&str]) -> Vec<Vec<Token>> {
+        cells
+            .iter()
+            .map(|c| match c.is_empty() {
+                true => vec![],
+                false => vec![text(c)],
+            })
+            .collect()
+    }
+
+    /// Examples from https://github.github.com/gfm/#tables-extension-
+    #[test]
+    fn test_table() {
+        let parse = |input: &str| parse_lmarkdown(input.as_bytes()).unwrap();
+        let table =
+            |align: Vec<Option<Alignment>>, header: &[&str], rows: &[&[&str]]| Token::Table {
+                align,
+                header: row(header),
+                rows: rows.iter().map(|r| row(r)).collect(),
+            };
+
+        assert_eq!(
+            parse("| foo | bar |\n| --- | --- |\n| baz | bim |"),
+            vec![table(vec![None, None], &["foo", "bar"], &[&["baz", "bim"]])]
+        );
+        assert_eq!(
+            parse("| abc | defghi |\n:-: | -----------:\nbar | baz"),
+            vec![table(
+                vec![Some(Alignment::Center), Some(Alignment::Right)],
+                &["abc", "defghi"],
+                &[&["bar", "baz"]]
+            )]
+        );
+        assert_eq!(
+            parse("| f\\|oo  |\n| ------ |\n| b `\\|` az |\n| b **\\|** im |"),
+            vec![Token::Table {
+                align: vec![None],
+                header: row(&["f|oo"]),
+                rows: vec![
+                    vec![vec![
+                        text("b "),
+                        Token::Code {
+                            info: None,
+                            text: "|".into()
+                        },
+                        text(" az")
+                    ]],
+                    vec![vec![
+                        text("b "),
+                        Token::Bold { text: "|".into() },
+                        text(" im")
+                    ]],
+                ]
+            }]
+        );
+        // interrupted by a block quote
+        assert_eq!(
+            parse("| abc | def |\n| --- | --- |\n| bar | baz |\n> bar"),
+            vec![
+                table(vec![None, None], &["abc", "def"], &[&["bar", "baz"]]),
+                Token::BlockQuote {
+                    tokens: vec![p(vec![text("bar")])]
+                }
+            ]
+        );
+        // ends at a blank line
+        assert_eq!(
+            parse("| abc | def |\n| --- | --- |\n| bar | baz |\nbar\n\nbar"),
+            vec![
+                table(
+                    vec![None, None],
+                    &["abc", "def"],
+                    &[&["bar", "baz"], &["bar", ""]]
+                ),
+                p(vec![text("bar")])
+            ]
+        );
+        // header has to match the delimiter row
+        assert!(!matches!(
+            parse("| abc | def |\n| --- |\n| bar |")[0],
+            Token::Table { .. }
+        ));
+        // missing cells are empty and excess cells are ignored
+        assert_eq!(
+            parse("| abc | def |\n| --- | --- |\n| bar |\n| bar | baz | boo |"),
+            vec![table(
+                vec![None, None],
+                &["abc", "def"],
+                &[&["bar", ""], &["bar", "baz"]]
+            )]
+        );
+        assert_eq!(
+            parse("| abc | def |\n| --- | --- |"),
+            vec![table(vec![None, None], &["abc", "def"], &[])]
+        );
+    }
+
+    #[test]
+    fn test_footnotes() {
+        let input = "Text[^1] and [^ not] [^].\n\n[^1]: Note with **bold**\n    on two lines\n\n    Second paragraph\n\nAfter\n";
+        let reference = |label: &str| Token::FootnoteReference {
+            label: label.into(),
+        };
+        let expected = vec![
+            Token::Paragraph {
+                tokens: vec![text("Text"), reference("1"), text(" and [^ not] [^].")],
+                text: "Text[^1] and [^ not] [^].\n".into(),
+            },
+            Token::FootnoteDefinition {
+                label: "1".into(),
+                tokens: vec![
+                    Token::Paragraph {
+                        tokens: vec![
+                            text("Note with "),
+                            Token::Bold {
+                                text: "bold".into(),
+                            },
+                            Token::SoftBreak,
+                            text("on two lines"),
+                        ],
+                        text: "Note with **bold**\non two lines\n".into(),
+                    },
+                    p(vec![text("Second paragraph")]),
+                ],
+            },
+            Token::Paragraph {
+                tokens: vec![text("After")],
+                text: "After\n".into(),
+            },
+        ];
+
+        let reader: Box<dyn Read> = Box::new(Cursor::new(input));
+        let tokens = parse_lmarkdown(reader).unwrap();
+        assert_eq!(expected, tokens);
+    }
+
+    #[test]
+    fn test_non_ascii() {
+        let input =
+            "Über [Привет](./Привет мир.md) **ß** *é* ![Café](./café.png) <https://例え.jp>";
+        let expected = vec![Token::Paragraph {
+            tokens: vec![
+                text("Über "),
+                Token::Link {
+                    tokens: vec![text("Привет")],
+                    href: "./Привет мир.md".into(),
+                    title: None,
+                },
+                text(" "),
+                Token::Bold { text: "ß".into() },
+                text(" "),
+                Token::Emphasis { text: "é".into() },
+                text(" "),
+                Token::Image {
+                    tokens: vec![text("Café")],
+                    src: "./café.png".into(),
+                    title: None,
+                },
+                text(" "),
+                Token::Link {
+                    tokens: vec![text("https://例え.jp")],
+                    href: "https://例え.jp".into(),
+                    title: None,
+                },
+            ],
+            text: input.into(),
+        }];
+
+        let reader: Box<dyn Read> = Box::new(Cursor::new(input));
+        let tokens = parse_lmarkdown(reader).unwrap();
+        assert_eq!(expected, tokens);
+    }
+
+    #[test]
+    fn test_wiki_link() {
+        let input = r#"[[Page Name]] [[notes/Other#Heading | other]] [[not
+a link]]"#;
+        let expected = vec![Token::Paragraph {
+            tokens: vec![
+                Token::Link {
+                    tokens: vec![text("Page Name")],
+                    href: "[[Page Name]]".into(),
+                    title: None,
+                },
+                text(" "),
+                Token::Link {
+                    tokens: vec![text("other")],
+                    href: "[[notes/Other#Heading]]".into(),
+                    title: None,
+                },
+                text(" [[not"),
+                Token::SoftBreak,
+                text("a link]]"),
+            ],
+            text: input.into(),
+        }];
+
+        let reader: Box<dyn Read> = Box::new(Cursor::new(input));
+        let tokens = parse_lmarkdown(reader).unwrap();
+        assert_eq!(expected, tokens);
+
+        // non-ascii text is consumed by characters
+        let input = "[[Café]] après";
+        let expected = vec![
+            Token::Link {
+                tokens: vec![text("Café")],
+                href: "[[Café]]".into(),
+                title: None,
+            },
+            text(" après"),
+        ];
+        let mut reader = crate::char_reader::CharReader::new(input.as_bytes());
+        let tokens = super::inline_token::read_inline_tokens(&mut reader).unwrap();
+        assert_eq!(expected, tokens);
+    }
 }
@@ -0,0 +1,94 @@
+use super::Token;
+
+impl Token {
+    /// Direct children of a token, list items and table cells are flattened
+    pub fn children(&self) -> Vec<&Token> {
+        match self {
+            Token::Heading { tokens, .. }
+            | Token::Paragraph { tokens, .. }
+            | Token::BlockQuote { tokens }
+            | Token::FootnoteDefinition { tokens, .. }
+            | Token::Link { tokens, .. }
+            | Token::Image { tokens, .. }
+            | Token::Html { tokens, .. } => tokens.iter().collect(),
+            Token::BulletList { items, .. } | Token::OrderedList { items } => {
+                items.iter().flatten().collect()
+            }
+            Token::Table { header, rows, .. } => header
+                .iter()
+                .chain(rows.iter().flatten())
+                .flatten()
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Mutable direct children of a token, list items and table cells are flattened
+    pub fn children_mut(&mut self) -> Vec<&mut Token> {
+        match self {
+            Token::Heading { tokens, .. }
+            | Token::Paragraph { tokens, .. }
+            | Token::BlockQuote { tokens }
+            | Token::FootnoteDefinition { tokens, .. }
+            | Token::Link { tokens, .. }
+            | Token::Image { tokens, .. }
+            | Token::Html { tokens, .. } => tokens.iter_mut().collect(),
+            Token::BulletList { items, .. } | Token::OrderedList { items } => {
+                items.iter_mut().flatten().collect()
+            }
+            Token::Table { header, rows, .. } => header
+                .iter_mut()
+                .chain(rows.iter_mut().flatten())
+                .flatten()
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Call `f` for every token in `tokens` and all of their children, parents before children
+pub fn visit_tokens<'a>(tokens: &'a [Token], mut f: impl FnMut(&'a Token)) {
+    let mut queue: Vec<&'a Token> = tokens.iter().rev().collect();
+    while let Some(token) = queue.pop() {
+        f(token);
+        queue.extend(token.children().into_iter().rev());
+    }
+}
+
+/// Call `f` for every token in `tokens` and all of their children, parents before children
+///
+/// `f` may replace the token it is given, the children of the new token are walked afterwards.
+pub fn walk_tokens(tokens: &mut [Token], mut f: impl FnMut(&mut Token)) {
+    let mut queue: Vec<&mut Token> = tokens.iter_mut().rev().collect();
+    while let Some(token) = queue.pop() {
+        f(token);
+        queue.extend(token.children_mut().into_iter().rev());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lmarkdown::parse_lmarkdown;
+
+    use super::{visit_tokens, walk_tokens, Token};
+
+    #[test]
+    fn test_walk_tokens() {
+        let input = "[a](a.md)\n\n> - [b](b.md)\n>   <div>\n>   [c](c.md)\n>   </div>\n";
+        let mut tokens = parse_lmarkdown(input.as_bytes()).unwrap();
+
+        walk_tokens(&mut tokens, |token| {
+            if let Token::Link { href, .. } = token {
+                *href = href.replace(".md", ".html");
+            }
+        });
+
+        let mut hrefs = vec![];
+        visit_tokens(&tokens, |token| {
+            if let Token::Link { href, .. } = token {
+                hrefs.push(href.as_str());
+            }
+        });
+        assert_eq!(hrefs, vec!["a.html", "b.html", "c.html"]);
+    }
+}
@@ -0,0 +1,550 @@
+use virtual_dom::Attributes;
+
+use super::{wiki_link_target, Alignment, Token};
+
+/// Serialize block tokens back to lmarkdown, parsing the result gives back the same tokens
+pub fn to_markdown(tokens: &[Token]) -> String {
+    to_markdown_wrapped(tokens, None)
+}
+
+/// Serialize block tokens back to lmarkdown wrapping paragraphs at `width` characters
+pub fn to_markdown_wrapped(tokens: &[Token], width: Option<usize>) -> String {
+    let mut markdown = write_blocks(tokens, width);
+    markdown.push('\n');
+    markdown
+}
+
+/// Check if `a` and `b` have the same structure, ignoring the source text kept by some tokens
+/// and how text is wrapped
+pub fn is_equivalent(a: &[Token], b: &[Token]) -> bool {
+    without_source(a.to_vec()) == without_source(b.to_vec())
+}
+
+/// Remove the raw source text kept by some tokens, it is not part of the structure, and turn soft
+/// breaks into spaces
+fn without_source(tokens: Vec<Token>) -> Vec<Token> {
+    let mut normalized: Vec<Token> = vec![];
+    for token in tokens {
+        let text = match token {
+            Token::SoftBreak => " ".to_string(),
+            Token::Text { text } => text,
+            token => {
+                normalized.push(token);
+                continue;
+            }
+        };
+        // collapse whitespace, keeping a single space at the start and end
+        let mut collapsed = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if text.starts_with(char::is_whitespace) {
+            collapsed.insert(0, ' ');
+        }
+        if text.ends_with(char::is_whitespace) && !collapsed.trim().is_empty() {
+            collapsed.push(' ');
+        }
+        let text = collapsed;
+        match normalized.last_mut() {
+            Some(Token::Text { text: previous }) => {
+                if previous.ends_with(' ') && text.starts_with(' ') {
+                    previous.push_str(&text[1..]);
+                } else {
+                    previous.push_str(&text);
+                }
+            }
+            _ => normalized.push(Token::Text { text }),
+        }
+    }
+    normalized
+        .into_iter()
+        .map(|token| match token {
+            Token::Paragraph { tokens, .. } => Token::Paragraph {
+                text: String::new(),
+                tokens: without_source(tokens),
+            },
+            Token::Heading { tokens, depth, .. } => Token::Heading {
+                text: String::new(),
+                tokens: without_source(tokens),
+                depth,
+            },
+            Token::BlockQuote { tokens } => Token::BlockQuote {
+                tokens: without_source(tokens),
+            },
+            Token::FootnoteDefinition { label, tokens } => Token::FootnoteDefinition {
+                label,
+                tokens: without_source(tokens),
+            },
+            Token::Html {
+                tokens,
+                tag,
+                attributes,
+            } => Token::Html {
+                tokens: without_source(tokens),
+                tag,
+                attributes,
+            },
+            Token::Table {
+                align,
+                header,
+                rows,
+            } => Token::Table {
+                align,
+                header: header.into_iter().map(without_source).collect(),
+                rows: rows
+                    .into_iter()
+                    .map(|row| row.into_iter().map(without_source).collect())
+                    .collect(),
+            },
+            Token::BulletList { items, checked } => Token::BulletList {
+                items: items.into_iter().map(without_source).collect(),
+                checked,
+            },
+            Token::OrderedList { items } => Token::OrderedList {
+                items: items.into_iter().map(without_source).collect(),
+            },
+            token => token,
+        })
+        .collect()
+}
+
+/// Write block tokens separated by an empty line
+fn write_blocks(tokens: &[Token], width: Option<usize>) -> String {
+    tokens
+        .iter()
+        .map(|t| write_block(t, width))
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+/// Sort all keys of `table` and its sub tables
+fn sorted(table: &toml::Table) -> toml::Table {
+    let mut keys: Vec<&String> = table.keys().collect();
+    keys.sort();
+    keys.into_iter()
+        .map(|key| {
+            let value = match &table[key] {
+                toml::Value::Table(table) => toml::Value::Table(sorted(table)),
+                value => value.clone(),
+            };
+            (key.clone(), value)
+        })
+        .collect()
+}
+
+fn write_block(token: &Token, width: Option<usize>) -> String {
+    match token {
+        Token::Attributes { table } => {
+            let toml = toml::to_string(&sorted(table)).unwrap_or_default();
+            format!("<!--\n{toml}-->")
+        }
+        Token::Heading { tokens, depth, .. } => {
+            format!("{} {}", "#".repeat(*depth as usize), write_inline(tokens))
+        }
+        Token::Paragraph { tokens, .. } => match width {
+            Some(width) => wrap(tokens, width),
+            None => write_inline(tokens),
+        },
+        Token::BulletList { items, checked } => {
+            write_list(items, checked, width, |_| "- ".to_string())
+        }
+        Token::OrderedList { items } => write_list(items, &[], width, |i| format!("{}. ", i + 1)),
+        Token::BlockQuote { tokens } => write_blocks(tokens, width.map(|w| w.saturating_sub(2)))
+            .lines()
+            .map(|line| format!("> {line}").trim_end().to_string())
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Token::FootnoteDefinition { label, tokens } => {
+            write_blocks(tokens, width.map(|w| w.saturating_sub(4)))
+                .lines()
+                .enumerate()
+                .map(|(i, line)| match (i, line.is_empty()) {
+                    (0, _) => format!("[^{label}]: {line}"),
+                    (_, true) => String::new(),
+                    (_, false) => format!("    {line}"),
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        }
+        Token::Table {
+            align,
+            header,
+            rows,
+        } => {
+            let row = |cells: &[Vec<Token>]| {
+                let cells: Vec<String> = cells
+                    .iter()
+                    .map(|cell| write_inline(cell).replace('|', "\\|"))
+                    .collect();
+                format!("| {} |", cells.join(" | "))
+            };
+            let delimiter: Vec<&str> = align
+                .iter()
+                .map(|align| match align {
+                    Some(Alignment::Left) => ":--",
+                    Some(Alignment::Center) => ":-:",
+                    Some(Alignment::Right) => "--:",
+                    None => "---",
+                })
+                .collect();
+            let mut lines = vec![row(header), format!("| {} |", delimiter.join(" | "))];
+            lines.extend(rows.iter().map(|cells| row(cells)));
+            lines.join("\n")
+        }
+        Token::Code { info, text } => {
+            let fence = fence(text, '`', 3);
+            let text = text.strip_suffix('\n').unwrap_or(text);
+            format!("{fence}{}\n{text}\n{fence}", info.as_deref().unwrap_or(""))
+        }
+        Token::Html {
+            tokens,
+            tag,
+            attributes,
+        } => {
+            if tokens.is_empty() {
+                return write_html(tag, attributes, None);
+            }
+            let content = if tokens.iter().any(|t| t.is_block_token()) {
+                write_blocks(tokens, width)
+            } else {
+                write_inline(tokens)
+            };
+            write_html(tag, attributes, Some(&format!("\n{content}\n")))
+        }
+        Token::Comment { raw } => format!("<!--{raw}-->"),
+        Token::ThematicBreak => "---".to_string(),
+        // inline token at the root, write it as its own paragraph
+        token => write_inline(std::slice::from_ref(token)),
+    }
+}
+
+/// Write list items with their content indented to the width of the marker, items that are
+/// `checked` start with a task list marker
+fn write_list(
+    items: &[Vec<Token>],
+    checked: &[Option<bool>],
+    width: Option<usize>,
+    marker: impl Fn(usize) -> String,
+) -> String {
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, blocks)| {
+            let marker = marker(i);
+            let indent = " ".repeat(marker.len());
+            let mut item = match checked.get(i) {
+                Some(Some(true)) => "[x] ".to_string(),
+                Some(Some(false)) => "[ ] ".to_string(),
+                _ => String::new(),
+            };
+            // nested lists directly follow the item text, other blocks need an empty line
+            for (j, block) in blocks.iter().enumerate() {
+                if j > 0 {
+                    item.push('\n');
+                    if !matches!(block, Token::BulletList { .. } | Token::OrderedList { .. }) {
+                        item.push('\n');
+                    }
+                }
+                item.push_str(&write_block(
+                    block,
+                    width.map(|w| w.saturating_sub(indent.len())),
+                ));
+            }
+            item.lines()
+                .enumerate()
+                .map(|(j, line)| match (j, line.is_empty()) {
+                    (0, _) => format!("{marker}{line}"),
+                    (_, true) => String::new(),
+                    (_, false) => format!("{indent}{line}"),
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn write_inline(tokens: &[Token]) -> String {
+    let mut markdown = String::new();
+    for token in tokens {
+        match token {
+            Token::Text { text } => markdown.push_str(text),
+            Token::Bold { text } => markdown.push_str(&format!("**{text}**")),
+            Token::Emphasis { text } => markdown.push_str(&format!("*{text}*")),
+            Token::Strikethrough { text } => markdown.push_str(&format!("~~{text}~~")),
+            Token::Code { text, .. } => {
+                let fence = fence(text, '`', 1);
+                // keep backticks at the edges apart from the fence
+                if text.starts_with('`') || text.ends_with('`') {
+                    markdown.push_str(&format!("{fence} {text} {fence}"))
+                } else {
+                    markdown.push_str(&format!("{fence}{text}{fence}"))
+                }
+            }
+            Token::Link { tokens, href, .. } if wiki_link_target(href).is_some() => {
+                let target = wiki_link_target(href).expect("is a wiki link");
+                let text = write_inline(tokens);
+                match text == target {
+                    true => markdown.push_str(&format!("[[{target}]]")),
+                    false => markdown.push_str(&format!("[[{target}|{text}]]")),
+                }
+            }
+            Token::Link {
+                tokens,
+                href,
+                title,
+            } => markdown.push_str(&format!(
+                "[{}]({href}{})",
+                write_inline(tokens),
+                write_title(title)
+            )),
+            Token::Image { tokens, src, title } => markdown.push_str(&format!(
+                "![{}]({src}{})",
+                write_inline(tokens),
+                write_title(title)
+            )),
+            Token::Html {
+                tokens,
+                tag,
+                attributes,
+            } => {
+                let content = (!tokens.is_empty()).then(|| write_inline(tokens));
+                markdown.push_str(&write_html(tag, attributes, content.as_deref()))
+            }
+            Token::Comment { raw } => markdown.push_str(&format!("<!--{raw}-->")),
+            Token::FootnoteReference { label } => markdown.push_str(&format!("[^{label}]")),
+            Token::HardBreak => markdown.push_str("\\\n"),
+            Token::SoftBreak => markdown.push('\n'),
+            // block tokens inside of inline content (eg. html blocks)
+            token => markdown.push_str(&write_block(token, None)),
+        }
+    }
+    markdown
+}
+
+/// Could `word` be mistaken for the start of a block when it starts a line
+fn starts_block(word: &str) -> bool {
+    let digits = word.trim_start_matches(|c: char| c.is_ascii_digit());
+    matches!(word, "-" | "+" | "*")
+        || word.starts_with(['#', '>', '=', '<', '`', '|'])
+        || word.starts_with("---")
+        || (word.starts_with("[^") && word.contains("]:"))
+        || (digits.len() < word.len() && (digits.starts_with('.') || digits.starts_with(')')))
+}
+
+/// Write inline tokens reflowed to lines of at most `width` characters, tokens other than text
+/// are never split
+fn wrap(tokens: &[Token], width: usize) -> String {
+    // words with `None` for hard breaks
+    let mut words: Vec<Option<String>> = vec![];
+    let mut word = String::new();
+    for token in tokens {
+        match token {
+            Token::Text { text } => {
+                for c in text.chars() {
+                    if c.is_whitespace() {
+                        if !word.is_empty() {
+                            words.push(Some(std::mem::take(&mut word)));
+                        }
+                    } else {
+                        word.push(c);
+                    }
+                }
+            }
+            Token::SoftBreak => {
+                if !word.is_empty() {
+                    words.push(Some(std::mem::take(&mut word)));
+                }
+            }
+            Token::HardBreak => {
+                if !word.is_empty() {
+                    words.push(Some(std::mem::take(&mut word)));
+                }
+                words.push(None);
+            }
+            token => word.push_str(&write_inline(std::slice::from_ref(token))),
+        }
+    }
+    if !word.is_empty() {
+        words.push(Some(word));
+    }
+
+    let mut markdown = String::new();
+    let mut line_length = 0;
+    for word in words {
+        let Some(word) = word else {
+            markdown.push_str("\\\n");
+            line_length = 0;
+            continue;
+        };
+        let length = word.chars().count();
+        if line_length > 0 {
+            if line_length + 1 + length > width && !starts_block(&word) {
+                markdown.push('\n');
+                line_length = 0;
+            } else {
+                markdown.push(' ');
+                line_length += 1;
+            }
+        }
+        markdown.push_str(&word);
+        line_length += length;
+    }
+    markdown
+}
+
+fn write_title(title: &Option<String>) -> String {
+    match title {
+        Some(title) if title.contains('"') => format!(" '{title}'"),
+        Some(title) => format!(" \"{title}\""),
+        None => String::new(),
+    }
+}
+
+fn write_html(tag: &str, attributes: &Attributes, content: Option<&str>) -> String {
+    let mut start_tag = tag.to_string();
+    for (key, value) in attributes {
+        if value.is_empty() {
+            start_tag.push_str(&format!(" {key}"));
+        } else if value.contains('"') {
+            start_tag.push_str(&format!(" {key}='{value}'"));
+        } else {
+            start_tag.push_str(&format!(" {key}=\"{value}\""));
+        }
+    }
+    match content {
+        Some(content) => format!("<{start_tag}>{content}</{tag}>"),
+        None if is_void_element(tag) => format!("<{start_tag}/>"),
+        None => format!("<{start_tag}></{tag}>"),
+    }
+}
+
+fn is_void_element(tag: &str) -> bool {
+    matches!(
+        tag,
+        "base"
+            | "img"
+            | "br"
+            | "col"
+            | "embed"
+            | "hr"
+            | "area"
+            | "input"
+            | "link"
+            | "meta"
+            | "param"
+            | "source"
+            | "track"
+            | "wbr"
+    )
+}
+
+/// Fence longer than any run of `c` in `text`
+fn fence(text: &str, c: char, minimum: usize) -> String {
+    let mut longest = 0;
+    let mut current = 0;
+    for t in text.chars() {
+        if t == c {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    c.to_string().repeat(minimum.max(longest + 1))
+}
+
+impl Token {
+    /// Serialize this token to lmarkdown, see [`to_markdown`]
+    pub fn to_markdown(&self) -> String {
+        to_markdown(std::slice::from_ref(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lmarkdown::parse_lmarkdown;
+
+    use super::{is_equivalent, to_markdown, to_markdown_wrapped};
+
+    #[test]
+    fn test_round_trip() {
+        let input = r#"<!--
+title = "Round trip"
+
+[blog]
+root = true
+-->
+# Heading with *emphasis* and **bold**
+
+A paragraph
+over two lines\
+with a `code` span, an ![image](./image.png "Title") and a [link](./page.md).
+See [[Other Page]] and [[Other Page|this page]].
+
+- item
+- item with **bold** and ~~struck~~ text
+  - nested item
+
+- [ ] open task
+- [x] done task
+
+1. first
+2. second
+
+> quoted text
+
+A footnote[^1] and another[^note].
+
+[^1]: Footnote text
+
+[^note]: First paragraph
+    with a second line
+
+    - and a list
+
+| Name | `a\|b` | Count |
+| :-- | :-: | --: |
+| **x** | y |
+| [link](./page.md) | z | 1 |
+
+```rust
+fn main() {}
+```
+
+<div class="note">
+Some **html** content
+</div>
+
+<!-- a comment -->
+"#;
+        let tokens = parse_lmarkdown(input.as_bytes()).unwrap();
+        let markdown = to_markdown(&tokens);
+        let round_trip = parse_lmarkdown(markdown.as_bytes()).unwrap();
+        assert!(is_equivalent(&tokens, &round_trip));
+        // writing is stable
+        assert_eq!(
+            markdown,
+            to_markdown(&parse_lmarkdown(markdown.as_bytes()).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        let input = "A long paragraph with a [link that is long](./page.md) and **bold text** \
+                     that wraps, - not a list\n";
+        let tokens = parse_lmarkdown(input.as_bytes()).unwrap();
+        let markdown = to_markdown_wrapped(&tokens, Some(30));
+        assert_eq!(
+            markdown,
+            "A long paragraph with a\n[link that is long](./page.md)\nand **bold text** that wraps, -\nnot a list\n"
+        );
+        let round_trip = parse_lmarkdown(markdown.as_bytes()).unwrap();
+        assert!(is_equivalent(&tokens, &round_trip));
+        assert_eq!(
+            to_markdown(&tokens),
+            to_markdown(&round_trip)
+                .replace('\n', " ")
+                .trim_end()
+                .to_string()
+                + "\n"
+        );
+    }
+}
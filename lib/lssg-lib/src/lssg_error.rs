@@ -1,11 +1,12 @@
 use core::fmt;
 use std::io;
 
+use image::ImageError;
 use zip::result::ZipError;
 
 use crate::parse_error::ParseError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LssgErrorKind {
     ParseError,
     /// Render error
@@ -14,13 +15,14 @@ pub enum LssgErrorKind {
     /// Error with the sitetree
     SiteTree,
     Io,
+    /// Warnings that fail the build in strict mode
+    Strict,
 }
 
 #[derive(Debug)]
 pub struct LssgError {
     message: String,
     context: Option<String>,
-    #[allow(dead_code)]
     kind: LssgErrorKind,
 }
 impl LssgError {
@@ -44,6 +46,14 @@ impl LssgError {
         Self::new(message, LssgErrorKind::Io)
     }
 
+    pub fn strict<S: Into<String>>(message: S) -> LssgError {
+        Self::new(message, LssgErrorKind::Strict)
+    }
+
+    pub fn kind(&self) -> LssgErrorKind {
+        self.kind
+    }
+
     pub fn with_context(mut self, context: impl Into<String>) -> Self {
         self.context = Some(context.into());
         self
@@ -69,6 +79,11 @@ impl From<ZipError> for LssgError {
         Self::new(&error.to_string(), LssgErrorKind::Io)
     }
 }
+impl From<ImageError> for LssgError {
+    fn from(error: ImageError) -> Self {
+        Self::new(&error.to_string(), LssgErrorKind::Io)
+    }
+}
 impl std::error::Error for LssgError {}
 
 impl fmt::Display for LssgError {
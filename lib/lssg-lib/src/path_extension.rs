@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 use crate::LssgError;
 
@@ -10,20 +10,17 @@ pub trait PathExtension {
 
 impl PathExtension for Path {
     fn canonicalize_nonexistent_path(&self) -> PathBuf {
-        let mut canonicalized_path = vec![];
-        let path = self.to_string_lossy();
-        let parts = path.split("/");
-        for p in parts {
-            if p == "." {
-                continue;
+        let mut canonicalized_path = PathBuf::new();
+        for component in self.components() {
+            match component {
+                Component::CurDir => continue,
+                Component::ParentDir if canonicalized_path.file_name().is_some() => {
+                    canonicalized_path.pop();
+                }
+                c => canonicalized_path.push(c),
             }
-            if p == ".." && canonicalized_path.len() > 0 {
-                canonicalized_path.pop();
-                continue;
-            }
-            canonicalized_path.push(p);
         }
-        PathBuf::from(canonicalized_path.join("/"))
+        canonicalized_path
     }
 
     /// Make it easier to get the filestem parsed as string
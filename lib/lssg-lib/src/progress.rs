@@ -0,0 +1,147 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+/// Slow parts of a build that report their progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Downloading remote pages, stylesheets and files
+    Fetching,
+    /// Rendering pages to html
+    Rendering,
+}
+impl Phase {
+    /// What is counted in this phase
+    pub fn items(&self) -> &'static str {
+        match self {
+            Phase::Fetching => "files",
+            Phase::Rendering => "pages",
+        }
+    }
+}
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Phase::Fetching => "Fetching",
+            Phase::Rendering => "Rendering",
+        })
+    }
+}
+
+/// Receives the progress of builds, set with [`set_reporter`] to show it in your own UI
+pub trait ProgressReporter: Send + Sync {
+    /// `total` items are going to be processed in `phase`
+    fn start(&self, _phase: Phase, _total: usize) {}
+
+    /// `done` out of `total` items of `phase` are processed, `elapsed` since the start of `phase`
+    fn update(&self, phase: Phase, done: usize, total: usize, elapsed: Duration);
+
+    fn finish(&self, _phase: Phase, _total: usize, _elapsed: Duration) {}
+}
+
+/// Logs the progress at most every [`LogReporter::INTERVAL`] with an estimate of the time left
+pub struct LogReporter {
+    last_log: Mutex<Instant>,
+}
+impl LogReporter {
+    pub const INTERVAL: Duration = Duration::from_secs(2);
+
+    pub fn new() -> LogReporter {
+        LogReporter {
+            last_log: Mutex::new(Instant::now()),
+        }
+    }
+}
+impl Default for LogReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl ProgressReporter for LogReporter {
+    fn start(&self, _phase: Phase, _total: usize) {
+        *self.last_log.lock().unwrap() = Instant::now();
+    }
+
+    fn update(&self, phase: Phase, done: usize, total: usize, elapsed: Duration) {
+        let mut last_log = self.last_log.lock().unwrap();
+        if last_log.elapsed() < Self::INTERVAL || done == 0 {
+            return;
+        }
+        *last_log = Instant::now();
+        let left = elapsed.mul_f64((total - done) as f64 / done as f64);
+        info!(
+            "{phase} {} {done}/{total} ({}%), about {}s left",
+            phase.items(),
+            done * 100 / total,
+            left.as_secs() + 1
+        );
+    }
+
+    fn finish(&self, phase: Phase, total: usize, elapsed: Duration) {
+        info!(
+            "{phase} {total} {} done in {:.1}s",
+            phase.items(),
+            elapsed.as_secs_f64()
+        );
+    }
+}
+
+static REPORTER: RwLock<Option<Arc<dyn ProgressReporter>>> = RwLock::new(None);
+
+/// Set where the progress of builds is reported to, defaults to [`LogReporter`]
+pub fn set_reporter(reporter: impl ProgressReporter + 'static) {
+    *REPORTER.write().unwrap() = Some(Arc::new(reporter));
+}
+
+fn reporter() -> Arc<dyn ProgressReporter> {
+    if let Some(reporter) = REPORTER.read().unwrap().as_ref() {
+        return reporter.clone();
+    }
+    REPORTER
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(LogReporter::new()))
+        .clone()
+}
+
+/// Progress of a single phase, can be advanced from multiple threads
+pub(crate) struct Progress {
+    phase: Phase,
+    total: usize,
+    done: AtomicUsize,
+    started: Instant,
+    reporter: Arc<dyn ProgressReporter>,
+}
+impl Progress {
+    pub fn start(phase: Phase, total: usize) -> Progress {
+        let reporter = reporter();
+        reporter.start(phase, total);
+        Progress {
+            phase,
+            total,
+            done: AtomicUsize::new(0),
+            started: Instant::now(),
+            reporter,
+        }
+    }
+
+    /// Mark one more item as done
+    pub fn advance(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        self.reporter
+            .update(self.phase, done, self.total, self.started.elapsed());
+    }
+}
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.reporter
+            .finish(self.phase, self.total, self.started.elapsed());
+    }
+}
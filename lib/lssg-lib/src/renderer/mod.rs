@@ -6,6 +6,7 @@ pub use token_renderer::TokenRenderer;
 
 mod renderer;
 pub use renderer::*;
+pub use virtual_dom::OutputMode;
 
 mod render_context;
 pub use render_context::*;
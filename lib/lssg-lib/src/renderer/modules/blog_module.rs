@@ -1,4 +1,7 @@
-use std::{collections::HashSet, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
 use chrono::{DateTime, NaiveDate, Utc};
 use log::{error, warn};
@@ -9,12 +12,15 @@ use crate::{
     lmarkdown::Token,
     lssg_error::LssgError,
     renderer::RenderContext,
-    sitetree::{Input, SiteNode, SiteNodeKind, Stylesheet},
+    sitetree::{Input, SiteNode, SiteNodeKind, SiteTree, Stylesheet},
     tree::DFS,
 };
-use virtual_dom::{to_attributes, Document, DomNode};
+use virtual_dom::{to_attributes, Document, DomNode, HeadPrecedence};
+
+use super::{util::base_url, RendererModule, TokenRenderer};
 
-use super::{RendererModule, TokenRenderer};
+mod webmention;
+use webmention::Webmention;
 
 const BLOG_STYLESHEET: &[u8] = include_bytes!("./blog_stylesheet.css");
 
@@ -23,14 +29,25 @@ pub struct BlogOptions {
     root: bool,
     /// When has an article been changed (%Y-%m-%d)
     modified_on: Option<String>,
+    /// When was an article first published (%Y-%m-%d)
+    published_on: Option<String>,
     tags: Option<Vec<String>>,
+    /// Name of the author added as h-card to posts, if not set on a post the one from the blog
+    /// root is used
+    author: Option<String>,
+    /// Fetch webmentions from webmention.io and render them below posts, set on the blog root.
+    /// Requires `base_url`
+    webmentions: bool,
 }
 impl Default for BlogOptions {
     fn default() -> Self {
         Self {
             root: false,
             modified_on: None,
+            published_on: None,
             tags: None,
+            author: None,
+            webmentions: false,
         }
     }
 }
@@ -38,8 +55,13 @@ impl Default for BlogOptions {
 pub struct BlogModule {
     post_site_ids: HashSet<usize>,
     root_site_ids: HashSet<usize>,
+    /// post to the blog root it belongs to
+    post_roots: HashMap<usize, usize>,
+    webmentions: HashMap<usize, Vec<Webmention>>,
     /// Local variable to keep track if date has been inserted
     has_inserted_date: bool,
+    /// Local variable with the content of the current post
+    post_content: Option<DomNode>,
 }
 
 impl BlogModule {
@@ -47,7 +69,56 @@ impl BlogModule {
         Self {
             post_site_ids: HashSet::new(),
             root_site_ids: HashSet::new(),
+            post_roots: HashMap::new(),
+            webmentions: HashMap::new(),
             has_inserted_date: false,
+            post_content: None,
+        }
+    }
+
+    /// Get the author of a post falling back to the author of the blog root
+    fn author(&self, context: &RenderContext) -> Option<String> {
+        let options: BlogOptions = self.options(context.page);
+        if options.author.is_some() {
+            return options.author;
+        }
+        let root = self.post_roots.get(&context.site_id)?;
+        match &context.site_tree[*root].kind {
+            SiteNodeKind::Page(page) => self.options::<BlogOptions>(page).author,
+            _ => None,
+        }
+    }
+
+    /// Fetch webmentions for all posts of blog roots that have `webmentions` enabled
+    fn fetch_webmentions(&mut self, site_tree: &SiteTree) {
+        let enabled: HashSet<usize> = self
+            .root_site_ids
+            .iter()
+            .filter(|id| match &site_tree[**id].kind {
+                SiteNodeKind::Page(page) => self.options::<BlogOptions>(page).webmentions,
+                _ => false,
+            })
+            .cloned()
+            .collect();
+        if enabled.is_empty() {
+            return;
+        }
+        let Some(base_url) = base_url(site_tree) else {
+            warn!("no base_url given, not fetching webmentions");
+            return;
+        };
+        for (post, root) in &self.post_roots {
+            if !enabled.contains(root) {
+                continue;
+            }
+            let target = format!("{base_url}{}", site_tree.path(*post));
+            match webmention::fetch(&target) {
+                Ok(mentions) if !mentions.is_empty() => {
+                    self.webmentions.insert(*post, mentions);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to fetch webmentions for {target}: {e}"),
+            }
         }
     }
 }
@@ -77,6 +148,8 @@ impl RendererModule for BlogModule {
             if let Some(parent) = site_tree.page_parent(site_id) {
                 if self.post_site_ids.contains(&parent) || self.root_site_ids.contains(&parent) {
                     self.post_site_ids.insert(site_id);
+                    let root = self.post_roots.get(&parent).cloned().unwrap_or(parent);
+                    self.post_roots.insert(site_id, root);
                 }
             }
 
@@ -94,6 +167,8 @@ impl RendererModule for BlogModule {
             }
         }
 
+        self.fetch_webmentions(site_tree);
+
         Ok(())
     }
 
@@ -110,14 +185,34 @@ impl RendererModule for BlogModule {
 
         // add article meta data
         let options: BlogOptions = self.options(context.page);
+        if self.post_site_ids.contains(&site_id) {
+            document.add_to_head(
+                dom!(<meta property="og:type" content="article"/>),
+                HeadPrecedence::Normal,
+            );
+        }
+        if let Some(date) = options.published_on {
+            document.add_to_head(
+                dom!(<meta property="article:published_time" content="{date}"/>),
+                HeadPrecedence::Normal,
+            );
+        }
         if let Some(date) = options.modified_on {
-            document
-                .head
-                .append_child(dom!(<meta property="article:modified_time" content="{date}"/>));
+            document.add_to_head(
+                dom!(<meta property="article:modified_time" content="{date}"/>),
+                HeadPrecedence::Normal,
+            );
+        }
+        for tag in options.tags.unwrap_or_default() {
+            document.add_to_head(
+                dom!(<meta property="article:tag" content="{tag}"/>),
+                HeadPrecedence::Normal,
+            );
         }
 
         // reset state
         self.has_inserted_date = false;
+        self.post_content = None;
 
         None
     }
@@ -140,20 +235,39 @@ impl RendererModule for BlogModule {
                 match get_date(self, context) {
                     Ok(date) => {
                         self.has_inserted_date = true;
+                        // h-entry microformat <https://microformats.org/wiki/h-entry>
                         let post = document.create_element_with_attributes(
                             "div",
-                            to_attributes([("class", "post")]),
+                            to_attributes([("class", "post h-entry")]),
                         );
                         let content = document.create_element_with_attributes(
                             "div",
-                            to_attributes([("class", "content")]),
+                            to_attributes([("class", "content e-content")]),
                         );
                         post.append_child(content.clone());
                         parent.append_child(post);
                         // render heading
                         tr.render(document, context, content.clone(), &vec![token.clone()]);
-                        content.append_child(dom!(<div class="post-updated-on">{date}</div>));
+                        if let Some(heading) = content.last_child() {
+                            heading.add_class("p-name");
+                        }
+
+                        let datetime = date.format("%Y-%m-%d").to_string();
+                        let date = date.format("%B %d, %Y").to_string();
+                        let updated_on = dom!(<div class="post-updated-on">"Updated on "</div>);
+                        updated_on.append_child(
+                            dom!(<time class="dt-updated" datetime="{datetime}">{date}</time>),
+                        );
+                        if let Some(author) = self.author(context) {
+                            let href = context.site_tree.href(site_id, context.site_tree.root());
+                            updated_on.append_child(document.create_text_node(" by "));
+                            updated_on.append_child(
+                                dom!(<a class="p-author h-card" href="{href}">{author}</a>),
+                            );
+                        }
+                        content.append_child(updated_on);
 
+                        self.post_content = Some(content.clone());
                         return Some(content);
                     }
                     Err(e) => error!("failed to read date from post: {e}"),
@@ -193,6 +307,15 @@ impl RendererModule for BlogModule {
         }
         return None;
     }
+
+    fn after_render<'n>(&mut self, _document: &mut Document, context: &RenderContext<'n>) {
+        if let (Some(content), Some(mentions)) = (
+            self.post_content.take(),
+            self.webmentions.get(&context.site_id),
+        ) {
+            content.append_child(webmention::render(mentions));
+        }
+    }
 }
 
 pub fn is_href_external(href: &str) -> bool {
@@ -200,13 +323,12 @@ pub fn is_href_external(href: &str) -> bool {
 }
 
 /// get the date from input and options
-fn get_date(module: &mut BlogModule, context: &RenderContext) -> Result<String, LssgError> {
+fn get_date(module: &mut BlogModule, context: &RenderContext) -> Result<NaiveDate, LssgError> {
     let po: BlogOptions = module.options(context.page);
 
     if let Some(date) = po.modified_on {
         match NaiveDate::from_str(&date) {
             Ok(date) => {
-                let date = date.format("Updated on %B %d, %Y").to_string();
                 return Ok(date);
             }
             Err(e) => warn!("could not parse modified_on to date: {e}"),
@@ -216,8 +338,7 @@ fn get_date(module: &mut BlogModule, context: &RenderContext) -> Result<String,
     match context.input {
         Some(Input::Local { path }) => {
             let date: DateTime<Utc> = path.metadata()?.modified()?.into();
-            let date = date.format("Updated on %B %d, %Y").to_string();
-            Ok(date)
+            Ok(date.date_naive())
         }
         Some(Input::External { .. }) => {
             return Err(LssgError::render(
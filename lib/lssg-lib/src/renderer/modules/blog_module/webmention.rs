@@ -0,0 +1,120 @@
+use std::io::Read;
+
+use chrono::Utc;
+use log::info;
+use proc_virtual_dom::dom;
+use reqwest::Url;
+use serde::Deserialize;
+use virtual_dom::{to_attributes, DomNode};
+
+use crate::{cache, lssg_error::LssgError, sitetree::Input};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Author {
+    name: Option<String>,
+    url: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Content {
+    text: Option<String>,
+}
+
+/// A single webmention in jf2 format <https://github.com/aaronpk/webmention.io#api>
+#[derive(Debug, Clone, Deserialize)]
+pub struct Webmention {
+    author: Option<Author>,
+    url: Option<String>,
+    /// "in-reply-to", "like-of", "repost-of", "bookmark-of" or "mention-of"
+    #[serde(rename = "wm-property")]
+    property: String,
+    content: Option<Content>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Feed {
+    children: Vec<Webmention>,
+}
+
+/// Cache namespace of fetched webmentions
+const CACHE_NAMESPACE: &str = "webmentions";
+
+/// Fetch all webmentions of `target` from webmention.io, they are kept in the build cache for a
+/// day
+pub fn fetch(target: &str) -> Result<Vec<Webmention>, LssgError> {
+    let url = Url::parse_with_params(
+        "https://webmention.io/api/mentions.jf2",
+        [("target", target), ("per-page", "100")],
+    )
+    .map_err(|e| LssgError::render(e.to_string()))?;
+    let key = format!("{url} {}", Utc::now().date_naive());
+
+    let mut json = String::new();
+    if let Some(mut file) = cache::get(CACHE_NAMESPACE, &key)? {
+        file.read_to_string(&mut json)?;
+        if let Ok(feed) = serde_json::from_str::<Feed>(&json) {
+            return Ok(feed.children);
+        }
+        json.clear();
+    }
+
+    info!("Fetching webmentions for {target}");
+    Input::from_string(url.as_str())?
+        .readable()?
+        .read_to_string(&mut json)?;
+    // nothing is read when the request got skipped (eg. offline)
+    if json.is_empty() {
+        return Ok(vec![]);
+    }
+    let feed: Feed = serde_json::from_str(&json).map_err(|e| LssgError::render(e.to_string()))?;
+    cache::put(CACHE_NAMESPACE, &key, json.as_bytes())?;
+    Ok(feed.children)
+}
+
+/// Render likes and reposts as counts and replies and mentions as list with h-cite
+pub fn render(mentions: &[Webmention]) -> DomNode {
+    let count = |property: &str| mentions.iter().filter(|m| m.property == property).count();
+    let likes = count("like-of");
+    let reposts = count("repost-of");
+
+    let section = dom!(<section class="webmentions"><h2>Webmentions</h2></section>);
+    if likes > 0 || reposts > 0 {
+        let summary = format!("{likes} likes, {reposts} reposts");
+        section.append_child(dom!(<p class="webmentions__summary">{summary}</p>));
+    }
+
+    let list = dom!(<ul class="webmentions__list"></ul>);
+    for mention in mentions
+        .iter()
+        .filter(|m| m.property == "in-reply-to" || m.property == "mention-of")
+    {
+        let class = if mention.property == "in-reply-to" {
+            "h-cite p-comment"
+        } else {
+            "h-cite"
+        };
+        let item = DomNode::create_element_with_attributes("li", to_attributes([("class", class)]));
+        let author = mention.author.clone();
+        let name = author
+            .as_ref()
+            .and_then(|a| a.name.clone())
+            .unwrap_or("Anonymous".into());
+        match author.and_then(|a| a.url) {
+            Some(url) => {
+                item.append_child(dom!(<a class="p-author h-card" href="{url}">{name}</a>))
+            }
+            None => item.append_child(dom!(<span class="p-author h-card">{name}</span>)),
+        }
+        if let Some(text) = mention.content.clone().and_then(|c| c.text) {
+            item.append_child(dom!(<p class="p-content">{text}</p>));
+        }
+        if let Some(url) = mention.url.clone() {
+            item.append_child(dom!(<a class="u-url" href="{url}">source</a>));
+        }
+        list.append_child(item);
+    }
+    if list.has_children() {
+        section.append_child(list);
+    }
+    section
+}
@@ -0,0 +1,290 @@
+use std::{collections::HashMap, io::Read};
+
+use log::{info, warn};
+use proc_virtual_dom::dom;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_extensions::Overwrite;
+
+use crate::{
+    cache,
+    lssg_error::LssgError,
+    renderer::RenderContext,
+    sitetree::{Input, Resource, SiteId, SiteNode, SiteNodeKind, SiteTree},
+    tree::DFS,
+};
+use virtual_dom::{decode_entities, Document, DomNode, HeadPrecedence};
+
+use super::{
+    util::{escape_xml, page_title},
+    RendererModule,
+};
+
+/// A followed feed, either only the url of the feed or a table
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum FeedEntry {
+    Url(String),
+    Feed(Feed),
+}
+impl From<FeedEntry> for Feed {
+    fn from(entry: FeedEntry) -> Self {
+        match entry {
+            FeedEntry::Url(url) => Feed {
+                url,
+                title: None,
+                site: None,
+            },
+            FeedEntry::Feed(feed) => feed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Feed {
+    /// Url of the rss or atom feed
+    url: String,
+    title: Option<String>,
+    /// Url of the website of the feed
+    site: Option<String>,
+}
+
+impl Feed {
+    /// Fetch the feed and read the title and website
+    fn fetch(url: &str) -> Result<Feed, LssgError> {
+        info!("Fetching feed {url}");
+        let mut xml = String::new();
+        Input::from_string(url)?
+            .readable()?
+            .read_to_string(&mut xml)?;
+        let text = |text: &str| {
+            decode_entities(
+                text.trim()
+                    .trim_start_matches("<![CDATA[")
+                    .trim_end_matches("]]>")
+                    .trim(),
+            )
+        };
+        let title = Regex::new(r"(?is)<title[^>]*>(.*?)</title>")
+            .unwrap()
+            .captures(&xml)
+            .map(|c| text(&c[1]))
+            .filter(|t| !t.is_empty());
+
+        // rss has the website in <link>, atom in <link rel="alternate" href="...">
+        let site = Regex::new(r"(?is)<link>(.*?)</link>")
+            .unwrap()
+            .captures(&xml)
+            .map(|c| text(&c[1]))
+            .or_else(|| {
+                let href = Regex::new(r#"href\s*=\s*["']([^"']*)["']"#).unwrap();
+                Regex::new(r"(?is)<link\s[^>]*>")
+                    .unwrap()
+                    .find_iter(&xml)
+                    .map(|link| link.as_str())
+                    .filter(|link| !link.contains("rel=\"") || link.contains("rel=\"alternate\""))
+                    .find_map(|link| href.captures(link).map(|c| text(&c[1])))
+            })
+            .filter(|s| !s.is_empty());
+
+        Ok(Feed {
+            url: url.to_string(),
+            title,
+            site,
+        })
+    }
+
+    /// Get the title and website of the feed at `url` from the build cache or otherwise fetch
+    /// and cache them
+    fn cached(url: &str) -> Result<Feed, LssgError> {
+        if let Some(mut file) = cache::get(CACHE_NAMESPACE, url)? {
+            let mut content = String::new();
+            file.read_to_string(&mut content)?;
+            if let Ok(feed) = toml::from_str(&content) {
+                return Ok(feed);
+            }
+        }
+        let feed = Feed::fetch(url)?;
+        // nothing is found when the feed got skipped (eg. offline), fetch it again next build
+        if feed.title.is_none() && feed.site.is_none() {
+            return Ok(feed);
+        }
+        if let Ok(content) = toml::to_string(&feed) {
+            cache::put(CACHE_NAMESPACE, url, content.as_bytes())?;
+        }
+        Ok(feed)
+    }
+
+    fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.url)
+    }
+}
+
+/// Cache namespace of the fetched feed metadata, so feeds are only fetched once
+const CACHE_NAMESPACE: &str = "blogroll";
+
+#[derive(Overwrite, Debug, Default)]
+pub struct BlogrollOptions {
+    /// Followed feeds, urls or tables with a `url` and optionally a `title` and `site`
+    feeds: Vec<FeedEntry>,
+    /// Fetch the title and website of feeds that don't have one, cached between builds
+    fetch: bool,
+}
+
+/// Outline Processor Markup Language file of `feeds` <http://opml.org/spec2.opml>
+fn opml(title: &str, feeds: &[Feed]) -> String {
+    let outlines: Vec<String> = feeds
+        .iter()
+        .map(|feed| {
+            let title = escape_xml(feed.title());
+            let mut outline = format!(
+                r#"    <outline type="rss" text="{title}" title="{title}" xmlUrl="{}""#,
+                escape_xml(&feed.url)
+            );
+            if let Some(site) = &feed.site {
+                outline.push_str(&format!(r#" htmlUrl="{}""#, escape_xml(site)));
+            }
+            outline.push_str("/>");
+            outline
+        })
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n  <head>\n    <title>{}</title>\n  </head>\n  <body>\n{}\n  </body>\n</opml>\n",
+        escape_xml(title),
+        outlines.join("\n")
+    )
+}
+
+struct Blogroll {
+    feeds: Vec<Feed>,
+    opml: SiteId,
+}
+
+/// Adds a list of followed feeds to pages with `[blogroll] feeds = [...]` and an OPML file
+/// (`blogroll.opml`) to import them into a feed reader
+#[derive(Default)]
+pub struct BlogrollModule {
+    blogrolls: HashMap<SiteId, Blogroll>,
+}
+
+impl BlogrollModule {
+    pub fn new() -> Self {
+        Self {
+            blogrolls: HashMap::new(),
+        }
+    }
+}
+
+impl RendererModule for BlogrollModule {
+    fn id(&self) -> &'static str {
+        "blogroll"
+    }
+
+    fn init(&mut self, site_tree: &mut SiteTree) -> Result<(), LssgError> {
+        let pages: Vec<SiteId> = DFS::new(site_tree)
+            .filter(|id| site_tree[*id].kind.is_page())
+            .collect();
+
+        for site_id in pages {
+            let SiteNodeKind::Page(page) = &site_tree[site_id].kind else {
+                continue;
+            };
+            let options: BlogrollOptions = self.options(page);
+            if options.feeds.is_empty() {
+                continue;
+            }
+            let title = page_title(page, &site_tree[site_id].name);
+
+            let mut feeds: Vec<Feed> = options.feeds.into_iter().map(Feed::from).collect();
+            if options.fetch {
+                for feed in feeds.iter_mut() {
+                    if feed.title.is_some() && feed.site.is_some() {
+                        continue;
+                    }
+                    let fetched = match Feed::cached(&feed.url) {
+                        Ok(fetched) => fetched,
+                        Err(e) => {
+                            warn!("Failed to fetch feed {}: {e}", feed.url);
+                            continue;
+                        }
+                    };
+                    feed.title = feed.title.take().or(fetched.title);
+                    feed.site = feed.site.take().or(fetched.site);
+                }
+            }
+
+            let opml = site_tree.add(SiteNode::resource(
+                "blogroll.opml",
+                site_id,
+                Resource::new_static(opml(&title, &feeds)),
+            ));
+            site_tree.add_link(site_id, opml);
+            self.blogrolls.insert(site_id, Blogroll { feeds, opml });
+        }
+
+        Ok(())
+    }
+
+    fn render_page<'n>(
+        &mut self,
+        document: &mut Document,
+        context: &RenderContext<'n>,
+    ) -> Option<String> {
+        if let Some(blogroll) = self.blogrolls.get(&context.site_id) {
+            let href = context.site_tree.href(context.site_id, blogroll.opml);
+            document.add_to_head(
+                dom!(<link rel="blogroll" type="text/xml" title="Blogroll" href="{href}"/>),
+                HeadPrecedence::Normal,
+            );
+        }
+        None
+    }
+
+    fn after_render<'n>(&mut self, document: &mut Document, context: &RenderContext<'n>) {
+        let Some(blogroll) = self.blogrolls.get(&context.site_id) else {
+            return;
+        };
+        let section = dom!(<section class="blogroll"></section>);
+        let list = dom!(<ul></ul>);
+        for feed in &blogroll.feeds {
+            let title = feed.title();
+            let url = &feed.url;
+            let item = dom!(<li></li>);
+            match &feed.site {
+                Some(site) => item.append_child(dom!(<a href="{site}">{title}</a>)),
+                None => item.append_child(DomNode::create_text(title)),
+            }
+            item.append_child(DomNode::create_text(" "));
+            item.append_child(dom!(<a class="blogroll__feed" href="{url}">(feed)</a>));
+            list.append_child(item);
+        }
+        section.append_child(list);
+        let href = context.site_tree.href(context.site_id, blogroll.opml);
+        section.append_child(dom!(<p><a href="{href}">Download as OPML</a></p>));
+        document.body.append_child(section);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{opml, Feed};
+
+    #[test]
+    fn test_opml() {
+        let feeds = vec![
+            Feed {
+                url: "https://example.com/feed.xml".into(),
+                title: Some("Tom & Jerry".into()),
+                site: Some("https://example.com".into()),
+            },
+            Feed {
+                url: "https://blog.org/atom.xml".into(),
+                title: None,
+                site: None,
+            },
+        ];
+        let opml = opml("Blogroll", &feeds);
+        assert!(opml.contains(r#"<outline type="rss" text="Tom &amp; Jerry" title="Tom &amp; Jerry" xmlUrl="https://example.com/feed.xml" htmlUrl="https://example.com"/>"#));
+        assert!(opml.contains(r#"text="https://blog.org/atom.xml""#));
+    }
+}
@@ -0,0 +1,301 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Read,
+};
+
+use log::{info, warn};
+use proc_virtual_dom::dom;
+use regex::Regex;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_extensions::Overwrite;
+use virtual_dom::{decode_entities, to_attributes, Document, DomNode};
+
+use crate::{
+    cache,
+    lmarkdown::{visit_tokens, Token},
+    lssg_error::LssgError,
+    renderer::RenderContext,
+    sitetree::{Input, Resource, SiteId, SiteNode, SiteNodeKind, SiteTree, Stylesheet},
+    tree::DFS,
+};
+
+use super::{RendererModule, TokenRenderer};
+
+const CARD_STYLESHEET: &[u8] = include_bytes!("./card_stylesheet.css");
+
+#[derive(Overwrite, Debug)]
+pub struct CardOptions {
+    /// Render urls that are on their own line as a card
+    bare_urls: bool,
+}
+impl Default for CardOptions {
+    fn default() -> Self {
+        Self { bare_urls: true }
+    }
+}
+
+/// OpenGraph metadata of a link
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LinkMetadata {
+    title: Option<String>,
+    description: Option<String>,
+    image: Option<String>,
+    site_name: Option<String>,
+}
+
+impl LinkMetadata {
+    fn fetch(url: &str) -> Result<LinkMetadata, LssgError> {
+        info!("Fetching link preview for {url}");
+        let mut html = String::new();
+        Input::from_string(url)?
+            .readable()?
+            .read_to_string(&mut html)?;
+        let mut metadata = LinkMetadata::default();
+
+        let meta_regex = Regex::new(r"(?is)<meta\s[^>]*>").unwrap();
+        let attribute_regex =
+            Regex::new(r#"(?is)([\w:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+        let mut description = None;
+        for meta in meta_regex.find_iter(&html) {
+            let attributes: HashMap<String, String> = attribute_regex
+                .captures_iter(meta.as_str())
+                .map(|c| {
+                    let value = c.get(2).or(c.get(3)).map(|v| v.as_str()).unwrap_or("");
+                    (c[1].to_lowercase(), decode_entities(value))
+                })
+                .collect();
+            let Some(content) = attributes.get("content").cloned() else {
+                continue;
+            };
+            let key = attributes.get("property").or(attributes.get("name"));
+            match key.map(|k| k.as_str()) {
+                Some("og:title") => metadata.title = Some(content),
+                Some("og:description") => metadata.description = Some(content),
+                Some("og:image") => metadata.image = Some(content),
+                Some("og:site_name") => metadata.site_name = Some(content),
+                Some("description") => description = Some(content),
+                _ => {}
+            }
+        }
+
+        // fallback to regular html metadata
+        if metadata.title.is_none() {
+            let title_regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+            metadata.title = title_regex
+                .captures(&html)
+                .map(|c| decode_entities(c[1].trim()));
+        }
+        if metadata.description.is_none() {
+            metadata.description = description;
+        }
+        // make image url absolute
+        if let Some(image) = &metadata.image {
+            metadata.image = Url::parse(url)
+                .and_then(|u| u.join(image))
+                .map(|u| u.to_string())
+                .ok();
+        }
+
+        Ok(metadata)
+    }
+
+    /// Get the metadata of `url` from the build cache or otherwise fetch and cache it
+    fn cached(url: &str) -> Result<LinkMetadata, LssgError> {
+        if let Some(mut file) = cache::get(CACHE_NAMESPACE, url)? {
+            let mut content = String::new();
+            file.read_to_string(&mut content)?;
+            if let Ok(metadata) = toml::from_str(&content) {
+                return Ok(metadata);
+            }
+        }
+        let metadata = LinkMetadata::fetch(url)?;
+        // nothing is found when the link got skipped (eg. offline), fetch it again next build
+        if metadata.is_empty() {
+            return Ok(metadata);
+        }
+        if let Ok(content) = toml::to_string(&metadata) {
+            cache::put(CACHE_NAMESPACE, url, content.as_bytes())?;
+        }
+        Ok(metadata)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.description.is_none()
+            && self.image.is_none()
+            && self.site_name.is_none()
+    }
+}
+
+/// Cache namespace of the fetched link metadata, so links are only fetched once
+const CACHE_NAMESPACE: &str = "link_cards";
+
+fn is_bare_url(text: &str) -> bool {
+    text.starts_with("http") && Url::parse(text).is_ok() && !text.contains(char::is_whitespace)
+}
+
+/// Get the url of a token if it should be rendered as card
+fn card_url(token: &Token, options: &CardOptions) -> Option<String> {
+    match token {
+        Token::Paragraph { tokens, .. } if options.bare_urls => match tokens.as_slice() {
+            [Token::Text { text }] if is_bare_url(text) => Some(text.clone()),
+            _ => None,
+        },
+        Token::Html {
+            tag, attributes, ..
+        } if tag == "a" && attributes.contains_key("card") => attributes.get("href").cloned(),
+        _ => None,
+    }
+}
+
+/// Find all card urls in `tokens`
+fn card_urls(tokens: &[Token], options: &CardOptions) -> Vec<String> {
+    let mut urls = vec![];
+    visit_tokens(tokens, |token| {
+        if let Some(url) = card_url(token, options) {
+            urls.push(url);
+        }
+    });
+    urls
+}
+
+struct Card {
+    metadata: LinkMetadata,
+    image: Option<SiteId>,
+}
+
+/// Renders links marked with a `card` attribute (`<a href="..." card></a>`) and bare urls as a
+/// preview card using the OpenGraph metadata of the link
+#[derive(Default)]
+pub struct CardModule {
+    cards: HashMap<String, Card>,
+}
+
+impl CardModule {
+    pub fn new() -> Self {
+        Self {
+            cards: HashMap::new(),
+        }
+    }
+}
+
+impl RendererModule for CardModule {
+    fn id(&self) -> &'static str {
+        "card"
+    }
+
+    fn init(&mut self, site_tree: &mut SiteTree) -> Result<(), LssgError> {
+        let pages: Vec<usize> = DFS::new(site_tree)
+            .filter(|id| site_tree[*id].kind.is_page())
+            .collect();
+
+        let mut page_urls: Vec<(SiteId, Vec<String>)> = vec![];
+        for id in pages {
+            if let SiteNodeKind::Page(page) = &site_tree[id].kind {
+                let options: CardOptions = self.options(page);
+                let urls = card_urls(page.tokens(), &options);
+                if !urls.is_empty() {
+                    page_urls.push((id, urls));
+                }
+            }
+        }
+        if page_urls.is_empty() {
+            return Ok(());
+        }
+
+        let root = site_tree.root();
+        let stylesheet = site_tree.add(SiteNode::stylesheet(
+            "card.css",
+            root,
+            Stylesheet::from_readable(CARD_STYLESHEET)?,
+        ));
+        let mut folder = None;
+        let mut fetched = HashSet::new();
+        for (id, urls) in page_urls {
+            site_tree.add_link(id, stylesheet);
+            for url in urls {
+                if !fetched.insert(url.clone()) {
+                    continue;
+                }
+                let metadata = match LinkMetadata::cached(&url) {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        warn!("Failed to fetch link preview for {url}: {e}");
+                        continue;
+                    }
+                };
+
+                let image = match metadata.image.as_ref().map(|i| Input::from_string(i)) {
+                    Some(Ok(input)) => {
+                        // fetched now so a card without image is rendered when it is unavailable
+                        match input.readable().and_then(Resource::from_readable) {
+                            Ok(resource) => {
+                                let folder = *folder.get_or_insert_with(|| {
+                                    site_tree.add(SiteNode::folder("cards", root))
+                                });
+                                let name = input
+                                    .filename()
+                                    .ok()
+                                    .filter(|n| !n.is_empty())
+                                    .unwrap_or("image".into());
+                                let name = format!("{}-{name}", self.cards.len());
+                                Some(site_tree.add(SiteNode::resource(name, folder, resource)))
+                            }
+                            Err(e) => {
+                                warn!("Failed to fetch preview image of {url}: {e}");
+                                None
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+                self.cards.insert(url, Card { metadata, image });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn render_body<'n>(
+        &mut self,
+        document: &mut Document,
+        context: &RenderContext<'n>,
+        parent: DomNode,
+        token: &Token,
+        _tr: &mut TokenRenderer,
+    ) -> Option<DomNode> {
+        if self.cards.is_empty() {
+            return None;
+        }
+        let options: CardOptions = self.options(context.page);
+        let url = card_url(token, &options)?;
+        let card = self.cards.get(&url)?;
+
+        let a = document.create_element_with_attributes(
+            "a",
+            to_attributes([("class", "card"), ("href", url.as_str())]),
+        );
+        if let Some(image) = card.image {
+            let src = context.site_tree.href(context.site_id, image);
+            a.append_child(dom!(<img class="card__image" src="{src}" alt="" loading="lazy" />));
+        }
+        let content = dom!(<div class="card__content"></div>);
+        let title = card.metadata.title.clone().unwrap_or(url.clone());
+        content.append_child(dom!(<div class="card__title">{title}</div>));
+        if let Some(description) = card.metadata.description.clone() {
+            content.append_child(dom!(<p class="card__description">{description}</p>));
+        }
+        let site = card.metadata.site_name.clone().unwrap_or_else(|| {
+            Url::parse(&url)
+                .ok()
+                .and_then(|u| u.host_str().map(|h| h.to_string()))
+                .unwrap_or_default()
+        });
+        content.append_child(dom!(<span class="card__site">{site}</span>));
+        a.append_child(content);
+        parent.append_child(a);
+
+        Some(parent)
+    }
+}
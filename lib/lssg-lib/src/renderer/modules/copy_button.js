@@ -0,0 +1,9 @@
+for (const button of document.getElementsByClassName("code-copy")) {
+  button.addEventListener("click", () => {
+    const code = button.parentElement.querySelector("code");
+    navigator.clipboard.writeText(code.innerText).then(() => {
+      button.textContent = "Copied";
+      setTimeout(() => (button.textContent = "Copy"), 2000);
+    });
+  });
+}
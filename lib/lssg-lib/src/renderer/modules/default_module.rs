@@ -1,43 +1,171 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+};
 
-use log::{error, warn};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::NaiveDateTime;
+use log::{error, info, warn};
 
 use proc_virtual_dom::dom;
 use regex::Regex;
 use serde_extensions::Overwrite;
+use sha2::{Digest, Sha384};
 
 use crate::{
-    lmarkdown::Token,
+    lmarkdown::{visit_tokens, wiki_link_target, CodeInfo, Token},
     lssg_error::LssgError,
-    sitetree::{Input, Page, Relation, Resource, SiteNode, SiteNodeKind, SiteTree, Stylesheet},
+    sitetree::{
+        ImageMetadata, Input, Page, Relation, Resource, SiteId, SiteNode, SiteNodeKind, SiteTree,
+        Stylesheet,
+    },
     tree::DFS,
 };
-use virtual_dom::{self, parse_html, to_attributes, Document, DomNode, DomNodeKind, Html};
+use virtual_dom::{
+    self, parse_html_with_warnings, to_attributes, Attributes, Document, DomNode, DomNodeKind,
+    HeadPrecedence, Html, ParseOptions,
+};
 
 use crate::renderer::{RenderContext, RendererModule, TokenRenderer};
 
-use super::util::{process_href, tokens_to_text};
+use super::util::{format_size, page_description, process_href, tokens_to_text};
 
+mod backlinks;
+mod csp;
+mod data_table;
+mod favicon;
+mod footnotes;
+mod link_hygiene;
 mod render_html;
+mod sanitize;
+mod scripts;
+mod sidenotes;
+mod svg_sprites;
+mod variables;
+use csp::ContentSecurityPolicy;
+pub use csp::CspOutput;
+use footnotes::Footnotes;
+use link_hygiene::{link_hygiene, LinkHygiene};
+use scripts::{add_script, ScriptLoading};
 
 const DEFAULT_STYLESHEET: &[u8] = include_bytes!("./default_stylesheet.css");
-const DEFAULT_JS: &str = include_str!("./default.js");
+const PRINT_STYLESHEET: &[u8] = include_bytes!("./print_stylesheet.css");
+/// Name of the stylesheet that is only applied when printing
+const PRINT_STYLESHEET_NAME: &str = "print.css";
+/// Styles of code blocks with a filename, highlighted lines, line numbers or copy button,
+/// only linked from pages with such a code block
+const CODE_STYLESHEET: &[u8] = include_bytes!("./code_stylesheet.css");
+const CODE_STYLESHEET_NAME: &str = "code.css";
+const DEFAULT_JS_NAME: &str = "default.js";
+/// Modal showing the images of a carousel, only added to `default.js` when used
+const CAROUSEL_JS: &str = include_str!("./carousel.js");
+/// Click handler of the copy button on code blocks, only added to `default.js` when used
+const COPY_BUTTON_JS: &str = include_str!("./copy_button.js");
+/// Maximum number of characters of generated descriptions, longer ones are cut off in search
+/// results
+const DESCRIPTION_LENGTH: usize = 160;
 
 #[derive(Debug, Clone, Overwrite)]
 struct PropegatedOptions {
     /// Add extra resources
     pub title: String,
+    /// Format of `<title>` and og:title, `{page}` is replaced by the first heading of the page and
+    /// `{site}` by `title`
+    pub title_template: String,
+    /// Format of the title of the root page (eg. "{page}" to leave out the site title), if not
+    /// set `title_template` is used
+    pub home_title_template: Option<String>,
     /// Translates to meta tags <https://www.w3schools.com/tags/tag_meta.asp>
-    pub meta: HashMap<String, String>,
+    pub meta: BTreeMap<String, String>,
+    /// Use the start of the first paragraph as description when `meta.description` isn't set
+    pub auto_description: bool,
     /// Lang attribute ("en") <https://www.w3schools.com/tags/ref_language_codes.asp>
     pub language: String,
+    /// Text direction ("ltr", "rtl"), if not set it is derived from `language`
+    pub dir: Option<String>,
+    /// Add `integrity` and `crossorigin` attributes to stylesheets and scripts hosted in the SiteTree
+    /// <https://developer.mozilla.org/en-US/docs/Web/Security/Subresource_Integrity>
+    pub integrity: bool,
+    /// Generate a Content Security Policy from all used origins ("meta", "headers" or "both")
+    pub csp: Option<CspOutput>,
+    /// Strip scripts, styles, frames, event handlers and dangerous urls from the page content,
+    /// use when rendering untrusted markdown
+    pub sanitize: bool,
+    /// Source image from which the favicon set (ico, png, apple-touch-icon and a web app
+    /// manifest with a maskable icon) is generated, only read from the root page
+    pub favicon: Option<String>,
+    /// Webmention endpoint (eg. "https://webmention.io/example.com/webmention")
+    /// <https://www.w3.org/TR/webmention/>
+    pub webmention: Option<String>,
+    /// Pingback endpoint (eg. "https://webmention.io/example.com/xmlrpc")
+    pub pingback: Option<String>,
+    /// Replace `{{ site.title }}`, `{{ page.title }}` and `{{ build.year }}` placeholders in text
+    pub variables: bool,
+    /// Show footnotes as sidenotes next to the text on wide screens
+    pub sidenotes: bool,
+    /// Render images that are alone in a paragraph and have a title or are followed by emphasized
+    /// text as `<figure>` with caption
+    pub figures: bool,
+    /// Add `loading="lazy"`, `decoding="async"` and the intrinsic `width` and `height` to images
+    pub lazy_images: bool,
+    /// Load images through a CDN or image transform service, `{path}` is replaced by the path of
+    /// the image and `{width}` by each of `image_widths` (eg.
+    /// "https://cdn.example.com/{width}/{path}")
+    pub image_cdn: Option<String>,
+    /// Widths in the `srcset` of images loaded through `image_cdn`
+    pub image_widths: Vec<u32>,
+    /// Put inline svgs that are used multiple times on a page in a single `<symbol>` and
+    /// reference it with `<use>`
+    pub svg_sprites: bool,
+    /// Append the file type and size to links to files (eg. "report.pdf")
+    pub download_badges: bool,
+    /// Use the EXIF description of photos as alt text and caption when they don't have one, show
+    /// when figures were taken and sort paragraphs of only images by the date they were taken
+    pub exif: bool,
+    /// Add a button to fenced code blocks that copies the code to the clipboard
+    pub copy_button: bool,
+    /// Show line numbers next to fenced code blocks, can be changed per block with `linenos` or
+    /// `linenos=false` in the info string
+    pub line_numbers: bool,
+    /// How scripts are loaded ("blocking", "defer", "async", "module" or "inline"), can be
+    /// changed per script with `?load=defer` in its link
+    pub script_loading: ScriptLoading,
+    /// Upgrade outbound links to https, strip tracking parameters and normalize trailing slashes
+    pub link_hygiene: LinkHygiene,
+    /// Add a "Pages that link here" section to the bottom of pages
+    pub backlinks: bool,
 }
 impl Default for PropegatedOptions {
     fn default() -> Self {
         Self {
-            meta: HashMap::new(),
+            meta: BTreeMap::new(),
+            auto_description: true,
             title: String::new(),
+            title_template: "{page} - {site}".into(),
+            home_title_template: None,
             language: "en".into(),
+            dir: None,
+            integrity: false,
+            csp: None,
+            sanitize: false,
+            favicon: None,
+            webmention: None,
+            pingback: None,
+            variables: true,
+            sidenotes: false,
+            figures: true,
+            lazy_images: true,
+            image_cdn: None,
+            image_widths: vec![480, 960, 1920],
+            svg_sprites: false,
+            download_badges: false,
+            exif: false,
+            copy_button: false,
+            line_numbers: false,
+            script_loading: ScriptLoading::Blocking,
+            link_hygiene: LinkHygiene::default(),
+            backlinks: false,
         }
     }
 }
@@ -46,37 +174,457 @@ impl Default for PropegatedOptions {
 pub struct SinglePageOptions {
     /// If this page is a root don't reuse options from parent
     pub root: bool,
+    /// Only inherit these options from the parent (eg. `["meta", "language"]`), also applies to
+    /// root pages
+    pub inherit: Option<Vec<String>>,
+    /// Don't inherit these options from the parent (eg. `["title"]`)
+    pub no_inherit: Vec<String>,
+    /// Content of the robots meta tag (eg. "noindex, nofollow")
+    pub robots: Option<String>,
+    /// If false this page won't be listed in sitemaps and feeds
+    pub sitemap: bool,
+    /// Render this page but leave it out of navigation, sitemaps and listings and ask search
+    /// engines not to index it, it is only reachable by its url
+    pub unlisted: bool,
 }
 impl Default for SinglePageOptions {
     fn default() -> Self {
-        Self { root: false }
+        Self {
+            root: false,
+            inherit: None,
+            no_inherit: vec![],
+            robots: None,
+            sitemap: true,
+            unlisted: false,
+        }
+    }
+}
+impl SinglePageOptions {
+    /// Get the options of a single page, allows other modules to read these options
+    pub fn from_page(page: &Page) -> SinglePageOptions {
+        let mut options = SinglePageOptions::default();
+        if let Some(table) = page.attributes() {
+            if let Err(e) = options.overwrite(table.clone()) {
+                error!("Failed to parse options for 'default' module: {e}")
+            }
+        }
+        options
+    }
+
+    /// Get the options of page `id` overwritten by the `_options.toml` of the folders it is in and
+    /// then by the attributes of the page itself
+    pub fn resolve(site_tree: &SiteTree, id: SiteId) -> SinglePageOptions {
+        let SiteNodeKind::Page(page) = &site_tree[id].kind else {
+            return SinglePageOptions::default();
+        };
+        let mut options = SinglePageOptions::default();
+        for table in folder_options(site_tree, id, None, &mut HashMap::new()) {
+            if let Err(e) = options.overwrite(table) {
+                error!("Failed to parse {FOLDER_OPTIONS} for 'default' module: {e}")
+            }
+        }
+        if let Some(table) = page.attributes() {
+            if let Err(e) = options.overwrite(table.clone()) {
+                error!("Failed to parse options for 'default' module: {e}")
+            }
+        }
+        options
+    }
+
+    /// If option `key` is inherited from the parent page
+    pub fn inherits(&self, key: &str) -> bool {
+        let inherited = match &self.inherit {
+            Some(inherit) => inherit.iter().any(|k| k == key),
+            None => !self.root,
+        };
+        inherited && !self.no_inherit.iter().any(|k| k == key)
+    }
+
+    /// If this page should be listed in sitemaps, feeds and listings
+    pub fn is_listed(&self) -> bool {
+        let noindex = self
+            .robots
+            .as_ref()
+            .map(|r| r.contains("noindex"))
+            .unwrap_or(false);
+        self.sitemap && !self.unlisted && !noindex
     }
 }
 
+/// Name of the file in a source folder with options for every page in that folder
+pub const FOLDER_OPTIONS: &str = "_options.toml";
+
+/// Get the `_options.toml` tables that apply to page `id`, from the folder of the root page down
+/// to the folder of the page. Folders that also contain page `parent` are skipped, their options
+/// are inherited from the parent.
+fn folder_options(
+    site_tree: &SiteTree,
+    id: SiteId,
+    parent: Option<SiteId>,
+    cache: &mut HashMap<PathBuf, Option<toml::Table>>,
+) -> Vec<toml::Table> {
+    let (Some(Input::Local { path: root }), Some(Input::Local { path })) = (
+        site_tree.get_input(site_tree.root()),
+        site_tree.get_input(id),
+    ) else {
+        return vec![];
+    };
+    let Some(root) = root.parent() else {
+        return vec![];
+    };
+    let parent_path = match parent.and_then(|parent| site_tree.get_input(parent)) {
+        Some(Input::Local { path }) => Some(path),
+        _ => None,
+    };
+
+    let mut tables = vec![];
+    for folder in path.ancestors().skip(1) {
+        if !folder.starts_with(root) || parent_path.is_some_and(|p| p.starts_with(folder)) {
+            break;
+        }
+        let table = cache.entry(folder.to_path_buf()).or_insert_with(|| {
+            let content = fs::read_to_string(folder.join(FOLDER_OPTIONS)).ok()?;
+            match toml::from_str(&content) {
+                Ok(table) => Some(table),
+                Err(e) => {
+                    error!("Failed to parse {:?}: {e}", folder.join(FOLDER_OPTIONS));
+                    None
+                }
+            }
+        });
+        if let Some(table) = table {
+            tables.push(table.clone());
+        }
+    }
+    tables.reverse();
+    tables
+}
+
+/// Options of every page, inherited from the page parent, overwritten by `_options.toml` of the
+/// folders the page is in and then by the attributes of the page itself. Which options are
+/// inherited can be limited per page with `root`, `inherit` and `no_inherit`
 fn create_options_map(
     module: &DefaultModule,
     site_tree: &SiteTree,
 ) -> Result<HashMap<usize, PropegatedOptions>, LssgError> {
     let mut options_map: HashMap<usize, PropegatedOptions> = HashMap::new();
+    // tables the options of a page are made of in the order they are applied, inherited options
+    // are filtered by key so these are kept instead of the options themselves
+    let mut tables_map: HashMap<usize, Vec<toml::Table>> = HashMap::new();
+    let mut folder_cache = HashMap::new();
     for id in DFS::new(site_tree) {
         if let SiteNodeKind::Page(page) = &site_tree[id].kind {
-            if let Some(parent) = site_tree.page_parent(id) {
-                if let Some(parent_options) = options_map.get(&parent) {
-                    let parent_options = parent_options.clone();
-                    let options: PropegatedOptions =
-                        module.options_with_default(page, parent_options);
-                    options_map.insert(id, options.clone());
-                    continue;
+            let single = SinglePageOptions::from_page(page);
+            let parent = site_tree.page_parent(id);
+            let mut tables = parent
+                .and_then(|parent| tables_map.get(&parent))
+                .cloned()
+                .unwrap_or_default();
+            let mut options = PropegatedOptions::default();
+            for table in &mut tables {
+                table.retain(|key, _| single.inherits(key));
+                // errors are logged for the page the table comes from
+                let _ = options.overwrite(table.clone());
+            }
+            for table in folder_options(site_tree, id, parent, &mut folder_cache) {
+                if let Err(e) = options.overwrite(table.clone()) {
+                    error!("Failed to parse {FOLDER_OPTIONS} for 'default' module: {e}")
                 }
+                tables.push(table);
             }
-
-            let options: PropegatedOptions = module.options(page);
-            options_map.insert(id, options.clone());
+            let options: PropegatedOptions = module.options_with_default(page, options);
+            if let Some(table) = page.attributes() {
+                tables.push(table.clone());
+            }
+            options_map.insert(id, options);
+            tables_map.insert(id, tables);
         }
     }
     Ok(options_map)
 }
 
+/// Class of a line in a diff, None for unchanged lines
+fn diff_line_class(line: &str) -> Option<&'static str> {
+    // file headers
+    if line.starts_with("+++") || line.starts_with("---") {
+        return None;
+    }
+    match line.chars().next() {
+        Some('+') => Some("diff-added"),
+        Some('-') => Some("diff-removed"),
+        _ if line.starts_with("@@") => Some("diff-hunk"),
+        _ => None,
+    }
+}
+
+/// Parts of the default module that need an asset and are used by at least one page
+#[derive(Debug, Default)]
+struct UsedFeatures {
+    carousel: bool,
+    copy_button: bool,
+    code_block: bool,
+}
+
+impl UsedFeatures {
+    fn from_pages(options_map: &HashMap<usize, PropegatedOptions>, site_tree: &SiteTree) -> Self {
+        let mut features = UsedFeatures::default();
+        for (id, options) in options_map {
+            let SiteNodeKind::Page(page) = &site_tree[*id].kind else {
+                continue;
+            };
+            visit_tokens(page.tokens(), |token| match token {
+                Token::Html { tag, .. } if tag == "carousel" => features.carousel = true,
+                Token::Code { info: Some(_), .. } => {
+                    features.copy_button |= options.copy_button;
+                    features.code_block |=
+                        code_block(token, options.copy_button, options.line_numbers).is_some();
+                }
+                _ => {}
+            });
+        }
+        features
+    }
+}
+
+/// Options of a code token rendered as a code block, None if it is rendered as plain `<code>`
+fn code_block(token: &Token, copy_button: bool, line_numbers: bool) -> Option<CodeBlock> {
+    let code_info = token.code_info();
+    let copy_button = copy_button && code_info.is_some();
+    let diff = code_info.as_ref().is_some_and(|info| info.is_diff());
+    let line_numbers = code_info
+        .as_ref()
+        .map(|info| info.line_numbers.unwrap_or(line_numbers))
+        .unwrap_or(false);
+    let info = code_info.unwrap_or_default();
+    if info.filename.is_none()
+        && info.highlight.is_empty()
+        && !copy_button
+        && !line_numbers
+        && !diff
+    {
+        return None;
+    }
+    Some(CodeBlock {
+        info,
+        copy_button,
+        line_numbers,
+        diff,
+    })
+}
+
+struct CodeBlock {
+    info: CodeInfo,
+    copy_button: bool,
+    line_numbers: bool,
+    diff: bool,
+}
+
+/// Add the asset `name` of the default module to the page being rendered
+fn require_asset(context: &RenderContext, name: &str) {
+    if let Some(id) = context
+        .site_tree
+        .get_by_name(name, context.site_tree.root())
+    {
+        context.require_asset(*id);
+    }
+}
+
+/// `src` and `srcset` of an image loaded through the `image_cdn` template, widths larger than the
+/// image itself are replaced by its own width. None if the template needs a width and there is
+/// none.
+fn cdn_image(
+    template: &str,
+    path: &str,
+    widths: &[u32],
+    image_width: Option<u32>,
+) -> Option<(String, Option<String>)> {
+    let path = path.trim_start_matches('/');
+    let url = |width: u32| {
+        template
+            .replace("{path}", path)
+            .replace("{width}", &width.to_string())
+    };
+    if !template.contains("{width}") {
+        return Some((template.replace("{path}", path), None));
+    }
+
+    let mut widths: Vec<u32> = widths
+        .iter()
+        .map(|w| match image_width {
+            Some(image_width) => (*w).min(image_width),
+            None => *w,
+        })
+        .collect();
+    widths.sort();
+    widths.dedup();
+    let src = url(*widths.last()?);
+    if widths.len() == 1 {
+        return Some((src, None));
+    }
+    let srcset = widths
+        .iter()
+        .map(|w| format!("{} {w}w", url(*w)))
+        .collect::<Vec<String>>()
+        .join(", ");
+    Some((src, Some(srcset)))
+}
+
+/// Badge with the extension and size of a file, `<span class="default__download_badge">PDF, 1.2 MB</span>`
+fn download_badge(name: &str, resource: &Resource) -> DomNode {
+    let mut parts = vec![];
+    if let Some((_, extension)) = name.rsplit_once('.') {
+        parts.push(extension.to_uppercase());
+    }
+    if let Some(size) = resource.size() {
+        parts.push(format_size(size));
+    }
+    let label = parts.join(", ");
+    dom!(<span class="default__download_badge">{label}</span>)
+}
+
+/// Get the value of `key` from the query of `src` (eg. "./icon.svg?inline=false")
+fn query_param<'a>(src: &'a str, key: &str) -> Option<&'a str> {
+    let (_, query) = src.split_once('?')?;
+    let query = query.split('#').next().unwrap_or(query);
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find_map(|(k, v)| if k == key { Some(v) } else { None })
+}
+
+/// The node `src` was discovered as link to from the current page
+fn discovered(context: &RenderContext, src: &str) -> Option<usize> {
+    context
+        .site_tree
+        .links_from(context.site_id)
+        .into_iter()
+        .find_map(|l| match &l.relation {
+            Relation::Discovered { raw_path } if raw_path == src => Some(l.to),
+            _ => None,
+        })
+}
+
+/// If paragraph `tokens` only contain an image with a title or an image followed by emphasized text
+/// return the image without title and the caption
+fn figure(tokens: &[Token]) -> Option<(Token, Vec<Token>)> {
+    let tokens: Vec<&Token> = tokens
+        .iter()
+        .filter(|t| match t {
+            Token::SoftBreak | Token::HardBreak => false,
+            Token::Text { text } => !text.trim().is_empty(),
+            _ => true,
+        })
+        .collect();
+    match tokens.as_slice() {
+        [Token::Image { tokens, src, title }, Token::Emphasis { text }] => Some((
+            Token::Image {
+                tokens: tokens.clone(),
+                src: src.clone(),
+                title: title.clone(),
+            },
+            vec![Token::Text { text: text.clone() }],
+        )),
+        [Token::Image {
+            tokens,
+            src,
+            title: Some(title),
+        }] => Some((
+            Token::Image {
+                tokens: tokens.clone(),
+                src: src.clone(),
+                title: None,
+            },
+            vec![Token::Text {
+                text: title.clone(),
+            }],
+        )),
+        _ => None,
+    }
+}
+
+/// Languages written from right to left
+const RTL_LANGUAGES: [&str; 10] = ["ar", "arc", "dv", "fa", "he", "ku", "ps", "sd", "ur", "yi"];
+
+/// Get the text direction of a page using the `dir` option or else based on the language, returns
+/// None if the browser default (ltr) can be used
+fn text_direction(options: &PropegatedOptions) -> Option<String> {
+    if let Some(dir) = &options.dir {
+        return Some(dir.clone());
+    }
+    let primary_language = options
+        .language
+        .split(['-', '_'])
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+    if RTL_LANGUAGES.contains(&primary_language.as_str()) {
+        Some("rtl".into())
+    } else {
+        None
+    }
+}
+
+/// Compute the sha384 subresource integrity hash of a stylesheet or resource
+fn integrity(site_tree: &SiteTree, id: usize) -> Result<String, LssgError> {
+    let mut hasher = Sha384::new();
+    match &site_tree[id].kind {
+        // hash the bytes on disk, which can include a source map comment
+        SiteNodeKind::Stylesheet(stylesheet) => {
+            hasher.update(stylesheet.output(&site_tree[id].name))
+        }
+        SiteNodeKind::Resource(resource) => {
+            io::copy(&mut resource.readable()?, &mut hasher)?;
+        }
+        _ => {
+            return Err(LssgError::render(
+                "integrity can only be computed for stylesheets and resources",
+            ))
+        }
+    }
+    Ok(format!("sha384-{}", STANDARD.encode(hasher.finalize())))
+}
+
+/// Add `integrity` and `crossorigin` attributes to an element linking to `id`
+fn set_integrity(element: &mut DomNode, site_tree: &SiteTree, id: usize) {
+    match integrity(site_tree, id) {
+        Ok(hash) => {
+            element.set_attribute("integrity", hash);
+            element.set_attribute("crossorigin", "anonymous".into());
+        }
+        Err(e) => error!("failed to compute integrity of {}: {e}", site_tree[id].name),
+    }
+}
+
+/// `<link rel="stylesheet">` to the stylesheet `id`
+fn stylesheet_link(
+    document: &Document,
+    context: &RenderContext,
+    id: usize,
+    integrity: bool,
+) -> DomNode {
+    let mut stylesheet = document.create_element_with_attributes(
+        "link",
+        to_attributes([
+            ("rel", "stylesheet"),
+            ("href", &context.site_tree.href(context.site_id, id)),
+        ]),
+    );
+    if integrity {
+        set_integrity(&mut stylesheet, context.site_tree, id);
+    }
+    stylesheet
+}
+
+/// Fill in `{page}` and `{site}` of a title template, when either is missing the other is
+/// used on its own
+fn format_title(template: &str, page: Option<&str>, site: &str) -> String {
+    match page {
+        Some(page) if site.is_empty() => page.to_string(),
+        Some(page) => template.replace("{page}", page).replace("{site}", site),
+        None => site.to_string(),
+    }
+}
+
 /// Render everything meant to go into <head>
 fn head(document: &mut Document, context: &RenderContext, options: &PropegatedOptions) {
     let RenderContext {
@@ -87,9 +635,7 @@ fn head(document: &mut Document, context: &RenderContext, options: &PropegatedOp
     } = context;
     let site_id = *site_id;
 
-    let head = &document.head;
-    let mut title = options.title.clone();
-    if let Some(header) = page
+    let header = page
         .tokens()
         .iter()
         .find(|t| {
@@ -98,47 +644,93 @@ fn head(document: &mut Document, context: &RenderContext, options: &PropegatedOp
             }
             false
         })
-        .cloned()
-    {
-        let header = tokens_to_text(&vec![header]);
-        title = format!("{header} - {title}");
-    }
+        .map(|header| tokens_to_text(&vec![header.clone()]));
+    let template = match &options.home_title_template {
+        Some(template) if site_id == site_tree.root() => template,
+        _ => &options.title_template,
+    };
+    let title = format_title(template, header.as_deref(), &options.title);
 
-    head.append_child(dom!(<title>{title}</title>));
-    let title = options.title.clone();
-    head.append_child(dom!(<meta property="og:title" content="{title}" />));
-    let title = options.title.clone();
-    head.append_child(dom!(<meta name="twitter:title" content="{title}" />));
+    let text = title.clone();
+    document.add_to_head(dom!(<title>{text}</title>), HeadPrecedence::Normal);
+    document.add_to_head(
+        dom!(<meta property="og:title" content="{title}" />),
+        HeadPrecedence::Normal,
+    );
+    document.add_to_head(
+        dom!(<meta name="twitter:title" content="{title}" />),
+        HeadPrecedence::Normal,
+    );
+    // other modules can set their own type, like blog posts being an article
+    document.add_to_head(
+        dom!(<meta property="og:type" content="website" />),
+        HeadPrecedence::Fallback,
+    );
+    // Open Graph uses underscores ("en_US")
+    let locale = options.language.replace('-', "_");
+    document.add_to_head(
+        dom!(<meta property="og:locale" content="{locale}" />),
+        HeadPrecedence::Normal,
+    );
+
+    // assets required by the content come first so linked stylesheets can override them
+    for id in context.required_assets() {
+        match site_tree[id].kind {
+            SiteNodeKind::Stylesheet { .. } => {
+                let stylesheet = stylesheet_link(document, context, id, options.integrity);
+                document.add_to_head(stylesheet, HeadPrecedence::Normal);
+            }
+            SiteNodeKind::Resource { .. } if site_tree[id].name.ends_with("js") => add_script(
+                document,
+                site_tree,
+                site_id,
+                id,
+                options.script_loading,
+                options.integrity,
+            ),
+            _ => {}
+        }
+    }
 
     // add stylesheets and favicon
     // reverse the order of insertion because latest css is applied last
+    let mut print_stylesheet = None;
     for link in site_tree.links_from(site_id).into_iter().rev() {
         match link.relation {
             Relation::External | Relation::Discovered { .. } => match site_tree[link.to].kind {
-                SiteNodeKind::Resource { .. } if site_tree[link.to].name == "favicon.ico" => {
-                    head.append_child(document.create_element_with_attributes(
-                        "link",
-                        to_attributes([
-                            ("rel", "icon"),
-                            ("type", "image/x-icon"),
-                            ("href", &site_tree.rel_path(site_id, link.to)),
-                        ]),
-                    ));
+                SiteNodeKind::Resource { .. } if favicon::is_favicon(&site_tree[link.to].name) => {
+                    let href = site_tree.href(site_id, link.to);
+                    if let Some(icon) = favicon::link(document, &site_tree[link.to].name, &href) {
+                        document.add_to_head(icon, HeadPrecedence::Normal);
+                    }
                 }
                 SiteNodeKind::Resource { .. } if site_tree[link.to].name.ends_with("js") => {
-                    let path = &site_tree.rel_path(site_id, link.to);
-                    document
-                        .body
-                        .append_child(dom!(<script src="{path}"></script>));
+                    let loading = match &link.relation {
+                        Relation::Discovered { raw_path } => {
+                            query_param(raw_path, "load").and_then(ScriptLoading::from_query)
+                        }
+                        _ => None,
+                    };
+                    add_script(
+                        document,
+                        site_tree,
+                        site_id,
+                        link.to,
+                        loading.unwrap_or(options.script_loading),
+                        options.integrity,
+                    );
                 }
                 SiteNodeKind::Stylesheet { .. } => {
-                    head.append_child(document.create_element_with_attributes(
-                        "link",
-                        to_attributes([
-                            ("rel", "stylesheet"),
-                            ("href", &site_tree.rel_path(site_id, link.to)),
-                        ]),
-                    ));
+                    let mut stylesheet =
+                        stylesheet_link(document, context, link.to, options.integrity);
+                    if site_tree.get_by_name(PRINT_STYLESHEET_NAME, site_tree.root())
+                        == Some(&link.to)
+                    {
+                        stylesheet.set_attribute("media", "print".into());
+                        print_stylesheet = Some(stylesheet);
+                        continue;
+                    }
+                    document.add_to_head(stylesheet, HeadPrecedence::Normal);
                 }
                 _ => {}
             },
@@ -146,49 +738,84 @@ fn head(document: &mut Document, context: &RenderContext, options: &PropegatedOp
         }
     }
 
+    // print styles should overwrite all other styles
+    if let Some(print_stylesheet) = print_stylesheet {
+        document.add_to_head(print_stylesheet, HeadPrecedence::Normal);
+    }
+
+    let single_options = SinglePageOptions::resolve(context.site_tree, context.site_id);
+    let robots = match single_options.unlisted {
+        true => single_options.robots.or(Some("noindex".into())),
+        false => single_options.robots,
+    };
+    if let Some(robots) = robots {
+        document.add_to_head(
+            dom!(<meta name="robots" content="{robots}" />),
+            HeadPrecedence::Override,
+        );
+    }
+
+    if let Some(webmention) = options.webmention.clone() {
+        document.add_to_head(
+            dom!(<link rel="webmention" href="{webmention}" />),
+            HeadPrecedence::Normal,
+        );
+    }
+    if let Some(pingback) = options.pingback.clone() {
+        document.add_to_head(
+            dom!(<link rel="pingback" href="{pingback}" />),
+            HeadPrecedence::Normal,
+        );
+    }
+
     // meta tags
-    head.append_child(document.create_element_with_attributes(
+    let viewport = document.create_element_with_attributes(
         "meta",
         to_attributes([
             ("name", "viewport"),
             ("content", r#"width=device-width, initial-scale=1"#),
         ]),
-    ));
-    head.append_child(
-        document.create_element_with_attributes("meta", to_attributes([("charset", "utf-8")])),
     );
-    for (key, value) in &options.meta {
-        if key == "description" {
-            head.append_child(document.create_element_with_attributes(
-                "meta",
-                to_attributes([("name", key), ("content", value)]),
-            ));
+    document.add_to_head(viewport, HeadPrecedence::Normal);
+    document.add_to_head(dom!(<meta charset="utf-8" />), HeadPrecedence::Normal);
+    let mut meta: Vec<(String, String, HeadPrecedence)> = options
+        .meta
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone(), HeadPrecedence::Override))
+        .collect();
+    if options.auto_description && !options.meta.contains_key("description") {
+        if let Some(description) = page_description(page, DESCRIPTION_LENGTH) {
+            meta.push(("description".into(), description, HeadPrecedence::Fallback));
         }
+    }
+    let card = match options.meta.contains_key("image") || options.meta.contains_key("og:image") {
+        true => "summary_large_image",
+        false => "summary",
+    };
+    document.add_to_head(
+        dom!(<meta name="twitter:card" content="{card}" />),
+        HeadPrecedence::Normal,
+    );
+    for (key, value, precedence) in meta {
+        let mut tags = vec![];
         match key.as_str() {
             "description" | "image" => {
-                head.append_child(document.create_element_with_attributes(
-                    "meta",
-                    to_attributes([("property", &format!("og:{}", key)), ("content", value)]),
-                ));
-                head.append_child(document.create_element_with_attributes(
-                    "meta",
-                    to_attributes([("name", &format!("twitter:{}", key)), ("content", value)]),
-                ));
-                continue;
+                if key == "description" {
+                    tags.push(("name", key.clone()));
+                }
+                tags.push(("property", format!("og:{key}")));
+                tags.push(("name", format!("twitter:{key}")));
             }
-            _ => {}
+            // Open Graph (https://ogp.me/) uses property instead of name
+            _ if key.starts_with("og:") => tags.push(("property", key)),
+            _ => tags.push(("name", key)),
         }
-        // Open Graph (https://ogp.me/) uses property instead of name
-        if key.starts_with("og:") {
-            head.append_child(document.create_element_with_attributes(
+        for (attribute, name) in tags {
+            let meta = document.create_element_with_attributes(
                 "meta",
-                to_attributes([("property", key), ("content", value)]),
-            ));
-        } else {
-            head.append_child(document.create_element_with_attributes(
-                "meta",
-                to_attributes([("name", key), ("content", value)]),
-            ));
+                to_attributes([(attribute, name.as_str()), ("content", value.as_str())]),
+            );
+            document.add_to_head(meta, precedence);
         }
     }
 }
@@ -197,13 +824,143 @@ fn head(document: &mut Document, context: &RenderContext, options: &PropegatedOp
 pub struct DefaultModule {
     /// Map of all site pages to options. Considers options from parents.
     options_map: HashMap<usize, PropegatedOptions>,
+    /// Content Security Policy of all rendered pages combined
+    csp: ContentSecurityPolicy,
+    /// Cached width and height of image resources
+    image_dimensions: HashMap<usize, Option<(u32, u32)>>,
+    /// Cached EXIF metadata of image resources
+    image_metadata: HashMap<usize, Option<ImageMetadata>>,
+    /// Footnotes of the page that is being rendered
+    footnotes: Option<(usize, Footnotes)>,
 }
 
 impl DefaultModule {
     pub fn new() -> Self {
         Self {
             options_map: HashMap::new(),
+            csp: ContentSecurityPolicy::default(),
+            image_dimensions: HashMap::new(),
+            image_metadata: HashMap::new(),
+            footnotes: None,
+        }
+    }
+
+    /// If `tokens` only contain images sort them by the date they were taken, images without a
+    /// date keep their position relative to each other at the end
+    fn sort_by_taken_on(&mut self, context: &RenderContext, tokens: &[Token]) -> Vec<Token> {
+        let is_image = |t: &Token| matches!(t, Token::Image { .. });
+        let only_images = tokens.iter().all(|t| match t {
+            Token::Image { .. } | Token::SoftBreak | Token::HardBreak => true,
+            Token::Text { text } => text.trim().is_empty(),
+            _ => false,
+        });
+        if !only_images || tokens.iter().filter(|t| is_image(t)).count() < 2 {
+            return tokens.to_vec();
+        }
+        let mut images: Vec<(Option<NaiveDateTime>, Token)> = tokens
+            .iter()
+            .filter(|t| is_image(t))
+            .map(|t| match t {
+                Token::Image { src, .. } => (
+                    self.image_metadata(context, src).and_then(|m| m.taken_on),
+                    t.clone(),
+                ),
+                _ => unreachable!(),
+            })
+            .collect();
+        images.sort_by_key(|(taken_on, _)| (taken_on.is_none(), *taken_on));
+        let mut images = images.into_iter().map(|(_, t)| t);
+        tokens
+            .iter()
+            .map(|t| match is_image(t) {
+                true => images.next().unwrap(),
+                false => t.clone(),
+            })
+            .collect()
+    }
+
+    /// Figure with the EXIF description as caption if `tokens` only contain an image
+    fn exif_figure(
+        &mut self,
+        context: &RenderContext,
+        tokens: &[Token],
+    ) -> Option<(Token, Vec<Token>)> {
+        let mut images = tokens.iter().filter(|t| match t {
+            Token::Text { text } => !text.trim().is_empty(),
+            Token::SoftBreak | Token::HardBreak => false,
+            _ => true,
+        });
+        let image = images.next()?;
+        if images.next().is_some() {
+            return None;
+        }
+        let Token::Image { src, .. } = image else {
+            return None;
+        };
+        let description = self.image_metadata(context, src)?.description?;
+        Some((image.clone(), vec![Token::Text { text: description }]))
+    }
+
+    /// Get the EXIF metadata of the image `src` links to from the current page
+    fn image_metadata(&mut self, context: &RenderContext, src: &str) -> Option<ImageMetadata> {
+        let id = discovered(context, src)?;
+        self.image_metadata
+            .entry(id)
+            .or_insert_with(|| match &context.site_tree[id].kind {
+                SiteNodeKind::Resource(resource) => resource.image_metadata(),
+                _ => None,
+            })
+            .clone()
+    }
+
+    /// Get the intrinsic width and height of an image resource by reading its header
+    fn image_dimensions(&mut self, site_tree: &SiteTree, id: usize) -> Option<(u32, u32)> {
+        *self.image_dimensions.entry(id).or_insert_with(|| {
+            let SiteNodeKind::Resource(resource) = &site_tree[id].kind else {
+                return None;
+            };
+            let mut content = vec![];
+            resource.readable().ok()?.read_to_end(&mut content).ok()?;
+            image::ImageReader::new(io::Cursor::new(content))
+                .with_guessed_format()
+                .ok()?
+                .into_dimensions()
+                .ok()
+        })
+    }
+
+    /// Footnotes of the page that is being rendered, starting over for every page
+    fn footnotes(&mut self, context: &RenderContext) -> &mut Footnotes {
+        if self.footnotes.as_ref().map(|(id, _)| *id) != Some(context.site_id) {
+            self.footnotes = Some((context.site_id, Footnotes::new(context.page.tokens())));
         }
+        &mut self.footnotes.as_mut().expect("footnotes are set").1
+    }
+
+    /// Generate favicons from the `favicon` option of the root page and link them from root
+    fn add_favicons(&self, site_tree: &mut SiteTree) -> Result<(), LssgError> {
+        let root = site_tree.root();
+        let options: PropegatedOptions = match &site_tree[root].kind {
+            SiteNodeKind::Page(page) => self.options(page),
+            _ => return Ok(()),
+        };
+        let Some(source) = options.favicon else {
+            return Ok(());
+        };
+        let input = match site_tree.get_input(root) {
+            Some(input) => input.new(&source)?,
+            None => return Ok(()),
+        };
+
+        for (name, content) in favicon::generate(&input, &options.title)? {
+            if site_tree.get_by_name(name, root).is_some() {
+                warn!("{name} already exists, not replacing it with generated favicon");
+                continue;
+            }
+            let id = site_tree.add(SiteNode::resource(name, root, Resource::Static { content }));
+            site_tree.add_link(root, id);
+        }
+        Ok(())
     }
 }
 
@@ -218,12 +975,49 @@ impl RendererModule for DefaultModule {
             .filter(|id| site_tree[*id].kind.is_page())
             .collect();
 
-        let default_js = site_tree.add(SiteNode::resource(
-            "default.js",
-            site_tree.root(),
-            Resource::new_static(DEFAULT_JS.to_owned()),
-        ));
-        site_tree.add_link(site_tree.root(), default_js);
+        // data files are read while rendering, track them so changes to them are noticed
+        for id in &pages {
+            let (SiteNodeKind::Page(page), Some(input)) =
+                (&site_tree[*id].kind, site_tree.get_input(*id))
+            else {
+                continue;
+            };
+            for file in data_table::data_files(page, input) {
+                site_tree.add_source(file);
+            }
+        }
+
+        let options_map = create_options_map(self, site_tree)?;
+        // add linked local files as downloads where `download_badges` is enabled
+        for id in &pages {
+            if options_map.get(id).is_some_and(|o| o.download_badges) {
+                site_tree.add_downloads(*id)?;
+            }
+        }
+
+        // only add the assets that are used, pages require them while rendering
+        let features = UsedFeatures::from_pages(&options_map, site_tree);
+        let mut js = vec![];
+        if features.carousel {
+            js.push(CAROUSEL_JS);
+        }
+        if features.copy_button {
+            js.push(COPY_BUTTON_JS);
+        }
+        if !js.is_empty() {
+            site_tree.add(SiteNode::resource(
+                DEFAULT_JS_NAME,
+                site_tree.root(),
+                Resource::new_static(js.join("\n")),
+            ));
+        }
+        if features.code_block {
+            site_tree.add(SiteNode::stylesheet(
+                CODE_STYLESHEET_NAME,
+                site_tree.root(),
+                Stylesheet::from_readable(CODE_STYLESHEET)?,
+            ));
+        }
 
         let default_stylesheet = site_tree.add(SiteNode::stylesheet(
             "default.css",
@@ -232,6 +1026,15 @@ impl RendererModule for DefaultModule {
         ));
         site_tree.add_link(site_tree.root(), default_stylesheet);
 
+        let print_stylesheet = site_tree.add(SiteNode::stylesheet(
+            PRINT_STYLESHEET_NAME,
+            site_tree.root(),
+            Stylesheet::from_readable(PRINT_STYLESHEET)?,
+        ));
+        site_tree.add_link(site_tree.root(), print_stylesheet);
+
+        self.add_favicons(site_tree)?;
+
         let mut relation_map: HashMap<usize, Vec<usize>> = HashMap::new();
         // propegate relations to stylesheets and favicon from parent to child
         for id in pages {
@@ -252,7 +1055,7 @@ impl RendererModule for DefaultModule {
                         let node = &site_tree[link.to];
                         match node.kind {
                             SiteNodeKind::Stylesheet { .. } => Some(link.to),
-                            SiteNodeKind::Resource { .. } if node.name == "favicon.ico" => {
+                            SiteNodeKind::Resource { .. } if favicon::is_favicon(&node.name) => {
                                 Some(link.to)
                             }
                             _ => None,
@@ -296,22 +1099,91 @@ impl RendererModule for DefaultModule {
         let site_tree = context.site_tree;
         let body = &document.body;
 
+        let options = self
+            .options_map
+            .get(&site_id)
+            .expect("expected options map to contain all page ids");
+
+        if let Some(section) = self
+            .footnotes
+            .take()
+            .filter(|(id, _)| *id == site_id)
+            .and_then(|(_, footnotes)| footnotes.section())
+        {
+            body.append_child(section);
+        }
+
+        // sanitize rendered content before anything else gets added
+        if options.sanitize {
+            sanitize::sanitize(body);
+        }
+
+        if options.sidenotes {
+            sidenotes::sidenotes(body);
+        }
+
+        if options.svg_sprites {
+            svg_sprites::svg_sprites(body);
+        }
+
+        link_hygiene(body, &options.link_hygiene);
+
+        // error pages are served from any url so links relative to them would break
+        if site_tree.is_error_page(site_id) {
+            for d in body.descendants() {
+                if let DomNodeKind::Element { attributes, .. } = &mut *d.kind_mut() {
+                    for key in ["href", "src"] {
+                        if let Some(value) = attributes.get_mut(key) {
+                            *value = site_tree.absolute_href(site_id, value);
+                        }
+                    }
+                }
+            }
+        }
+
+        if options.backlinks {
+            if let Some(backlinks) = backlinks::backlinks(context) {
+                body.append_child(backlinks);
+            }
+        }
+
+        // move all dom elements to under #content
+        let content =
+            document.create_element_with_attributes("main", to_attributes([("id", "content")]));
+        for child in body.children() {
+            child.detach();
+            content.append_child(child);
+        }
+        body.append_child(content);
+
         // add breacrumbs if not root
         if context.site_id != context.site_tree.root() {
-            let nav = document
-                .create_element_with_attributes("nav", to_attributes([("class", "breadcrumbs")]));
+            let nav = document.create_element_with_attributes(
+                "nav",
+                to_attributes([("class", "breadcrumbs"), ("aria-label", "Breadcrumbs")]),
+            );
 
             nav.append_child(document.create_text_node("/"));
 
             let parents = site_tree.parents(site_id);
             let parents_length = parents.len();
             for (i, p) in parents.into_iter().rev().enumerate() {
-                let a = document.create_element_with_attributes(
-                    "a",
-                    to_attributes([("href", site_tree.rel_path(site_id, p))]),
-                );
-                a.append_child(document.create_text_node(site_tree[p].name.clone()));
-                nav.append_child(a);
+                let name = document.create_text_node(site_tree[p].name.clone());
+                // unlisted pages are not linked to
+                let listed = match &site_tree[p].kind {
+                    SiteNodeKind::Page(_) => SinglePageOptions::resolve(site_tree, p).is_listed(),
+                    _ => true,
+                };
+                if listed {
+                    let a = document.create_element_with_attributes(
+                        "a",
+                        to_attributes([("href", site_tree.href(site_id, p))]),
+                    );
+                    a.append_child(name);
+                    nav.append_child(a);
+                } else {
+                    nav.append_child(name);
+                }
                 if i != parents_length - 1 {
                     nav.append_child(document.create_text_node("/"));
                 }
@@ -321,32 +1193,64 @@ impl RendererModule for DefaultModule {
             body.prepend(nav);
         }
 
-        // move all dom elements to under #content
-        let content =
-            document.create_element_with_attributes("div", to_attributes([("id", "content")]));
-        for child in body.children() {
-            child.detach();
-            content.append_child(child);
-        }
-        body.append_child(content);
+        // allow keyboard users to skip navigation
+        body.prepend(dom!(<a class="default__skip_link" href="#content">Skip to content</a>));
 
         // add watermark
         body.append_child(dom!(<footer id="watermark">Generated by <a href="https://github.com/lyr-7D1h/lssg">LSSG</a></footer>));
 
-        let options = self
-            .options_map
-            .get(&site_id)
-            .expect("expected options map to contain all page ids");
-
-        // Add language to html tag
+        // Add language and text direction to html tag
         if let DomNodeKind::Element { attributes, .. } = &mut *document.root().kind_mut() {
-            attributes.insert("lang".to_owned(), options.language.clone());
+            attributes.insert("lang".into(), options.language.clone());
+            if let Some(dir) = text_direction(options) {
+                attributes.insert("dir".into(), dir);
+            }
         }
 
         // fill head
         head(document, context, options);
     }
 
+    fn finish_render<'n>(&mut self, document: &mut Document, context: &RenderContext<'n>) {
+        let options = self
+            .options_map
+            .get(&context.site_id)
+            .expect("expected options map to contain all page ids");
+        // built last so elements added by other modules (eg. docs, headers) are included
+        if let Some(output) = &options.csp {
+            let mut csp = ContentSecurityPolicy::from_document(document);
+            for link in context.site_tree.links_from(context.site_id) {
+                if let SiteNodeKind::Stylesheet(stylesheet) = &context.site_tree[link.to].kind {
+                    csp.add_stylesheet(stylesheet);
+                }
+            }
+            if output.meta() {
+                let policy = csp.to_string();
+                // policy only applies to elements after it so it has to be first
+                document.add_to_head(
+                    dom!(<meta http-equiv="Content-Security-Policy" content="{policy}" />),
+                    HeadPrecedence::Normal,
+                );
+            }
+            self.csp.extend(&csp);
+        }
+    }
+
+    fn finish(&mut self, site_tree: &SiteTree, output_directory: &Path) -> Result<(), LssgError> {
+        let options = self
+            .options_map
+            .get(&site_tree.root())
+            .expect("expected options map to contain root");
+        if let Some(output) = &options.csp {
+            if output.headers() {
+                let path = output_directory.join("_headers");
+                info!("Writing {path:?}");
+                fs::write(path, self.csp.to_headers_file())?;
+            }
+        }
+        Ok(())
+    }
+
     fn render_body<'n>(
         &mut self,
         document: &mut Document,
@@ -355,6 +1259,18 @@ impl RendererModule for DefaultModule {
         token: &crate::lmarkdown::Token,
         tr: &mut TokenRenderer,
     ) -> Option<DomNode> {
+        let (variables, copy_button, line_numbers) = self
+            .options_map
+            .get(&context.site_id)
+            .map(|o| (o.variables, o.copy_button, o.line_numbers))
+            .unwrap_or((false, false, false));
+        let text = |text: &String| {
+            if variables {
+                variables::substitute(text, context)
+            } else {
+                text.clone()
+            }
+        };
         match token {
             Token::OrderedList { items, .. } => {
                 let ol = document.create_element("ol");
@@ -375,11 +1291,26 @@ impl RendererModule for DefaultModule {
                 }
                 parent.append_child(ol);
             }
-            Token::BulletList { items, .. } => {
+            Token::BulletList { items, checked } => {
                 let ul = document.create_element("ul");
-                for tokens in items {
+                for (i, tokens) in items.iter().enumerate() {
                     let li = document.create_element("li");
                     ul.append_child(li.clone());
+                    if let Some(Some(checked)) = checked.get(i) {
+                        li.add_class("task-list-item");
+                        let mut attributes = to_attributes([
+                            ("type", "checkbox"),
+                            ("class", "task-list-item-checkbox"),
+                            ("disabled", ""),
+                        ]);
+                        if *checked {
+                            attributes.insert("checked".into(), String::new());
+                        }
+                        li.append_child(
+                            document.create_element_with_attributes("input", attributes),
+                        );
+                        li.append_child(document.create_text_node(" "));
+                    }
                     // don't render paragraphs inside of lists
                     let tokens = tokens
                         .into_iter()
@@ -400,35 +1331,28 @@ impl RendererModule for DefaultModule {
                 parent.append_child(document.create_element("hr"));
             }
             Token::Image { tokens, src, title } => {
+                let token_src = src;
                 let mut resource_id = None;
-                // if local page return relative src
-                let src = if Input::is_relative(src) {
-                    let to_id = context
-                        .site_tree
-                        .links_from(context.site_id)
-                        .into_iter()
-                        .find_map(|l| {
-                            if let Relation::Discovered { raw_path: path } = &l.relation {
-                                if path == src {
-                                    return Some(l.to);
-                                }
-                            }
-                            None
-                        });
-
-                    if let Some(to_id) = to_id {
-                        resource_id = Some(to_id);
-                        context.site_tree.path(to_id)
-                    } else {
+                // if local or mirrored image return path to the resource
+                let to_id = discovered(context, src);
+                let src = if let Some(to_id) = to_id {
+                    resource_id = Some(to_id);
+                    context.site_tree.path(to_id)
+                } else {
+                    if Input::is_relative(src) {
                         warn!("Could not find node where {src:?} points to");
-                        src.to_owned()
                     }
-                } else {
-                    src.to_owned()
+                    context.site_tree.prefixed(src)
                 };
 
-                // inject svg into html
-                if src.ends_with(".svg") {
+                let svg_sprites = self
+                    .options_map
+                    .get(&context.site_id)
+                    .map(|o| o.svg_sprites)
+                    .unwrap_or(false);
+                // inject svg into html unless disabled with `?inline=false`
+                let inline = query_param(token_src, "inline") != Some("false");
+                if src.ends_with(".svg") && inline {
                     let readable = if let Some(id) = resource_id {
                         match &context.site_tree[id].kind {
                             SiteNodeKind::Resource(r) => r.readable(),
@@ -450,8 +1374,12 @@ impl RendererModule for DefaultModule {
                     match readable {
                         Ok(r) => {
                             // get first valid html tag
-                            let mut html = parse_html(r)
-                                .unwrap()
+                            let (html, warnings) =
+                                parse_html_with_warnings(r, ParseOptions::default()).unwrap();
+                            for warning in warnings {
+                                warn!("{src}:{warning}");
+                            }
+                            let mut html = html
                                 .into_iter()
                                 .find(|e| match e {
                                     Html::Comment { .. } | Html::Text { .. } => false,
@@ -486,9 +1414,18 @@ impl RendererModule for DefaultModule {
                                             format!("0 0 {width} {height}"),
                                         );
                                     }
-                                    attributes.remove(&"style".to_string());
-                                    attributes.remove(&"width".to_string());
-                                    attributes.remove(&"height".to_string());
+                                    attributes.shift_remove("style");
+                                    attributes.shift_remove("width");
+                                    attributes.shift_remove("height");
+                                    if svg_sprites {
+                                        // mark svg so repeated svgs can be turned into sprites
+                                        attributes.insert(
+                                            svg_sprites::SVG_ID_ATTRIBUTE.into(),
+                                            resource_id
+                                                .map(|id| id.to_string())
+                                                .unwrap_or(src.clone()),
+                                        );
+                                    }
 
                                     parent.append_child(html);
                                     return Some(parent);
@@ -511,13 +1448,104 @@ impl RendererModule for DefaultModule {
                     return Some(parent);
                 }
 
-                let alt = tokens_to_text(tokens);
-                #[allow(unused_variables)]
+                let mut alt = tokens_to_text(tokens);
+                let exif = self
+                    .options_map
+                    .get(&context.site_id)
+                    .map(|o| o.exif)
+                    .unwrap_or(false);
+                if alt.is_empty() && exif {
+                    if let Some(description) = self
+                        .image_metadata(context, token_src)
+                        .and_then(|m| m.description)
+                    {
+                        alt = description;
+                    }
+                }
+                let mut img = dom!(<img src="{src}" alt="{alt}" />);
+                let (image_cdn, image_widths) = self
+                    .options_map
+                    .get(&context.site_id)
+                    .map(|o| (o.image_cdn.clone(), o.image_widths.clone()))
+                    .unwrap_or_default();
+                if let (Some(template), Some(id)) = (image_cdn, resource_id) {
+                    let image_width = self
+                        .image_dimensions(context.site_tree, id)
+                        .map(|(width, _)| width);
+                    match cdn_image(&template, &src, &image_widths, image_width) {
+                        Some((src, srcset)) => {
+                            img.set_attribute("src", src);
+                            if let Some(srcset) = srcset {
+                                img.set_attribute("srcset", srcset);
+                            }
+                        }
+                        None => warn!("no width to load {src} through image_cdn"),
+                    }
+                }
                 if let Some(title) = title {
-                    parent.append_child(dom!(<img src="{src}" alt="{alt}" title={title} />))
-                } else {
-                    parent.append_child(dom!(<img src="{src}" alt="{alt}" />))
+                    img.set_attribute("title", title.clone());
                 }
+                let lazy_images = self
+                    .options_map
+                    .get(&context.site_id)
+                    .map(|o| o.lazy_images)
+                    .unwrap_or(false);
+                if lazy_images {
+                    img.set_attribute("loading", "lazy".into());
+                    img.set_attribute("decoding", "async".into());
+                    // prevent layout shift by reserving space for the image
+                    if let Some((width, height)) =
+                        resource_id.and_then(|id| self.image_dimensions(context.site_tree, id))
+                    {
+                        img.set_attribute("width", width.to_string());
+                        img.set_attribute("height", height.to_string());
+                    }
+                }
+                parent.append_child(img)
+            }
+            Token::Table {
+                align,
+                header,
+                rows,
+            } => {
+                let table = document.create_element_with_attributes(
+                    "table",
+                    to_attributes([("class", "default__table")]),
+                );
+                let thead = document.create_element("thead");
+                let tbody = document.create_element("tbody");
+                for (i, cells) in std::iter::once(header).chain(rows).enumerate() {
+                    let row = document.create_element("tr");
+                    for (cell, align) in cells.iter().zip(align) {
+                        let mut attributes = Attributes::new();
+                        if let Some(align) = align {
+                            let style = format!("text-align: {}", align.as_str());
+                            attributes.insert("style".into(), style);
+                        }
+                        let tag = if i == 0 { "th" } else { "td" };
+                        let element = document.create_element_with_attributes(tag, attributes);
+                        tr.render(document, context, element.clone(), cell);
+                        row.append_child(element);
+                    }
+                    match i {
+                        0 => thead.append_child(row),
+                        _ => tbody.append_child(row),
+                    }
+                }
+                table.append_child(thead);
+                if !rows.is_empty() {
+                    table.append_child(tbody);
+                }
+                parent.append_child(table);
+            }
+            Token::FootnoteReference { label } => match self.footnotes(context).reference(label) {
+                Some(reference) => parent.append_child(reference),
+                None => parent.append_child(document.create_text_node(format!("[^{label}]"))),
+            },
+            Token::FootnoteDefinition { label, tokens } => {
+                let li = document.create_element("li");
+                tr.render(document, context, li.clone(), tokens);
+                self.footnotes(context).define(label, li);
             }
             Token::BlockQuote { tokens, .. } => {
                 let blockquote = document.create_element("blockquote");
@@ -536,27 +1564,121 @@ impl RendererModule for DefaultModule {
                 parent.append_child(heading)
             }
             Token::Paragraph { tokens, .. } => {
+                let (figures, exif) = self
+                    .options_map
+                    .get(&context.site_id)
+                    .map(|o| (o.figures, o.exif))
+                    .unwrap_or((false, false));
+                let tokens = &match exif {
+                    true => self.sort_by_taken_on(context, tokens),
+                    false => tokens.clone(),
+                };
+                if figures {
+                    let figure = figure(tokens).or_else(|| match exif {
+                        true => self.exif_figure(context, tokens),
+                        false => None,
+                    });
+                    if let Some((image, caption)) = figure {
+                        let figure = document.create_element("figure");
+                        tr.render(document, context, figure.clone(), &vec![image.clone()]);
+                        let figcaption = document.create_element("figcaption");
+                        tr.render(document, context, figcaption.clone(), &caption);
+                        if let (true, Token::Image { src, .. }) = (exif, &image) {
+                            if let Some(taken_on) =
+                                self.image_metadata(context, src).and_then(|m| m.taken_on)
+                            {
+                                let datetime = taken_on.format("%Y-%m-%dT%H:%M").to_string();
+                                let date = taken_on.format("%B %d, %Y").to_string();
+                                figcaption.append_child(document.create_text_node(" "));
+                                figcaption
+                                    .append_child(dom!(<time datetime="{datetime}">{date}</time>));
+                            }
+                        }
+                        figure.append_child(figcaption);
+                        parent.append_child(figure);
+                        return Some(parent);
+                    }
+                }
                 let p = document.create_element("p");
                 tr.render(document, context, p.clone(), tokens);
                 parent.append_child(p)
             }
-            Token::Bold { text } => {
+            Token::Bold { text: t } => {
                 let b = document.create_element("b");
-                b.append_child(document.create_text_node(text));
+                b.append_child(document.create_text_node(text(t)));
                 parent.append_child(b)
             }
-            Token::Emphasis { text } => {
+            Token::Emphasis { text: t } => {
                 let e = document.create_element("em");
-                e.append_child(document.create_text_node(text));
+                e.append_child(document.create_text_node(text(t)));
                 parent.append_child(e)
             }
+            Token::Strikethrough { text: t } => {
+                let del = document.create_element("del");
+                del.append_child(document.create_text_node(text(t)));
+                parent.append_child(del)
+            }
             Token::Code {
                 text: code,
                 info: _,
             } => {
                 let code_html = document.create_element("code");
-                code_html.append_child(document.create_text_node(code));
-                parent.append_child(code_html)
+                let Some(CodeBlock {
+                    info: code_info,
+                    copy_button,
+                    line_numbers,
+                    diff,
+                }) = code_block(token, copy_button, line_numbers)
+                else {
+                    code_html.append_child(document.create_text_node(code));
+                    parent.append_child(code_html);
+                    return Some(parent);
+                };
+                require_asset(context, CODE_STYLESHEET_NAME);
+
+                let block = match line_numbers {
+                    true => dom!(<div class="code-block line-numbers"></div>),
+                    false => dom!(<div class="code-block"></div>),
+                };
+                if let Some(filename) = &code_info.filename {
+                    block.append_child(dom!(<div class="code-filename">{filename}</div>));
+                }
+                if copy_button {
+                    require_asset(context, DEFAULT_JS_NAME);
+                    block.append_child(
+                        dom!(<button class="code-copy" type="button" aria-label="Copy code">Copy</button>),
+                    );
+                }
+                if let Some(language) = code_info.code_language() {
+                    code_html.add_class(&format!("language-{language}"));
+                }
+                let lines: Vec<&str> = code.trim_end_matches('\n').split('\n').collect();
+                for (i, line) in lines.iter().enumerate() {
+                    // keep line breaks inside the lines so copying keeps them
+                    let line = match i + 1 == lines.len() {
+                        true => line.to_string(),
+                        false => format!("{line}\n"),
+                    };
+                    let diff_class = diff.then(|| diff_line_class(&line)).flatten();
+                    let highlighted = code_info.is_highlighted(i + 1);
+                    // every line needs an element to be numbered by the stylesheet
+                    if !highlighted && !line_numbers && diff_class.is_none() {
+                        code_html.append_child(document.create_text_node(line));
+                        continue;
+                    }
+                    let class = match diff_class {
+                        Some(diff_class) => format!("code-line {diff_class}"),
+                        None => "code-line".to_string(),
+                    };
+                    let line_html = DomNode::create_element_with_attributes(
+                        if highlighted { "mark" } else { "span" },
+                        to_attributes([("class", class)]),
+                    );
+                    line_html.append_child(DomNode::create_text(line));
+                    code_html.append_child(line_html);
+                }
+                block.append_child(code_html);
+                parent.append_child(block)
             }
             Token::Link {
                 tokens,
@@ -567,44 +1689,53 @@ impl RendererModule for DefaultModule {
                 if tokens.len() == 0 {
                     return Some(parent);
                 }
+                // unresolved wiki link
+                if wiki_link_target(href).is_some() {
+                    tr.render(document, context, parent.clone(), tokens);
+                    return Some(parent);
+                }
 
-                let href = &process_href(href, context);
-                // if local page return relative path
-                let href = if Page::is_href_to_page(href) {
-                    let to_id = context
-                        .site_tree
-                        .links_from(context.site_id)
-                        .into_iter()
-                        .find_map(|l| {
-                            if let Relation::Discovered { raw_path: path } = &l.relation {
-                                if path == href {
-                                    return Some(l.to);
-                                }
-                            }
-                            None
-                        });
-
-                    if let Some(to_id) = to_id {
-                        let rel_path = context.site_tree.path(to_id);
-                        rel_path
-                    } else {
-                        warn!("Could not find node where {href:?} points to");
-                        href.to_owned()
-                    }
-                } else {
-                    href.to_owned()
+                // discovered pages and files
+                let to_id = context
+                    .site_tree
+                    .links_from(context.site_id)
+                    .into_iter()
+                    .find_map(|l| match &l.relation {
+                        Relation::Discovered { raw_path } | Relation::Download { raw_path }
+                            if raw_path == href =>
+                        {
+                            Some(l.to)
+                        }
+                        _ => None,
+                    });
+                let href = match to_id {
+                    Some(to_id) => context.site_tree.path(to_id),
+                    None => process_href(href, context),
                 };
 
                 let mut attributes = to_attributes([("href", href)]);
                 if let Some(title) = title {
-                    attributes.insert("title".to_owned(), title.to_owned());
+                    attributes.insert("title".into(), title.to_owned());
                 }
                 let a = document.create_element_with_attributes("a", attributes);
                 tr.render(document, context, a.clone(), tokens);
+                let download_badges = self
+                    .options_map
+                    .get(&context.site_id)
+                    .map(|o| o.download_badges)
+                    .unwrap_or(false);
+                if download_badges {
+                    if let Some(to_id) = to_id {
+                        let node = &context.site_tree[to_id];
+                        if let SiteNodeKind::Resource(resource) = &node.kind {
+                            a.append_child(download_badge(&node.name, resource));
+                        }
+                    }
+                }
                 parent.append_child(a);
             }
-            Token::Text { text } => {
-                parent.append_child(document.create_text_node(text));
+            Token::Text { text: t } => {
+                parent.append_child(document.create_text_node(text(t)));
             }
             Token::Html {
                 tag,
@@ -622,3 +1753,134 @@ impl RendererModule for DefaultModule {
         Some(parent)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folder_options() {
+        let root = std::env::temp_dir().join(format!("lssg-folder-options-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("blog/drafts")).unwrap();
+        fs::write(
+            root.join(FOLDER_OPTIONS),
+            "title = \"Folder\"\nlanguage = \"de\"",
+        )
+        .unwrap();
+        fs::write(
+            root.join("home.md"),
+            "<!--\ntitle = \"Home\"\n-->\n# Home\n\n[Blog](./blog/blog.md)",
+        )
+        .unwrap();
+        fs::write(
+            root.join("blog/blog.md"),
+            "# Blog\n\n[Draft](./drafts/draft.md)",
+        )
+        .unwrap();
+        fs::write(
+            root.join("blog/drafts").join(FOLDER_OPTIONS),
+            "language = \"nl\"\nunlisted = true",
+        )
+        .unwrap();
+        fs::write(root.join("blog/drafts/draft.md"), "# Draft").unwrap();
+
+        let input = Input::from_string(root.join("home.md").to_str().unwrap()).unwrap();
+        let site_tree = SiteTree::from_input(input).unwrap();
+        let options_map = create_options_map(&DefaultModule::new(), &site_tree).unwrap();
+        let options = |name: &str| {
+            let id = (0..site_tree.len())
+                .find(|id| site_tree[*id].name == name && site_tree[*id].kind.is_page())
+                .unwrap();
+            &options_map[&id]
+        };
+        assert_eq!(options("home").title, "Home");
+        assert_eq!(options("home").language, "de");
+        // options of the parent page are not overwritten by the folders above it
+        assert_eq!(options("blog").title, "Home");
+        assert_eq!(options("draft").title, "Home");
+        assert_eq!(options("draft").language, "nl");
+        // single page options are read from the folders as well
+        let draft = (0..site_tree.len())
+            .find(|id| site_tree[*id].name == "draft" && site_tree[*id].kind.is_page())
+            .unwrap();
+        assert!(!SinglePageOptions::resolve(&site_tree, draft).is_listed());
+        assert!(SinglePageOptions::resolve(&site_tree, site_tree.root()).is_listed());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_download_badges() {
+        let root =
+            std::env::temp_dir().join(format!("lssg-download-badges-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("reports")).unwrap();
+        fs::write(
+            root.join("home.md"),
+            "# Home\n\n[Reports](./reports/reports.md) [Notes](./notes.txt)",
+        )
+        .unwrap();
+        fs::write(root.join("notes.txt"), "notes").unwrap();
+        fs::write(
+            root.join("reports").join(FOLDER_OPTIONS),
+            "download_badges = true",
+        )
+        .unwrap();
+        fs::write(
+            root.join("reports/reports.md"),
+            "# Reports\n\n[Report](./report.pdf)",
+        )
+        .unwrap();
+        fs::write(root.join("reports/report.pdf"), "%PDF").unwrap();
+
+        let input = Input::from_string(root.join("home.md").to_str().unwrap()).unwrap();
+        let mut site_tree = SiteTree::from_input(input).unwrap();
+        DefaultModule::new().init(&mut site_tree).unwrap();
+        let downloads: Vec<&str> = (0..site_tree.len())
+            .flat_map(|id| site_tree.links_from(id))
+            .filter(|link| matches!(link.relation, Relation::Download { .. }))
+            .map(|link| site_tree[link.to].name.as_str())
+            .collect();
+        assert_eq!(downloads, vec!["report.pdf"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_unlisted_navigation() {
+        let root = std::env::temp_dir().join(format!("lssg-unlisted-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("home.md"),
+            "# Home\n\n<sitetree></sitetree>\n\n[Drafts](./drafts.md)",
+        )
+        .unwrap();
+        fs::write(
+            root.join("drafts.md"),
+            "<!--\nunlisted = true\n-->\n# Drafts\n\n[Post](./post.md)",
+        )
+        .unwrap();
+        fs::write(root.join("post.md"), "# Post").unwrap();
+
+        let input = Input::from_string(root.join("home.md").to_str().unwrap()).unwrap();
+        let mut site_tree = SiteTree::from_input(input).unwrap();
+        let mut renderer: crate::renderer::Renderer = crate::renderer::Renderer::new();
+        renderer.add_module(DefaultModule::new());
+        renderer.init(&mut site_tree);
+        renderer.after_init(&site_tree);
+        let page = |name: &str| {
+            (0..site_tree.len())
+                .find(|id| site_tree[*id].name == name && site_tree[*id].kind.is_page())
+                .unwrap()
+        };
+
+        let home = renderer.render(&site_tree, site_tree.root()).unwrap();
+        assert!(home.contains("<div class=\"default__sitetree\"></div>"));
+        let post = renderer.render(&site_tree, page("post")).unwrap();
+        assert!(post.contains(">home</a>/drafts/post</nav>"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
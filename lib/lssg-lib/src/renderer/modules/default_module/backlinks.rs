@@ -0,0 +1,52 @@
+use proc_virtual_dom::dom;
+use virtual_dom::{to_attributes, DomNode};
+
+use crate::{
+    renderer::{util::page_title, RenderContext},
+    sitetree::{Relation, SiteNodeKind},
+};
+
+use super::SinglePageOptions;
+
+/// "Pages that link here" section listing the listed pages with a link to the current page,
+/// sorted by title. None if no page links here.
+pub fn backlinks(context: &RenderContext) -> Option<DomNode> {
+    let site_tree = context.site_tree;
+    let mut pages: Vec<(String, usize)> = vec![];
+    for link in site_tree.links_to(context.site_id) {
+        if link.from == context.site_id
+            || !matches!(link.relation, Relation::Discovered { .. })
+            || pages.iter().any(|(_, id)| *id == link.from)
+        {
+            continue;
+        }
+        let SiteNodeKind::Page(page) = &site_tree[link.from].kind else {
+            continue;
+        };
+        if !SinglePageOptions::resolve(site_tree, link.from).is_listed() {
+            continue;
+        }
+        pages.push((page_title(page, &site_tree[link.from].name), link.from));
+    }
+    if pages.is_empty() {
+        return None;
+    }
+    pages.sort();
+
+    let items: Vec<DomNode> = pages
+        .into_iter()
+        .map(|(title, id)| {
+            let a = DomNode::create_element_with_attributes(
+                "a",
+                to_attributes([("href", site_tree.href(context.site_id, id))]),
+            );
+            a.append_child(DomNode::create_text(title));
+            let li = dom!(<li></li>);
+            li.append_child(a);
+            li
+        })
+        .collect();
+    Some(
+        dom!(<section class="default__backlinks"><h2>Pages that link here</h2><ul>{items}</ul></section>),
+    )
+}
@@ -0,0 +1,196 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use reqwest::Url;
+use serde::Deserialize;
+use virtual_dom::{Document, DomNodeKind};
+
+use crate::sitetree::{Stylesheet, StylesheetLink};
+
+/// Where to put the generated Content Security Policy
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CspOutput {
+    /// `<meta http-equiv="Content-Security-Policy">` in every page
+    Meta,
+    /// `_headers` file in the output directory (Netlify, Cloudflare Pages)
+    Headers,
+    Both,
+}
+impl CspOutput {
+    pub fn meta(&self) -> bool {
+        *self == CspOutput::Meta || *self == CspOutput::Both
+    }
+    pub fn headers(&self) -> bool {
+        *self == CspOutput::Headers || *self == CspOutput::Both
+    }
+}
+
+/// Get the CSP source expression for an url used in a document
+fn source(url: &str) -> Option<String> {
+    if url.starts_with("data:") {
+        return Some("data:".into());
+    }
+    if url.starts_with("http") {
+        let url = Url::parse(url).ok()?;
+        return Some(url.origin().ascii_serialization());
+    }
+    // relative and root relative urls are hosted by the site itself
+    Some("'self'".into())
+}
+
+fn is_font(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    [".woff", ".woff2", ".ttf", ".otf", ".eot"]
+        .iter()
+        .any(|ext| path.ends_with(ext))
+}
+
+/// Content Security Policy created from all origins used by scripts, styles, fonts, images,
+/// frames and connections
+/// <https://developer.mozilla.org/en-US/docs/Web/HTTP/CSP>
+#[derive(Debug, Clone, Default)]
+pub struct ContentSecurityPolicy {
+    /// directive to sources
+    directives: BTreeMap<&'static str, BTreeSet<String>>,
+}
+
+impl ContentSecurityPolicy {
+    pub fn from_document(document: &Document) -> ContentSecurityPolicy {
+        let mut csp = ContentSecurityPolicy::default();
+        csp.add("default-src", "'self'".into());
+        for node in document.root().descendants() {
+            if let DomNodeKind::Element { tag, attributes } = &*node.kind() {
+                if attributes.contains_key("style") {
+                    csp.add("style-src", "'unsafe-inline'".into());
+                }
+                let (directive, url) = match tag.as_str() {
+                    "script" => match attributes.get("src") {
+                        Some(src) => ("script-src", src),
+                        None => {
+                            csp.add("script-src", "'unsafe-inline'".into());
+                            continue;
+                        }
+                    },
+                    "style" => {
+                        csp.add("style-src", "'unsafe-inline'".into());
+                        continue;
+                    }
+                    "link" => match (attributes.get("rel"), attributes.get("href")) {
+                        (Some(rel), Some(href)) if rel == "stylesheet" => ("style-src", href),
+                        (Some(rel), Some(href)) if rel == "icon" => ("img-src", href),
+                        (Some(rel), Some(href)) if rel == "preload" => {
+                            match attributes.get("as").map(|a| a.as_str()) {
+                                Some("font") => ("font-src", href),
+                                Some("fetch") => ("connect-src", href),
+                                _ => continue,
+                            }
+                        }
+                        _ => continue,
+                    },
+                    "img" => match attributes.get("src") {
+                        Some(src) => ("img-src", src),
+                        None => continue,
+                    },
+                    "video" | "audio" | "source" => match attributes.get("src") {
+                        Some(src) => ("media-src", src),
+                        None => continue,
+                    },
+                    "iframe" => match attributes.get("src") {
+                        Some(src) => ("frame-src", src),
+                        None => continue,
+                    },
+                    "a" => match attributes.get("ping") {
+                        Some(ping) => ("connect-src", ping),
+                        None => continue,
+                    },
+                    _ => continue,
+                };
+                if let Some(source) = source(url) {
+                    csp.add(directive, source);
+                }
+            }
+        }
+        csp
+    }
+
+    /// Add the fonts and images loaded by `stylesheet`
+    pub fn add_stylesheet(&mut self, stylesheet: &Stylesheet) {
+        for link in stylesheet.links() {
+            let StylesheetLink::Url(url) = link else {
+                continue;
+            };
+            let directive = if is_font(url) { "font-src" } else { "img-src" };
+            if let Some(source) = source(url) {
+                self.add(directive, source);
+            }
+        }
+    }
+
+    fn add(&mut self, directive: &'static str, source: String) {
+        self.directives.entry(directive).or_default().insert(source);
+    }
+
+    /// Add all sources from `other` to self
+    pub fn extend(&mut self, other: &ContentSecurityPolicy) {
+        for (directive, sources) in &other.directives {
+            self.directives
+                .entry(directive)
+                .or_default()
+                .extend(sources.iter().cloned());
+        }
+    }
+
+    /// Render a `_headers` file applying the policy to every path
+    pub fn to_headers_file(&self) -> String {
+        format!("/*\n  Content-Security-Policy: {self}\n")
+    }
+}
+
+impl fmt::Display for ContentSecurityPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let directives: Vec<String> = self
+            .directives
+            .iter()
+            .map(|(directive, sources)| {
+                let sources: Vec<&str> = sources.iter().map(|s| s.as_str()).collect();
+                format!("{directive} {}", sources.join(" "))
+            })
+            .collect();
+        f.write_str(&directives.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_virtual_dom::dom;
+    use virtual_dom::HeadPrecedence;
+
+    use super::*;
+
+    #[test]
+    fn test_fonts_and_connections() {
+        let mut document = Document::new();
+        document.add_to_head(
+            dom!(<link rel="preload" as="font" href="https://fonts.example.com/a.woff2"/>),
+            HeadPrecedence::Normal,
+        );
+        document
+            .body
+            .append_child(dom!(<a href="/docs" ping="https://stats.example.com/ping">Docs</a>));
+        let mut csp = ContentSecurityPolicy::from_document(&document);
+        let stylesheet = Stylesheet::from_readable(
+            "@font-face { src: url(\"./mono.ttf\") } body { background: url('https://img.example.com/bg.png') }"
+                .as_bytes(),
+        )
+        .unwrap();
+        csp.add_stylesheet(&stylesheet);
+
+        assert_eq!(
+            csp.to_string(),
+            "connect-src https://stats.example.com; default-src 'self'; font-src 'self' https://fonts.example.com; img-src https://img.example.com"
+        );
+    }
+}
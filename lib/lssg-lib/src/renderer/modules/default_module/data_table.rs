@@ -0,0 +1,239 @@
+use std::io::Read;
+
+use log::error;
+use virtual_dom::{to_attributes, Attributes, DomNode};
+
+use crate::{
+    lmarkdown::{visit_tokens, Token},
+    lssg_error::LssgError,
+    renderer::RenderContext,
+    sitetree::{Input, Page},
+};
+
+/// Rows of a data file, first row is the header
+type Rows = Vec<Vec<String>>;
+
+/// Parse csv with quoted fields <https://www.rfc-editor.org/rfc/rfc4180>
+fn parse_csv(content: &str, delimiter: char) -> Rows {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => row.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+fn json_to_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        v => v.to_string(),
+    }
+}
+
+/// Parse a json array of objects or an array of arrays
+fn parse_json(content: &str) -> Result<Rows, LssgError> {
+    let value: serde_json::Value =
+        serde_json::from_str(content).map_err(|e| LssgError::render(e.to_string()))?;
+    let items = value
+        .as_array()
+        .ok_or(LssgError::render("json data should be an array"))?;
+    let mut rows = vec![];
+    match items.first() {
+        Some(serde_json::Value::Object(first)) => {
+            let header: Vec<String> = first.keys().cloned().collect();
+            for item in items {
+                rows.push(
+                    header
+                        .iter()
+                        .map(|key| item.get(key).map(json_to_string).unwrap_or_default())
+                        .collect(),
+                );
+            }
+            rows.insert(0, header);
+        }
+        _ => {
+            for item in items {
+                let row = match item.as_array() {
+                    Some(row) => row.iter().map(json_to_string).collect(),
+                    None => vec![json_to_string(item)],
+                };
+                rows.push(row);
+            }
+        }
+    }
+    Ok(rows)
+}
+
+fn toml_to_string(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+/// Parse the first array of tables in a toml file (eg. `[[row]]`)
+fn parse_toml(content: &str) -> Result<Rows, LssgError> {
+    let table: toml::Table =
+        toml::from_str(content).map_err(|e| LssgError::render(e.to_string()))?;
+    let items = table
+        .values()
+        .find_map(|v| v.as_array())
+        .ok_or(LssgError::render(
+            "toml data should contain an array of tables",
+        ))?;
+    let mut header: Vec<String> = vec![];
+    for item in items {
+        if let Some(item) = item.as_table() {
+            for key in item.keys() {
+                if !header.contains(key) {
+                    header.push(key.clone());
+                }
+            }
+        }
+    }
+    let mut rows = vec![];
+    for item in items {
+        rows.push(
+            header
+                .iter()
+                .map(|key| item.get(key).map(toml_to_string).unwrap_or_default())
+                .collect(),
+        );
+    }
+    rows.insert(0, header);
+    Ok(rows)
+}
+
+fn read_rows(input: &Input) -> Result<Rows, LssgError> {
+    let mut content = String::new();
+    input.readable()?.read_to_string(&mut content)?;
+    let filename = input.filename()?;
+    let extension = filename.rsplit('.').next().unwrap_or("");
+    match extension {
+        "csv" => Ok(parse_csv(&content, ',')),
+        "tsv" => Ok(parse_csv(&content, '\t')),
+        "json" => parse_json(&content),
+        "toml" => parse_toml(&content),
+        _ => Err(LssgError::render(format!(
+            "unsupported data file {filename:?}, use csv, tsv, json or toml"
+        ))),
+    }
+}
+
+/// Data files of the tables in `page`, read from `input`
+pub fn data_files(page: &Page, input: &Input) -> Vec<Input> {
+    let mut files = vec![];
+    visit_tokens(page.tokens(), |token| {
+        if let Token::Html {
+            tag, attributes, ..
+        } = token
+        {
+            if let Some(src) = attributes.get("src").filter(|_| tag == "datatable") {
+                files.extend(input.new(src).ok());
+            }
+        }
+    });
+    files
+}
+
+/// Render a data file into a table, `<datatable src="./benchmarks.csv"></datatable>`
+///
+/// Set `header="false"` if the first row is not a header
+pub fn data_table(context: &RenderContext, parent: &DomNode, attributes: &Attributes) {
+    let Some(src) = attributes.get("src") else {
+        error!("datatable requires a src attribute");
+        return;
+    };
+    let rows = match context
+        .input
+        .ok_or(LssgError::render("page does not have an Input"))
+    {
+        Ok(input) => input.new(src).and_then(|input| read_rows(&input)),
+        Err(e) => Err(e),
+    };
+    let mut rows = match rows {
+        Ok(rows) => rows.into_iter(),
+        Err(e) => {
+            error!("Failed to read data file {src:?}: {e}");
+            return;
+        }
+    };
+
+    let table = DomNode::create_element_with_attributes(
+        "table",
+        to_attributes([("class", "default__data_table")]),
+    );
+    if attributes.get("header").map(|h| h.as_str()) != Some("false") {
+        if let Some(header) = rows.next() {
+            let tr = DomNode::create_element("tr");
+            for cell in header {
+                let th = DomNode::create_element("th");
+                th.append_child(DomNode::create_text(cell));
+                tr.append_child(th);
+            }
+            let thead = DomNode::create_element("thead");
+            thead.append_child(tr);
+            table.append_child(thead);
+        }
+    }
+    let tbody = DomNode::create_element("tbody");
+    for row in rows {
+        let tr = DomNode::create_element("tr");
+        for cell in row {
+            let td = DomNode::create_element("td");
+            td.append_child(DomNode::create_text(cell));
+            tr.append_child(td);
+        }
+        tbody.append_child(tr);
+    }
+    table.append_child(tbody);
+    parent.append_child(table);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let rows = parse_csv("name,value\n\"a, b\",\"say \"\"hi\"\"\"\r\nc,1\n", ',');
+        assert_eq!(
+            rows,
+            vec![
+                vec!["name".to_string(), "value".to_string()],
+                vec!["a, b".to_string(), "say \"hi\"".to_string()],
+                vec!["c".to_string(), "1".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_column_order() {
+        let header = vec!["name".to_string(), "age".to_string()];
+        let rows = parse_json(r#"[{"name": "a", "age": 1}]"#).unwrap();
+        assert_eq!(rows[0], header);
+        let rows = parse_toml("[[row]]\nname = \"a\"\nage = 1\n").unwrap();
+        assert_eq!(rows[0], header);
+    }
+}
@@ -0,0 +1,137 @@
+use std::io::{Cursor, Read};
+
+use image::{
+    codecs::ico::{IcoEncoder, IcoFrame},
+    imageops::FilterType,
+    DynamicImage, ExtendedColorType, ImageFormat, RgbaImage,
+};
+use serde_json::json;
+use virtual_dom::{to_attributes, Document, DomNode};
+
+use crate::{lssg_error::LssgError, sitetree::Input};
+
+/// Sizes put into `favicon.ico`
+const ICO_SIZES: [u32; 3] = [16, 32, 48];
+
+/// Icons generated from the `favicon` source image as (name, size)
+const PNG_ICONS: [(&str, u32); 3] = [
+    ("favicon-32x32.png", 32),
+    ("apple-touch-icon.png", 180),
+    ("icon-512x512.png", 512),
+];
+
+/// Icon that is only referenced from the web app manifest, for launchers that crop icons
+const MASKABLE_ICON: &str = "icon-512x512.png";
+
+/// Web app manifest listing the maskable icon
+const MANIFEST: &str = "site.webmanifest";
+
+/// If `name` is a favicon recognized by the default module
+pub fn is_favicon(name: &str) -> bool {
+    name == "favicon.ico" || name == MANIFEST || PNG_ICONS.iter().any(|(n, _)| *n == name)
+}
+
+/// Scale `image` to fit a transparent square of `size` with `padding` pixels on every side
+fn square(image: &DynamicImage, size: u32, padding: u32) -> RgbaImage {
+    let inner = size - padding * 2;
+    let scaled = image.resize(inner, inner, FilterType::Lanczos3).to_rgba8();
+    let mut canvas = RgbaImage::new(size, size);
+    let x = (size - scaled.width()) / 2;
+    let y = (size - scaled.height()) / 2;
+    image::imageops::overlay(&mut canvas, &scaled, x.into(), y.into());
+    canvas
+}
+
+fn png(image: &RgbaImage) -> Result<Vec<u8>, LssgError> {
+    let mut buf = Cursor::new(vec![]);
+    image.write_to(&mut buf, ImageFormat::Png)?;
+    Ok(buf.into_inner())
+}
+
+/// Manifest with the maskable icon, the icon is next to it so it is referenced relatively
+fn manifest(name: &str) -> Vec<u8> {
+    let mut manifest = json!({
+        "icons": [{
+            "src": MASKABLE_ICON,
+            "sizes": "512x512",
+            "type": "image/png",
+            "purpose": "maskable"
+        }]
+    });
+    if !name.is_empty() {
+        manifest["name"] = json!(name);
+    }
+    manifest.to_string().into_bytes()
+}
+
+/// Generate `favicon.ico`, png icons and a web app manifest for `site_name` from a single source
+/// image
+pub fn generate(input: &Input, site_name: &str) -> Result<Vec<(&'static str, Vec<u8>)>, LssgError> {
+    let mut source = vec![];
+    input.readable()?.read_to_end(&mut source)?;
+    let image = image::load_from_memory(&source)?;
+
+    let frames = ICO_SIZES
+        .iter()
+        .map(|size| {
+            let icon = square(&image, *size, 0);
+            IcoFrame::as_png(icon.as_raw(), *size, *size, ExtendedColorType::Rgba8)
+        })
+        .collect::<Result<Vec<IcoFrame>, _>>()?;
+    let mut ico = vec![];
+    IcoEncoder::new(&mut ico).encode_images(&frames)?;
+
+    let mut icons = vec![("favicon.ico", ico)];
+    for (name, size) in PNG_ICONS {
+        // maskable icons get cropped to a circle so keep the image within the 80% safe zone
+        let padding = if name == MASKABLE_ICON { size / 10 } else { 0 };
+        icons.push((name, png(&square(&image, size, padding))?));
+    }
+    icons.push((MANIFEST, manifest(site_name)));
+    Ok(icons)
+}
+
+/// Create the `<link>` for a favicon named `name`, None for icons only listed in the manifest
+pub fn link(document: &Document, name: &str, href: &str) -> Option<DomNode> {
+    let attributes = match PNG_ICONS.iter().find(|(n, _)| *n == name) {
+        Some((n, _)) if *n == MASKABLE_ICON => return None,
+        Some((n, size)) => {
+            let rel = if *n == "apple-touch-icon.png" {
+                "apple-touch-icon"
+            } else {
+                "icon"
+            };
+            let sizes = format!("{size}x{size}");
+            to_attributes([
+                ("rel", rel),
+                ("type", "image/png"),
+                ("sizes", &sizes),
+                ("href", href),
+            ])
+        }
+        None if name == MANIFEST => to_attributes([("rel", "manifest"), ("href", href)]),
+        None => to_attributes([("rel", "icon"), ("type", "image/x-icon"), ("href", href)]),
+    };
+    Some(document.create_element_with_attributes("link", attributes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest() {
+        let manifest: serde_json::Value = serde_json::from_slice(&manifest("Site")).unwrap();
+        assert_eq!(manifest["name"], "Site");
+        assert_eq!(manifest["icons"][0]["src"], MASKABLE_ICON);
+        assert_eq!(manifest["icons"][0]["purpose"], "maskable");
+
+        let document = Document::new();
+        assert!(link(&document, MASKABLE_ICON, "/icon-512x512.png").is_none());
+        let link = link(&document, MANIFEST, "/site.webmanifest").unwrap();
+        assert_eq!(
+            link.to_string(),
+            r#"<link rel="manifest" href="/site.webmanifest"/>"#
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::{HashMap, HashSet};
+
+use virtual_dom::{to_attributes, DomNode, DomNodeKind};
+
+use crate::lmarkdown::{visit_tokens, Token};
+
+/// Footnotes of the page that is being rendered
+pub struct Footnotes {
+    /// labels that have a definition
+    defined: HashSet<String>,
+    /// labels in the order they are first referenced, a footnote is numbered by its position
+    referenced: Vec<String>,
+    /// number of references to every label
+    references: HashMap<String, usize>,
+    /// rendered `<li>` of every definition
+    items: HashMap<String, DomNode>,
+}
+
+impl Footnotes {
+    pub fn new(tokens: &[Token]) -> Self {
+        let mut defined = HashSet::new();
+        visit_tokens(tokens, |token| {
+            if let Token::FootnoteDefinition { label, .. } = token {
+                defined.insert(label.clone());
+            }
+        });
+        Self {
+            defined,
+            referenced: vec![],
+            references: HashMap::new(),
+            items: HashMap::new(),
+        }
+    }
+
+    fn number(&self, label: &str) -> Option<usize> {
+        self.referenced
+            .iter()
+            .position(|l| l == label)
+            .map(|i| i + 1)
+    }
+
+    /// `<sup><a class="footnote-ref" href="#fn-1" id="fnref-1">1</a></sup>`, None if `label`
+    /// has no definition
+    pub fn reference(&mut self, label: &str) -> Option<DomNode> {
+        if !self.defined.contains(label) {
+            return None;
+        }
+        if self.number(label).is_none() {
+            self.referenced.push(label.to_string());
+        }
+        let n = self.number(label)?;
+        let count = self.references.entry(label.to_string()).or_insert(0);
+        *count += 1;
+        let id = match *count {
+            1 => format!("fnref-{n}"),
+            c => format!("fnref-{n}-{c}"),
+        };
+        let sup = DomNode::create_element("sup");
+        let a = DomNode::create_element_with_attributes(
+            "a",
+            to_attributes([
+                ("class", "footnote-ref".to_string()),
+                ("href", format!("#fn-{n}")),
+                ("id", id),
+            ]),
+        );
+        a.append_child(DomNode::create_text(n.to_string()));
+        sup.append_child(a);
+        Some(sup)
+    }
+
+    /// Store the rendered content of the definition of `label`
+    pub fn define(&mut self, label: &str, item: DomNode) {
+        self.items.entry(label.to_string()).or_insert(item);
+    }
+
+    /// `<section class="footnotes">` with all referenced footnotes in order of their first
+    /// reference with links back to their references. None if nothing is referenced.
+    pub fn section(&self) -> Option<DomNode> {
+        let ol = DomNode::create_element("ol");
+        for (i, label) in self.referenced.iter().enumerate() {
+            let Some(item) = self.items.get(label) else {
+                continue;
+            };
+            let n = i + 1;
+            let mut item = item.clone();
+            item.set_attribute("id", format!("fn-{n}"));
+            let count = self.references.get(label).copied().unwrap_or(1);
+            // add backlinks to the last paragraph if the footnote ends with one
+            let last_paragraph = item
+                .children()
+                .last()
+                .filter(|c| matches!(&*c.kind(), DomNodeKind::Element { tag, .. } if tag == "p"));
+            let target = last_paragraph.unwrap_or_else(|| item.clone());
+            for c in 1..=count {
+                let href = match c {
+                    1 => format!("#fnref-{n}"),
+                    c => format!("#fnref-{n}-{c}"),
+                };
+                let backref = DomNode::create_element_with_attributes(
+                    "a",
+                    to_attributes([
+                        ("class", "footnote-backref"),
+                        ("href", &href),
+                        ("aria-label", "Back to reference"),
+                    ]),
+                );
+                backref.append_child(DomNode::create_text("↩"));
+                target.append_child(DomNode::create_text(" "));
+                target.append_child(backref);
+            }
+            ol.append_child(item);
+        }
+        ol.children().next()?;
+
+        let section = DomNode::create_element_with_attributes(
+            "section",
+            to_attributes([("class", "footnotes")]),
+        );
+        section.append_child(ol);
+        Some(section)
+    }
+}
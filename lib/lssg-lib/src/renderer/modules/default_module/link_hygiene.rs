@@ -0,0 +1,201 @@
+use serde::Deserialize;
+use virtual_dom::{DomNode, DomNodeKind};
+
+/// Hosts that serve everything over https, subdomains included
+const HTTPS_HOSTS: [&str; 24] = [
+    "apple.com",
+    "archive.org",
+    "arxiv.org",
+    "crates.io",
+    "docs.rs",
+    "facebook.com",
+    "github.com",
+    "github.io",
+    "gitlab.com",
+    "google.com",
+    "instagram.com",
+    "linkedin.com",
+    "medium.com",
+    "microsoft.com",
+    "mozilla.org",
+    "npmjs.com",
+    "python.org",
+    "reddit.com",
+    "rust-lang.org",
+    "stackoverflow.com",
+    "twitter.com",
+    "wikipedia.org",
+    "x.com",
+    "youtube.com",
+];
+
+/// Query parameters only used for tracking, `utm_*` parameters are always removed
+const TRACKING_PARAMS: [&str; 10] = [
+    "fbclid", "gclid", "dclid", "msclkid", "twclid", "yclid", "igshid", "mc_cid", "mc_eid",
+    "_hsenc",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailingSlash {
+    Add,
+    Remove,
+}
+
+/// Cleaning of outbound links, set with a `[link_hygiene]` table
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LinkHygiene {
+    /// Upgrade `http://` links to hosts known to support https
+    pub https: bool,
+    /// Extra hosts to upgrade to https (eg. "example.com"), subdomains included
+    pub https_hosts: Vec<String>,
+    /// Remove tracking query parameters like `utm_source` and `fbclid`
+    pub strip_tracking: bool,
+    /// Extra query parameters to remove
+    pub tracking_params: Vec<String>,
+    /// Add ("add") or remove ("remove") the trailing slash of links to paths without an extension
+    pub trailing_slash: Option<TrailingSlash>,
+}
+
+impl LinkHygiene {
+    fn is_enabled(&self) -> bool {
+        self.https || self.strip_tracking || self.trailing_slash.is_some()
+    }
+
+    fn is_https_host(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        HTTPS_HOSTS
+            .iter()
+            .copied()
+            .chain(self.https_hosts.iter().map(|h| h.as_str()))
+            .any(|h| host == h || host.ends_with(&format!(".{h}")))
+    }
+
+    fn is_tracking_param(&self, key: &str) -> bool {
+        key.starts_with("utm_")
+            || TRACKING_PARAMS.contains(&key)
+            || self.tracking_params.iter().any(|p| p == key)
+    }
+
+    /// Clean an absolute http(s) url, other urls are returned as is
+    pub fn clean(&self, url: &str) -> String {
+        let Some((scheme, rest)) = url.split_once("://") else {
+            return url.to_string();
+        };
+        let mut scheme = scheme.to_lowercase();
+        if scheme != "http" && scheme != "https" {
+            return url.to_string();
+        }
+        let (rest, fragment) = match rest.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (rest, None),
+        };
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, ""),
+        };
+
+        // a port means the host might serve something else over http
+        if self.https && scheme == "http" {
+            let host = authority.rsplit('@').next().unwrap_or(authority);
+            if !host.contains(':') && self.is_https_host(host) {
+                scheme = "https".into();
+            }
+        }
+
+        let mut path = path.to_string();
+        let is_file = path
+            .rsplit('/')
+            .next()
+            .is_some_and(|name| name.contains('.'));
+        match self.trailing_slash {
+            Some(TrailingSlash::Add) if !path.ends_with('/') && !is_file => path.push('/'),
+            Some(TrailingSlash::Remove) if path.len() > 1 => {
+                path = path.trim_end_matches('/').to_string()
+            }
+            _ => {}
+        }
+
+        let mut url = format!("{scheme}://{authority}{path}");
+        if let Some(query) = query {
+            let params: Vec<&str> = query
+                .split('&')
+                .filter(|param| {
+                    let key = param.split('=').next().unwrap_or(param);
+                    !(self.strip_tracking && self.is_tracking_param(key))
+                })
+                .collect();
+            if !params.is_empty() {
+                url.push('?');
+                url.push_str(&params.join("&"));
+            }
+        }
+        if let Some(fragment) = fragment {
+            url.push('#');
+            url.push_str(fragment);
+        }
+        url
+    }
+}
+
+/// Clean the `href` of all links below `node`
+pub fn link_hygiene(node: &DomNode, options: &LinkHygiene) {
+    if !options.is_enabled() {
+        return;
+    }
+    for d in node.descendants() {
+        if let DomNodeKind::Element { tag, attributes } = &mut *d.kind_mut() {
+            if tag != "a" {
+                continue;
+            }
+            if let Some(href) = attributes.get_mut("href") {
+                *href = options.clean(href);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean() {
+        let options = LinkHygiene {
+            https: true,
+            https_hosts: vec!["example.com".into()],
+            strip_tracking: true,
+            tracking_params: vec!["ref".into()],
+            trailing_slash: Some(TrailingSlash::Add),
+        };
+        assert_eq!(
+            options.clean("http://en.wikipedia.org/wiki/Rust?utm_source=x&a=1#History"),
+            "https://en.wikipedia.org/wiki/Rust/?a=1#History"
+        );
+        assert_eq!(
+            options.clean("http://example.com?fbclid=1&ref=hn"),
+            "https://example.com/"
+        );
+        assert_eq!(
+            options.clean("http://unknown.org:8080/file.pdf"),
+            "http://unknown.org:8080/file.pdf"
+        );
+        assert_eq!(
+            options.clean("./about?utm_source=x"),
+            "./about?utm_source=x"
+        );
+        assert_eq!(options.clean("mailto:a@b.com"), "mailto:a@b.com");
+
+        let options = LinkHygiene {
+            trailing_slash: Some(TrailingSlash::Remove),
+            ..LinkHygiene::default()
+        };
+        assert_eq!(options.clean("https://a.com/b/"), "https://a.com/b");
+        assert_eq!(options.clean("https://a.com/"), "https://a.com/");
+    }
+}
@@ -1,9 +1,7 @@
-use std::collections::HashMap;
-
 use log::{error, warn};
 
 use proc_virtual_dom::dom;
-use virtual_dom::{to_attributes, Document, DomNode};
+use virtual_dom::{to_attributes, Attributes, Document, DomNode};
 
 use crate::{
     lmarkdown::Token,
@@ -15,12 +13,14 @@ use crate::{
     tree::Node,
 };
 
+use super::{data_table::data_table, SinglePageOptions};
+
 fn links_grid(
     document: &mut Document,
     context: &RenderContext,
     parent: &DomNode,
     tr: &mut TokenRenderer,
-    _attributes: &HashMap<String, String>,
+    _attributes: &Attributes,
     tokens: &Vec<Token>,
 ) {
     let links: Vec<DomNode> = tokens
@@ -68,7 +68,7 @@ fn links_boxes(
     context: &RenderContext,
     parent: &DomNode,
     tr: &mut TokenRenderer,
-    _attributes: &HashMap<String, String>,
+    _attributes: &Attributes,
     tokens: &Vec<Token>,
 ) {
     let links: DomNode = dom!(<nav class="links"></nav>).into();
@@ -127,7 +127,7 @@ fn links(
     context: &RenderContext,
     parent: &DomNode,
     tr: &mut TokenRenderer,
-    attributes: &HashMap<String, String>,
+    attributes: &Attributes,
     tokens: &Vec<Token>,
 ) {
     if attributes.contains_key("boxes") {
@@ -145,9 +145,10 @@ fn carousel(
     context: &RenderContext,
     parent: &DomNode,
     tr: &mut TokenRenderer,
-    _attributes: &HashMap<String, String>,
+    _attributes: &Attributes,
     tokens: &Vec<Token>,
 ) {
+    super::require_asset(context, super::DEFAULT_JS_NAME);
     let carousel = dom!(<div class="default__carausel"></div>);
 
     let main = dom!(<div class="default__carausel_main"></div>);
@@ -178,7 +179,7 @@ fn carousel(
     parent.append_child(carousel);
 }
 
-pub fn sitetree(context: &RenderContext, parent: &DomNode, attributes: &HashMap<String, String>) {
+pub fn sitetree(context: &RenderContext, parent: &DomNode, attributes: &Attributes) {
     let ignore_list = attributes
         .get("ignore")
         .map(|s| s.split(',').collect())
@@ -199,6 +200,10 @@ fn sitetree_recurs(id: usize, context: &RenderContext, ignore_list: &Vec<&str>)
     if ignore_list.contains(&name.as_str()) {
         return None;
     }
+    // unlisted pages are left out together with the pages below them
+    if node.kind.is_page() && !SinglePageOptions::resolve(context.site_tree, id).is_listed() {
+        return None;
+    }
     let children: Vec<DomNode> = context.site_tree[id]
         .children()
         .into_iter()
@@ -222,7 +227,7 @@ pub fn render_html(
     parent: &DomNode,
     tr: &mut TokenRenderer,
     tag: &str,
-    attributes: &HashMap<String, String>,
+    attributes: &Attributes,
     tokens: &Vec<Token>,
 ) -> Option<DomNode> {
     match tag {
@@ -236,6 +241,7 @@ pub fn render_html(
         }
         "links" => links(document, context, parent, tr, attributes, tokens),
         "sitetree" => sitetree(context, parent, attributes),
+        "datatable" => data_table(context, parent, attributes),
         "carousel" => carousel(document, context, parent, tr, attributes, tokens),
         _ => {
             let element = document.create_element_with_attributes(tag, attributes.clone());
@@ -0,0 +1,59 @@
+use virtual_dom::{DomNode, DomNodeKind};
+
+/// Elements that can execute code or change how the page is loaded
+const DANGEROUS_ELEMENTS: [&str; 9] = [
+    "script", "style", "iframe", "frame", "frameset", "object", "embed", "base", "meta",
+];
+
+/// Attributes that contain urls
+const URL_ATTRIBUTES: [&str; 6] = [
+    "href",
+    "src",
+    "action",
+    "formaction",
+    "xlink:href",
+    "poster",
+];
+
+/// check if an url uses a scheme that can execute code
+fn is_dangerous_url(url: &str) -> bool {
+    // browsers ignore whitespace and control characters in schemes
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_lowercase();
+    url.starts_with("javascript:")
+        || url.starts_with("vbscript:")
+        || (url.starts_with("data:") && !url.starts_with("data:image/"))
+}
+
+/// Remove script, style and frame elements, event handler attributes and dangerous urls from all
+/// descendants of `node`, used for rendering untrusted markdown
+pub fn sanitize(node: &DomNode) {
+    let descendants: Vec<DomNode> = node.descendants().skip(1).collect();
+    for d in descendants {
+        let remove = match &mut *d.kind_mut() {
+            DomNodeKind::Element { tag, attributes } => {
+                if DANGEROUS_ELEMENTS.contains(&tag.to_lowercase().as_str()) {
+                    true
+                } else {
+                    attributes.retain(|key, value| {
+                        let key = key.to_lowercase();
+                        if key.starts_with("on") {
+                            return false;
+                        }
+                        !(URL_ATTRIBUTES.contains(&key.as_str()) && is_dangerous_url(value))
+                    });
+                    false
+                }
+            }
+            DomNodeKind::Text { .. } => false,
+            // conditional comments can hold markup some browsers still run
+            DomNodeKind::Comment { .. } => true,
+        };
+        if remove {
+            d.detach();
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::io::Read;
+
+use log::error;
+use proc_virtual_dom::dom;
+use serde::Deserialize;
+use virtual_dom::{Document, HeadPrecedence};
+
+use crate::sitetree::{SiteNodeKind, SiteTree};
+
+use super::set_integrity;
+
+/// How a script is loaded, set for all scripts with `script_loading` or for a single one with
+/// `?load=defer` in its link
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptLoading {
+    /// `<script src>` at the end of body
+    #[default]
+    Blocking,
+    /// `<script defer>` in head, runs in order after the page is parsed
+    Defer,
+    /// `<script async>` in head, runs as soon as it is loaded
+    Async,
+    /// `<script type="module">` in head, deferred like `defer`
+    Module,
+    /// Content of the script written at the end of body, saves a request for small scripts
+    Inline,
+}
+
+impl ScriptLoading {
+    /// Parse the `load` query parameter of a link
+    pub fn from_query(value: &str) -> Option<ScriptLoading> {
+        match value {
+            "blocking" => Some(ScriptLoading::Blocking),
+            "defer" => Some(ScriptLoading::Defer),
+            "async" => Some(ScriptLoading::Async),
+            "module" => Some(ScriptLoading::Module),
+            "inline" => Some(ScriptLoading::Inline),
+            _ => None,
+        }
+    }
+}
+
+/// Add the script resource `id` to the page `site_id`
+pub fn add_script(
+    document: &mut Document,
+    site_tree: &SiteTree,
+    site_id: usize,
+    id: usize,
+    loading: ScriptLoading,
+    integrity: bool,
+) {
+    if loading == ScriptLoading::Inline {
+        let SiteNodeKind::Resource(resource) = &site_tree[id].kind else {
+            return;
+        };
+        let mut content = String::new();
+        if let Err(e) = resource
+            .readable()
+            .and_then(|mut r| Ok(r.read_to_string(&mut content)?))
+        {
+            error!("failed to inline {}: {e}", site_tree[id].name);
+            return;
+        }
+        // the script would end at a closing tag in its content
+        let content = content.replace("</script", "<\\/script");
+        document.body.append_child(dom!(<script>{content}</script>));
+        return;
+    }
+
+    let src = site_tree.href(site_id, id);
+    let mut script = dom!(<script src="{src}"></script>);
+    if integrity {
+        set_integrity(&mut script, site_tree, id);
+    }
+    match loading {
+        ScriptLoading::Defer => script.set_attribute("defer", String::new()),
+        ScriptLoading::Async => script.set_attribute("async", String::new()),
+        ScriptLoading::Module => script.set_attribute("type", "module".into()),
+        ScriptLoading::Blocking | ScriptLoading::Inline => {
+            document.body.append_child(script);
+            return;
+        }
+    }
+    document.add_to_head(script, HeadPrecedence::Normal);
+}
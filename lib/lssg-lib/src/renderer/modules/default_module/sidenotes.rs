@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use virtual_dom::{to_attributes, Atom, DomNode, DomNodeKind, Html};
+
+fn tag(node: &DomNode) -> Option<Atom> {
+    match &*node.kind() {
+        DomNodeKind::Element { tag, .. } => Some(tag.clone()),
+        _ => None,
+    }
+}
+
+fn deep_clone(node: &DomNode) -> Option<DomNode> {
+    let html: Html = node.clone().into();
+    DomNode::from_html(html)
+}
+
+/// Copy every footnote next to its reference as sidenote
+///
+/// Footnotes are expected to be rendered as `<a class="footnote-ref" href="#fn-1">` references
+/// and a `<section class="footnotes">` with `<li id="fn-1">` items. The stylesheet shows
+/// sidenotes in the margin on wide viewports and the footnotes section on small ones.
+pub fn sidenotes(body: &DomNode) {
+    let Some(section) = body
+        .descendants()
+        .find(|n| tag(n).as_deref() == Some("section") && n.has_class("footnotes"))
+    else {
+        return;
+    };
+
+    let footnotes: HashMap<String, DomNode> = section
+        .descendants()
+        .filter_map(|n| match &*n.kind() {
+            DomNodeKind::Element { tag, attributes } if tag == "li" => {
+                attributes.get("id").map(|id| (id.clone(), n.clone()))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let references: Vec<(DomNode, String)> = body
+        .descendants()
+        .filter_map(|n| match &*n.kind() {
+            DomNodeKind::Element { tag, attributes } if tag == "a" => attributes
+                .get("href")
+                .and_then(|href| href.strip_prefix('#'))
+                .filter(|id| footnotes.contains_key(*id))
+                .map(|id| (n.clone(), id.to_string())),
+            _ => None,
+        })
+        .filter(|(n, _)| !n.ancestors().any(|a| a.has_class("footnotes")))
+        .collect();
+
+    for (reference, id) in references {
+        let sidenote = DomNode::create_element_with_attributes(
+            "span",
+            to_attributes([("class", "default__sidenote"), ("role", "note")]),
+        );
+        let number = DomNode::create_element("sup");
+        for child in reference.children() {
+            if let Some(child) = deep_clone(&child) {
+                number.append_child(child);
+            }
+        }
+        sidenote.append_child(number);
+        sidenote.append_child(DomNode::create_text(" "));
+
+        // paragraphs are not allowed inside of span so add their content instead
+        for child in footnotes[&id].children() {
+            let nodes: Vec<DomNode> = if tag(&child).as_deref() == Some("p") {
+                child.children().collect()
+            } else {
+                vec![child]
+            };
+            for node in nodes {
+                if node.has_class("footnote-backref") {
+                    continue;
+                }
+                if let Some(node) = deep_clone(&node) {
+                    sidenote.append_child(node);
+                }
+            }
+        }
+
+        // insert after `<sup>` if reference is wrapped in it
+        let anchor = match reference.parent() {
+            Some(parent) if tag(&parent).as_deref() == Some("sup") => parent,
+            _ => reference,
+        };
+        anchor.insert_after(sidenote);
+    }
+
+    section.add_class("default__sidenotes");
+}
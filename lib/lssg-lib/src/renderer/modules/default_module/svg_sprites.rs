@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use virtual_dom::{to_attributes, DomNode, DomNodeKind};
+
+/// Attribute used to mark inlined svgs with the resource they came from
+pub const SVG_ID_ATTRIBUTE: &str = "data-svg-id";
+
+/// Move inlined svgs that occur more than once into a single `<symbol>` and replace their
+/// content with `<use href="#svg-sprite-N">`
+pub fn svg_sprites(body: &DomNode) {
+    let mut groups: HashMap<String, Vec<DomNode>> = HashMap::new();
+    let mut order = vec![];
+    for node in body.descendants() {
+        let id = match &mut *node.kind_mut() {
+            DomNodeKind::Element { tag, attributes } if tag == "svg" => {
+                attributes.shift_remove(SVG_ID_ATTRIBUTE)
+            }
+            _ => None,
+        };
+        if let Some(id) = id {
+            if !groups.contains_key(&id) {
+                order.push(id.clone());
+            }
+            groups.entry(id).or_default().push(node);
+        }
+    }
+
+    let sprites = DomNode::create_element_with_attributes(
+        "svg",
+        to_attributes([("class", "default__svg_sprites"), ("aria-hidden", "true")]),
+    );
+    for id in order {
+        let svgs = &groups[&id];
+        if svgs.len() < 2 {
+            continue;
+        }
+
+        let symbol_id = format!("svg-sprite-{}", sprites.children().count());
+        let viewbox = match &*svgs[0].kind() {
+            DomNodeKind::Element { attributes, .. } => attributes.get("viewbox").cloned(),
+            _ => None,
+        };
+        let mut symbol =
+            DomNode::create_element_with_attributes("symbol", to_attributes([("id", &symbol_id)]));
+        if let Some(viewbox) = viewbox {
+            symbol.set_attribute("viewbox", viewbox);
+        }
+        for child in svgs[0].children().collect::<Vec<_>>() {
+            child.detach();
+            symbol.append_child(child);
+        }
+        sprites.append_child(symbol);
+
+        let href = format!("#{symbol_id}");
+        for svg in svgs {
+            for child in svg.children().collect::<Vec<_>>() {
+                child.detach();
+            }
+            svg.append_child(DomNode::create_element_with_attributes(
+                "use",
+                to_attributes([("href", href.as_str())]),
+            ));
+        }
+    }
+
+    if sprites.has_children() {
+        body.prepend(sprites);
+    }
+}
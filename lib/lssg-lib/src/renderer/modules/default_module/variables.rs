@@ -0,0 +1,73 @@
+use chrono::Local;
+use log::warn;
+
+use crate::{renderer::RenderContext, sitetree::SiteNodeKind};
+
+/// Get a value from attributes using a dotted path (eg. "meta.description")
+fn lookup(table: Option<&toml::Table>, path: &str) -> Option<String> {
+    let mut keys = path.split('.');
+    let mut value = table?.get(keys.next()?)?;
+    for key in keys {
+        value = value.get(key)?;
+    }
+    match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Table(_) | toml::Value::Array(_) => None,
+        v => Some(v.to_string()),
+    }
+}
+
+/// Resolve a variable name like "site.title", "page.meta.author" or "build.year"
+fn resolve(name: &str, context: &RenderContext) -> Option<String> {
+    let (scope, path) = name.split_once('.')?;
+    match scope {
+        "site" => match &context.site_tree[context.site_tree.root()].kind {
+            SiteNodeKind::Page(page) => lookup(page.attributes(), path),
+            _ => None,
+        },
+        "page" => lookup(context.page.attributes(), path),
+        "build" => match path {
+            "year" => Some(Local::now().format("%Y").to_string()),
+            "date" => Some(Local::now().format("%Y-%m-%d").to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Replace `{{ site.* }}`, `{{ page.* }}` and `{{ build.* }}` placeholders with their values
+///
+/// `\{{` is kept as literal `{{` and unknown variables are left as is
+pub fn substitute(text: &str, context: &RenderContext) -> String {
+    if !text.contains("{{") {
+        return text.to_string();
+    }
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        // escaped placeholder
+        if rest[..start].ends_with('\\') {
+            result.push_str(&rest[..start - 1]);
+            result.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        result.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let placeholder = &rest[start..start + end + 2];
+        let name = placeholder[2..placeholder.len() - 2].trim();
+        match resolve(name, context) {
+            Some(value) => result.push_str(&value),
+            None => {
+                warn!("Unknown variable {name:?}");
+                result.push_str(placeholder);
+            }
+        }
+        rest = &rest[start + end + 2..];
+    }
+    result.push_str(rest);
+    result
+}
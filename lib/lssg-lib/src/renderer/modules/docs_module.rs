@@ -0,0 +1,281 @@
+use log::warn;
+use proc_virtual_dom::dom;
+use serde::{Deserialize, Serialize};
+use serde_extensions::Overwrite;
+use virtual_dom::{to_attributes, Document, DomNode, DomNodeKind};
+
+use crate::{
+    lssg_error::LssgError,
+    renderer::RenderContext,
+    sitetree::{Page, Resource, SiteId, SiteNode, SiteNodeKind, SiteTree, Stylesheet},
+};
+
+use super::{util::page_title, RendererModule, SinglePageOptions};
+
+const DOCS_STYLESHEET: &[u8] = include_bytes!("./docs_stylesheet.css");
+
+#[derive(Deserialize, Debug, Clone)]
+struct DocsVersion {
+    name: String,
+    url: String,
+}
+
+#[derive(Overwrite, Debug, Clone)]
+pub struct DocsOptions {
+    /// Show a collapsible tree of all pages next to the content
+    sidebar: bool,
+    /// Url to edit the source files (eg. "https://github.com/user/repo/edit/main/docs"), the
+    /// path of the page relative to the root page is appended
+    edit_url: Option<String>,
+    /// Other versions of the docs with a `name` and `url`
+    versions: Vec<DocsVersion>,
+    /// Name of the version of these docs, defaults to the first version
+    version: Option<String>,
+}
+impl Default for DocsOptions {
+    fn default() -> Self {
+        Self {
+            sidebar: true,
+            edit_url: None,
+            versions: vec![],
+            version: None,
+        }
+    }
+}
+
+/// Page in the table of contents
+#[derive(Debug, Serialize)]
+struct TocEntry {
+    #[serde(skip)]
+    id: SiteId,
+    title: String,
+    path: String,
+    children: Vec<TocEntry>,
+}
+
+impl TocEntry {
+    fn contains(&self, id: SiteId) -> bool {
+        self.id == id || self.children.iter().any(|c| c.contains(id))
+    }
+}
+
+/// Listed pages below `id`, pages in folders are added to the closest parent page
+fn toc_entries(site_tree: &SiteTree, id: SiteId) -> Vec<TocEntry> {
+    let mut entries = vec![];
+    for child in &site_tree[id].children {
+        match &site_tree[*child].kind {
+            SiteNodeKind::Page(page) => {
+                if !SinglePageOptions::resolve(site_tree, *child).is_listed()
+                    || site_tree.is_error_page(*child)
+                {
+                    continue;
+                }
+                entries.push(TocEntry {
+                    id: *child,
+                    title: page_title(page, &site_tree[*child].name),
+                    path: site_tree.path(*child),
+                    children: toc_entries(site_tree, *child),
+                })
+            }
+            SiteNodeKind::Folder => entries.extend(toc_entries(site_tree, *child)),
+            _ => {}
+        }
+    }
+    entries
+}
+
+/// Nested list of `entries`, branches leading to the current page are open
+fn sidebar_list(entries: &[TocEntry], context: &RenderContext) -> DomNode {
+    let list = dom!(<ul></ul>);
+    for entry in entries {
+        let href = context.site_tree.href(context.site_id, entry.id);
+        let link = DomNode::create_element_with_attributes("a", to_attributes([("href", href)]));
+        link.append_child(DomNode::create_text(entry.title.clone()));
+        if entry.id == context.site_id {
+            if let DomNodeKind::Element { attributes, .. } = &mut *link.kind_mut() {
+                attributes.insert("aria-current".into(), "page".into());
+            }
+        }
+
+        let item = dom!(<li></li>);
+        if entry.children.is_empty() {
+            item.append_child(link);
+        } else {
+            let details = match entry.contains(context.site_id) {
+                true => DomNode::create_element_with_attributes(
+                    "details",
+                    to_attributes([("open", "")]),
+                ),
+                false => DomNode::create_element("details"),
+            };
+            let summary = dom!(<summary></summary>);
+            summary.append_child(link);
+            details.append_child(summary);
+            details.append_child(sidebar_list(&entry.children, context));
+            item.append_child(details);
+        }
+        list.append_child(item);
+    }
+    list
+}
+
+/// Link to edit the source of the current page, None if the source is not relative to the root
+fn edit_link(edit_url: &str, context: &RenderContext) -> Option<DomNode> {
+    let site_tree = context.site_tree;
+    let root = site_tree.get_input(site_tree.root())?;
+    let input = site_tree.get_input(context.site_id)?;
+    let path = root.make_relative(input)?;
+    if path.starts_with("..") {
+        return None;
+    }
+    let href = format!("{}/{path}", edit_url.trim_end_matches('/'));
+    Some(dom!(<p class="docs-edit"><a href="{href}">Edit this page</a></p>))
+}
+
+/// Dropdown with links to the other versions of the docs
+fn version_switcher(options: &DocsOptions) -> DomNode {
+    let current = options
+        .version
+        .clone()
+        .or_else(|| options.versions.first().map(|v| v.name.clone()))
+        .unwrap_or_default();
+    let summary = current.clone();
+    let switcher = dom!(<details class="docs-versions"><summary>{summary}</summary></details>);
+    let list = dom!(<ul></ul>);
+    for version in &options.versions {
+        let link = DomNode::create_element_with_attributes(
+            "a",
+            to_attributes([("href", version.url.as_str())]),
+        );
+        link.append_child(DomNode::create_text(version.name.clone()));
+        if version.name == current {
+            if let DomNodeKind::Element { attributes, .. } = &mut *link.kind_mut() {
+                attributes.insert("aria-current".into(), "true".into());
+            }
+        }
+        let item = dom!(<li></li>);
+        item.append_child(link);
+        list.append_child(item);
+    }
+    switcher.append_child(list);
+    switcher
+}
+
+/// Documentation style navigation enabled with `docs = true` or a `[docs]` table on the root
+/// page: writes a `toc.json` of all pages (title, path and children) for client side navigation
+/// and shows them as a sidebar, with optionally a version switcher and edit links
+#[derive(Default)]
+pub struct DocsModule {
+    /// options of the root page, None if not enabled
+    root_options: Option<DocsOptions>,
+    toc: Vec<TocEntry>,
+}
+
+impl DocsModule {
+    pub fn new() -> Self {
+        Self {
+            root_options: None,
+            toc: vec![],
+        }
+    }
+
+    fn docs_options(&self, page: &Page) -> Option<DocsOptions> {
+        match page.attributes()?.get(self.id())? {
+            toml::Value::Boolean(true) => Some(DocsOptions::default()),
+            toml::Value::Table(_) => Some(self.options(page)),
+            _ => None,
+        }
+    }
+}
+
+impl RendererModule for DocsModule {
+    fn id(&self) -> &'static str {
+        "docs"
+    }
+
+    fn init(&mut self, site_tree: &mut SiteTree) -> Result<(), LssgError> {
+        let root = site_tree.root();
+        let SiteNodeKind::Page(page) = &site_tree[root].kind else {
+            return Ok(());
+        };
+        let Some(options) = self.docs_options(page) else {
+            return Ok(());
+        };
+        let toc = TocEntry {
+            id: root,
+            title: page_title(page, &site_tree[root].name),
+            path: site_tree.path(root),
+            children: toc_entries(site_tree, root),
+        };
+        let json = match serde_json::to_string_pretty(&toc) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to serialize toc.json: {e}");
+                return Ok(());
+            }
+        };
+        site_tree.add(SiteNode::resource(
+            "toc.json",
+            root,
+            Resource::new_static(json),
+        ));
+
+        let stylesheet = site_tree.add(SiteNode::stylesheet(
+            "docs.css",
+            root,
+            Stylesheet::from_readable(DOCS_STYLESHEET)?,
+        ));
+        let pages: Vec<SiteId> = (0..site_tree.len())
+            .filter(|id| site_tree[*id].kind.is_page())
+            .collect();
+        for page in pages {
+            site_tree.add_link(page, stylesheet);
+        }
+        self.toc = vec![toc];
+        self.root_options = Some(options);
+        Ok(())
+    }
+
+    fn after_render<'n>(&mut self, document: &mut Document, context: &RenderContext<'n>) {
+        let Some(root_options) = &self.root_options else {
+            return;
+        };
+        let options = self.options_with_default(context.page, root_options.clone());
+        let main = document.body.children().find(|c| match &*c.kind() {
+            DomNodeKind::Element { tag, .. } => tag == "main",
+            _ => false,
+        });
+
+        if let Some(edit_url) = &options.edit_url {
+            if let Some(link) = edit_link(edit_url, context) {
+                match &main {
+                    Some(main) => main.append_child(link),
+                    None => document.body.append_child(link),
+                }
+            }
+        }
+
+        let versions = (!options.versions.is_empty()).then(|| version_switcher(&options));
+        if !options.sidebar {
+            if let Some(versions) = versions {
+                match &main {
+                    Some(main) => main.prepend(versions),
+                    None => document.body.prepend(versions),
+                }
+            }
+            return;
+        }
+        let sidebar = DomNode::create_element_with_attributes(
+            "nav",
+            to_attributes([("class", "docs-sidebar"), ("aria-label", "Pages")]),
+        );
+        if let Some(versions) = versions {
+            sidebar.append_child(versions);
+        }
+        sidebar.append_child(sidebar_list(&self.toc, context));
+        match main {
+            Some(main) => main.insert_before(sidebar),
+            None => document.body.prepend(sidebar),
+        }
+    }
+}
@@ -0,0 +1,473 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use log::{info, warn};
+use proc_virtual_dom::dom;
+use serde_extensions::Overwrite;
+use virtual_dom::{
+    parse_html, to_attributes, Document, DomNode, DomNodeKind, HeadPrecedence, OutputMode,
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    budget::resolve,
+    lssg_error::LssgError,
+    renderer::RenderContext,
+    sitetree::{Page, SiteId, SiteNodeKind, SiteTree},
+    tree::DFS,
+};
+
+use super::{
+    util::{base_url, escape_xml, media_type, page_title, source_modified},
+    RendererModule,
+};
+
+#[derive(Overwrite, Debug)]
+pub struct EpubOptions {
+    /// Title of the book, defaults to the title of the page
+    title: Option<String>,
+    author: Option<String>,
+    /// Add a download link to the epub at the end of the page
+    link: bool,
+}
+impl Default for EpubOptions {
+    fn default() -> Self {
+        Self {
+            title: None,
+            author: None,
+            link: true,
+        }
+    }
+}
+
+/// Packages a page and all pages below it into a `{name}.epub` next to pages with `epub = true`
+/// or an `[epub]` table, using the rendered html of the pages
+#[derive(Default)]
+pub struct EpubModule {}
+
+impl EpubModule {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Options of `page` if an epub should be generated for it
+    fn epub_options(&self, page: &Page) -> Option<EpubOptions> {
+        match page.attributes()?.get(self.id())? {
+            toml::Value::Boolean(true) => Some(EpubOptions::default()),
+            toml::Value::Table(_) => Some(self.options(page)),
+            _ => None,
+        }
+    }
+}
+
+fn epub_name(site_tree: &SiteTree, id: SiteId) -> String {
+    format!("{}.epub", site_tree[id].name)
+}
+
+/// Path of the written html of page `id`
+fn html_path(site_tree: &SiteTree, output_directory: &Path, id: SiteId) -> PathBuf {
+    site_tree
+        .rel_path(site_tree.root(), id)
+        .split('/')
+        .fold(output_directory.to_path_buf(), |path, part| path.join(part))
+        .join("index.html")
+}
+
+/// Page `id` and all pages below it in the order they are in the SiteTree
+fn subtree_pages(site_tree: &SiteTree, id: SiteId, pages: &mut Vec<SiteId>) {
+    if site_tree[id].kind.is_page() && !site_tree.is_error_page(id) {
+        pages.push(id);
+    }
+    for child in &site_tree[id].children {
+        subtree_pages(site_tree, *child, pages);
+    }
+}
+
+/// Files from the output directory that are added to the book
+#[derive(Default)]
+struct Resources {
+    /// path in the output directory, path in the book and media type
+    files: Vec<(PathBuf, String, &'static str)>,
+}
+
+impl Resources {
+    /// Path in the book of the file at `path`, None if it can't be added
+    fn add(&mut self, path: &Path, folder: &str) -> Option<String> {
+        if let Some((_, href, _)) = self.files.iter().find(|(p, _, _)| p == path) {
+            return Some(href.clone());
+        }
+        let name = path.file_name()?.to_string_lossy().to_string();
+        let media_type = media_type(&name)?;
+        // prefix with the index, different folders can have files with the same name
+        let href = format!("{folder}/{}-{name}", self.files.len());
+        self.files
+            .push((path.to_path_buf(), href.clone(), media_type));
+        Some(href)
+    }
+}
+
+fn remove_attribute(node: &DomNode, name: &str) {
+    if let DomNodeKind::Element { attributes, .. } = &mut *node.kind_mut() {
+        attributes.shift_remove(name);
+    }
+}
+
+fn attribute(node: &DomNode, name: &str) -> Option<String> {
+    match &*node.kind() {
+        DomNodeKind::Element { attributes, .. } => attributes.get(name).cloned(),
+        DomNodeKind::Text { .. } | DomNodeKind::Comment { .. } => None,
+    }
+}
+
+struct Chapter<'a> {
+    title: String,
+    html_path: &'a Path,
+    /// chapter file of every page in the book
+    chapters: &'a HashMap<PathBuf, String>,
+    output_directory: &'a Path,
+    site_tree: &'a SiteTree,
+}
+
+impl Chapter<'_> {
+    /// Convert the written html of a page into an xhtml chapter, returns the chapter and the
+    /// language of the page
+    fn xhtml(&self, resources: &mut Resources) -> Result<(String, Option<String>), LssgError> {
+        let url_prefix = self.site_tree.url_prefix();
+        let resolve = |url: &str| resolve(url, self.html_path, self.output_directory, url_prefix);
+        let html = parse_html(File::open(self.html_path)?)?;
+        let page = Document::from_html(html)
+            .map_err(|e| LssgError::render(format!("Failed to parse {:?}: {e}", self.html_path)))?;
+        let language = attribute(&page.root(), "lang");
+
+        let mut chapter = Document::new();
+        if let DomNodeKind::Element { attributes, .. } = &mut *chapter.root().kind_mut() {
+            attributes.insert("xmlns:epub".into(), "http://www.idpf.org/2007/ops".into());
+            if let Some(language) = &language {
+                attributes.insert("lang".into(), language.clone());
+            }
+        }
+        chapter.add_to_head(dom!(<meta charset="utf-8"/>), HeadPrecedence::Normal);
+        let title = &self.title;
+        chapter.add_to_head(dom!(<title>{title}</title>), HeadPrecedence::Normal);
+        for link in page.get_elements_by_tag_name("link") {
+            let is_stylesheet = attribute(&link, "rel").as_deref() == Some("stylesheet")
+                && attribute(&link, "media").as_deref() != Some("print");
+            let Some(href) = attribute(&link, "href")
+                .filter(|_| is_stylesheet)
+                .and_then(|href| resolve(&href))
+                .and_then(|path| resources.add(&path, "styles"))
+            else {
+                continue;
+            };
+            chapter.add_to_head(
+                dom!(<link rel="stylesheet" href="{href}"/>),
+                HeadPrecedence::Normal,
+            );
+        }
+
+        let content = page
+            .get_elements_by_tag_name("main")
+            .into_iter()
+            .next()
+            .unwrap_or(page.body.clone());
+        // scripted content is not supported by all readers
+        for node in content.get_elements_by_tag_name("script") {
+            node.detach();
+        }
+        for node in content.get_elements_by_tag_name("source") {
+            node.detach();
+        }
+        // download links of the site
+        for node in content.get_elements_by_tag_name("p") {
+            if matches!(attribute(&node, "class").as_deref(), Some("epub" | "pdf")) {
+                node.detach();
+            }
+        }
+        for mut img in content.get_elements_by_tag_name("img") {
+            remove_attribute(&img, "srcset");
+            remove_attribute(&img, "sizes");
+            remove_attribute(&img, "loading");
+            match attribute(&img, "src")
+                .and_then(|src| resolve(&src))
+                .and_then(|path| resources.add(&path, "images"))
+            {
+                Some(src) => img.set_attribute("src", src),
+                None => warn!(
+                    "Image {:?} of {:?} is not added to the epub",
+                    attribute(&img, "src").unwrap_or_default(),
+                    self.html_path
+                ),
+            }
+        }
+        for mut a in content.get_elements_by_tag_name("a") {
+            let Some(href) = attribute(&a, "href") else {
+                continue;
+            };
+            if href.starts_with('#') || href.contains(':') {
+                continue;
+            }
+            let fragment = href.find('#').map(|i| &href[i..]).unwrap_or("");
+            match resolve(&href).and_then(|path| self.chapters.get(&path)) {
+                Some(chapter) => a.set_attribute("href", format!("{chapter}{fragment}")),
+                // link to the site for pages that are not part of the book
+                None => {
+                    if let (Some(base_url), Some(path)) =
+                        (base_url(self.site_tree), href.strip_prefix('/'))
+                    {
+                        a.set_attribute("href", format!("{base_url}/{path}"))
+                    }
+                }
+            }
+        }
+        chapter.body.append_child(content);
+
+        Ok((chapter.serialize(OutputMode::Xhtml), language))
+    }
+}
+
+/// Table of contents required by EPUB 3
+fn nav(title: &str, chapters: &[(String, String)]) -> String {
+    let mut document = Document::new();
+    if let DomNodeKind::Element { attributes, .. } = &mut *document.root().kind_mut() {
+        attributes.insert("xmlns:epub".into(), "http://www.idpf.org/2007/ops".into());
+    }
+    document.add_to_head(dom!(<title>{title}</title>), HeadPrecedence::Normal);
+    let nav = DomNode::create_element_with_attributes(
+        "nav",
+        to_attributes([("epub:type", "toc"), ("id", "toc")]),
+    );
+    nav.append_child(dom!(<h1>{title}</h1>));
+    let list = dom!(<ol></ol>);
+    for (href, title) in chapters {
+        list.append_child(dom!(<li><a href="{href}">{title}</a></li>));
+    }
+    nav.append_child(list);
+    document.body.append_child(nav);
+    document.serialize(OutputMode::Xhtml)
+}
+
+struct Package<'a> {
+    identifier: String,
+    title: &'a str,
+    author: Option<&'a str>,
+    language: &'a str,
+    /// chapter files in reading order
+    chapters: &'a [(String, String)],
+    resources: &'a Resources,
+    /// last modification of the pages in the book
+    modified: DateTime<Utc>,
+}
+
+impl Package<'_> {
+    /// Package document (`package.opf`) <https://www.w3.org/TR/epub-33/#sec-package-doc>
+    fn opf(&self) -> String {
+        let mut manifest = vec![
+            r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#.to_string(),
+        ];
+        let mut spine = vec![];
+        for (i, (href, _)) in self.chapters.iter().enumerate() {
+            manifest.push(format!(
+                r#"<item id="chapter-{i}" href="{href}" media-type="application/xhtml+xml"/>"#
+            ));
+            spine.push(format!(r#"<itemref idref="chapter-{i}"/>"#));
+        }
+        for (i, (_, href, media_type)) in self.resources.files.iter().enumerate() {
+            manifest.push(format!(
+                r#"<item id="resource-{i}" href="{}" media-type="{media_type}"/>"#,
+                escape_xml(href)
+            ));
+        }
+        let author = self
+            .author
+            .map(|author| format!("\n    <dc:creator>{}</dc:creator>", escape_xml(author)))
+            .unwrap_or_default();
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="id">{}</dc:identifier>
+    <dc:title>{}</dc:title>
+    <dc:language>{}</dc:language>{author}
+    <meta property="dcterms:modified">{}</meta>
+  </metadata>
+  <manifest>
+    {}
+  </manifest>
+  <spine>
+    {}
+  </spine>
+</package>
+"#,
+            escape_xml(&self.identifier),
+            escape_xml(self.title),
+            escape_xml(self.language),
+            self.modified.format("%Y-%m-%dT%H:%M:%SZ"),
+            manifest.join("\n    "),
+            spine.join("\n    ")
+        )
+    }
+}
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="EPUB/package.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+/// Write the epub of page `id` and the pages below it
+fn write_epub(
+    site_tree: &SiteTree,
+    output_directory: &Path,
+    id: SiteId,
+    options: &EpubOptions,
+) -> Result<(), LssgError> {
+    let SiteNodeKind::Page(page) = &site_tree[id].kind else {
+        return Ok(());
+    };
+    let title = options
+        .title
+        .clone()
+        .unwrap_or_else(|| page_title(page, &site_tree[id].name));
+
+    let mut pages = vec![];
+    subtree_pages(site_tree, id, &mut pages);
+    let chapters: HashMap<PathBuf, String> = pages
+        .iter()
+        .enumerate()
+        .map(|(i, page)| {
+            (
+                html_path(site_tree, output_directory, *page),
+                format!("chapter-{i}.xhtml"),
+            )
+        })
+        .collect();
+
+    let mut resources = Resources::default();
+    let mut language = None;
+    // file name, title and content of every chapter
+    let mut files = vec![];
+    for page_id in &pages {
+        let SiteNodeKind::Page(page) = &site_tree[*page_id].kind else {
+            continue;
+        };
+        let html_path = html_path(site_tree, output_directory, *page_id);
+        let chapter = Chapter {
+            title: page_title(page, &site_tree[*page_id].name),
+            html_path: &html_path,
+            chapters: &chapters,
+            output_directory,
+            site_tree,
+        };
+        let (xhtml, page_language) = chapter.xhtml(&mut resources)?;
+        language = language.or(page_language);
+        files.push((chapters[&html_path].clone(), chapter.title, xhtml));
+    }
+
+    // use the latest change of a page so the book only changes with its pages
+    let modified = pages
+        .iter()
+        .filter_map(|page| source_modified(site_tree, *page))
+        .max()
+        .unwrap_or(DateTime::UNIX_EPOCH);
+
+    let path = html_path(site_tree, output_directory, id).with_file_name(epub_name(site_tree, id));
+    info!("Writing {path:?}");
+    let file_options = SimpleFileOptions::default().last_modified_time(
+        zip::DateTime::from_date_and_time(
+            modified.year() as u16,
+            modified.month() as u8,
+            modified.day() as u8,
+            modified.hour() as u8,
+            modified.minute() as u8,
+            modified.second() as u8,
+        )
+        .unwrap_or_default(),
+    );
+    let mut zip = ZipWriter::new(File::create(&path)?);
+    // the mimetype has to be the first file and can't be compressed
+    zip.start_file(
+        "mimetype",
+        file_options.compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/epub+zip")?;
+    zip.start_file("META-INF/container.xml", file_options)?;
+    zip.write_all(CONTAINER.as_bytes())?;
+
+    let toc: Vec<(String, String)> = files
+        .iter()
+        .map(|(name, title, _)| (name.clone(), title.clone()))
+        .collect();
+    let package = Package {
+        identifier: match base_url(site_tree) {
+            Some(base_url) => format!("{base_url}{}", site_tree.path(id)),
+            None => format!("urn:lssg:{}", site_tree.path(id)),
+        },
+        title: &title,
+        author: options.author.as_deref(),
+        language: language.as_deref().unwrap_or("en"),
+        chapters: &toc,
+        resources: &resources,
+        modified,
+    };
+    zip.start_file("EPUB/package.opf", file_options)?;
+    zip.write_all(package.opf().as_bytes())?;
+    zip.start_file("EPUB/nav.xhtml", file_options)?;
+    zip.write_all(nav(&title, &toc).as_bytes())?;
+    for (name, _, xhtml) in files {
+        zip.start_file(format!("EPUB/{name}"), file_options)?;
+        zip.write_all(xhtml.as_bytes())?;
+    }
+    for (path, href, _) in &resources.files {
+        zip.start_file(format!("EPUB/{href}"), file_options)?;
+        zip.write_all(&fs::read(path)?)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+impl RendererModule for EpubModule {
+    fn id(&self) -> &'static str {
+        "epub"
+    }
+
+    fn after_render<'n>(&mut self, document: &mut Document, context: &RenderContext<'n>) {
+        let Some(options) = self.epub_options(context.page) else {
+            return;
+        };
+        if !options.link || context.site_tree.is_error_page(context.site_id) {
+            return;
+        }
+        let path = context.site_tree.path(context.site_id);
+        let href = format!(
+            "{}/{}",
+            path.trim_end_matches('/'),
+            epub_name(context.site_tree, context.site_id)
+        );
+        let link = dom!(<p class="epub"><a href="{href}" download>Download EPUB</a></p>);
+        document.body.append_child(link);
+    }
+
+    fn finish(&mut self, site_tree: &SiteTree, output_directory: &Path) -> Result<(), LssgError> {
+        for id in DFS::new(site_tree) {
+            let SiteNodeKind::Page(page) = &site_tree[id].kind else {
+                continue;
+            };
+            let Some(options) = self.epub_options(page) else {
+                continue;
+            };
+            if site_tree.is_error_page(id) {
+                warn!("Not generating epub for error page");
+                continue;
+            }
+            write_epub(site_tree, output_directory, id, &options)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,420 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
+use log::warn;
+use proc_virtual_dom::dom;
+use serde_extensions::Overwrite;
+
+use crate::{
+    lmarkdown::Token,
+    lssg_error::LssgError,
+    renderer::RenderContext,
+    sitetree::{Resource, SiteId, SiteNode, SiteNodeKind, SiteTree, Stylesheet},
+    tree::DFS,
+};
+use virtual_dom::{Document, DomNode, HeadPrecedence};
+
+use super::{
+    util::{base_url, page_title, source_modified},
+    RendererModule, SinglePageOptions, TokenRenderer,
+};
+
+const EVENTS_STYLESHEET: &[u8] = include_bytes!("./events_stylesheet.css");
+
+#[derive(Overwrite, Debug, Default)]
+pub struct EventsOptions {
+    /// Pages linked from this page are events, a listing of them is added to this page
+    root: bool,
+    /// Start of the event (%Y-%m-%d or %Y-%m-%d %H:%M)
+    date: Option<String>,
+    /// End of the event, same format as `date`
+    end: Option<String>,
+    location: Option<String>,
+}
+
+/// A point in time of an event, without a time the event lasts the whole day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum EventTime {
+    Day(NaiveDate),
+    DateTime(NaiveDateTime),
+}
+impl EventTime {
+    fn parse(text: &str) -> Option<EventTime> {
+        if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+            return Some(EventTime::Day(date));
+        }
+        ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+            .map(EventTime::DateTime)
+    }
+
+    fn date(&self) -> NaiveDate {
+        match self {
+            EventTime::Day(date) => *date,
+            EventTime::DateTime(datetime) => datetime.date(),
+        }
+    }
+
+    /// Value for the html `datetime` attribute
+    fn datetime(&self) -> String {
+        match self {
+            EventTime::Day(date) => date.format("%Y-%m-%d").to_string(),
+            EventTime::DateTime(datetime) => datetime.format("%Y-%m-%dT%H:%M").to_string(),
+        }
+    }
+
+    fn display(&self) -> String {
+        match self {
+            EventTime::Day(date) => date.format("%B %d, %Y").to_string(),
+            EventTime::DateTime(datetime) => datetime.format("%B %d, %Y %H:%M").to_string(),
+        }
+    }
+
+    /// iCalendar property, times are floating (local to wherever the event is)
+    fn ics(&self, name: &str) -> String {
+        match self {
+            EventTime::Day(date) => format!("{name};VALUE=DATE:{}", date.format("%Y%m%d")),
+            EventTime::DateTime(datetime) => {
+                format!("{name}:{}", datetime.format("%Y%m%dT%H%M%S"))
+            }
+        }
+    }
+
+    /// iCalendar `DTEND`, which is exclusive so the end day is written as the day after
+    fn ics_end(&self) -> String {
+        match self {
+            EventTime::Day(date) => EventTime::Day(date.succ_opt().unwrap_or(*date)).ics("DTEND"),
+            EventTime::DateTime(_) => self.ics("DTEND"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Event {
+    site_id: SiteId,
+    title: String,
+    start: EventTime,
+    end: Option<EventTime>,
+    location: Option<String>,
+}
+impl Event {
+    fn is_upcoming(&self, today: NaiveDate) -> bool {
+        self.end.unwrap_or(self.start).date() >= today
+    }
+}
+
+/// Escape text for use in an iCalendar property value
+fn escape_ics(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Split lines longer than 75 octets as required by iCalendar
+fn fold_ics(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+/// iCalendar feed of `events` <https://www.rfc-editor.org/rfc/rfc5545>
+fn ics(events: &[Event], site_tree: &SiteTree) -> String {
+    let base_url = base_url(site_tree);
+    let host = base_url
+        .as_deref()
+        .and_then(|url| url.split("://").nth(1))
+        .unwrap_or("lssg");
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//lssg//events//EN".to_string(),
+    ];
+    for event in events {
+        let path = site_tree.path(event.site_id);
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!(
+            "UID:{}@{host}",
+            path.trim_matches('/').replace('/', "-")
+        ));
+        // the last change of the event page keeps the feed the same between builds
+        let stamp = source_modified(site_tree, event.site_id)
+            .map(|date| date.naive_utc())
+            .unwrap_or(event.start.date().and_time(NaiveTime::MIN));
+        lines.push(format!("DTSTAMP:{}", stamp.format("%Y%m%dT%H%M%SZ")));
+        lines.push(event.start.ics("DTSTART"));
+        if let Some(end) = &event.end {
+            lines.push(end.ics_end());
+        }
+        lines.push(format!("SUMMARY:{}", escape_ics(&event.title)));
+        if let Some(location) = &event.location {
+            lines.push(format!("LOCATION:{}", escape_ics(location)));
+        }
+        if let Some(base_url) = &base_url {
+            lines.push(format!("URL:{base_url}{path}"));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|l| fold_ics(l) + "\r\n").collect()
+}
+
+/// Renders pages linked from a page with `[events] root = true` as events, using their `date`,
+/// `end` and `location` options, and adds a listing of upcoming and past events and an
+/// iCalendar feed (`events.ics`) to the root
+#[derive(Default)]
+pub struct EventsModule {
+    /// events of every root, sorted by start
+    roots: HashMap<SiteId, Vec<Event>>,
+    /// the ics resource of every root
+    calendars: HashMap<SiteId, SiteId>,
+    events: HashMap<SiteId, Event>,
+    /// Local variable to keep track if the event details have been inserted
+    has_inserted_details: bool,
+}
+
+impl EventsModule {
+    pub fn new() -> Self {
+        Self {
+            roots: HashMap::new(),
+            calendars: HashMap::new(),
+            events: HashMap::new(),
+            has_inserted_details: false,
+        }
+    }
+
+    fn listing(&self, root: SiteId, context: &RenderContext) -> DomNode {
+        let today = Local::now().date_naive();
+        let events = &self.roots[&root];
+        let upcoming: Vec<&Event> = events.iter().filter(|e| e.is_upcoming(today)).collect();
+        // most recent first
+        let past: Vec<&Event> = events
+            .iter()
+            .rev()
+            .filter(|e| !e.is_upcoming(today))
+            .collect();
+
+        let section = dom!(<section class="events"></section>);
+        for (title, events) in [("Upcoming events", upcoming), ("Past events", past)] {
+            if events.is_empty() {
+                continue;
+            }
+            section.append_child(dom!(<h2>{title}</h2>));
+            let list = dom!(<ul class="event-list"></ul>);
+            for event in events {
+                let href = context.site_tree.href(root, event.site_id);
+                let datetime = event.start.datetime();
+                let date = event.start.display();
+                let title = &event.title;
+                let item = dom!(<li class="h-event"></li>);
+                item.append_child(dom!(<time class="dt-start" datetime="{datetime}">{date}</time>));
+                item.append_child(DomNode::create_text(" "));
+                item.append_child(dom!(<a class="p-name u-url" href="{href}">{title}</a>));
+                if let Some(location) = &event.location {
+                    item.append_child(dom!(<span class="p-location">{location}</span>));
+                }
+                list.append_child(item);
+            }
+            section.append_child(list);
+        }
+        if let Some(calendar) = self.calendars.get(&root) {
+            let href = context.site_tree.href(root, *calendar);
+            section.append_child(
+                dom!(<p><a class="event-calendar" href="{href}">Subscribe to the calendar</a></p>),
+            );
+        }
+        section
+    }
+}
+
+impl RendererModule for EventsModule {
+    fn id(&self) -> &'static str {
+        "events"
+    }
+
+    fn init(&mut self, site_tree: &mut SiteTree) -> Result<(), LssgError> {
+        let pages: Vec<SiteId> = DFS::new(site_tree)
+            .filter(|id| site_tree[*id].kind.is_page())
+            .collect();
+
+        let mut roots: HashSet<SiteId> = HashSet::new();
+        // event to the root it belongs to
+        let mut event_roots: HashMap<SiteId, SiteId> = HashMap::new();
+        for site_id in pages {
+            let SiteNodeKind::Page(page) = &site_tree[site_id].kind else {
+                continue;
+            };
+            let options: EventsOptions = self.options(page);
+            if options.root {
+                roots.insert(site_id);
+                self.roots.insert(site_id, vec![]);
+                continue;
+            }
+            // pages under an event are part of the same listing
+            let Some(root) = site_tree.page_parent(site_id).and_then(|parent| {
+                if roots.contains(&parent) {
+                    Some(parent)
+                } else {
+                    event_roots.get(&parent).cloned()
+                }
+            }) else {
+                continue;
+            };
+            event_roots.insert(site_id, root);
+
+            let Some(date) = options.date else {
+                continue;
+            };
+            let Some(start) = EventTime::parse(&date) else {
+                warn!("could not parse event date {date:?}, expected %Y-%m-%d or %Y-%m-%d %H:%M");
+                continue;
+            };
+            let end = options.end.and_then(|end| {
+                let parsed = EventTime::parse(&end);
+                if parsed.is_none() {
+                    warn!("could not parse event end {end:?}");
+                }
+                parsed
+            });
+            let event = Event {
+                site_id,
+                title: page_title(page, &site_tree[site_id].name),
+                start,
+                end,
+                location: options.location,
+            };
+            // unlisted events are only left out of the listing and calendar
+            if SinglePageOptions::resolve(site_tree, site_id).is_listed() {
+                self.roots.entry(root).or_default().push(event.clone());
+            }
+            self.events.insert(site_id, event);
+        }
+
+        if self.roots.is_empty() {
+            return Ok(());
+        }
+        let stylesheet = site_tree.add(SiteNode::stylesheet(
+            "events.css",
+            site_tree.root(),
+            Stylesheet::from_readable(EVENTS_STYLESHEET)?,
+        ));
+        for (root, events) in self.roots.iter_mut() {
+            events.sort_by_key(|e| e.start);
+            let calendar = site_tree.add(SiteNode::resource(
+                "events.ics",
+                *root,
+                Resource::new_static(ics(events, site_tree)),
+            ));
+            site_tree.add_link(*root, calendar);
+            site_tree.add_link(*root, stylesheet);
+            self.calendars.insert(*root, calendar);
+        }
+        for event in self.events.keys() {
+            site_tree.add_link(*event, stylesheet);
+        }
+
+        Ok(())
+    }
+
+    fn render_page<'n>(
+        &mut self,
+        document: &mut Document,
+        context: &RenderContext<'n>,
+    ) -> Option<String> {
+        self.has_inserted_details = false;
+        if let Some(calendar) = self.calendars.get(&context.site_id) {
+            let href = context.site_tree.href(context.site_id, *calendar);
+            document.add_to_head(
+                dom!(<link rel="alternate" type="text/calendar" title="Events" href="{href}"/>),
+                HeadPrecedence::Normal,
+            );
+        }
+        None
+    }
+
+    fn render_body<'n>(
+        &mut self,
+        document: &mut Document,
+        context: &RenderContext<'n>,
+        parent: DomNode,
+        token: &Token,
+        tr: &mut TokenRenderer,
+    ) -> Option<DomNode> {
+        let event = self.events.get(&context.site_id)?;
+        match token {
+            Token::Heading { depth: 1, .. } if !self.has_inserted_details => {
+                self.has_inserted_details = true;
+                tr.render(document, context, parent.clone(), &vec![token.clone()]);
+
+                let datetime = event.start.datetime();
+                let date = event.start.display();
+                let details = dom!(<div class="event-details"></div>);
+                details
+                    .append_child(dom!(<time class="dt-start" datetime="{datetime}">{date}</time>));
+                if let Some(end) = &event.end {
+                    let datetime = end.datetime();
+                    let date = end.display();
+                    details.append_child(document.create_text_node(" - "));
+                    details.append_child(
+                        dom!(<time class="dt-end" datetime="{datetime}">{date}</time>),
+                    );
+                }
+                if let Some(location) = &event.location {
+                    details.append_child(dom!(<span class="p-location">{location}</span>));
+                }
+                parent.append_child(details);
+                Some(parent)
+            }
+            _ => None,
+        }
+    }
+
+    fn after_render<'n>(&mut self, document: &mut Document, context: &RenderContext<'n>) {
+        if self.roots.contains_key(&context.site_id) {
+            document
+                .body
+                .append_child(self.listing(context.site_id, context));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_ics, fold_ics, EventTime};
+
+    #[test]
+    fn test_ics() {
+        assert_eq!(
+            EventTime::parse("2024-05-01").unwrap().ics("DTSTART"),
+            "DTSTART;VALUE=DATE:20240501"
+        );
+        assert_eq!(
+            EventTime::parse("2024-05-01 19:30").unwrap().ics("DTSTART"),
+            "DTSTART:20240501T193000"
+        );
+        assert_eq!(
+            EventTime::parse("2024-05-31").unwrap().ics_end(),
+            "DTEND;VALUE=DATE:20240601"
+        );
+        assert_eq!(
+            EventTime::parse("2024-05-01 21:00").unwrap().ics_end(),
+            "DTEND:20240501T210000"
+        );
+        assert_eq!(escape_ics("Room 1, Hall; B"), "Room 1\\, Hall\\; B");
+        let folded = fold_ics(&format!("SUMMARY:{}", "a".repeat(100)));
+        assert!(folded.split("\r\n").all(|line| line.len() <= 75));
+        assert_eq!(
+            folded.replace("\r\n ", ""),
+            format!("SUMMARY:{}", "a".repeat(100))
+        );
+    }
+}
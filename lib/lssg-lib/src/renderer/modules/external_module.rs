@@ -1,38 +1,151 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Read,
+    path::Path,
+};
 
+use log::warn;
+use reqwest::Url;
 use serde_extensions::Overwrite;
-use virtual_dom::{parse_html, Document};
+use virtual_dom::{parse_html_with_warnings, to_attributes, Document, DomNodeKind, ParseOptions};
 
 use crate::{
     lssg_error::LssgError,
     renderer::RenderContext,
-    sitetree::{Page, Resource, SiteId, SiteNode, SiteNodeKind, Stylesheet},
+    sitetree::{Page, Resource, SiteId, SiteNode, SiteNodeKind, SiteTree, Stylesheet},
     tree::DFS,
 };
 
 use super::RendererModule;
 
+/// Icons bundled with lssg that can be used as icon provider
+const BUILTIN_ICONS: [(&str, &str); 3] = [
+    ("mail", include_str!("./external_module/mail.svg")),
+    ("rss", include_str!("./external_module/rss.svg")),
+    ("link", include_str!("./external_module/link.svg")),
+];
+
 #[derive(Overwrite, Debug)]
 pub struct ExternalModuleOptions {
     href: Option<String>,
+    /// Map of links to icons, only read from the root page. Keys are domains ("github.com") or
+    /// "mailto" and "rss", values are a builtin icon ("mail", "rss", "link") or a path to an svg
+    icons: Option<BTreeMap<String, String>>,
 }
 
 impl Default for ExternalModuleOptions {
     fn default() -> Self {
-        Self { href: None }
+        Self {
+            href: None,
+            icons: None,
+        }
+    }
+}
+
+/// Get the icon provider key that matches `href`
+fn icon_key<'a, V>(href: &str, icons: &'a BTreeMap<String, V>) -> Option<&'a String> {
+    if href.starts_with("mailto:") {
+        return icons.get_key_value("mailto").map(|(k, _)| k);
+    }
+    let url = Url::parse(href).ok()?;
+    let path = url.path();
+    if path.ends_with(".xml")
+        || path.ends_with(".rss")
+        || path.ends_with(".atom")
+        || path.ends_with("/feed")
+    {
+        if let Some((key, _)) = icons.get_key_value("rss") {
+            return Some(key);
+        }
     }
+    let host = url.host_str()?;
+    icons
+        .keys()
+        .filter(|domain| host == *domain || host.ends_with(&format!(".{domain}")))
+        .max_by_key(|domain| domain.len())
 }
 
 pub struct ExternalModule {
     external_pages: HashMap<SiteId, Document>,
+    /// icon provider keys with the svg used for it
+    icons: BTreeMap<String, SiteId>,
 }
 
 impl ExternalModule {
     pub fn new() -> Self {
         Self {
             external_pages: HashMap::new(),
+            icons: BTreeMap::new(),
         }
     }
+
+    /// Add only the icons that are used by links in the site to an `icons` folder
+    fn add_icons(&mut self, site_tree: &mut SiteTree) -> Result<(), LssgError> {
+        let root = site_tree.root();
+        let providers = match &site_tree[root].kind {
+            SiteNodeKind::Page(page) => {
+                let options: ExternalModuleOptions = self.options(page);
+                options.icons
+            }
+            _ => None,
+        };
+        let Some(providers) = providers else {
+            return Ok(());
+        };
+
+        let mut used: Vec<String> = vec![];
+        for id in DFS::new(site_tree) {
+            if let SiteNodeKind::Page(page) = &site_tree[id].kind {
+                for (_, href, _) in page.links() {
+                    if let Some(key) = icon_key(href, &providers) {
+                        if !used.contains(key) {
+                            used.push(key.clone());
+                        }
+                    }
+                }
+            }
+        }
+        if used.is_empty() {
+            return Ok(());
+        }
+
+        let folder = match site_tree.get_by_name("icons", root) {
+            Some(id) => *id,
+            None => site_tree.add(SiteNode::folder("icons", root)),
+        };
+        // icon name to id, so icons used by multiple providers are only added once
+        let mut added: HashMap<String, SiteId> = HashMap::new();
+        for key in used {
+            let icon = &providers[&key];
+            if let Some(id) = added.get(icon) {
+                self.icons.insert(key, *id);
+                continue;
+            }
+            let (name, content) = match BUILTIN_ICONS.iter().find(|(name, _)| name == icon) {
+                Some((name, svg)) => (format!("{name}.svg"), svg.as_bytes().to_vec()),
+                None => {
+                    let input = match site_tree.get_input(root) {
+                        Some(input) => input.new(icon)?,
+                        None => continue,
+                    };
+                    if !icon.ends_with(".svg") {
+                        warn!("Icon {icon:?} for {key:?} is not an svg");
+                    }
+                    let mut content = vec![];
+                    input.readable()?.read_to_end(&mut content)?;
+                    (input.filename()?, content)
+                }
+            };
+            let id = site_tree.add(SiteNode::resource(
+                name,
+                folder,
+                Resource::Static { content },
+            ));
+            added.insert(icon.clone(), id);
+            self.icons.insert(key, id);
+        }
+        Ok(())
+    }
 }
 
 impl RendererModule for ExternalModule {
@@ -44,6 +157,8 @@ impl RendererModule for ExternalModule {
         &mut self,
         site_tree: &mut crate::sitetree::SiteTree,
     ) -> Result<(), crate::lssg_error::LssgError> {
+        self.add_icons(site_tree)?;
+
         let pages: Vec<usize> = DFS::new(site_tree)
             .filter(|id| site_tree[*id].kind.is_page())
             .collect();
@@ -83,13 +198,17 @@ impl RendererModule for ExternalModule {
                             }
                             if "index.html" == file_name {
                                 site_tree[parent_id].kind = SiteNodeKind::Page(Page::empty());
-                                let document =
-                                    Document::from_html(parse_html(file)?).map_err(|e| {
-                                        LssgError::new(
-                                            e.to_string(),
-                                            crate::lssg_error::LssgErrorKind::ParseError,
-                                        )
-                                    })?;
+                                let (html, warnings) =
+                                    parse_html_with_warnings(file, ParseOptions::default())?;
+                                for warning in warnings {
+                                    warn!("{}:{warning}", path.display());
+                                }
+                                let document = Document::from_html(html).map_err(|e| {
+                                    LssgError::new(
+                                        e.to_string(),
+                                        crate::lssg_error::LssgErrorKind::ParseError,
+                                    )
+                                })?;
                                 self.external_pages.insert(parent_id, document);
                                 continue;
                             }
@@ -115,4 +234,33 @@ impl RendererModule for ExternalModule {
         }
         None
     }
+
+    fn after_render<'n>(&mut self, document: &mut Document, context: &RenderContext<'n>) {
+        if self.icons.is_empty() {
+            return;
+        }
+        for node in document.body.descendants() {
+            let href = match &*node.kind() {
+                DomNodeKind::Element { tag, attributes } if tag == "a" => {
+                    match attributes.get("href") {
+                        Some(href) => href.clone(),
+                        None => continue,
+                    }
+                }
+                _ => continue,
+            };
+            if let Some(key) = icon_key(&href, &self.icons) {
+                let src = context.site_tree.href(context.site_id, self.icons[key]);
+                node.prepend(document.create_element_with_attributes(
+                    "img",
+                    to_attributes([
+                        ("class", "external__icon"),
+                        ("src", src.as_str()),
+                        ("alt", ""),
+                        ("aria-hidden", "true"),
+                    ]),
+                ));
+            }
+        }
+    }
 }
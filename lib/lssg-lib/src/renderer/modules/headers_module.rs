@@ -0,0 +1,182 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use log::info;
+use serde::Deserialize;
+use serde_extensions::Overwrite;
+use serde_json::json;
+
+use crate::{
+    lssg_error::LssgError,
+    sitetree::{Page, SiteNodeKind, SiteTree, MIRROR_FOLDER},
+};
+
+use super::RendererModule;
+
+/// Which files to write for the host
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeadersFormat {
+    /// `_headers` and `_redirects` (Netlify, Cloudflare Pages)
+    Netlify,
+    /// `vercel.json`
+    Vercel,
+    Both,
+}
+
+#[derive(Overwrite, Debug)]
+pub struct HeadersOptions {
+    format: HeadersFormat,
+    /// Add headers that prevent sniffing content types, framing and leaking the referrer
+    security: bool,
+    /// `Cache-Control` by path pattern (eg. `"/fonts/*" = "public, max-age=86400"`)
+    cache: BTreeMap<String, String>,
+    /// Permanent redirects from old paths to new paths or urls
+    redirects: BTreeMap<String, String>,
+}
+impl Default for HeadersOptions {
+    fn default() -> Self {
+        Self {
+            format: HeadersFormat::Netlify,
+            security: true,
+            cache: BTreeMap::new(),
+            redirects: BTreeMap::new(),
+        }
+    }
+}
+
+const SECURITY_HEADERS: [(&str, &str); 3] = [
+    ("X-Content-Type-Options", "nosniff"),
+    ("X-Frame-Options", "DENY"),
+    ("Referrer-Policy", "strict-origin-when-cross-origin"),
+];
+
+/// Headers by path pattern
+type Rules = BTreeMap<String, Vec<(String, String)>>;
+
+/// Vercel matches paths with path-to-regexp instead of `*` wildcards
+fn vercel_source(pattern: &str) -> String {
+    pattern.replace('*', "(.*)")
+}
+
+/// Writes the headers and redirects of the site in the format of static hosts, enabled with
+/// `headers = true` or a `[headers]` table on the root page
+#[derive(Default)]
+pub struct HeadersModule {}
+
+impl HeadersModule {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn headers_options(&self, page: &Page) -> Option<HeadersOptions> {
+        match page.attributes()?.get(self.id())? {
+            toml::Value::Boolean(true) => Some(HeadersOptions::default()),
+            toml::Value::Table(_) => Some(self.options(page)),
+            _ => None,
+        }
+    }
+}
+
+impl RendererModule for HeadersModule {
+    fn id(&self) -> &'static str {
+        "headers"
+    }
+
+    fn finish(&mut self, site_tree: &SiteTree, output_directory: &Path) -> Result<(), LssgError> {
+        let SiteNodeKind::Page(page) = &site_tree[site_tree.root()].kind else {
+            return Ok(());
+        };
+        let Some(options) = self.headers_options(page) else {
+            return Ok(());
+        };
+
+        let mut rules = Rules::new();
+        if options.security {
+            rules.insert(
+                site_tree.prefixed("/*"),
+                SECURITY_HEADERS
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            );
+        }
+        // mirrored files are named by a hash of their url so they never change
+        let mut cache = BTreeMap::from([(
+            format!("/{MIRROR_FOLDER}/*"),
+            "public, max-age=31536000, immutable".to_string(),
+        )]);
+        cache.extend(options.cache);
+        for (pattern, value) in cache {
+            rules
+                .entry(site_tree.prefixed(&pattern))
+                .or_default()
+                .push(("Cache-Control".into(), value));
+        }
+        let redirects: Vec<(String, String)> = options
+            .redirects
+            .iter()
+            .map(|(from, to)| (site_tree.prefixed(from), site_tree.prefixed(to)))
+            .collect();
+
+        if options.format != HeadersFormat::Vercel {
+            let mut headers = String::new();
+            for (pattern, values) in &rules {
+                headers.push_str(&format!("{pattern}\n"));
+                for (key, value) in values {
+                    headers.push_str(&format!("  {key}: {value}\n"));
+                }
+            }
+            // the default module may already have written the content security policy
+            let path = output_directory.join("_headers");
+            info!("Writing {path:?}");
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?
+                .write_all(headers.as_bytes())?;
+
+            if !redirects.is_empty() {
+                let redirects: String = redirects
+                    .iter()
+                    .map(|(from, to)| format!("{from} {to} 301\n"))
+                    .collect();
+                let path = output_directory.join("_redirects");
+                info!("Writing {path:?}");
+                fs::write(path, redirects)?;
+            }
+        }
+
+        if options.format != HeadersFormat::Netlify {
+            let headers: Vec<serde_json::Value> = rules
+                .iter()
+                .map(|(pattern, values)| {
+                    let values: Vec<serde_json::Value> = values
+                        .iter()
+                        .map(|(key, value)| json!({ "key": key, "value": value }))
+                        .collect();
+                    json!({ "source": vercel_source(pattern), "headers": values })
+                })
+                .collect();
+            let redirects: Vec<serde_json::Value> = redirects
+                .iter()
+                .map(|(from, to)| {
+                    json!({ "source": vercel_source(from), "destination": to, "permanent": true })
+                })
+                .collect();
+            let config = json!({ "headers": headers, "redirects": redirects });
+            let path = output_directory.join("vercel.json");
+            info!("Writing {path:?}");
+            fs::write(
+                path,
+                serde_json::to_string_pretty(&config)
+                    .map_err(|e| LssgError::render(e.to_string()))?,
+            )?;
+        }
+        Ok(())
+    }
+}
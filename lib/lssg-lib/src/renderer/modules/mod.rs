@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use log::error;
 use serde_extensions::Overwrite;
 
@@ -6,20 +8,45 @@ use crate::{
     sitetree::{Page, SiteTree},
     LssgError,
 };
-use virtual_dom::{Document, DomNode};
+use virtual_dom::{Document, DomBackend};
 
 mod external_module;
 pub use external_module::*;
 mod blog_module;
 pub use blog_module::*;
+mod blogroll_module;
+pub use blogroll_module::*;
+mod card_module;
+pub use card_module::*;
 mod default_module;
 pub use default_module::*;
+mod docs_module;
+pub use docs_module::*;
+mod epub_module;
+pub use epub_module::*;
+mod events_module;
+pub use events_module::*;
+mod headers_module;
+pub use headers_module::*;
+mod notebook_module;
+pub use notebook_module::*;
+#[cfg(feature = "pdf")]
+mod pdf_module;
+#[cfg(feature = "pdf")]
+pub use pdf_module::*;
+mod prose_module;
+pub use prose_module::*;
+mod sitemap_module;
+pub use sitemap_module::*;
+mod updates_module;
+pub use updates_module::*;
 pub mod util;
 
 use super::{RenderContext, TokenRenderer};
 
+/// Hooks into building the site and rendering pages, pages are built on the `D` dom backend
 #[allow(unused)]
-pub trait RendererModule {
+pub trait RendererModule<D: DomBackend = Document> {
     /// Return a static identifier for this module
     fn id(&self) -> &'static str;
 
@@ -35,31 +62,36 @@ pub trait RendererModule {
 
     /// Modify DomTree before rendering page
     /// return Some(String) if you want to render the page yourself and ignore renderer for this page
-    fn render_page<'n>(
-        &mut self,
-        dom: &mut Document,
-        context: &RenderContext<'n>,
-    ) -> Option<String> {
+    fn render_page<'n>(&mut self, dom: &mut D, context: &RenderContext<'n>) -> Option<String> {
         None
     }
 
     /// Render a token before default token renderer returns parent id for following tokens if it parsed this token otherwise None
     fn render_body<'n>(
         &mut self,
-        dom: &mut Document,
+        dom: &mut D,
         context: &RenderContext<'n>,
-        parent: DomNode,
+        parent: D::Node,
         token: &Token,
-        tr: &mut TokenRenderer,
-    ) -> Option<DomNode> {
+        tr: &mut TokenRenderer<D>,
+    ) -> Option<D::Node> {
         None
     }
 
     /// Gets called after body has been rendered, can be used for final changes to the dom
-    fn after_render<'n>(&mut self, dom: &mut Document, context: &RenderContext<'n>) {}
+    fn after_render<'n>(&mut self, dom: &mut D, context: &RenderContext<'n>) {}
+
+    /// Gets called after all modules ran `after_render`, for changes that depend on the final dom
+    fn finish_render<'n>(&mut self, dom: &mut D, context: &RenderContext<'n>) {}
+
+    /// Gets called once after all pages have been rendered and written, can be used to write
+    /// extra files to `output_directory`
+    fn finish(&mut self, site_tree: &SiteTree, output_directory: &Path) -> Result<(), LssgError> {
+        Ok(())
+    }
 
     /// get options by overwriting provided `default` with Token::Attributes
-    fn options_with_default<D: Overwrite + Default>(&self, page: &Page, mut default: D) -> D
+    fn options_with_default<O: Overwrite + Default>(&self, page: &Page, mut default: O) -> O
     where
         Self: Sized,
     {
@@ -82,11 +114,11 @@ pub trait RendererModule {
     }
 
     /// get default options overwritten with Token::Attributes
-    fn options<D: Overwrite + Default>(&self, page: &Page) -> D
+    fn options<O: Overwrite + Default>(&self, page: &Page) -> O
     where
         Self: Sized,
     {
-        let mut o = D::default();
+        let mut o = O::default();
         if let Some(Token::Attributes { table: toml }) = page.tokens().first() {
             // default options are defined on root of table
             if self.id() == "default" {
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::{error, warn};
+use proc_virtual_dom::dom;
+use regex::Regex;
+use serde::Deserialize;
+use serde_extensions::Overwrite;
+use serde_json::Value;
+
+use crate::{
+    lmarkdown::{parse_lmarkdown, visit_tokens, Token},
+    lssg_error::LssgError,
+    renderer::RenderContext,
+    sitetree::{Relation, Resource, SiteId, SiteNode, SiteNodeKind, SiteTree, Stylesheet},
+    tree::DFS,
+};
+use virtual_dom::{parse_html_with_warnings, Document, DomNode, ParseOptions};
+
+use super::{RendererModule, TokenRenderer};
+
+const NOTEBOOK_STYLESHEET: &[u8] = include_bytes!("./notebook_stylesheet.css");
+
+#[derive(Overwrite, Debug)]
+pub struct NotebookOptions {
+    /// Show the outputs of code cells
+    outputs: bool,
+}
+impl Default for NotebookOptions {
+    fn default() -> Self {
+        Self { outputs: true }
+    }
+}
+
+/// Text in a notebook is either a single string or a list of lines
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MultilineString {
+    One(String),
+    Lines(Vec<String>),
+}
+impl MultilineString {
+    fn text(&self) -> String {
+        match self {
+            MultilineString::One(text) => text.clone(),
+            MultilineString::Lines(lines) => lines.concat(),
+        }
+    }
+}
+
+/// Jupyter notebook file format <https://nbformat.readthedocs.io/en/latest/format_description.html>
+#[derive(Deserialize)]
+struct NotebookFile {
+    cells: Vec<NotebookCell>,
+    #[serde(default)]
+    metadata: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "cell_type", rename_all = "lowercase")]
+enum NotebookCell {
+    Markdown {
+        source: MultilineString,
+    },
+    Code {
+        source: MultilineString,
+        #[serde(default)]
+        outputs: Vec<NotebookOutput>,
+    },
+    Raw {},
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "output_type", rename_all = "snake_case")]
+enum NotebookOutput {
+    Stream {
+        text: MultilineString,
+    },
+    ExecuteResult {
+        data: HashMap<String, Value>,
+    },
+    DisplayData {
+        data: HashMap<String, Value>,
+    },
+    Error {
+        ename: String,
+        evalue: String,
+        #[serde(default)]
+        traceback: Vec<String>,
+    },
+}
+
+/// Text of a mime bundle value
+fn data_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(lines) => lines.iter().filter_map(|l| l.as_str()).collect(),
+        _ => String::new(),
+    }
+}
+
+enum Output {
+    Text(String),
+    Html(String),
+    Image(SiteId),
+}
+
+enum Cell {
+    Markdown(Vec<Token>),
+    Code { code: Token, outputs: Vec<Output> },
+}
+
+/// Get the href of a paragraph that only contains a link to a notebook
+fn notebook_href(token: &Token) -> Option<&String> {
+    match token {
+        Token::Paragraph { tokens, .. } => match tokens.as_slice() {
+            [Token::Link { href, .. }]
+                if href
+                    .split(['?', '#'])
+                    .next()
+                    .is_some_and(|p| p.ends_with(".ipynb")) =>
+            {
+                Some(href)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Inlines Jupyter notebooks (`.ipynb`) that are linked on their own line into the page, images
+/// in the outputs are saved as resources next to the notebook
+#[derive(Default)]
+pub struct NotebookModule {
+    /// notebook of every (page, href) pair
+    notebooks: HashMap<(SiteId, String), Vec<Cell>>,
+}
+
+impl NotebookModule {
+    pub fn new() -> Self {
+        Self {
+            notebooks: HashMap::new(),
+        }
+    }
+
+    /// Convert the notebook in resource `id` to cells, adding images as resources
+    fn convert(
+        &self,
+        site_tree: &mut SiteTree,
+        id: SiteId,
+        options: &NotebookOptions,
+    ) -> Result<Vec<Cell>, LssgError> {
+        let SiteNodeKind::Resource(resource) = &site_tree[id].kind else {
+            return Err(LssgError::render("notebook is not a resource"));
+        };
+        let notebook: NotebookFile = serde_json::from_reader(resource.readable()?)
+            .map_err(|e| LssgError::render(format!("invalid notebook: {e}")))?;
+        let language = ["/kernelspec/language", "/language_info/name"]
+            .into_iter()
+            .find_map(|p| notebook.metadata.pointer(p).and_then(|l| l.as_str()))
+            .unwrap_or("python")
+            .to_string();
+        let parent = site_tree[id].parent.unwrap_or(site_tree.root());
+        let stem = site_tree[id].name.trim_end_matches(".ipynb").to_string();
+        let ansi = Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").unwrap();
+
+        let mut cells = vec![];
+        for (i, cell) in notebook.cells.into_iter().enumerate() {
+            let (source, outputs) = match cell {
+                NotebookCell::Markdown { source } => {
+                    cells.push(Cell::Markdown(parse_lmarkdown(source.text().as_bytes())?));
+                    continue;
+                }
+                NotebookCell::Code { source, outputs } => (source, outputs),
+                NotebookCell::Raw {} => continue,
+            };
+            let code = Token::Code {
+                text: source.text(),
+                info: Some(language.clone()),
+            };
+            let outputs = match options.outputs {
+                true => outputs,
+                false => vec![],
+            };
+
+            let mut cell_outputs = vec![];
+            for (j, output) in outputs.into_iter().enumerate() {
+                let data = match output {
+                    NotebookOutput::Stream { text } => {
+                        cell_outputs.push(Output::Text(text.text()));
+                        continue;
+                    }
+                    NotebookOutput::Error {
+                        ename,
+                        evalue,
+                        traceback,
+                    } => {
+                        let text = match traceback.is_empty() {
+                            true => format!("{ename}: {evalue}"),
+                            false => traceback.join("\n"),
+                        };
+                        cell_outputs.push(Output::Text(ansi.replace_all(&text, "").into()));
+                        continue;
+                    }
+                    NotebookOutput::ExecuteResult { data }
+                    | NotebookOutput::DisplayData { data } => data,
+                };
+
+                // use the richest representation
+                let image = [
+                    ("image/png", "png"),
+                    ("image/jpeg", "jpg"),
+                    ("image/svg+xml", "svg"),
+                ]
+                .into_iter()
+                .find_map(|(mime, extension)| Some((data.get(mime)?, extension)));
+                if let Some((value, extension)) = image {
+                    let content = match extension {
+                        "svg" => data_text(value).into_bytes(),
+                        _ => match STANDARD.decode(data_text(value).replace('\n', "")) {
+                            Ok(content) => content,
+                            Err(e) => {
+                                warn!("Invalid image in cell {i} of {stem}.ipynb: {e}");
+                                continue;
+                            }
+                        },
+                    };
+                    let image = site_tree.add(SiteNode::resource(
+                        format!("{stem}-{i}-{j}.{extension}"),
+                        parent,
+                        Resource::from_readable(content.as_slice())?,
+                    ));
+                    cell_outputs.push(Output::Image(image));
+                } else if let Some(html) = data.get("text/html") {
+                    cell_outputs.push(Output::Html(data_text(html)));
+                } else if let Some(text) = data.get("text/plain") {
+                    cell_outputs.push(Output::Text(data_text(text)));
+                }
+            }
+            cells.push(Cell::Code {
+                code,
+                outputs: cell_outputs,
+            });
+        }
+        Ok(cells)
+    }
+}
+
+impl RendererModule for NotebookModule {
+    fn id(&self) -> &'static str {
+        "notebook"
+    }
+
+    fn init(&mut self, site_tree: &mut SiteTree) -> Result<(), LssgError> {
+        let pages: Vec<usize> = DFS::new(site_tree)
+            .filter(|id| site_tree[*id].kind.is_page())
+            .collect();
+
+        let mut stylesheet = None;
+        for page_id in pages {
+            let SiteNodeKind::Page(page) = &site_tree[page_id].kind else {
+                continue;
+            };
+            let options: NotebookOptions = self.options(page);
+            let mut hrefs = vec![];
+            visit_tokens(page.tokens(), |token| {
+                if let Some(href) = notebook_href(token) {
+                    hrefs.push(href.clone());
+                }
+            });
+
+            for href in hrefs {
+                let Some(id) =
+                    site_tree
+                        .links_from(page_id)
+                        .into_iter()
+                        .find_map(|l| match &l.relation {
+                            Relation::Discovered { raw_path } if *raw_path == href => Some(l.to),
+                            _ => None,
+                        })
+                else {
+                    continue;
+                };
+                let cells = match self.convert(site_tree, id, &options) {
+                    Ok(cells) => cells,
+                    Err(e) => {
+                        error!("Failed to convert notebook {href}: {e}");
+                        continue;
+                    }
+                };
+                self.notebooks.insert((page_id, href), cells);
+
+                let stylesheet = match stylesheet {
+                    Some(stylesheet) => stylesheet,
+                    None => *stylesheet.insert(site_tree.add(SiteNode::stylesheet(
+                        "notebook.css",
+                        site_tree.root(),
+                        Stylesheet::from_readable(NOTEBOOK_STYLESHEET)?,
+                    ))),
+                };
+                site_tree.add_link(page_id, stylesheet);
+            }
+        }
+        Ok(())
+    }
+
+    fn render_body<'n>(
+        &mut self,
+        document: &mut Document,
+        context: &RenderContext<'n>,
+        parent: DomNode,
+        token: &Token,
+        tr: &mut TokenRenderer,
+    ) -> Option<DomNode> {
+        let href = notebook_href(token)?;
+        let cells = self.notebooks.get(&(context.site_id, href.clone()))?;
+
+        let notebook = dom!(<div class="notebook"></div>);
+        for cell in cells {
+            match cell {
+                Cell::Markdown(tokens) => {
+                    tr.render(document, context, notebook.clone(), tokens);
+                }
+                Cell::Code { code, outputs } => {
+                    let cell = dom!(<div class="notebook__cell"></div>);
+                    tr.render(document, context, cell.clone(), &vec![code.clone()]);
+                    for output in outputs {
+                        let output_html = dom!(<div class="notebook__output"></div>);
+                        match output {
+                            Output::Text(text) => {
+                                output_html.append_child(dom!(<code>{text}</code>));
+                            }
+                            Output::Html(html) => match parse_html_with_warnings(
+                                html.as_bytes(),
+                                ParseOptions::default(),
+                            ) {
+                                Ok((html, warnings)) => {
+                                    for warning in warnings {
+                                        warn!("Html output in {href}:{warning}");
+                                    }
+                                    for node in html.into_iter().filter_map(DomNode::from_html) {
+                                        output_html.append_child(node);
+                                    }
+                                }
+                                Err(e) => warn!("Invalid html output in {href}: {e}"),
+                            },
+                            Output::Image(id) => {
+                                let src = context.site_tree.href(context.site_id, *id);
+                                output_html.append_child(dom!(<img src="{src}" alt="" />));
+                            }
+                        }
+                        cell.append_child(output_html);
+                    }
+                    notebook.append_child(cell);
+                }
+            }
+        }
+        parent.append_child(notebook);
+
+        Some(parent)
+    }
+}
@@ -0,0 +1,153 @@
+use std::{path::Path, process::Command};
+
+use log::{info, warn};
+use proc_virtual_dom::dom;
+use serde_extensions::Overwrite;
+use virtual_dom::Document;
+
+use crate::{
+    lssg_error::LssgError,
+    renderer::RenderContext,
+    sitetree::{Page, SiteNodeKind, SiteTree},
+    tree::DFS,
+};
+
+use super::RendererModule;
+
+mod server;
+use server::Server;
+
+/// Browsers that are tried in order when no `browser` is given
+const BROWSERS: [&str; 5] = [
+    "chromium",
+    "chromium-browser",
+    "google-chrome",
+    "google-chrome-stable",
+    "microsoft-edge",
+];
+
+#[derive(Overwrite, Debug)]
+pub struct PdfOptions {
+    /// Path or name of a chromium based browser used to print the page
+    browser: Option<String>,
+    /// Add a download link to the pdf at the end of the page
+    link: bool,
+}
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            browser: None,
+            link: true,
+        }
+    }
+}
+
+/// Emits a `{name}.pdf` next to pages with `pdf = true` or a `[pdf]` table by printing the
+/// rendered html with a headless chromium based browser
+pub struct PdfModule {}
+
+impl PdfModule {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Options of `page` if a pdf should be generated for it
+    fn pdf_options(&self, page: &Page) -> Option<PdfOptions> {
+        match page.attributes()?.get(self.id())? {
+            toml::Value::Boolean(true) => Some(PdfOptions::default()),
+            toml::Value::Table(_) => Some(self.options(page)),
+            _ => None,
+        }
+    }
+}
+
+fn pdf_name(site_tree: &SiteTree, id: usize) -> String {
+    format!("{}.pdf", site_tree[id].name)
+}
+
+/// Print the page at `url` to `pdf` using the first browser that is available
+fn print(browser: Option<&str>, url: &str, pdf: &Path) -> Result<(), LssgError> {
+    let browsers = match browser {
+        Some(browser) => vec![browser],
+        None => BROWSERS.to_vec(),
+    };
+    for browser in browsers {
+        let output = Command::new(browser)
+            .arg("--headless")
+            .arg("--disable-gpu")
+            .arg("--no-pdf-header-footer")
+            .arg(format!("--print-to-pdf={}", pdf.to_string_lossy()))
+            .arg(url)
+            .output();
+        match output {
+            Ok(output) if output.status.success() => return Ok(()),
+            Ok(output) => {
+                return Err(LssgError::render(format!(
+                    "{browser} failed to print {url}: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                )))
+            }
+            // browser not installed, try next
+            Err(_) => continue,
+        }
+    }
+    Err(LssgError::render(
+        "no chromium based browser found, set `browser` in [pdf]",
+    ))
+}
+
+impl RendererModule for PdfModule {
+    fn id(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn after_render<'n>(&mut self, document: &mut Document, context: &RenderContext<'n>) {
+        let Some(options) = self.pdf_options(context.page) else {
+            return;
+        };
+        if !options.link || context.site_tree.is_error_page(context.site_id) {
+            return;
+        }
+        let path = context.site_tree.path(context.site_id);
+        let href = format!(
+            "{}/{}",
+            path.trim_end_matches('/'),
+            pdf_name(context.site_tree, context.site_id)
+        );
+        let link = dom!(<p class="pdf"><a href="{href}" download>Download PDF</a></p>);
+        document.body.append_child(link);
+    }
+
+    fn finish(&mut self, site_tree: &SiteTree, output_directory: &Path) -> Result<(), LssgError> {
+        // started on the first pdf
+        let mut server = None;
+        for id in DFS::new(site_tree) {
+            let SiteNodeKind::Page(page) = &site_tree[id].kind else {
+                continue;
+            };
+            let Some(options) = self.pdf_options(page) else {
+                continue;
+            };
+            if site_tree.is_error_page(id) {
+                warn!("Not generating pdf for error page");
+                continue;
+            }
+            let directory = site_tree
+                .rel_path(site_tree.root(), id)
+                .split('/')
+                .fold(output_directory.to_path_buf(), |path, part| path.join(part));
+            let pdf = directory.join(pdf_name(site_tree, id));
+            let server = match &mut server {
+                Some(server) => server,
+                None => server.insert(Server::start(output_directory, site_tree.url_prefix())?),
+            };
+            info!("Writing {pdf:?}");
+            print(
+                options.browser.as_deref(),
+                &server.url(&site_tree.path(id)),
+                &pdf,
+            )?;
+        }
+        Ok(())
+    }
+}
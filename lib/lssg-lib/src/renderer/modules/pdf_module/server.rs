@@ -0,0 +1,171 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, ErrorKind, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use log::debug;
+use percent_encoding::percent_decode_str;
+
+use crate::{lssg_error::LssgError, renderer::modules::util::media_type};
+
+/// Local http server serving the output directory while printing, so root relative urls (eg.
+/// `/images/logo.png`) resolve like they do on the hosted site
+pub struct Server {
+    address: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Server {
+    /// Serve `directory` on a free port of localhost, `{url_prefix}/path` is read from
+    /// `directory/path`
+    pub fn start(directory: &Path, url_prefix: &str) -> Result<Server, LssgError> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        // non blocking so the server can be stopped
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let directory = directory.to_path_buf();
+        let url_prefix = url_prefix.to_string();
+        let stopped = stop.clone();
+        let handle = thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let directory = directory.clone();
+                        let url_prefix = url_prefix.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = respond(stream, &directory, &url_prefix) {
+                                debug!("Failed to respond to request: {e}");
+                            }
+                        });
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(10))
+                    }
+                    Err(e) => debug!("Failed to accept connection: {e}"),
+                }
+            }
+        });
+
+        Ok(Server {
+            address,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /// Url of `path` (eg. "/blog/") on this server
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.address)
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// File in `directory` requested by `target` (eg. "/blog/?q=1"), `None` if it is outside of it
+fn file(directory: &Path, url_prefix: &str, target: &str) -> Option<PathBuf> {
+    let path = target.split(['?', '#']).next().unwrap_or(target);
+    let path = path.strip_prefix(url_prefix).unwrap_or(path);
+    let path = percent_decode_str(path).decode_utf8().ok()?;
+    let mut file = directory.to_path_buf();
+    for part in path.split('/').filter(|p| !p.is_empty() && *p != ".") {
+        if part == ".." {
+            return None;
+        }
+        file.push(part);
+    }
+    if file.is_dir() {
+        file.push("index.html");
+    }
+    Some(file)
+}
+
+fn respond(stream: TcpStream, directory: &Path, url_prefix: &str) -> Result<(), LssgError> {
+    stream.set_nonblocking(false)?;
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // skip the headers
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let target = request.split(' ').nth(1).unwrap_or("/");
+    let mut stream = &stream;
+    match file(directory, url_prefix, target).and_then(|file| Some((fs::read(&file).ok()?, file))) {
+        Some((content, file)) => {
+            let name = file.to_string_lossy();
+            let content_type = match media_type(&name) {
+                Some(media_type) => media_type,
+                None if name.ends_with(".html") => "text/html; charset=utf-8",
+                None => "application/octet-stream",
+            };
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                content.len()
+            )?;
+            stream.write_all(&content)?;
+        }
+        None => {
+            stream.write_all(
+                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            )?;
+        }
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn test_server() {
+        let directory =
+            std::env::temp_dir().join(format!("lssg-pdf-server-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("images")).unwrap();
+        fs::write(directory.join("index.html"), "<h1>Home</h1>").unwrap();
+        fs::write(directory.join("images/logo.png"), "png").unwrap();
+
+        let server = Server::start(&directory, "/repo").unwrap();
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(server.address).unwrap();
+            write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        assert!(get("/repo/").ends_with("<h1>Home</h1>"));
+        let image = get("/repo/images/logo.png");
+        assert!(image.contains("Content-Type: image/png"));
+        assert!(image.ends_with("png"));
+        assert!(get("/repo/../secret").starts_with("HTTP/1.1 404"));
+        assert!(get("/repo/missing.png").starts_with("HTTP/1.1 404"));
+        drop(server);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,302 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    io::Read,
+};
+
+use log::{error, warn};
+use regex::Regex;
+use serde_extensions::Overwrite;
+
+use crate::{
+    lmarkdown::Token,
+    lssg_error::LssgError,
+    sitetree::{Input, SiteId, SiteNodeKind, SiteTree},
+    tree::DFS,
+};
+
+use super::RendererModule;
+
+mod hunspell;
+use hunspell::Dictionary;
+
+#[derive(Overwrite, Debug, Clone)]
+pub struct ProseOptions {
+    /// Hunspell dictionary (eg. "./en_US.dic") to check spelling with, affixes are read from the
+    /// `.aff` file next to it
+    dictionary: Option<String>,
+    /// Words that are spelled correctly but are not in the dictionary
+    words: Vec<String>,
+    /// Report sentences with more words than this, 0 to disable
+    max_sentence_words: usize,
+    /// Report the same word twice in a row ("the the")
+    repeated_words: bool,
+}
+impl Default for ProseOptions {
+    fn default() -> Self {
+        Self {
+            dictionary: None,
+            words: vec![],
+            max_sentence_words: 40,
+            repeated_words: true,
+        }
+    }
+}
+
+/// A problem found in the text of a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    /// Path of the page
+    pub page: String,
+    /// The heading the text is under
+    pub heading: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.heading {
+            Some(heading) => write!(f, "{} > {heading}: {}", self.page, self.message),
+            None => write!(f, "{}: {}", self.page, self.message),
+        }
+    }
+}
+
+/// Prose of inline tokens, leaving out code, html and images
+fn inline_text(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    for token in tokens {
+        match token {
+            Token::Text { text: t }
+            | Token::Bold { text: t }
+            | Token::Emphasis { text: t }
+            | Token::Strikethrough { text: t } => text.push_str(t),
+            Token::Link { tokens, .. } => text.push_str(&inline_text(tokens)),
+            Token::SoftBreak | Token::HardBreak => text.push(' '),
+            // keep words around code apart
+            Token::Code { .. } | Token::Image { .. } | Token::Html { .. } => text.push_str(" , "),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Every block of text in `tokens` with the heading it is under
+fn text_blocks(
+    tokens: &[Token],
+    heading: &mut Option<String>,
+    blocks: &mut Vec<(Option<String>, String)>,
+) {
+    for token in tokens {
+        match token {
+            Token::Heading { tokens, .. } => {
+                let text = inline_text(tokens);
+                *heading = Some(text.trim().to_string());
+                blocks.push((heading.clone(), text));
+            }
+            Token::Paragraph { tokens, .. } => blocks.push((heading.clone(), inline_text(tokens))),
+            Token::BulletList { items, .. } | Token::OrderedList { items } => {
+                for item in items {
+                    text_blocks(item, heading, blocks);
+                }
+            }
+            Token::BlockQuote { tokens } | Token::FootnoteDefinition { tokens, .. } => {
+                text_blocks(tokens, heading, blocks)
+            }
+            Token::Table { header, rows, .. } => {
+                for cell in header.iter().chain(rows.iter().flatten()) {
+                    blocks.push((heading.clone(), inline_text(cell)));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Words of `text` with urls, numbers and code like words removed
+fn words(text: &str) -> Vec<&str> {
+    let word = Regex::new(r"[\p{L}\p{N}_][\p{L}\p{N}_'’]*").unwrap();
+    let url = Regex::new(r"\S+://\S+|\S+@\S+\.\S+").unwrap();
+    let urls: Vec<(usize, usize)> = url.find_iter(text).map(|m| (m.start(), m.end())).collect();
+    word.find_iter(text)
+        .filter(|m| !urls.iter().any(|(s, e)| m.start() >= *s && m.end() <= *e))
+        .map(|m| m.as_str().trim_end_matches(['\'', '’']))
+        .collect()
+}
+
+/// Check the spelling of `text`, ignoring acronyms and words with numbers or mixed case
+fn check_spelling(text: &str, dictionary: &Dictionary, allowed: &HashSet<String>) -> Vec<String> {
+    let mut misspelled = vec![];
+    for word in words(text) {
+        let is_code_like = word.contains(|c: char| c.is_numeric() || c == '_')
+            || word.chars().skip(1).any(|c| c.is_uppercase());
+        if is_code_like
+            || dictionary.contains(word)
+            || allowed.contains(&word.to_lowercase())
+            // possessive
+            || word
+                .strip_suffix("'s")
+                .or(word.strip_suffix("’s"))
+                .is_some_and(|w| dictionary.contains(w))
+        {
+            continue;
+        }
+        if !misspelled.contains(&word) {
+            misspelled.push(word);
+        }
+    }
+    misspelled
+        .into_iter()
+        .map(|word| format!("unknown word {word:?}"))
+        .collect()
+}
+
+fn check_style(text: &str, options: &ProseOptions) -> Vec<String> {
+    let mut findings = vec![];
+    if options.repeated_words {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        for pair in words.windows(2) {
+            let word = pair[0].trim_matches(|c: char| !c.is_alphanumeric());
+            // punctuation between words ends the repetition ("it is. Is it")
+            if !word.is_empty()
+                && pair[0].ends_with(|c: char| c.is_alphanumeric())
+                && word
+                    .eq_ignore_ascii_case(pair[1].trim_end_matches(|c: char| !c.is_alphanumeric()))
+            {
+                findings.push(format!("repeated word \"{word} {word}\""));
+            }
+        }
+    }
+    if options.max_sentence_words > 0 {
+        let sentence_end = Regex::new(r"[.!?]+(\s|$)").unwrap();
+        for sentence in sentence_end.split(text) {
+            let words: Vec<&str> = sentence
+                .split_whitespace()
+                .filter(|w| w.contains(char::is_alphanumeric))
+                .collect();
+            if words.len() > options.max_sentence_words {
+                findings.push(format!(
+                    "sentence of {} words (\"{} ...\")",
+                    words.len(),
+                    words[..6].join(" ")
+                ));
+            }
+        }
+    }
+    findings
+}
+
+/// Checks the spelling and style of the text of all pages, findings are logged as warnings and
+/// listed by `lssg check`
+#[derive(Default)]
+pub struct ProseModule {
+    findings: Vec<Finding>,
+}
+
+impl ProseModule {
+    pub fn new() -> Self {
+        Self { findings: vec![] }
+    }
+
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+}
+
+/// Read the hunspell dictionary at `path` relative to `input`
+fn read_dictionary(input: &Input, path: &str) -> Result<Dictionary, LssgError> {
+    let read = |input: Input| -> Result<String, LssgError> {
+        let mut content = String::new();
+        input.readable()?.read_to_string(&mut content)?;
+        Ok(content)
+    };
+    let dic = read(input.new(path)?)?;
+    let aff = match path.strip_suffix(".dic") {
+        Some(stem) => read(input.new(&format!("{stem}.aff"))?).ok(),
+        None => None,
+    };
+    Ok(Dictionary::parse(&dic, aff.as_deref()))
+}
+
+impl RendererModule for ProseModule {
+    fn id(&self) -> &'static str {
+        "prose"
+    }
+
+    fn init(&mut self, site_tree: &mut SiteTree) -> Result<(), LssgError> {
+        let root_options: ProseOptions = match &site_tree[site_tree.root()].kind {
+            SiteNodeKind::Page(page) => self.options(page),
+            _ => ProseOptions::default(),
+        };
+        let pages: Vec<SiteId> = DFS::new(site_tree)
+            .filter(|id| site_tree[*id].kind.is_page())
+            .collect();
+
+        let mut dictionaries: HashMap<String, Option<Dictionary>> = HashMap::new();
+        for id in pages {
+            let SiteNodeKind::Page(page) = &site_tree[id].kind else {
+                continue;
+            };
+            let options = self.options_with_default(page, root_options.clone());
+            let dictionary = match (&options.dictionary, site_tree.get_input(id)) {
+                (Some(path), Some(input)) => dictionaries
+                    .entry(path.clone())
+                    .or_insert_with(|| match read_dictionary(input, path) {
+                        Ok(dictionary) => Some(dictionary),
+                        Err(e) => {
+                            error!("Failed to read dictionary {path}: {e}");
+                            None
+                        }
+                    })
+                    .as_ref(),
+                _ => None,
+            };
+            let allowed: HashSet<String> = options.words.iter().map(|w| w.to_lowercase()).collect();
+
+            let mut blocks = vec![];
+            text_blocks(page.tokens(), &mut None, &mut blocks);
+            for (heading, text) in blocks {
+                let mut messages = check_style(&text, &options);
+                if let Some(dictionary) = dictionary {
+                    messages.extend(check_spelling(&text, dictionary, &allowed));
+                }
+                for message in messages {
+                    let finding = Finding {
+                        page: site_tree.path(id),
+                        heading: heading.clone(),
+                        message,
+                    };
+                    warn!("{finding}");
+                    self.findings.push(finding);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_style, words, ProseOptions};
+
+    #[test]
+    fn test_check_style() {
+        let options = ProseOptions {
+            max_sentence_words: 5,
+            ..ProseOptions::default()
+        };
+        assert_eq!(
+            check_style("It is the the best. Is it?", &options),
+            vec!["repeated word \"the the\""]
+        );
+        assert_eq!(check_style("It is. Is it?", &options), Vec::<String>::new());
+        assert_eq!(
+            check_style("one two three four five six seven.", &options),
+            vec!["sentence of 7 words (\"one two three four five six ...\")"]
+        );
+        assert_eq!(
+            words("See https://example.com, it's v2!"),
+            vec!["See", "it's", "v2"]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::{HashMap, HashSet};
+
+use regex::Regex;
+
+/// How flags of words and affixes are written in a hunspell dictionary
+#[derive(Clone, Copy)]
+enum FlagType {
+    /// Single character
+    Char,
+    /// Two characters
+    Long,
+    /// Comma separated numbers
+    Num,
+}
+
+impl FlagType {
+    fn split(self, flags: &str) -> Vec<String> {
+        match self {
+            FlagType::Char => flags.chars().map(|c| c.to_string()).collect(),
+            FlagType::Long => flags
+                .chars()
+                .collect::<Vec<char>>()
+                .chunks(2)
+                .map(|c| c.iter().collect())
+                .collect(),
+            FlagType::Num => flags.split(',').map(|f| f.trim().to_string()).collect(),
+        }
+    }
+}
+
+struct AffixRule {
+    strip: String,
+    add: String,
+    condition: Option<Regex>,
+}
+
+struct Affix {
+    is_prefix: bool,
+    cross_product: bool,
+    rules: Vec<AffixRule>,
+}
+
+impl Affix {
+    /// Every word formed by applying a rule of this affix to `word`
+    fn apply(&self, word: &str) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.condition.as_ref().is_none_or(|c| c.is_match(word)))
+            .filter_map(|rule| match self.is_prefix {
+                true => word
+                    .strip_prefix(rule.strip.as_str())
+                    .map(|w| format!("{}{w}", rule.add)),
+                false => word
+                    .strip_suffix(rule.strip.as_str())
+                    .map(|w| format!("{w}{}", rule.add)),
+            })
+            .collect()
+    }
+}
+
+/// Word list read from a hunspell dictionary (`.dic`) with its prefixes and suffixes (`.aff`)
+/// expanded, compounding and other advanced affix options are not supported
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    pub fn parse(dic: &str, aff: Option<&str>) -> Dictionary {
+        let mut flag_type = FlagType::Char;
+        let mut affixes: HashMap<String, Affix> = HashMap::new();
+        for line in aff.unwrap_or("").lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts.as_slice() {
+                ["FLAG", kind, ..] => {
+                    flag_type = match *kind {
+                        "long" => FlagType::Long,
+                        "num" => FlagType::Num,
+                        _ => FlagType::Char,
+                    }
+                }
+                [kind @ ("PFX" | "SFX"), flag, cross_product, _count] => {
+                    affixes.insert(
+                        flag.to_string(),
+                        Affix {
+                            is_prefix: *kind == "PFX",
+                            cross_product: *cross_product == "Y",
+                            rules: vec![],
+                        },
+                    );
+                }
+                ["PFX" | "SFX", flag, strip, add, rest @ ..] => {
+                    let Some(affix) = affixes.get_mut(*flag) else {
+                        continue;
+                    };
+                    let empty = |s: &str| match s {
+                        "0" => String::new(),
+                        s => s.to_string(),
+                    };
+                    // continuation flags after the affix are not supported
+                    let add = add.split('/').next().unwrap_or("");
+                    let condition = match rest.first() {
+                        None | Some(&".") => None,
+                        Some(condition) => {
+                            let pattern = match affix.is_prefix {
+                                true => format!("^{condition}"),
+                                false => format!("{condition}$"),
+                            };
+                            match Regex::new(&pattern) {
+                                Ok(regex) => Some(regex),
+                                Err(_) => continue,
+                            }
+                        }
+                    };
+                    affix.rules.push(AffixRule {
+                        strip: empty(strip),
+                        add: empty(add),
+                        condition,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        let mut words = HashSet::new();
+        for (i, line) in dic.lines().enumerate() {
+            // first line is the amount of words
+            if i == 0 && line.trim().parse::<usize>().is_ok() {
+                continue;
+            }
+            let Some(entry) = line.split_whitespace().next() else {
+                continue;
+            };
+            let (word, flags) = entry.split_once('/').unwrap_or((entry, ""));
+            let affixes: Vec<&Affix> = flag_type
+                .split(flags)
+                .iter()
+                .filter_map(|flag| affixes.get(flag))
+                .collect();
+
+            let suffixed: Vec<String> = affixes
+                .iter()
+                .filter(|a| !a.is_prefix)
+                .flat_map(|a| a.apply(word))
+                .collect();
+            for prefix in affixes.iter().filter(|a| a.is_prefix) {
+                words.extend(prefix.apply(word));
+                if prefix.cross_product {
+                    // only suffixes that allow combining
+                    for suffix in affixes.iter().filter(|a| !a.is_prefix && a.cross_product) {
+                        for suffixed in suffix.apply(word) {
+                            words.extend(prefix.apply(&suffixed));
+                        }
+                    }
+                }
+            }
+            words.extend(suffixed);
+            words.insert(word.to_string());
+        }
+        Dictionary { words }
+    }
+
+    /// If `word` is in the dictionary, words at the start of a sentence may be capitalized
+    pub fn contains(&self, word: &str) -> bool {
+        if self.words.contains(word) {
+            return true;
+        }
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(first) if first.is_uppercase() => {
+                let lowercase: String = first.to_lowercase().chain(chars).collect();
+                self.words.contains(&lowercase)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dictionary;
+
+    #[test]
+    fn test_dictionary() {
+        let aff =
+            "SET UTF-8\nPFX U Y 1\nPFX U 0 un .\nSFX D Y 2\nSFX D 0 d e\nSFX D y ied [^aeiou]y\n";
+        let dic = "3\nbake/DU\ncarry/D\nhello\n";
+        let dictionary = Dictionary::parse(dic, Some(aff));
+        for word in [
+            "bake", "baked", "unbake", "unbaked", "carried", "hello", "Hello",
+        ] {
+            assert!(dictionary.contains(word), "{word}");
+        }
+        for word in ["carryed", "uncarry", "helo", "HeLLo"] {
+            assert!(!dictionary.contains(word), "{word}");
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::{fs, path::Path};
+
+use log::{info, warn};
+
+use crate::{lssg_error::LssgError, sitetree::SiteTree, tree::DFS};
+
+use super::{
+    util::{base_url, escape_xml},
+    RendererModule, SinglePageOptions,
+};
+
+/// Generates a `sitemap.xml` of all listed pages <https://www.sitemaps.org/protocol.html>
+///
+/// Pages with `sitemap = false`, `unlisted = true` or `robots = "noindex"` are excluded
+#[derive(Default)]
+pub struct SitemapModule {}
+
+impl SitemapModule {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl RendererModule for SitemapModule {
+    fn id(&self) -> &'static str {
+        "sitemap"
+    }
+
+    fn finish(&mut self, site_tree: &SiteTree, output_directory: &Path) -> Result<(), LssgError> {
+        // sitemaps only allow absolute urls
+        let base_url = match base_url(site_tree) {
+            Some(base_url) => base_url,
+            None => {
+                warn!("no base_url given for sitemap, not generating sitemap.xml");
+                return Ok(());
+            }
+        };
+
+        let mut urls = vec![];
+        for id in DFS::new(site_tree) {
+            if site_tree[id].kind.is_page() {
+                if !SinglePageOptions::resolve(site_tree, id).is_listed()
+                    || site_tree.is_error_page(id)
+                {
+                    continue;
+                }
+                let url = escape_xml(&format!("{base_url}{}", site_tree.path(id)));
+                urls.push(format!("  <url><loc>{url}</loc></url>"));
+            }
+        }
+
+        let sitemap = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n{}\n</urlset>\n",
+            urls.join("\n")
+        );
+        let path = output_directory.join("sitemap.xml");
+        info!("Writing {path:?}");
+        fs::write(path, sitemap)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{debug, warn};
+use proc_virtual_dom::dom;
+use serde_extensions::Overwrite;
+use virtual_dom::{Document, DomNode};
+
+use crate::{
+    lssg_error::LssgError,
+    renderer::RenderContext,
+    sitetree::{Input, Page, SiteId, SiteNode, SiteNodeKind, SiteTree, Stylesheet},
+    tree::DFS,
+};
+
+use super::{util::page_title, RendererModule, SinglePageOptions};
+
+const UPDATES_STYLESHEET: &[u8] = include_bytes!("./updates_stylesheet.css");
+
+#[derive(Overwrite, Debug)]
+pub struct UpdatesOptions {
+    /// Name of the page listing the most recently updated pages, added below the root page (eg.
+    /// "whats-new"). Set on the root page
+    page: Option<String>,
+    /// Heading of the generated page
+    title: String,
+    /// Number of pages listed on the generated page
+    count: usize,
+    /// Number of recently updated pages listed at the end of this page, 0 for none
+    widget: usize,
+}
+impl Default for UpdatesOptions {
+    fn default() -> Self {
+        Self {
+            page: None,
+            title: "What's new".into(),
+            count: 10,
+            widget: 0,
+        }
+    }
+}
+
+/// A page and the date it last changed
+#[derive(Debug, Clone)]
+pub struct Update {
+    pub site_id: SiteId,
+    pub title: String,
+    pub date: NaiveDate,
+    /// Title before the last change, if the change renamed the page
+    pub previous_title: Option<String>,
+}
+
+/// Where the date of a page comes from
+enum Source {
+    /// last commit that changed the file, with its path relative to the top of the repository
+    Git {
+        top: PathBuf,
+        path: String,
+    },
+    /// the file has changes that are not committed (yet), using its modification time
+    Uncommitted {
+        top: PathBuf,
+        path: String,
+    },
+    Modified,
+}
+
+/// Run git in `directory`, None if it failed or isn't installed
+fn git(directory: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Commit dates of all files in the repository `directory` is in, and the files with uncommitted
+/// changes. Paths are relative to the top of the repository.
+fn git_history(directory: &Path) -> Option<(PathBuf, HashMap<String, NaiveDate>, Vec<String>)> {
+    let top = PathBuf::from(git(directory, &["rev-parse", "--show-toplevel"])?.trim());
+    let log = git(
+        directory,
+        &["log", "--format=%x00%cs", "--name-only", "--", "."],
+    )?;
+    let mut dates = HashMap::new();
+    let mut date = None;
+    for line in log.lines().filter(|l| !l.is_empty()) {
+        if let Some(d) = line.strip_prefix('\0') {
+            date = NaiveDate::parse_from_str(d, "%Y-%m-%d").ok();
+            continue;
+        }
+        // newest commit comes first
+        if let Some(date) = date {
+            dates.entry(line.to_string()).or_insert(date);
+        }
+    }
+    let changed = git(directory, &["diff", "--name-only", "HEAD", "--", "."])
+        .map(|diff| diff.lines().map(|l| l.to_string()).collect())
+        .unwrap_or_default();
+    Some((top, dates, changed))
+}
+
+/// Title of the page before its last change, None if it didn't change
+fn previous_title(source: &Source, name: &str, title: &str) -> Option<String> {
+    let (top, path, revision) = match source {
+        Source::Git { top, path } => {
+            let log = git(top, &["log", "-n", "2", "--format=%H", "--", path])?;
+            (top, path, log.lines().nth(1)?.to_string())
+        }
+        Source::Uncommitted { top, path } => (top, path, "HEAD".to_string()),
+        Source::Modified => return None,
+    };
+    let content = git(top, &["show", &format!("{revision}:{path}")])?;
+    let page = Page::from_readable(content.as_bytes()).ok()?;
+    let previous = page_title(&page, name);
+    (previous != title).then_some(previous)
+}
+
+/// Listed pages sorted by the date they last changed, most recent first. Dates come from the
+/// last commit that changed a page, or the modification time of pages that aren't committed.
+/// The previous title is looked up for the first `count` pages.
+pub fn recent_updates(site_tree: &SiteTree, count: usize) -> Vec<Update> {
+    let root_directory = match site_tree.get_input(site_tree.root()) {
+        Some(Input::Local { path }) => path.parent().map(|p| p.to_path_buf()),
+        _ => None,
+    };
+    let history = root_directory.as_deref().and_then(git_history);
+    if history.is_none() {
+        debug!("Not in a git repository, using modification times for updates");
+    }
+
+    let mut updates: Vec<(Update, Source)> = vec![];
+    for id in DFS::new(site_tree) {
+        let SiteNodeKind::Page(page) = &site_tree[id].kind else {
+            continue;
+        };
+        if !SinglePageOptions::resolve(site_tree, id).is_listed() || site_tree.is_error_page(id) {
+            continue;
+        }
+        let Some(Input::Local { path }) = site_tree.get_input(id) else {
+            continue;
+        };
+        let Ok(canonical) = fs::canonicalize(path) else {
+            continue;
+        };
+
+        let git_path = history.as_ref().and_then(|(top, dates, changed)| {
+            let rel_path = canonical.strip_prefix(top).ok()?;
+            let rel_path: Vec<String> = rel_path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect();
+            let rel_path = rel_path.join("/");
+            let date = dates.get(&rel_path).cloned();
+            Some((
+                top.clone(),
+                rel_path.clone(),
+                date,
+                changed.contains(&rel_path),
+            ))
+        });
+        let modified = || -> Option<NaiveDate> {
+            let date: DateTime<Utc> = fs::metadata(&canonical).ok()?.modified().ok()?.into();
+            Some(date.date_naive())
+        };
+        let (date, source) = match git_path {
+            Some((top, path, Some(date), false)) => (date, Source::Git { top, path }),
+            Some((top, path, Some(_), true)) => match modified() {
+                Some(date) => (date, Source::Uncommitted { top, path }),
+                None => continue,
+            },
+            _ => match modified() {
+                Some(date) => (date, Source::Modified),
+                None => continue,
+            },
+        };
+        let update = Update {
+            site_id: id,
+            title: page_title(page, &site_tree[id].name),
+            date,
+            previous_title: None,
+        };
+        updates.push((update, source));
+    }
+    updates.sort_by(|(a, _), (b, _)| b.date.cmp(&a.date).then(a.title.cmp(&b.title)));
+
+    updates
+        .into_iter()
+        .enumerate()
+        .map(|(i, (mut update, source))| {
+            if i < count {
+                let name = &site_tree[update.site_id].name;
+                update.previous_title = previous_title(&source, name, &update.title);
+            }
+            update
+        })
+        .collect()
+}
+
+/// List of `updates` with links relative to page `from`
+pub fn updates_list(site_tree: &SiteTree, from: SiteId, updates: &[Update]) -> DomNode {
+    let list = dom!(<ul class="updates-list"></ul>);
+    for update in updates {
+        let href = site_tree.href(from, update.site_id);
+        let datetime = update.date.format("%Y-%m-%d").to_string();
+        let date = update.date.format("%B %d, %Y").to_string();
+        let title = &update.title;
+        let item = dom!(<li></li>);
+        item.append_child(dom!(<time datetime="{datetime}">{date}</time>));
+        item.append_child(DomNode::create_text(" "));
+        if let Some(previous) = &update.previous_title {
+            item.append_child(dom!(<del class="updates-previous-title">{previous}</del>));
+            item.append_child(DomNode::create_text(" "));
+        }
+        item.append_child(dom!(<a href="{href}">{title}</a>));
+        list.append_child(item);
+    }
+    list
+}
+
+/// Adds a page listing the most recently updated pages when the root page has `[updates] page`
+/// set and a short list of them to pages with `[updates] widget`
+#[derive(Default)]
+pub struct UpdatesModule {
+    updates: Vec<Update>,
+    /// the generated page with the number of pages it lists
+    page: Option<(SiteId, usize)>,
+    /// pages with a widget with the number of pages it lists
+    widgets: HashMap<SiteId, usize>,
+}
+
+impl UpdatesModule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All listed pages, most recently updated first
+    pub fn updates(&self) -> &[Update] {
+        &self.updates
+    }
+}
+
+impl RendererModule for UpdatesModule {
+    fn id(&self) -> &'static str {
+        "updates"
+    }
+
+    fn init(&mut self, site_tree: &mut SiteTree) -> Result<(), LssgError> {
+        let root = site_tree.root();
+        let mut root_options = UpdatesOptions::default();
+        for id in DFS::new(site_tree) {
+            let SiteNodeKind::Page(page) = &site_tree[id].kind else {
+                continue;
+            };
+            let options: UpdatesOptions = self.options(page);
+            if options.widget > 0 {
+                self.widgets.insert(id, options.widget);
+            }
+            if id == root {
+                root_options = options;
+            }
+        }
+        if root_options.page.is_none() && self.widgets.is_empty() {
+            return Ok(());
+        }
+
+        let count = self
+            .widgets
+            .values()
+            .chain([&root_options.count])
+            .max()
+            .cloned()
+            .unwrap_or(0);
+        self.updates = recent_updates(site_tree, count);
+
+        if let Some(name) = root_options.page {
+            match site_tree.get_by_name(&name, root) {
+                Some(_) => warn!("{name:?} already exists, not adding an updates page"),
+                None => {
+                    let markdown = format!("# {}\n", root_options.title);
+                    let page = Page::from_readable(markdown.as_bytes())?;
+                    let id = site_tree.add(SiteNode::page(name, root, page));
+                    self.page = Some((id, root_options.count));
+                }
+            }
+        }
+
+        let stylesheet = site_tree.add(SiteNode::stylesheet(
+            "updates.css",
+            root,
+            Stylesheet::from_readable(UPDATES_STYLESHEET)?,
+        ));
+        for id in self
+            .widgets
+            .keys()
+            .chain(self.page.iter().map(|(id, _)| id))
+        {
+            site_tree.add_link(*id, stylesheet);
+        }
+        Ok(())
+    }
+
+    fn after_render<'n>(&mut self, document: &mut Document, context: &RenderContext<'n>) {
+        let site_tree = context.site_tree;
+        let updates = || self.updates.iter().filter(|u| u.site_id != context.site_id);
+        if let Some((id, count)) = self.page {
+            if id == context.site_id {
+                let updates: Vec<Update> = updates().take(count).cloned().collect();
+                document
+                    .body
+                    .append_child(updates_list(site_tree, id, &updates));
+                return;
+            }
+        }
+        if let Some(count) = self.widgets.get(&context.site_id) {
+            let updates: Vec<Update> = updates().take(*count).cloned().collect();
+            if updates.is_empty() {
+                return;
+            }
+            let widget = dom!(<aside class="updates-widget"><h2>Recent updates</h2></aside>);
+            widget.append_child(updates_list(site_tree, context.site_id, &updates));
+            document.body.append_child(widget);
+        }
+    }
+}
@@ -1,11 +1,124 @@
+use chrono::{DateTime, Utc};
 use log::warn;
 
 use crate::{
     lmarkdown::Token,
     renderer::RenderContext,
-    sitetree::{Page, Relation},
+    sitetree::{Input, Page, Relation, SiteId, SiteNodeKind, SiteTree},
 };
 
+/// Absolute url of the site without trailing slash and url prefix (eg. "https://example.com"),
+/// set with `base_url` on the root of the attributes of the root page
+pub fn base_url(site_tree: &SiteTree) -> Option<String> {
+    match &site_tree[site_tree.root()].kind {
+        SiteNodeKind::Page(page) => page.attributes()?.get("base_url")?.as_str().map(|url| {
+            // paths already contain the prefix
+            let url = url.trim_end_matches('/');
+            url.strip_suffix(site_tree.url_prefix())
+                .unwrap_or(url)
+                .to_string()
+        }),
+        _ => None,
+    }
+}
+
+/// Modification time of the local file node `id` is made from, None for remote or generated nodes
+pub fn source_modified(site_tree: &SiteTree, id: SiteId) -> Option<DateTime<Utc>> {
+    match site_tree.get_input(id)? {
+        Input::Local { path } => Some(path.metadata().ok()?.modified().ok()?.into()),
+        Input::External { .. } => None,
+    }
+}
+
+/// Format bytes as human readable size (eg. "1.2 MB")
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Media type of a file based on the extension of `name` (eg. "image/png"), None if unknown
+pub fn media_type(name: &str) -> Option<&'static str> {
+    let (_, extension) = name.rsplit_once('.')?;
+    let media_type = match extension.to_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        _ => return None,
+    };
+    Some(media_type)
+}
+
+/// Title of a page from the `title` option or its first heading
+pub fn page_title(page: &Page, name: &str) -> String {
+    if let Some(title) = page
+        .attributes()
+        .and_then(|a| a.get("title"))
+        .and_then(|t| t.as_str())
+    {
+        return title.to_string();
+    }
+    page.tokens()
+        .iter()
+        .find_map(|t| match t {
+            Token::Heading { tokens, .. } => Some(tokens_to_text(tokens)),
+            _ => None,
+        })
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Text of the first paragraph of a page cut at a word boundary to at most `max` characters, used
+/// as description of pages that don't have one
+pub fn page_description(page: &Page, max: usize) -> Option<String> {
+    let text = page.tokens().iter().find_map(|t| match t {
+        Token::Paragraph { tokens, .. } => {
+            let text = tokens_to_text(tokens);
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            (!text.is_empty()).then_some(text)
+        }
+        _ => None,
+    })?;
+    if text.chars().count() <= max {
+        return Some(text);
+    }
+    let end = text
+        .char_indices()
+        .nth(max)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+    let cut = match text[..=end].rfind(' ') {
+        Some(space) if space > 0 => &text[..space],
+        _ => &text[..end],
+    };
+    Some(format!("{}…", cut.trim_end_matches([',', '.', ';', ':'])))
+}
+
 pub fn tokens_to_text(tokens: &Vec<Token>) -> String {
     let mut result = String::new();
     for t in tokens {
@@ -39,6 +152,6 @@ pub fn process_href(href: &String, context: &RenderContext) -> String {
             href.to_owned()
         }
     } else {
-        href.to_owned()
+        context.site_tree.prefixed(href)
     }
 }
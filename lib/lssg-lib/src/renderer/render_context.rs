@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+
 use crate::sitetree::{Input, Page, SiteTree};
 
 #[derive(Clone)]
@@ -7,4 +9,32 @@ pub struct RenderContext<'n> {
     pub page: &'n Page,
     /// Where the page was read from. is None when page was generated.
     pub input: Option<&'n Input>,
+    /// Resources needed by the content of the page, in the order they were required
+    required_assets: RefCell<Vec<usize>>,
+}
+
+impl<'n> RenderContext<'n> {
+    pub fn new(site_tree: &'n SiteTree, site_id: usize, page: &'n Page) -> RenderContext<'n> {
+        RenderContext {
+            site_tree,
+            site_id,
+            page,
+            input: site_tree.get_input(site_id),
+            required_assets: RefCell::new(vec![]),
+        }
+    }
+
+    /// Mark the resource `id` (eg. a stylesheet for code blocks) as needed by this page, they
+    /// are linked when rendering the head so pages only load what they use
+    pub fn require_asset(&self, id: usize) {
+        let mut assets = self.required_assets.borrow_mut();
+        if !assets.contains(&id) {
+            assets.push(id);
+        }
+    }
+
+    /// Resources required while rendering this page
+    pub fn required_assets(&self) -> Vec<usize> {
+        self.required_assets.borrow().clone()
+    }
 }
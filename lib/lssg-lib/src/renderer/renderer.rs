@@ -1,29 +1,53 @@
-use log::{debug, error};
+use std::path::Path;
+
+use log::{debug, error, warn};
 
 use crate::{
     sitetree::{SiteNodeKind, SiteTree},
     LssgError,
 };
-use virtual_dom::Document;
+use virtual_dom::{Document, DomBackend, OutputMode};
 
 use super::modules::RendererModule;
 use super::{RenderContext, TokenRenderer};
 
 /// HtmlRenderer is responsible for the process of converting the site tree into the final HTML output.
 /// It does this by managing a queue of tokens to be rendered and delegating the rendering process to different modules.
-pub struct Renderer {
-    modules: Vec<Box<dyn RendererModule>>,
+///
+/// Pages are built on the `D` dom backend, all modules are written for the reference counted
+/// [`Document`] but custom modules can use another backend like [`virtual_dom::ArenaDocument`].
+pub struct Renderer<D: DomBackend = Document> {
+    modules: Vec<Box<dyn RendererModule<D>>>,
+    output_mode: OutputMode,
 }
 
-impl Renderer {
-    pub fn new() -> Renderer {
-        Renderer { modules: vec![] }
+impl<D: DomBackend> Default for Renderer<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: DomBackend> Renderer<D> {
+    pub fn new() -> Renderer<D> {
+        Renderer {
+            modules: vec![],
+            output_mode: OutputMode::Html,
+        }
+    }
+
+    /// Write pages as html (default) or xhtml
+    pub fn set_output_mode(&mut self, output_mode: OutputMode) {
+        self.output_mode = output_mode;
     }
 
-    pub fn add_module(&mut self, module: impl RendererModule + 'static) {
+    pub fn add_module(&mut self, module: impl RendererModule<D> + 'static) {
         self.modules.push(Box::new(module));
     }
 
+    pub fn add_boxed_module(&mut self, module: Box<dyn RendererModule<D>>) {
+        self.modules.push(module);
+    }
+
     /// Will run init on all modules, will remove modules if it fails
     pub fn init(&mut self, site_tree: &mut SiteTree) {
         debug!("running init");
@@ -64,6 +88,22 @@ impl Renderer {
         }
     }
 
+    /// Will run finish on all modules, stops at the first module that fails
+    pub fn finish(
+        &mut self,
+        site_tree: &SiteTree,
+        output_directory: &Path,
+    ) -> Result<(), LssgError> {
+        debug!("running finish");
+        for module in &mut self.modules {
+            if let Err(e) = module.finish(site_tree, output_directory) {
+                error!("Failed to do finish on {}: {e}", module.id());
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
     /// Transform site id into a html page
     pub fn render(&mut self, site_tree: &SiteTree, site_id: usize) -> Result<String, LssgError> {
         // get the site node
@@ -73,14 +113,9 @@ impl Renderer {
             _ => return Err(LssgError::render("Invalid node type given")),
         };
 
-        let mut dom = Document::new();
+        let mut dom = D::new();
 
-        let context = RenderContext {
-            input: site_tree.get_input(site_id),
-            site_tree,
-            site_id,
-            page,
-        };
+        let context = RenderContext::new(site_tree, site_id, page);
 
         // initialize modules
         for module in &mut self.modules {
@@ -99,13 +134,22 @@ impl Renderer {
             module.after_render(&mut dom, &context);
         }
 
+        for module in &mut self.modules {
+            debug!("running finish_render on {}", module.id());
+            module.finish_render(&mut dom, &context);
+        }
+
         // sanitize html
         dom.sanitize();
 
+        for error in dom.validate() {
+            warn!("Invalid html in {}: {error}", site_tree.path(site_id));
+        }
+
         // println!("{dom}");
         // println!("{dom:?}");
         // println!("{:?}", tree.get_mut(9));
         // println!("{page:#?}");
-        Ok(dom.to_string())
+        Ok(dom.serialize(self.output_mode))
     }
 }
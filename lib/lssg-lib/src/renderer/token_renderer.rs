@@ -2,29 +2,29 @@ use log::warn;
 
 use super::{RenderContext, RendererModule};
 use crate::lmarkdown::Token;
-use virtual_dom::{Document, DomNode};
+use virtual_dom::{Document, DomBackend};
 
 /// used for recursively rendering
-pub struct TokenRenderer {
-    modules: *mut Vec<Box<dyn RendererModule>>,
+pub struct TokenRenderer<D: DomBackend = Document> {
+    modules: *mut Vec<Box<dyn RendererModule<D>>>,
 }
 
-impl<'a> TokenRenderer {
-    pub fn new(modules: &'a mut Vec<Box<dyn RendererModule>>) -> TokenRenderer {
+impl<'a, D: DomBackend> TokenRenderer<D> {
+    pub fn new(modules: &'a mut Vec<Box<dyn RendererModule<D>>>) -> TokenRenderer<D> {
         // turn into pointer to allow for recursive call backs in render()
-        let modules: *mut Vec<Box<dyn RendererModule>> = modules;
+        let modules: *mut Vec<Box<dyn RendererModule<D>>> = modules;
         TokenRenderer { modules }
     }
 
     /// Render using other modules
     pub fn render_down(
         &mut self,
-        current_module: &dyn RendererModule,
-        dom: &mut Document,
+        current_module: &dyn RendererModule<D>,
+        dom: &mut D,
         context: &RenderContext<'a>,
-        mut parent: DomNode,
+        mut parent: D::Node,
         tokens: &Vec<Token>,
-    ) -> DomNode {
+    ) -> D::Node {
         'l: for token in tokens.iter() {
             let modules = unsafe { self.modules.as_mut().unwrap() };
             for module in modules.iter_mut() {
@@ -43,11 +43,11 @@ impl<'a> TokenRenderer {
 
     pub fn render(
         &mut self,
-        document: &mut Document,
+        document: &mut D,
         context: &RenderContext<'a>,
-        mut parent: DomNode,
+        mut parent: D::Node,
         tokens: &Vec<Token>,
-    ) -> DomNode {
+    ) -> D::Node {
         'l: for token in tokens.iter() {
             let modules = unsafe { self.modules.as_mut().unwrap() };
             for module in modules.iter_mut() {
@@ -63,8 +63,9 @@ impl<'a> TokenRenderer {
     }
 
     /// consume self and return a parsed domtree
-    pub fn start_render(mut self, document: &mut Document, context: &RenderContext) {
+    pub fn start_render(mut self, document: &mut D, context: &RenderContext) {
         let tokens = context.page.tokens();
-        self.render(document, context, document.body.clone(), tokens);
+        let body = document.body();
+        self.render(document, context, body, tokens);
     }
 }
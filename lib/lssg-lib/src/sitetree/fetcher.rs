@@ -0,0 +1,255 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Cursor, Read},
+    sync::{Condvar, Mutex, RwLock},
+    thread,
+    time::Duration,
+};
+
+use log::{debug, info, warn};
+use reqwest::{blocking::Client, StatusCode, Url};
+use serde::Deserialize;
+use serde_extensions::Overwrite;
+
+use crate::{
+    cache,
+    lssg_error::LssgError,
+    progress::{Phase, Progress},
+};
+
+use super::Input;
+
+/// Cache namespace of the last successful fetches of remote inputs
+const CACHE_NAMESPACE: &str = "remote";
+
+/// What to do when a remote input can't be fetched after all retries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OfflineMode {
+    /// Fail the build
+    #[default]
+    Fail,
+    /// Use the content of the last successful fetch, fail if there is none
+    Cache,
+    /// Log a warning and use empty content
+    Skip,
+}
+
+/// Options for fetching remote inputs, set with a `[remote]` table on the root page
+#[derive(Overwrite, Debug, Clone)]
+pub struct RemoteOptions {
+    /// Timeout in seconds of a single request, including reading the body
+    pub timeout: u64,
+    /// Amount of times a failed request is retried
+    pub retries: u32,
+    /// Delay in milliseconds before the first retry, doubled for every next retry
+    pub backoff: u64,
+    pub offline: OfflineMode,
+}
+const DEFAULT_REMOTE_OPTIONS: RemoteOptions = RemoteOptions {
+    timeout: 30,
+    retries: 2,
+    backoff: 500,
+    offline: OfflineMode::Fail,
+};
+impl Default for RemoteOptions {
+    fn default() -> Self {
+        DEFAULT_REMOTE_OPTIONS
+    }
+}
+
+struct Remote {
+    options: RemoteOptions,
+    client: Option<Client>,
+}
+
+static REMOTE: RwLock<Remote> = RwLock::new(Remote {
+    options: DEFAULT_REMOTE_OPTIONS,
+    client: None,
+});
+
+/// Set the options used for all following remote requests
+pub fn set_remote_options(options: RemoteOptions) {
+    let mut remote = REMOTE.write().unwrap();
+    remote.options = options;
+    remote.client = None;
+}
+
+fn client() -> Result<(Client, RemoteOptions), LssgError> {
+    let remote = REMOTE.read().unwrap();
+    if let Some(client) = &remote.client {
+        return Ok((client.clone(), remote.options.clone()));
+    }
+    drop(remote);
+    let mut remote = REMOTE.write().unwrap();
+    let client = Client::builder()
+        .timeout(Duration::from_secs(remote.options.timeout))
+        .build()?;
+    remote.client = Some(client.clone());
+    Ok((client, remote.options.clone()))
+}
+
+/// Server errors and rate limiting might resolve themselves, other status codes won't
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+fn request(client: &Client, url: &Url) -> Result<reqwest::blocking::Response, (bool, LssgError)> {
+    match client.get(url.clone()).send() {
+        Ok(response) if response.status().is_success() => Ok(response),
+        Ok(response) => Err((
+            is_retryable(response.status()),
+            LssgError::from(response.error_for_status().unwrap_err()),
+        )),
+        Err(e) => Err((true, LssgError::from(e))),
+    }
+}
+
+/// Get `url` using the timeout, retry and offline policy from [`RemoteOptions`]
+pub fn get(url: &Url) -> Result<Box<dyn Read>, LssgError> {
+    let (client, options) = client()?;
+    let mut attempt = 0;
+    let error = loop {
+        match request(&client, url) {
+            // keep a copy of the content to fall back to
+            Ok(response) if options.offline == OfflineMode::Cache => {
+                match cache::put_readable(CACHE_NAMESPACE, url.as_str(), response) {
+                    Ok(content) => return Ok(Box::new(Cursor::new(content))),
+                    Err(e) if attempt < options.retries => {
+                        warn!("Failed to read {url}: {e}");
+                    }
+                    Err(e) => break e,
+                }
+            }
+            // stream the body instead of buffering it, resources can be large
+            Ok(response) => return Ok(Box::new(response)),
+            Err((true, e)) if attempt < options.retries => {
+                warn!("Failed to fetch {url}: {e}");
+            }
+            Err((_, e)) => break e,
+        }
+        let delay = options.backoff * 2u64.pow(attempt);
+        attempt += 1;
+        debug!(
+            "Retrying {url} in {delay}ms ({attempt}/{})",
+            options.retries
+        );
+        thread::sleep(Duration::from_millis(delay));
+    };
+
+    match options.offline {
+        OfflineMode::Fail => Err(error.with_context(url.to_string())),
+        OfflineMode::Cache => match cache::get(CACHE_NAMESPACE, url.as_str())? {
+            Some(file) => {
+                warn!("Failed to fetch {url}, using cached content: {error}");
+                Ok(Box::new(file))
+            }
+            None => Err(error.with_context(format!("{url} is not cached"))),
+        },
+        OfflineMode::Skip => {
+            warn!("Failed to fetch {url}, skipping: {error}");
+            Ok(Box::new(io::empty()))
+        }
+    }
+}
+
+/// Maximum amount of requests running at the same time
+const MAX_CONCURRENT: usize = 8;
+/// Maximum amount of requests running at the same time to a single host
+const MAX_CONCURRENT_PER_HOST: usize = 2;
+
+fn host(input: &Input) -> String {
+    match input {
+        Input::External { url } => url.host_str().unwrap_or_default().to_string(),
+        Input::Local { .. } => String::new(),
+    }
+}
+
+struct Queue<T> {
+    pending: VecDeque<T>,
+    /// amount of running jobs per host
+    running: HashMap<String, usize>,
+}
+
+/// Run `job` for every item with a bounded amount of threads while never running more than
+/// [`MAX_CONCURRENT_PER_HOST`] jobs for the same host at once
+pub fn for_each_concurrent<T, F>(items: Vec<T>, input: fn(&T) -> &Input, job: F)
+where
+    T: Send,
+    F: Fn(T) + Sync,
+{
+    if items.is_empty() {
+        return;
+    }
+    let workers = MAX_CONCURRENT.min(items.len());
+    let queue = Mutex::new(Queue {
+        pending: items.into(),
+        running: HashMap::new(),
+    });
+    let available = Condvar::new();
+
+    thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| loop {
+                let mut state = queue.lock().unwrap();
+                let (item, host) = loop {
+                    if state.pending.is_empty() {
+                        return;
+                    }
+                    // take the first item of which the host isn't busy
+                    let position = state.pending.iter().position(|item| {
+                        state.running.get(&host(input(item))).copied().unwrap_or(0)
+                            < MAX_CONCURRENT_PER_HOST
+                    });
+                    match position {
+                        Some(i) => {
+                            let item = state.pending.remove(i).unwrap();
+                            let host = host(input(&item));
+                            *state.running.entry(host.clone()).or_default() += 1;
+                            break (item, host);
+                        }
+                        None => state = available.wait(state).unwrap(),
+                    }
+                };
+                drop(state);
+
+                job(item);
+
+                let mut state = queue.lock().unwrap();
+                if let Some(running) = state.running.get_mut(&host) {
+                    *running -= 1;
+                }
+                available.notify_all();
+            });
+        }
+    });
+}
+
+/// Read all remote `inputs` concurrently into memory
+pub fn fetch_all(inputs: Vec<Input>) -> HashMap<Input, Result<Vec<u8>, LssgError>> {
+    let inputs: Vec<Input> = inputs
+        .into_iter()
+        .filter(|i| matches!(i, Input::External { .. }))
+        .collect();
+    if inputs.is_empty() {
+        return HashMap::new();
+    }
+    info!("Fetching {} remote inputs", inputs.len());
+    let results = Mutex::new(HashMap::new());
+    let progress = Progress::start(Phase::Fetching, inputs.len());
+    for_each_concurrent(
+        inputs,
+        |input| input,
+        |input| {
+            debug!("Fetching {}", input.to_string());
+            let content = input.readable().and_then(|mut r| {
+                let mut content = vec![];
+                r.read_to_end(&mut content)?;
+                Ok(content)
+            });
+            results.lock().unwrap().insert(input, content);
+            progress.advance();
+        },
+    );
+    results.into_inner().unwrap()
+}
@@ -0,0 +1,51 @@
+use std::io::Cursor;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use exif::{In, Tag, Value};
+
+/// Metadata a camera or photo editor stored in an image as EXIF
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImageMetadata {
+    /// When the photo was taken
+    pub taken_on: Option<NaiveDateTime>,
+    pub description: Option<String>,
+}
+
+impl ImageMetadata {
+    /// Read the EXIF of a jpeg, png, webp, tiff or heif image, None if it has none
+    pub fn from_bytes(content: &[u8]) -> Option<ImageMetadata> {
+        let exif = exif::Reader::new()
+            .read_from_container(&mut Cursor::new(content))
+            .ok()?;
+        let ascii = |tag: Tag| match exif.get_field(tag, In::PRIMARY).map(|f| &f.value) {
+            Some(Value::Ascii(values)) => values.first().cloned(),
+            _ => None,
+        };
+
+        let taken_on = [Tag::DateTimeOriginal, Tag::DateTime]
+            .into_iter()
+            .filter_map(ascii)
+            .find_map(|value| {
+                let datetime = exif::DateTime::from_ascii(&value).ok()?;
+                NaiveDate::from_ymd_opt(
+                    datetime.year.into(),
+                    datetime.month.into(),
+                    datetime.day.into(),
+                )?
+                .and_hms_opt(
+                    datetime.hour.into(),
+                    datetime.minute.into(),
+                    datetime.second.into(),
+                )
+            });
+        // cameras often fill the description with spaces
+        let description = ascii(Tag::ImageDescription)
+            .map(|d| String::from_utf8_lossy(&d).trim().to_string())
+            .filter(|d| !d.is_empty());
+
+        Some(ImageMetadata {
+            taken_on,
+            description,
+        })
+    }
+}
@@ -1,13 +1,21 @@
+mod fetcher;
+mod image_metadata;
 mod page;
 mod relational_graph;
 mod resource;
 mod site_node;
 mod site_tree;
 mod stylesheet;
+mod url_style;
+mod wiki_links;
 
+pub use fetcher::{for_each_concurrent, set_remote_options, OfflineMode, RemoteOptions};
+pub use image_metadata::ImageMetadata;
 pub use page::Page;
 pub use relational_graph::{Link, Relation};
+pub(crate) use resource::content_hash;
 pub use resource::Resource;
 pub use site_node::*;
 pub use site_tree::*;
-pub use stylesheet::Stylesheet;
+pub use stylesheet::{Stylesheet, StylesheetLink};
+pub use url_style::UrlStyle;
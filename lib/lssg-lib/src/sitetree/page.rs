@@ -1,66 +1,89 @@
+use std::io::Read;
+
 use crate::{
-    lmarkdown::{parse_lmarkdown, Token},
+    lmarkdown::{parse_lmarkdown, visit_tokens, walk_tokens, Token},
     lssg_error::LssgError,
 };
 
-use super::Input;
+use super::{resource::content_hash, Input};
 
 /// A SiteTree node representing a page made by a markdown file
 #[derive(Debug)]
 pub struct Page {
     tokens: Vec<Token>,
+    /// content hash of the markdown source
+    hash: String,
 }
 impl Page {
     pub fn empty() -> Page {
-        Page { tokens: vec![] }
+        Page {
+            tokens: vec![],
+            hash: content_hash(&[][..]).expect("hashing from memory can't fail"),
+        }
     }
 
     pub fn from_input(input: &Input) -> Result<Page, LssgError> {
-        let tokens = parse_lmarkdown(input.readable()?)?;
-        Ok(Page { tokens })
+        Self::from_readable(input.readable()?)
+    }
+
+    pub fn from_readable(mut readable: impl Read) -> Result<Page, LssgError> {
+        let mut source = vec![];
+        readable.read_to_end(&mut source)?;
+        let hash = content_hash(source.as_slice())?;
+        let tokens = parse_lmarkdown(source.as_slice())?;
+        Ok(Page { tokens, hash })
+    }
+
+    /// Content addressed hash (hex encoded sha256) of the markdown source, computed once when
+    /// parsing and independent of changes made to the tokens by modules
+    pub fn hash(&self) -> &str {
+        &self.hash
     }
 
     /// Discover any links inside of the page will return vec with (text, href)
     pub fn links(&self) -> Vec<(&Vec<Token>, &String, &Option<String>)> {
         let mut hrefs = vec![];
-        let mut queue: Vec<Vec<&Token>> = vec![self.tokens.iter().collect()];
-        while let Some(tokens) = queue.pop() {
-            for t in tokens {
-                if let Token::Link {
-                    tokens: text,
-                    href,
-                    title,
-                } = t
-                {
-                    hrefs.push((text, href, title));
-                    continue;
-                }
-                if let Some(tokens) = t.get_tokens() {
-                    queue.push(tokens);
-                }
+        visit_tokens(&self.tokens, |t| {
+            if let Token::Link {
+                tokens: text,
+                href,
+                title,
+            } = t
+            {
+                hrefs.push((text, href, title));
             }
-        }
+        });
         return hrefs;
     }
 
     /// Discover any images inside of the page
     pub fn images(&self) -> Vec<(&Vec<Token>, &String, &Option<String>)> {
         let mut srcs = vec![];
-        let mut queue: Vec<Vec<&Token>> = vec![self.tokens.iter().collect()];
-        while let Some(tokens) = queue.pop() {
-            for t in tokens {
-                if let Token::Image { tokens, src, title } = t {
-                    srcs.push((tokens, src, title));
-                    continue;
-                }
-                if let Some(tokens) = t.get_tokens() {
-                    queue.push(tokens);
-                }
+        visit_tokens(&self.tokens, |t| {
+            if let Token::Image { tokens, src, title } = t {
+                srcs.push((tokens, src, title));
             }
-        }
+        });
         return srcs;
     }
 
+    /// Call `f` with every link and image url in the page, including the `href` and `src` of html
+    /// elements, allowing them to be rewritten before rendering
+    pub fn rewrite_links(&mut self, mut f: impl FnMut(&mut String)) {
+        walk_tokens(&mut self.tokens, |t| match t {
+            Token::Link { href, .. } => f(href),
+            Token::Image { src, .. } => f(src),
+            Token::Html { attributes, .. } => {
+                for key in ["href", "src"] {
+                    if let Some(url) = attributes.get_mut(key) {
+                        f(url)
+                    }
+                }
+            }
+            _ => {}
+        });
+    }
+
     pub fn attributes(&self) -> Option<&toml::Table> {
         if let Some(Token::Attributes { table }) = self.tokens().first() {
             Some(table)
@@ -92,3 +115,15 @@ impl Page {
         &mut self.tokens
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash() {
+        let page = Page::from_readable("# Title".as_bytes()).unwrap();
+        assert_eq!(page.hash(), content_hash("# Title".as_bytes()).unwrap());
+        assert_ne!(page.hash(), Page::empty().hash());
+    }
+}
@@ -8,6 +8,8 @@ pub enum Relation {
     External,
     /// Found relation by parsing a file
     Discovered { raw_path: String },
+    /// Link to a local file that is offered as download (eg. "./report.pdf")
+    Download { raw_path: String },
 }
 
 #[derive(Debug, Clone)]
@@ -50,6 +52,13 @@ impl RelationalGraph {
             .collect()
     }
 
+    pub fn links_to(&self, node_id: usize) -> Vec<&Link> {
+        self.links[node_id]
+            .iter()
+            .filter(|l| l.to == node_id)
+            .collect()
+    }
+
     pub fn get(&self, node_id: usize) -> &Vec<Link> {
         self.links
             .get(node_id)
@@ -64,17 +73,18 @@ impl RelationalGraph {
 
     pub fn remove(&mut self, from: usize, to: usize) {
         let links = self.get_mut(from);
-        links.retain(|l| l.from == from && l.to == to);
+        links.retain(|l| !(l.from == from && l.to == to));
         let links = self.get_mut(to);
-        links.retain(|l| l.from == from && l.to == to);
+        links.retain(|l| !(l.from == from && l.to == to));
     }
 
     /// remove all links to and from `node_id`
     pub fn remove_all(&mut self, node_id: usize) {
-        // remove links pointing to node_id
+        // remove the links from the other nodes
         for Link { from, to, .. } in self[node_id].clone() {
-            if from != node_id {
-                self[from].retain(|l| l.from == from && l.to == to);
+            let other = if from == node_id { to } else { from };
+            if other != node_id {
+                self[other].retain(|l| !(l.from == from && l.to == to));
             }
         }
         self[node_id] = vec![];
@@ -1,18 +1,33 @@
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{self, Cursor, Read},
     path::Path,
+    sync::OnceLock,
 };
 
-use log::info;
+use log::debug;
+use sha2::{Digest, Sha256};
 
 use crate::lssg_error::LssgError;
 
-use super::Input;
+use super::{ImageMetadata, Input};
 
 pub enum Resource {
-    Static { content: Vec<u8> },
-    Fetched { input: Input },
+    Static {
+        content: Vec<u8>,
+    },
+    Fetched {
+        input: Input,
+        /// content hash, cached so remote inputs are only read once for hashing
+        hash: OnceLock<String>,
+    },
+}
+
+/// Hex encoded sha256 of everything in `readable`
+pub(crate) fn content_hash(mut readable: impl Read) -> Result<String, LssgError> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut readable, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
 }
 
 impl std::fmt::Debug for Resource {
@@ -23,7 +38,10 @@ impl std::fmt::Debug for Resource {
 
 impl Resource {
     pub fn new_fetched(input: Input) -> Result<Resource, LssgError> {
-        Ok(Resource::Fetched { input })
+        Ok(Resource::Fetched {
+            input,
+            hash: OnceLock::new(),
+        })
     }
 
     pub fn from_readable(mut content: impl Read) -> Result<Resource, LssgError> {
@@ -41,14 +59,101 @@ impl Resource {
     pub fn readable(&self) -> Result<Box<dyn Read>, LssgError> {
         match self {
             Resource::Static { content } => Ok(Box::new(Cursor::new(content.clone()))),
-            Resource::Fetched { input } => input.readable(),
+            Resource::Fetched { input, .. } => input.readable(),
+        }
+    }
+
+    /// Content addressed hash (hex encoded sha256), computed once for fetched resources
+    pub fn hash(&self) -> Result<String, LssgError> {
+        match self {
+            Resource::Static { content } => content_hash(content.as_slice()),
+            Resource::Fetched { input, hash } => {
+                if let Some(hash) = hash.get() {
+                    return Ok(hash.clone());
+                }
+                let computed = content_hash(input.readable()?)?;
+                Ok(hash.get_or_init(|| computed).clone())
+            }
         }
     }
 
-    pub fn write(&mut self, path: &Path) -> Result<(), LssgError> {
-        info!("Writing resource {path:?}",);
-        let mut file = File::create(path)?;
-        io::copy(&mut self.readable()?, &mut file)?;
+    /// Size in bytes, `None` if the resource is not a local file
+    pub fn size(&self) -> Option<u64> {
+        match self {
+            Resource::Static { content } => Some(content.len() as u64),
+            Resource::Fetched {
+                input: Input::Local { path },
+                ..
+            } => fs::metadata(path).ok().map(|m| m.len()),
+            Resource::Fetched { .. } => None,
+        }
+    }
+
+    /// EXIF metadata if this is an image that has it
+    pub fn image_metadata(&self) -> Option<ImageMetadata> {
+        let mut content = vec![];
+        self.readable().ok()?.read_to_end(&mut content).ok()?;
+        ImageMetadata::from_bytes(&content)
+    }
+
+    /// Remote resource, which is slow to write and can be written concurrently
+    pub fn is_remote(&self) -> bool {
+        matches!(
+            self,
+            Resource::Fetched {
+                input: Input::External { .. },
+                ..
+            }
+        )
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), LssgError> {
+        debug!("Writing resource {path:?}",);
+        // copy without loading the whole resource into memory
+        match self {
+            Resource::Static { content } => fs::write(path, content)?,
+            Resource::Fetched {
+                input: Input::Local { path: from },
+                ..
+            } => {
+                fs::copy(from, path)?;
+            }
+            Resource::Fetched { input, .. } => {
+                let mut file = File::create(path)?;
+                io::copy(&mut input.readable()?, &mut file)?;
+            }
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash() {
+        let a = Resource::new_static("a".into());
+        assert_eq!(a.hash().unwrap(), content_hash("a".as_bytes()).unwrap());
+        assert_eq!(
+            a.hash().unwrap(),
+            Resource::new_static("a".into()).hash().unwrap()
+        );
+        assert_ne!(
+            a.hash().unwrap(),
+            Resource::new_static("b".into()).hash().unwrap()
+        );
+
+        let path = std::env::temp_dir().join(format!("lssg-resource-{}", std::process::id()));
+        fs::write(&path, "a").unwrap();
+        let fetched = Resource::new_fetched(Input::Local { path: path.clone() }).unwrap();
+        assert_eq!(fetched.hash().unwrap(), a.hash().unwrap());
+        let changed = Resource::new_fetched(Input::Local { path: path.clone() }).unwrap();
+        fs::write(&path, "b").unwrap();
+        assert_ne!(changed.hash().unwrap(), a.hash().unwrap());
+        // fetched resources are only hashed once
+        assert_eq!(fetched.hash().unwrap(), a.hash().unwrap());
+
+        fs::remove_file(path).unwrap();
+    }
+}
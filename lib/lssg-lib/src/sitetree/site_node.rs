@@ -1,16 +1,24 @@
 use std::{
+    borrow::Cow,
     fs::{self, File},
-    io::{Cursor, Read},
+    io::Read,
     path::{Path, PathBuf},
 };
 
 use crate::{path_extension::PathExtension, tree::Node, LssgError};
 use pathdiff::diff_paths;
+use percent_encoding::percent_decode_str;
 use reqwest::Url;
 
+use super::fetcher;
 use super::stylesheet::Stylesheet;
 use super::{page::Page, Resource};
 
+/// Percent-decoded path of `url`, so names of remote files are the same as local ones
+fn url_path(url: &Url) -> Cow<'_, str> {
+    percent_decode_str(url.path()).decode_utf8_lossy()
+}
+
 /// Wrapper around absolute path to either an internal or external (http://) file
 #[derive(Debug, Clone, Hash, Eq, PartialEq)] // TODO check if Hash is valid
 pub enum Input {
@@ -26,12 +34,28 @@ impl Input {
             return Ok(Input::External { url });
         }
 
-        let mut path = PathBuf::from(&string);
-        path = fs::canonicalize(path)?;
+        let path = std::path::absolute(PathBuf::from(&string))?;
+        Input::local(path)
+    }
 
+    /// Create a local Input, `..` is resolved lexically so symlinked folders keep their place
+    /// in the site
+    fn local(path: PathBuf) -> Result<Input, LssgError> {
+        let path = path.canonicalize_nonexistent_path();
+        if !path.exists() {
+            return Err(LssgError::io(format!("{path:?} does not exist")));
+        }
         Ok(Input::Local { path })
     }
 
+    /// Path with all symlinks resolved, used to detect the same file reached through symlinks
+    pub fn canonical(&self) -> Option<PathBuf> {
+        match self {
+            Input::Local { path } => fs::canonicalize(path).ok(),
+            Input::External { .. } => None,
+        }
+    }
+
     pub fn make_relative(&self, to: &Input) -> Option<String> {
         match self {
             Input::Local { path: from_path } => match to {
@@ -41,9 +65,11 @@ impl Input {
                     } else {
                         from_path
                     };
-                    return diff_paths(to_path, from_path)
-                        .map(|p| p.to_str().map(|s| s.to_string()))
-                        .flatten();
+                    // always use `/` as separator, independent of platform
+                    let path = diff_paths(to_path, from_path)?;
+                    let parts: Option<Vec<&str>> =
+                        path.components().map(|c| c.as_os_str().to_str()).collect();
+                    return parts.map(|p| p.join("/"));
                 }
                 _ => return None,
             },
@@ -78,9 +104,10 @@ impl Input {
                 } else {
                     &path
                 };
-                let mut path = path.join(path_string);
-                path = fs::canonicalize(path)?;
-                return Ok(Input::Local { path });
+                // links to local files are often percent-encoded (eg. "./My%20Page.md")
+                let decoded = percent_decode_str(path_string).decode_utf8_lossy();
+                return Input::local(path.join(decoded.as_ref()))
+                    .or_else(|_| Input::local(path.join(path_string)));
             }
             Input::External { url } => {
                 // relative url path
@@ -92,13 +119,13 @@ impl Input {
     pub fn filestem(&self) -> Result<String, LssgError> {
         match self {
             Input::Local { path } => path.filestem_from_path(),
-            Input::External { url } => Path::new(url.path()).filestem_from_path(),
+            Input::External { url } => Path::new(url_path(url).as_ref()).filestem_from_path(),
         }
     }
     pub fn filename(&self) -> Result<String, LssgError> {
         match self {
             Input::Local { path } => path.filename_from_path(),
-            Input::External { url } => Path::new(url.path()).filename_from_path(),
+            Input::External { url } => Path::new(url_path(url).as_ref()).filename_from_path(),
         }
     }
     pub fn to_string(&self) -> String {
@@ -113,11 +140,7 @@ impl Input {
                 let file = File::open(path)?;
                 Ok(Box::new(file))
             }
-            Input::External { url } => {
-                let response = reqwest::blocking::get(url.clone())?;
-                let content = Cursor::new(response.bytes().unwrap());
-                Ok(Box::new(content))
-            }
+            Input::External { url } => fetcher::get(url),
         }
     }
 }
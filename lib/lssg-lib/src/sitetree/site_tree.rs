@@ -1,18 +1,34 @@
 use core::fmt;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    io::Read,
     ops::{Index, IndexMut},
+    path::PathBuf,
 };
 
-use log::{debug, warn};
-
-use crate::{tree::Tree, LssgError};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::{debug, error, warn};
+use serde_extensions::Overwrite;
+
+use crate::{
+    budget::parse_size,
+    lmarkdown::wiki_link_target,
+    path_extension::PathExtension,
+    renderer::util::{format_size, media_type},
+    tree::Tree,
+    LssgError,
+};
 
 use super::{
+    fetcher::{fetch_all, set_remote_options, RemoteOptions},
     page::Page,
     relational_graph::RelationalGraph,
     relational_graph::{Link, Relation},
+    resource::content_hash,
     stylesheet::{Stylesheet, StylesheetLink},
+    url_style::UrlStyle,
+    wiki_links::matches_name,
     Input, Resource, SiteNode, SiteNodeKind,
 };
 
@@ -78,6 +94,20 @@ fn rel_path(nodes: &Vec<SiteNode>, from: SiteId, to: SiteId) -> String {
 
 pub type SiteId = usize;
 
+/// How symlinked files are written to the output, set with `symlinks = "preserve"` on the root
+/// page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymlinkMode {
+    /// Write the content of the symlink target
+    #[default]
+    Copy,
+    /// Keep symlinks to files that are also part of the site as relative symlink
+    Preserve,
+}
+
+/// Folder remote images are mirrored to
+pub(crate) const MIRROR_FOLDER: &str = "remote";
+
 /// Code representation of all nodes within the site (hierarchy and how nodes are related)
 #[derive(Debug)]
 pub struct SiteTree {
@@ -88,7 +118,32 @@ pub struct SiteTree {
 
     /// cannonical paths to node ids
     input_to_id: HashMap<Input, SiteId>,
+    /// paths with symlinks resolved to page ids, to prevent symlink cycles
+    canonical_pages: HashMap<PathBuf, SiteId>,
+    symlinks: SymlinkMode,
+    /// download remote images into the site instead of hotlinking them, disabled with
+    /// `mirror_images = false` on the root page
+    mirror_images: bool,
+    /// filenames of pages that are the page of the folder they are in (eg. "projects/index.md"
+    /// is `/projects`), set with `index_files = ["index.md"]` on the root page
+    index_files: Vec<String>,
+    /// local resources of stylesheets up to this size in bytes are inlined as data uris, set with
+    /// `stylesheet_inline_limit = "4 KB"` on the root page
+    stylesheet_inline_limit: u64,
+    url_style: UrlStyle,
+    /// prefix of all absolute paths when hosted in a subdirectory (eg. "/repo"), set with
+    /// `url_prefix = "/repo/"` on the root page
+    url_prefix: String,
     rel_graph: RelationalGraph,
+    /// page that is written to `/404.html`
+    not_found_page: Option<SiteId>,
+    /// pages written to a fixed file at the root of the output (eg. "500.html") for hosts to
+    /// serve, including the 404 page
+    error_pages: BTreeMap<SiteId, String>,
+    /// content of remote pages and stylesheets that got fetched concurrently ahead of being added
+    prefetched: HashMap<Input, Result<Vec<u8>, LssgError>>,
+    /// files read while rendering that are not written themselves (eg. data files of a table)
+    sources: Vec<Input>,
 }
 
 impl SiteTree {
@@ -103,12 +158,105 @@ impl SiteTree {
             root: 0,
             root_input: input.clone(),
             input_to_id: HashMap::new(),
+            canonical_pages: HashMap::new(),
+            symlinks: SymlinkMode::default(),
+            mirror_images: true,
+            index_files: vec!["index.md".into(), "_index.md".into()],
+            stylesheet_inline_limit: 0,
+            url_style: UrlStyle::default(),
+            url_prefix: String::new(),
             rel_graph: RelationalGraph::new(),
+            not_found_page: None,
+            error_pages: BTreeMap::new(),
+            prefetched: HashMap::new(),
+            sources: vec![],
         };
         tree.add_page_under_parent(input, None)?;
+        tree.discover_not_found_page()?;
+        tree.discover_error_pages()?;
+        tree.resolve_wiki_links();
         Ok(tree)
     }
 
+    /// Find the 404 page, set by `not_found = "./404.md"` on the root page or `kind = "404"` on
+    /// any page
+    fn discover_not_found_page(&mut self) -> Result<(), LssgError> {
+        let not_found = match &self.nodes[self.root].kind {
+            SiteNodeKind::Page(page) => page
+                .attributes()
+                .and_then(|a| a.get("not_found"))
+                .and_then(|v| v.as_str())
+                .map(|href| href.to_string()),
+            _ => None,
+        };
+        if let Some(href) = not_found {
+            let input = self.root_input.new(&href)?;
+            let id = self.add_page_from_input(input, self.root)?;
+            self.not_found_page = Some(id);
+            return Ok(());
+        }
+
+        self.not_found_page = self.nodes.iter().position(|node| match &node.kind {
+            SiteNodeKind::Page(page) => {
+                page.attributes()
+                    .and_then(|a| a.get("kind"))
+                    .and_then(|v| v.as_str())
+                    == Some("404")
+            }
+            _ => false,
+        });
+        Ok(())
+    }
+
+    /// Add the pages of `[error_pages]` on the root page, the key is the name of the file (eg.
+    /// `500 = "./500.md"` is written to `/500.html`)
+    fn discover_error_pages(&mut self) -> Result<(), LssgError> {
+        if let Some(id) = self.not_found_page {
+            self.error_pages.insert(id, "404.html".to_string());
+        }
+        let pages: Vec<(String, String)> = match &self.nodes[self.root].kind {
+            SiteNodeKind::Page(page) => page
+                .attributes()
+                .and_then(|a| a.get("error_pages"))
+                .and_then(|v| v.as_table())
+                .map(|table| {
+                    table
+                        .iter()
+                        .filter_map(|(name, href)| Some((name.clone(), href.as_str()?.to_string())))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            _ => vec![],
+        };
+        for (name, href) in pages {
+            let input = self.root_input.new(&href)?;
+            let id = self.add_page_from_input(input, self.root)?;
+            if let Some(other) = self.error_pages.get(&id) {
+                warn!("{href:?} is already written to {other:?}, not writing it to {name}.html");
+                continue;
+            }
+            self.error_pages.insert(id, format!("{name}.html"));
+        }
+        Ok(())
+    }
+
+    /// The page used as 404 page, it keeps its name and gets written to the root of the output
+    pub fn not_found_page(&self) -> Option<SiteId> {
+        self.not_found_page
+    }
+
+    /// Name of the file at the root of the output that error page `id` is written to (eg.
+    /// "404.html"), None if `id` is not an error page
+    pub fn error_page_file(&self, id: SiteId) -> Option<&str> {
+        self.error_pages.get(&id).map(|name| name.as_str())
+    }
+
+    /// If `id` is the 404 page or one of the other error pages, these can be served from any url
+    /// so they use absolute paths and are not listed
+    pub fn is_error_page(&self, id: SiteId) -> bool {
+        self.error_pages.contains_key(&id)
+    }
+
     /// Check if node `id` has `parent_id` as (grand)parent node
     pub fn is_parent(&self, id: SiteId, parent_id: SiteId) -> bool {
         let mut parent = self.nodes[id].parent;
@@ -128,6 +276,29 @@ impl SiteTree {
             .find_map(|(input, i)| if *i == id { Some(input) } else { None })
     }
 
+    /// Track a file that is read while rendering but not part of the tree itself (eg. the data
+    /// file of a table), so it is included in [`SiteTree::source_paths`]
+    pub fn add_source(&mut self, input: Input) {
+        if !self.sources.contains(&input) {
+            self.sources.push(input);
+        }
+    }
+
+    /// Local files the site is generated from, sorted
+    pub fn source_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self
+            .input_to_id
+            .keys()
+            .chain(&self.sources)
+            .filter_map(|input| match input {
+                Input::Local { path } => Some(path.clone()),
+                Input::External { .. } => None,
+            })
+            .collect();
+        paths.sort();
+        paths
+    }
+
     // get a node by name by checking the children of `id`
     pub fn get_by_name(&self, name: &str, id: SiteId) -> Option<&SiteId> {
         self.nodes[id]
@@ -171,33 +342,298 @@ impl SiteTree {
         parents
     }
 
-    /// Get the absolute path of a node
+    /// Get the absolute path of a node, including the url prefix
     pub fn path(&self, id: SiteId) -> String {
-        absolute_path(&self.nodes, id)
+        if let Some(file) = self.error_page_file(id) {
+            return self.prefixed(&format!("/{file}"));
+        }
+        let path = self.with_trailing_slash(id, absolute_path(&self.nodes, id));
+        self.prefixed(&path)
+    }
+
+    /// Add the url prefix to root relative paths (eg. "/about" to "/repo/about")
+    pub fn prefixed(&self, path: &str) -> String {
+        if self.url_prefix.is_empty() || !path.starts_with('/') || path.starts_with("//") {
+            return path.to_string();
+        }
+        format!("{}{path}", self.url_prefix)
+    }
+
+    pub fn url_prefix(&self) -> &str {
+        &self.url_prefix
     }
 
-    /// Get the relative path between two nodes
+    /// Get the relative path between two nodes, error pages are always linked to by their
+    /// absolute path
     pub fn rel_path(&self, from: SiteId, to: SiteId) -> String {
-        rel_path(&self.nodes, from, to)
+        if self.is_error_page(to) {
+            return self.path(to);
+        }
+        self.with_trailing_slash(to, rel_path(&self.nodes, from, to))
+    }
+
+    /// Add `/` to paths of pages and folders if set by the url style
+    fn with_trailing_slash(&self, id: SiteId, mut path: String) -> String {
+        if self.url_style.trailing_slash
+            && matches!(
+                self.nodes[id].kind,
+                SiteNodeKind::Page(..) | SiteNodeKind::Folder
+            )
+            && !self.is_error_page(id)
+            && !path.ends_with('/')
+        {
+            path.push('/');
+        }
+        path
+    }
+
+    /// Find a page by its absolute path (eg. "/blog/first-post"), with or without url prefix and
+    /// trailing slash
+    pub fn page_by_path(&self, path: &str) -> Option<SiteId> {
+        let has_prefix = !self.url_prefix.is_empty()
+            && path
+                .strip_prefix(&self.url_prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+        let path = match has_prefix {
+            true => path.to_string(),
+            false => self.prefixed(path),
+        };
+        let path = path.trim_end_matches('/');
+        (0..self.nodes.len()).find(|id| {
+            self.nodes[*id].kind.is_page() && self.path(*id).trim_end_matches('/') == path
+        })
+    }
+
+    /// Path of the file `id` is written to, relative to the output directory (eg.
+    /// "blog/post/index.html")
+    pub fn output_path(&self, id: SiteId) -> String {
+        if let Some(file) = self.error_page_file(id) {
+            return file.to_string();
+        }
+        let path = self.rel_path(self.root, id);
+        let path = path.strip_prefix("./").unwrap_or(&path);
+        let path = if path == "." {
+            ""
+        } else {
+            path.trim_end_matches('/')
+        };
+        match &self.nodes[id].kind {
+            SiteNodeKind::Page(_) if path.is_empty() => "index.html".to_string(),
+            SiteNodeKind::Page(_) => format!("{path}/index.html"),
+            SiteNodeKind::Folder => format!("{path}/"),
+            _ => path.to_string(),
+        }
+    }
+
+    /// Size in bytes of the source of `id`, None for folders and remote sources
+    fn node_size(&self, id: SiteId) -> Option<u64> {
+        match &self.nodes[id].kind {
+            SiteNodeKind::Stylesheet(stylesheet) => Some(stylesheet.content().len() as u64),
+            SiteNodeKind::Resource(resource) => resource.size(),
+            SiteNodeKind::Page(_) => match self.get_input(id)? {
+                Input::Local { path } => fs::metadata(path).ok().map(|m| m.len()),
+                Input::External { .. } => None,
+            },
+            SiteNodeKind::Folder => None,
+        }
+    }
+
+    pub fn url_style(&self) -> &UrlStyle {
+        &self.url_style
+    }
+
+    /// Path to use when linking from `from` to `to`, relative except for the 404 page which can
+    /// be served from any url
+    pub fn href(&self, from: SiteId, to: SiteId) -> String {
+        if self.is_error_page(from) {
+            return self.path(to);
+        }
+        self.rel_path(from, to)
+    }
+
+    /// Absolute path of `href` relative to the folder of page `from` (eg. "../image.png" to
+    /// "/image.png"), fragments, absolute paths and urls are returned as is
+    pub fn absolute_href(&self, from: SiteId, href: &str) -> String {
+        if href.starts_with(['#', '/']) || href.contains(':') {
+            return href.to_string();
+        }
+        let folder = match self.nodes[from].parent {
+            Some(parent) => absolute_path(&self.nodes, parent),
+            None => "/".to_string(),
+        };
+        let mut parts: Vec<&str> = folder.split('/').filter(|p| !p.is_empty()).collect();
+        for part in href.split('/') {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    parts.pop();
+                }
+                part => parts.push(part),
+            }
+        }
+        let mut path = format!("/{}", parts.join("/"));
+        if href.ends_with('/') && !path.ends_with('/') {
+            path.push('/');
+        }
+        self.prefixed(&path)
     }
 
     pub fn ids(&self) -> Vec<SiteId> {
         (0..self.nodes.len() - 1).collect()
     }
 
+    /// Add the local files linked from page `id` (eg. "./report.pdf") so they can be downloaded
+    pub fn add_downloads(&mut self, id: SiteId) -> Result<(), LssgError> {
+        let (SiteNodeKind::Page(page), Some(input)) = (&self.nodes[id].kind, self.get_input(id))
+        else {
+            return Ok(());
+        };
+        let input = input.clone();
+        let links: Vec<String> = page
+            .links()
+            .into_iter()
+            .filter(|(text, href, ..)| !text.is_empty() && !Page::is_href_to_page(href))
+            .map(|(_, href, ..)| href.clone())
+            .filter(|href| {
+                Input::is_relative(href) && !href.starts_with('#') && !href.contains(':')
+            })
+            .collect();
+        let parent = self.nodes[id].parent.unwrap_or(self.root);
+        for href in links {
+            let Ok(input) = input.new(&href) else {
+                continue;
+            };
+            match &input {
+                Input::Local { path } if path.is_file() => {}
+                _ => continue,
+            }
+            let child_id = self.add_from_input(input, parent)?;
+            self.rel_graph
+                .add(id, child_id, Relation::Download { raw_path: href });
+        }
+        Ok(())
+    }
+
     /// add an external relation between two site nodes
     /// This will help create resources necessary for `from`
     pub fn add_link(&mut self, from: SiteId, to: SiteId) {
         self.rel_graph.add(from, to, Relation::External);
     }
 
+    /// Relative target of resource `id` if it should be written as symlink, only when
+    /// `symlinks = "preserve"` and the target is part of the site
+    pub fn preserved_symlink(&self, id: SiteId) -> Option<PathBuf> {
+        if self.symlinks != SymlinkMode::Preserve {
+            return None;
+        }
+        let SiteNodeKind::Resource(Resource::Fetched {
+            input: Input::Local { path },
+            ..
+        }) = &self.nodes[id].kind
+        else {
+            return None;
+        };
+        let target = fs::read_link(path).ok()?;
+        let resolved = path.parent()?.join(target).canonicalize_nonexistent_path();
+        let target_id = *self.input_to_id.get(&Input::Local { path: resolved })?;
+        if !matches!(self.nodes[target_id].kind, SiteNodeKind::Resource(..)) {
+            return None;
+        }
+        // relative to where both are written, which can differ from where they are read from
+        let parent = self.nodes[id].parent?;
+        Some(PathBuf::from(self.rel_path(parent, target_id)))
+    }
+
     /// Get all the relations from a single node to other nodes
     pub fn links_from(&self, from: SiteId) -> Vec<&Link> {
         self.rel_graph.links_from(from)
     }
 
+    /// Links pointing to `to`, like the pages linking to a page
+    pub fn links_to(&self, to: SiteId) -> Vec<&Link> {
+        self.rel_graph.links_to(to)
+    }
+
+    /// Update the resource paths inside of stylesheets to point to where the resources are in the
+    /// SiteTree and inline small resources, should only be called once after the SiteTree is
+    /// finished
+    pub fn update_stylesheet_resources(&mut self) {
+        let stylesheets: Vec<SiteId> = (0..self.nodes.len())
+            .filter(|id| matches!(self.nodes[*id].kind, SiteNodeKind::Stylesheet(..)))
+            .collect();
+        let mut inlined = HashSet::new();
+        for id in stylesheets {
+            let parent = self.nodes[id].parent.expect("stylesheet must have parent");
+            // raw paths are borrowed from the links, which are not changed by updating nodes
+            let updates: Vec<(&str, String)> = self
+                .rel_graph
+                .links_from(id)
+                .into_iter()
+                .filter_map(|link| match &link.relation {
+                    Relation::Discovered { raw_path } => {
+                        let updated = match self.data_uri(link.to) {
+                            Some(data_uri) => {
+                                inlined.insert((id, link.to));
+                                data_uri
+                            }
+                            None => self.rel_path(parent, link.to),
+                        };
+                        Some((raw_path.as_str(), updated))
+                    }
+                    _ => None,
+                })
+                .collect();
+            if let SiteNodeKind::Stylesheet(stylesheet) = &mut self.nodes[id].kind {
+                stylesheet.update_resources(&updates);
+            }
+        }
+
+        // resources that are only used by stylesheets that inlined them don't have to be written
+        let resources: HashSet<SiteId> = inlined.iter().map(|(_, to)| *to).collect();
+        for resource in resources {
+            let used = self.rel_graph.get(resource).iter().any(|link| {
+                link.to == resource
+                    && !matches!(link.relation, Relation::Family)
+                    && !inlined.contains(&(link.from, resource))
+            });
+            if !used {
+                self.remove(resource);
+            }
+        }
+    }
+
+    /// Data uri of a local resource that is smaller than `stylesheet_inline_limit`
+    fn data_uri(&self, id: SiteId) -> Option<String> {
+        if self.stylesheet_inline_limit == 0 {
+            return None;
+        }
+        let SiteNodeKind::Resource(resource) = &self.nodes[id].kind else {
+            return None;
+        };
+        if resource.is_remote() || resource.size()? > self.stylesheet_inline_limit {
+            return None;
+        }
+        // imported stylesheets and unknown files are kept as files
+        let mime_type = media_type(&self.nodes[id].name)
+            .filter(|t| t.starts_with("image/") || t.starts_with("font/"))?;
+        let mut content = vec![];
+        resource.readable().ok()?.read_to_end(&mut content).ok()?;
+        Some(format!(
+            "data:{mime_type};base64,{}",
+            STANDARD.encode(content)
+        ))
+    }
+
     /// Utility function to add a node, create a id and add to parent children
-    pub fn add(&mut self, node: SiteNode) -> SiteId {
+    pub fn add(&mut self, mut node: SiteNode) -> SiteId {
+        if node.parent.is_some() {
+            let has_extension = matches!(
+                node.kind,
+                SiteNodeKind::Resource(..) | SiteNodeKind::Stylesheet(..)
+            );
+            node.name = self.url_style.name(&node.name, has_extension);
+        }
+
         // check for name collisions
         if let Some(parent) = node.parent {
             if let Some(id) = self.get_by_name(&node.name, parent) {
@@ -216,6 +652,77 @@ impl SiteTree {
         id
     }
 
+    /// Sibling under `parent` with the same name as `name`, ignoring case because of
+    /// case-insensitive filesystems. A page and a folder with the same name are not a collision,
+    /// the page is written to the folder.
+    fn colliding_node(&self, name: &str, is_page: bool, parent: SiteId) -> Option<SiteId> {
+        let name = name.to_lowercase();
+        self.nodes[parent].children.iter().copied().find(|id| {
+            let node = &self.nodes[*id];
+            let is_folder = matches!(node.kind, SiteNodeKind::Folder);
+            node.name.to_lowercase() == name && !(is_page && is_folder)
+        })
+    }
+
+    /// Add a node read from `input` and register the input. Errors when the node would be written
+    /// to the same path as a node from another input, unless `[urls] disambiguate` is set then
+    /// a number is added to its name (eg. "about-2").
+    fn add_input(&mut self, mut node: SiteNode, input: &Input) -> Result<SiteId, LssgError> {
+        if let Some(id) = self.input_to_id.get(input) {
+            return Ok(*id);
+        }
+        if let Some(parent) = node.parent {
+            let is_page = matches!(node.kind, SiteNodeKind::Page(_));
+            let has_extension = matches!(
+                node.kind,
+                SiteNodeKind::Resource(..) | SiteNodeKind::Stylesheet(..)
+            );
+            node.name = self.url_style.name(&node.name, has_extension);
+
+            // a folder created for the files next to a page becomes the page itself
+            if let Some(id) = self.get_by_name(&node.name, parent).copied() {
+                if is_page && matches!(self.nodes[id].kind, SiteNodeKind::Folder) {
+                    self.nodes[id].kind = node.kind;
+                    self.input_to_id.insert(input.clone(), id);
+                    return Ok(id);
+                }
+            }
+
+            if let Some(other) = self.colliding_node(&node.name, is_page, parent) {
+                let other_source = match self.get_input(other) {
+                    Some(other) => other.to_string(),
+                    None => format!("generated {:?}", self.nodes[other].name),
+                };
+                let path = self.path(other);
+                if !self.url_style.disambiguate {
+                    return Err(LssgError::sitetree(format!(
+                        "{} and {other_source} are both written to {path:?}, rename one of them \
+                         or set `disambiguate = true` in [urls] on the root page",
+                        input.to_string()
+                    )));
+                }
+                let (stem, extension) = match node.name.rsplit_once('.') {
+                    Some((stem, extension)) if has_extension && !stem.is_empty() => {
+                        (stem.to_string(), format!(".{extension}"))
+                    }
+                    _ => (node.name.clone(), String::new()),
+                };
+                let name = (2..)
+                    .map(|i| format!("{stem}-{i}{extension}"))
+                    .find(|name| self.colliding_node(name, is_page, parent).is_none())
+                    .expect("a free name");
+                warn!(
+                    "{} and {other_source} are both written to {path:?}, naming it {name:?}",
+                    input.to_string()
+                );
+                node.name = name;
+            }
+        }
+        let id = self.add(node);
+        self.input_to_id.insert(input.clone(), id);
+        Ok(id)
+    }
+
     /// add from Input, will figure out what node to add from input and will register input not to
     /// be used for other nodes
     pub fn add_from_input(
@@ -238,14 +745,11 @@ impl SiteTree {
             self.add_page_from_input(input.clone(), parent_id)?
         } else {
             parent_id = self.create_folders(&input, parent_id)?;
-            let id = self.add(SiteNode {
-                name: input.filename()?,
-                parent: Some(parent_id),
-                children: vec![],
-                kind: SiteNodeKind::Resource(Resource::new_fetched(input.clone())?),
-            });
-            self.input_to_id.insert(input.clone(), id);
-            id
+            let resource = Resource::new_fetched(input.clone())?;
+            self.add_input(
+                SiteNode::resource(input.filename()?, parent_id, resource),
+                &input,
+            )?
         };
 
         Ok(id)
@@ -267,22 +771,113 @@ impl SiteTree {
             // TODO if this page exists should the location of the page be updated?
             return Ok(*id);
         }
+        // same page reached through a symlink
+        let canonical = input.canonical();
+        if let Some(id) = canonical.as_ref().and_then(|c| self.canonical_pages.get(c)) {
+            warn!(
+                "{} is a symlink to an existing page, using existing node instead",
+                input.to_string()
+            );
+            return Ok(*id);
+        }
 
+        let mut name = input.filestem().unwrap_or("root".to_string());
         if let Some(parent) = &mut parent {
             *parent = self.create_folders(&input, *parent)?;
+            // index page found while creating its folder
+            if let Some(id) = self.input_to_id.get(&input) {
+                return Ok(*id);
+            }
+            // index pages take the place of their folder
+            if self.is_index_page(&input) && *parent != self.root {
+                if let Some(folder_parent) = self.nodes[*parent].parent {
+                    name = self.nodes[*parent].name.clone();
+                    *parent = folder_parent;
+                }
+            }
         }
 
         // create early because of the need of an parent id
-        let page = Page::from_input(&input)?;
-        let id = self.add(SiteNode {
-            name: input.filestem().unwrap_or("root".to_string()),
-            parent,
-            children: vec![],
-            kind: SiteNodeKind::Page(page),
-        });
+        let page = match self.prefetched.remove(&input) {
+            Some(content) => Page::from_readable(content?.as_slice())?,
+            None => Page::from_input(&input)?,
+        };
+        let id = self.add_input(
+            SiteNode {
+                name,
+                parent,
+                children: vec![],
+                kind: SiteNodeKind::Page(page),
+            },
+            &input,
+        )?;
 
-        // register input
-        self.input_to_id.insert(input.clone(), id);
+        if let Some(canonical) = canonical {
+            self.canonical_pages.insert(canonical, id);
+        }
+
+        let page = match &self.nodes[id].kind {
+            SiteNodeKind::Page(page) => page,
+            _ => panic!("has to be page"),
+        };
+        if parent.is_none() {
+            self.symlinks = match page
+                .attributes()
+                .and_then(|a| a.get("symlinks"))
+                .and_then(|v| v.as_str())
+            {
+                Some("preserve") => SymlinkMode::Preserve,
+                Some("copy") | None => SymlinkMode::Copy,
+                Some(mode) => {
+                    warn!("Unknown symlinks mode {mode:?}, use \"copy\" or \"preserve\"");
+                    SymlinkMode::Copy
+                }
+            };
+            self.mirror_images = page
+                .attributes()
+                .and_then(|a| a.get("mirror_images"))
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true);
+            if let Some(value) = page.attributes().and_then(|a| a.get("index_files")) {
+                match value.clone().try_into::<Vec<String>>() {
+                    Ok(files) => self.index_files = files,
+                    Err(_) => warn!("Invalid index_files {value}, use eg. [\"index.md\"]"),
+                }
+            }
+            match page
+                .attributes()
+                .and_then(|a| a.get("stylesheet_inline_limit"))
+            {
+                Some(toml::Value::Integer(limit)) => self.stylesheet_inline_limit = *limit as u64,
+                Some(toml::Value::String(limit)) => match parse_size(limit) {
+                    Some(limit) => self.stylesheet_inline_limit = limit,
+                    None => warn!("Invalid stylesheet_inline_limit {limit:?}, use eg. \"4 KB\""),
+                },
+                _ => {}
+            }
+            if let Some(prefix) = page
+                .attributes()
+                .and_then(|a| a.get("url_prefix"))
+                .and_then(|v| v.as_str())
+            {
+                let prefix = prefix.trim_matches('/');
+                if !prefix.is_empty() {
+                    self.url_prefix = format!("/{prefix}");
+                }
+            }
+            if let Some(table) = page.attributes().and_then(|a| a.get("urls")) {
+                if let Err(e) = self.url_style.overwrite(table.clone()) {
+                    error!("Failed to parse [urls] options: {e}");
+                }
+            }
+            if let Some(table) = page.attributes().and_then(|a| a.get("remote")) {
+                let mut options = RemoteOptions::default();
+                match options.overwrite(table.clone()) {
+                    Ok(()) => set_remote_options(options),
+                    Err(e) => error!("Failed to parse [remote] options: {e}"),
+                }
+            }
+        }
 
         let page = match &self.nodes[id].kind {
             SiteNodeKind::Page(page) => page,
@@ -295,10 +890,16 @@ impl SiteTree {
             .into_iter()
             .map(|(text, href, ..)| (text.len() == 0, href.clone()))
             .collect();
+        self.prefetch(&input, &links);
         for (is_empty, href) in links {
             // if link has no text add whatever is in it
             if is_empty {
-                let input = input.new(&href)?;
+                // query is used for render options (eg. `?load=defer`)
+                let path = match Input::is_relative(&href) {
+                    true => href.split(['?', '#']).next().unwrap_or(&href),
+                    false => &href,
+                };
+                let input = input.new(path)?;
                 let child_id = self.add_from_input(input, id)?;
                 self.rel_graph
                     .add(id, child_id, Relation::Discovered { raw_path: href });
@@ -323,18 +924,204 @@ impl SiteTree {
             .into_iter()
             .map(|(_tokens, src, _title)| src.clone())
             .collect();
+        if self.mirror_images {
+            let remote: Vec<Input> = images
+                .iter()
+                .filter(|src| src.starts_with("http"))
+                .filter_map(|src| input.new(src).ok())
+                .filter(|input| {
+                    !self.input_to_id.contains_key(input) && !self.prefetched.contains_key(input)
+                })
+                .collect();
+            self.prefetched.extend(fetch_all(remote));
+        }
         for src in images {
             if Input::is_relative(&src) {
-                let input = input.new(&src);
+                // query is used for render options (eg. `?inline=false`)
+                let path = src.split(['?', '#']).next().unwrap_or(&src);
+                let input = input.new(path);
                 let child_id = self.add_from_input(input?, parent.unwrap_or(self.root))?;
                 self.rel_graph
                     .add(id, child_id, Relation::Discovered { raw_path: src });
+            } else if self.mirror_images && src.starts_with("http") {
+                match self.add_mirrored_image(&input, &src) {
+                    Ok(child_id) => {
+                        self.rel_graph
+                            .add(id, child_id, Relation::Discovered { raw_path: src })
+                    }
+                    Err(e) => warn!("Not mirroring {src}, linking to it instead: {e}"),
+                }
             }
         }
 
         return Ok(id);
     }
 
+    /// Resolve the wiki links (eg. `[[Page Name]]`) of all pages to relative paths of the pages in
+    /// the tree they refer to, unresolved links are kept as they are
+    fn resolve_wiki_links(&mut self) {
+        let pages: Vec<SiteId> = (0..self.nodes.len())
+            .filter(|id| self.nodes[*id].kind.is_page())
+            .collect();
+        for id in pages.iter().copied() {
+            let SiteNodeKind::Page(page) = &self.nodes[id].kind else {
+                continue;
+            };
+            let mut targets: Vec<String> = page
+                .links()
+                .into_iter()
+                .filter_map(|(_, href, _)| wiki_link_target(href).map(|t| t.to_string()))
+                .collect();
+            targets.sort();
+            targets.dedup();
+            let Some(from) = self.get_input(id).cloned() else {
+                continue;
+            };
+
+            let mut resolved = HashMap::new();
+            for target in targets {
+                let Some(to) = self.find_wiki_page(&target, id, &pages) else {
+                    continue;
+                };
+                let Some(path) = self.get_input(to).and_then(|to| from.make_relative(to)) else {
+                    continue;
+                };
+                let href = match path.starts_with("..") {
+                    true => path,
+                    false => format!("./{path}"),
+                };
+                self.rel_graph.add(
+                    id,
+                    to,
+                    Relation::Discovered {
+                        raw_path: href.clone(),
+                    },
+                );
+                resolved.insert(format!("[[{target}]]"), href);
+            }
+            if let SiteNodeKind::Page(page) = &mut self.nodes[id].kind {
+                page.rewrite_links(|href| {
+                    if let Some(resolved) = resolved.get(href) {
+                        *href = resolved.clone();
+                    }
+                });
+            }
+        }
+    }
+
+    /// Find the page wiki link `target` (eg. "Page Name#Heading") of page `from` refers to by
+    /// matching the names, or otherwise the `title` attribute, of `pages` ignoring case. When
+    /// multiple pages match the one closest to `from` is used.
+    fn find_wiki_page(&self, target: &str, from: SiteId, pages: &[SiteId]) -> Option<SiteId> {
+        // headings don't have ids, link to the page itself
+        let name = target.split('#').next().unwrap_or(target).trim();
+        let mut matches: Vec<SiteId> = pages
+            .iter()
+            .copied()
+            .filter(|id| {
+                let mut names: Vec<&str> = self
+                    .parents(*id)
+                    .into_iter()
+                    .filter(|p| *p != self.root)
+                    .map(|p| self.nodes[p].name.as_str())
+                    .collect();
+                names.reverse();
+                names.push(&self.nodes[*id].name);
+                matches_name(&names, name)
+            })
+            .collect();
+        if matches.is_empty() {
+            let title = name.to_lowercase();
+            matches = pages
+                .iter()
+                .copied()
+                .filter(|id| match &self.nodes[*id].kind {
+                    SiteNodeKind::Page(page) => page
+                        .attributes()
+                        .and_then(|a| a.get("title"))
+                        .and_then(|t| t.as_str())
+                        .is_some_and(|t| t.to_lowercase() == title),
+                    _ => false,
+                })
+                .collect();
+        }
+        let mut candidates: Vec<(usize, String, SiteId)> = matches
+            .into_iter()
+            .map(|to| {
+                let path = rel_path(&self.nodes, from, to);
+                (path.matches('/').count(), path, to)
+            })
+            .collect();
+        candidates.sort();
+
+        let Some((_, _, to)) = candidates.first() else {
+            warn!(
+                "Could not resolve wiki link [[{target}]] in {}",
+                self.path(from)
+            );
+            return None;
+        };
+        if candidates.len() > 1 {
+            let paths: Vec<String> = candidates.iter().map(|(_, _, id)| self.path(*id)).collect();
+            warn!(
+                "Wiki link [[{target}]] in {} matches {}, using {}",
+                self.path(from),
+                paths.join(", "),
+                self.path(*to)
+            );
+        }
+        Some(*to)
+    }
+
+    /// Add a remote image as resource to the `remote` folder, named by a hash of the url to
+    /// prevent collisions between images with the same filename
+    fn add_mirrored_image(&mut self, input: &Input, src: &str) -> Result<SiteId, LssgError> {
+        let input = input.new(src)?;
+        if let Some(id) = self.input_to_id.get(&input) {
+            return Ok(*id);
+        }
+        let hash = content_hash(input.to_string().as_bytes())?;
+        let name = format!("{}-{}", &hash[..16], input.filename()?);
+
+        let folder = match self.get_by_name(MIRROR_FOLDER, self.root) {
+            Some(id) => *id,
+            None => self.add(SiteNode::folder(MIRROR_FOLDER, self.root)),
+        };
+        // fetched while building the tree so unavailable images are linked to instead
+        let content = match self.prefetched.remove(&input) {
+            Some(content) => content?,
+            None => {
+                let mut content = vec![];
+                input.readable()?.read_to_end(&mut content)?;
+                content
+            }
+        };
+        let id = self.add(SiteNode::resource(
+            name,
+            folder,
+            Resource::from_readable(content.as_slice())?,
+        ));
+        self.input_to_id.insert(input, id);
+        Ok(id)
+    }
+
+    /// Concurrently fetch remote pages and stylesheets linked from `input` that are not yet part
+    /// of the tree
+    fn prefetch(&mut self, input: &Input, links: &[(bool, String)]) {
+        let inputs: Vec<Input> = links
+            .iter()
+            .filter(|(is_empty, href)| *is_empty || Page::is_href_to_page(href))
+            .filter_map(|(_, href)| input.new(href).ok())
+            .filter(|input| {
+                SiteNodeKind::input_is_page(input) || SiteNodeKind::input_is_stylesheet(input)
+            })
+            .filter(|input| {
+                !self.input_to_id.contains_key(input) && !self.prefetched.contains_key(input)
+            })
+            .collect();
+        self.prefetched.extend(fetch_all(inputs));
+    }
+
     /// Add a stylesheet and all resources needed by the stylesheet
     pub fn add_stylesheet_from_input(
         &mut self,
@@ -343,48 +1130,69 @@ impl SiteTree {
     ) -> Result<SiteId, LssgError> {
         parent = self.create_folders(&input, parent)?;
 
-        let stylesheet = Stylesheet::try_from(&input)?;
-        let stylesheet_links: Vec<String> = stylesheet
+        let stylesheet = match self.prefetched.remove(&input) {
+            Some(content) => {
+                let mut stylesheet = Stylesheet::from_readable(content?.as_slice())?;
+                stylesheet.set_source_name(input.filename()?);
+                stylesheet
+            }
+            None => Stylesheet::try_from(&input)?,
+        };
+        let mut stylesheet_links: Vec<String> = stylesheet
             .links()
             .into_iter()
             .map(|p| match p {
                 StylesheetLink::Import(s) | StylesheetLink::Url(s) => s.to_string(),
             })
             .collect();
+        stylesheet_links.sort();
+        stylesheet_links.dedup();
 
-        let parent = self.create_folders(&input, parent)?;
-        let stylesheet_id = self.add(SiteNode {
-            name: input.filename()?,
-            parent: Some(parent),
-            children: vec![],
-            kind: SiteNodeKind::Stylesheet(stylesheet),
-        });
+        let stylesheet_id = self.add_input(
+            SiteNode::stylesheet(input.filename()?, parent, stylesheet),
+            &input,
+        )?;
 
         for link in stylesheet_links {
-            let input = input.new(&link)?;
+            // root relative paths of local stylesheets are relative to the root input
+            let input = match (&input, link.strip_prefix('/')) {
+                (Input::Local { .. }, Some(path)) => self.root_input.new(path)?,
+                _ => input.new(&link)?,
+            };
             let parent = self.create_folders(&input, parent)?;
-            let resource_id = self.add(SiteNode {
-                name: input.filename()?,
-                parent: Some(parent),
-                children: vec![],
-                kind: SiteNodeKind::Resource(Resource::new_fetched(input.clone())?),
-            });
+            let resource = Resource::new_fetched(input.clone())?;
+            let resource_id = self.add_input(
+                SiteNode::resource(input.filename()?, parent, resource),
+                &input,
+            )?;
             self.rel_graph.add(
                 stylesheet_id,
                 resource_id,
                 Relation::Discovered { raw_path: link },
             );
-            self.input_to_id.insert(input, resource_id);
         }
 
-        // register input
-        self.input_to_id.insert(input, stylesheet_id);
-
         Ok(stylesheet_id)
     }
 
+    /// Check if `input` is an index page of a folder below the root page
+    fn is_index_page(&self, input: &Input) -> bool {
+        if !matches!(input, Input::Local { .. }) {
+            return false;
+        }
+        let Some(rel_path) = self.root_input.make_relative(input) else {
+            return false;
+        };
+        match rel_path.rsplit_once('/') {
+            Some((folder, file)) => {
+                !folder.starts_with("..") && self.index_files.iter().any(|f| f == file)
+            }
+            None => false,
+        }
+    }
+
     /// If local input and not outside of `root_input` it will create some extra folders for
-    /// structuring SiteTree
+    /// structuring SiteTree, folders with an index page (eg. `index.md`) become that page
     fn create_folders(&mut self, input: &Input, mut parent: SiteId) -> Result<SiteId, LssgError> {
         if let Some(rel_path) = self.root_input.make_relative(input) {
             // don't allow backtrack from root
@@ -394,13 +1202,15 @@ impl SiteTree {
             let parts: Vec<&str> = rel_path.split("/").collect();
             let parts = &parts[0..parts.len() - 1];
 
+            // folders from the root down to `parent` that are already part of the path
             let mut parents = self.parents(parent);
-            parents.push(parent);
             parents.reverse();
+            parents.push(parent);
+            parents.retain(|id| *id != self.root);
             for i in 0..parts.len() {
-                let name = parts[i];
+                let name = &self.url_style.name(parts[i], false);
                 if let Some(parent) = parents.get(i) {
-                    if self[*parent].name == name {
+                    if &self[*parent].name == name {
                         continue;
                     }
                 }
@@ -408,21 +1218,43 @@ impl SiteTree {
                     parent = *id;
                 } else {
                     debug!("creating folder {name:?} under {parent:?}");
+                    let folder_parent = parent;
                     parent = self.add(SiteNode {
                         name: name.to_string(),
-                        parent: Some(parent),
+                        parent: Some(folder_parent),
                         children: vec![],
                         kind: SiteNodeKind::Folder,
                     });
+                    // the index page of the folder becomes the folder itself
+                    let folder = parts[0..=i].join("/");
+                    let index = self
+                        .index_files
+                        .iter()
+                        .filter_map(|file| self.root_input.new(&format!("{folder}/{file}")).ok())
+                        .find(|index| matches!(index, Input::Local { path } if path.is_file()));
+                    if let Some(index) = index {
+                        self.add_page_under_parent(index, Some(folder_parent))?;
+                    }
                 }
             }
         }
         return Ok(parent);
     }
 
+    /// Remove a node and its descendants from the tree, their ids stay reserved so all other ids
+    /// remain valid
     pub fn remove(&mut self, id: SiteId) {
-        self.rel_graph.remove_all(id);
-        todo!("remove from tree");
+        if let Some(parent) = self.nodes[id].parent.take() {
+            self.nodes[parent].children.retain(|c| *c != id);
+        }
+        let mut queue = vec![id];
+        while let Some(id) = queue.pop() {
+            queue.append(&mut self.nodes[id].children);
+            self.nodes[id].parent = None;
+            self.rel_graph.remove_all(id);
+            self.input_to_id.retain(|_, i| *i != id);
+            self.error_pages.remove(&id);
+        }
     }
 
     /// Concat resources and minify what can be minified
@@ -445,78 +1277,39 @@ impl Tree for SiteTree {
 }
 
 impl fmt::Display for SiteTree {
+    /// Indented tree of all nodes with their id, kind, size and output path, eg.
+    ///
+    /// ```text
+    /// index [0] Page 120 B -> index.html
+    /// ├── default.css [1] Stylesheet 4.2 KB -> default.css
+    /// └── blog [2] Folder -> blog/
+    ///     └── post [3] Page 1.1 KB -> blog/post/index.html
+    /// ```
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // fill in table
-        let mut row_length = 0;
-        let mut table: Vec<Vec<Option<String>>> = vec![];
-        let mut prev_col = 0;
-        let mut queue = vec![(self.root(), 0)];
-        while let Some((n, col)) = queue.pop() {
-            let node = &self.nodes[n];
-            for c in &node.children {
-                queue.push((c.clone(), col + 1))
+        // (node, prefix of its children, prefix of the node itself)
+        let mut queue = vec![(self.root(), String::new(), String::new())];
+        while let Some((id, indent, prefix)) = queue.pop() {
+            let node = &self.nodes[id];
+            let mut line = format!("{prefix}{} [{id}] {}", node.name, node.kind.to_string());
+            if let Some(size) = self.node_size(id) {
+                line.push_str(&format!(" {}", format_size(size)));
             }
-
-            // create col if not exists
-            if let None = table.get(col) {
-                table.push(vec![]);
-            }
-
-            // fill in until we reach the current row where we are
-            let amount_rows_in_col = table[col].len();
-            // if going back fill all the way
-            if prev_col > col {
-                for _ in amount_rows_in_col..row_length {
-                    table[col].push(None);
-                }
-            } else {
-                // if going forward fill to current row - 1
-                for _ in amount_rows_in_col + 1..row_length {
-                    table[col].push(None);
-                }
-            }
-            prev_col = col;
-
-            let node_name = format!("{}({})({})", node.name, n, node.kind.to_string());
-            table[col].push(Some(node_name));
-
-            let amount_rows_in_col = table[col].len();
-            // update at what row we are
-            if amount_rows_in_col > row_length {
-                row_length = amount_rows_in_col;
-            }
-        }
-
-        // display table
-        let mut out = vec![String::new(); row_length];
-        for col in 0..table.len() {
-            let max_name_length = table[col]
-                .iter()
-                .map(|c| c.as_ref().map(|c| c.len()).unwrap_or(0))
-                .reduce(|a, b| a.max(b))
-                .unwrap_or(0);
-            for (row, entry) in table[col].iter().enumerate() {
-                match entry {
-                    Some(name) => {
-                        out[row] += name;
-                        out[row] += &" ".repeat(max_name_length - name.len());
-                        if let Some(next_column) = table.get(col + 1) {
-                            if let Some(Some(_)) = next_column.get(row) {
-                                out[row] += &" - ";
-                                continue;
-                            }
-                        }
-                        out[row] += &"   ";
-                    }
-                    None => out[row] += &" ".repeat(max_name_length + 3),
-                }
-            }
-            for row in table[col].len()..row_length {
-                out[row] += &" ".repeat(max_name_length + 3);
+            line.push_str(&format!(" -> {}", self.output_path(id)));
+            writeln!(f, "{line}")?;
+
+            // reversed so the first child gets popped first
+            for (i, child) in node.children.iter().enumerate().rev() {
+                let (child_prefix, child_indent) = match i == node.children.len() - 1 {
+                    true => ("└── ", "    "),
+                    false => ("├── ", "│   "),
+                };
+                queue.push((
+                    *child,
+                    format!("{indent}{child_indent}"),
+                    format!("{indent}{child_prefix}"),
+                ));
             }
         }
-
-        f.write_str(&out.join("\n"))?;
         Ok(())
     }
 }
@@ -533,3 +1326,188 @@ impl IndexMut<SiteId> for SiteTree {
         &mut self.nodes[index]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_preserved_symlink() {
+        let root = std::env::temp_dir().join(format!("lssg-symlinks-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("assets")).unwrap();
+        fs::create_dir_all(root.join("blog")).unwrap();
+        fs::write(
+            root.join("home.md"),
+            "<!--\nsymlinks = \"preserve\"\n-->\n# Home\n\n![](./assets/logo.png)\n[Blog](./blog/blog.md)",
+        )
+        .unwrap();
+        fs::write(root.join("blog/blog.md"), "# Blog\n\n![](./logo.png)").unwrap();
+        fs::write(root.join("assets/logo.png"), "png").unwrap();
+        // absolute targets are written relative as well
+        std::os::unix::fs::symlink(root.join("assets/logo.png"), root.join("blog/logo.png"))
+            .unwrap();
+
+        let input = Input::from_string(root.join("home.md").to_str().unwrap()).unwrap();
+        let site_tree = SiteTree::from_input(input).unwrap();
+        let link = (0..site_tree.len())
+            .find(|id| site_tree.path(*id).ends_with("blog/logo.png"))
+            .unwrap();
+        assert_eq!(
+            site_tree.preserved_symlink(link),
+            Some(PathBuf::from("../assets/logo.png"))
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_wiki_links() {
+        let root = std::env::temp_dir().join(format!("lssg-wiki-links-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("notes")).unwrap();
+        fs::write(
+            root.join("home.md"),
+            "# Home\n\n[Notes](./notes/notes.md) [[page name]] [[Other Title]] [[Missing]]",
+        )
+        .unwrap();
+        fs::write(
+            root.join("notes/notes.md"),
+            "# Notes\n\n[Page](./Page Name.md) [Other](./other.md)",
+        )
+        .unwrap();
+        fs::write(root.join("notes/Page Name.md"), "# Page").unwrap();
+        fs::write(
+            root.join("notes/other.md"),
+            "<!--\ntitle = \"Other Title\"\n-->\n# Other",
+        )
+        .unwrap();
+        // not part of the site so it can't be linked to
+        fs::write(root.join("Missing.md"), "# Missing").unwrap();
+
+        let input = Input::from_string(root.join("home.md").to_str().unwrap()).unwrap();
+        let site_tree = SiteTree::from_input(input).unwrap();
+        let SiteNodeKind::Page(home) = &site_tree[site_tree.root()].kind else {
+            panic!("root has to be a page");
+        };
+        let hrefs: Vec<&str> = home
+            .links()
+            .into_iter()
+            .map(|(_, href, _)| href.as_str())
+            .collect();
+        assert_eq!(
+            hrefs,
+            vec![
+                "./notes/notes.md",
+                "./notes/Page Name.md",
+                "./notes/other.md",
+                "[[Missing]]"
+            ]
+        );
+        let linked: Vec<String> = site_tree
+            .links_from(site_tree.root())
+            .into_iter()
+            .filter(|link| matches!(link.relation, Relation::Discovered { .. }))
+            .map(|link| site_tree.path(link.to))
+            .collect();
+        assert!(linked.contains(&"/notes/notes/Page Name".to_string()));
+        assert!(linked.contains(&"/notes/notes/other".to_string()));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_page_by_path() {
+        let root = std::env::temp_dir().join(format!("lssg-site-tree-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("home.md"),
+            "<!--\nurl_prefix = \"/repo\"\n-->\n# Home\n\n[About](./about.md)",
+        )
+        .unwrap();
+        fs::write(root.join("about.md"), "# About").unwrap();
+
+        let input = Input::from_string(root.join("home.md").to_str().unwrap()).unwrap();
+        let site_tree = SiteTree::from_input(input).unwrap();
+        let about = site_tree.page_by_path("/about");
+        assert!(about.is_some());
+        assert_eq!(site_tree.page_by_path("/repo/about/"), about);
+        assert_eq!(site_tree.page_by_path("/"), Some(site_tree.root()));
+        assert_eq!(site_tree.page_by_path("/repo"), Some(site_tree.root()));
+        assert_eq!(site_tree.page_by_path("/repository"), None);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_error_page_hrefs() {
+        let root = std::env::temp_dir().join(format!("lssg-error-page-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("errors")).unwrap();
+        fs::write(
+            root.join("home.md"),
+            "<!--\nnot_found = \"./errors/404.md\"\n-->\n# Home",
+        )
+        .unwrap();
+        fs::write(root.join("errors/404.md"), "# Not Found").unwrap();
+
+        let input = Input::from_string(root.join("home.md").to_str().unwrap()).unwrap();
+        let site_tree = SiteTree::from_input(input).unwrap();
+        let not_found = (0..site_tree.len())
+            .find(|id| site_tree.is_error_page(*id))
+            .unwrap();
+        assert_eq!(site_tree.path(not_found), "/404.html");
+        assert_eq!(site_tree.href(site_tree.root(), not_found), "/404.html");
+        assert_eq!(
+            site_tree.absolute_href(not_found, "./image.png"),
+            "/errors/image.png"
+        );
+        assert_eq!(
+            site_tree.absolute_href(not_found, "../files/report.pdf"),
+            "/files/report.pdf"
+        );
+        assert_eq!(site_tree.absolute_href(not_found, "#top"), "#top");
+        assert_eq!(
+            site_tree.absolute_href(not_found, "https://example.com/a"),
+            "https://example.com/a"
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_inlined_stylesheet_resources() {
+        let root = std::env::temp_dir().join(format!("lssg-inline-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("home.md"),
+            "<!--\nstylesheet_inline_limit = 1024\n-->\n# Home",
+        )
+        .unwrap();
+        fs::write(
+            root.join("style.css"),
+            "body { background: url(\"./dot.png\"); }",
+        )
+        .unwrap();
+        fs::write(root.join("dot.png"), [0u8; 16]).unwrap();
+
+        let input = Input::from_string(root.join("home.md").to_str().unwrap()).unwrap();
+        let mut site_tree = SiteTree::from_input(input.clone()).unwrap();
+        let stylesheet = site_tree
+            .add_stylesheet_from_input(input.new("./style.css").unwrap(), site_tree.root())
+            .unwrap();
+        let dot = site_tree.links_from(stylesheet)[0].to;
+        site_tree.update_stylesheet_resources();
+
+        assert!(!site_tree[site_tree.root()].children.contains(&dot));
+        assert!(site_tree.links_from(stylesheet).is_empty());
+        assert!(site_tree.links_to(dot).is_empty());
+        assert!(site_tree.get_input(dot).is_none());
+        assert!(!site_tree.source_paths().contains(&root.join("dot.png")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,9 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 use std::{fs::write, io::Read};
 
-use log::info;
+use log::debug;
 use regex::Regex;
+use serde_json::json;
 
 use crate::{sitetree::Input, LssgError};
 
@@ -13,18 +14,57 @@ pub enum StylesheetLink {
     Url(String),
 }
 
+/// Original stylesheet that is part of a condensed stylesheet
+#[derive(Debug, Clone)]
+struct Source {
+    /// Name shown when debugging (eg. "main.css")
+    name: Option<String>,
+    content: String,
+}
+
 /// Stylesheet representation for resource discovering and condensing multiple stylesheets into one
 #[derive(Debug, Clone)]
 pub struct Stylesheet {
     content: String,
     /// map from raw matching string to path
-    links: HashMap<String, StylesheetLink>,
+    links: BTreeMap<String, StylesheetLink>,
+    /// stylesheets the content is made of in order
+    sources: Vec<Source>,
+}
+
+const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Base64 variable length quantity used by source maps
+fn vlq(value: i64, out: &mut String) {
+    let mut value = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    } as u64;
+    loop {
+        let mut digit = value & 0b11111;
+        value >>= 5;
+        if value > 0 {
+            // continuation bit
+            digit |= 0b100000;
+        }
+        out.push(BASE64_CHARS[digit as usize] as char);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+/// Path of a url without its query string and fragment (eg. "font.woff2?v=4#iefix" becomes
+/// "font.woff2"), these are kept in the stylesheet when the path is updated
+fn strip_suffix(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or(url)
 }
 
-fn links(content: &str) -> HashMap<String, StylesheetLink> {
-    let mut resources = HashMap::new();
+fn links(content: &str) -> BTreeMap<String, StylesheetLink> {
+    let mut resources = BTreeMap::new();
     let re = Regex::new(
-        r#"@import ['"](.*)['"]|@import url\(['"]([^")]*)['"]\)|url\(['"]([^")]*)['"]\)"#,
+        r#"@import ['"](.*)['"]|@import url\(['"]([^")]*)['"]\)|url\(\s*['"]?([^'")]*)['"]?\s*\)"#,
     )
     .unwrap();
     for r in re.captures_iter(&content).into_iter() {
@@ -42,7 +82,13 @@ fn links(content: &str) -> HashMap<String, StylesheetLink> {
 
             resources.insert(r[0].into(), StylesheetLink::Import(path));
         } else {
-            resources.insert(r[0].into(), StylesheetLink::Url(r[3].to_string()));
+            let path = strip_suffix(r[3].trim());
+            // skip inline data, protocol relative urls and references to elements (eg.
+            // `url(#gradient)` in svg)
+            if path.is_empty() || path.starts_with("data:") || path.starts_with("//") {
+                continue;
+            }
+            resources.insert(r[0].into(), StylesheetLink::Url(path.to_string()));
         }
     }
     return resources;
@@ -53,26 +99,173 @@ impl Stylesheet {
         let mut content = String::new();
         readable.read_to_string(&mut content)?;
         let links = links(&content);
-        Ok(Stylesheet { content, links })
+        let sources = vec![Source {
+            name: None,
+            content: content.clone(),
+        }];
+        Ok(Stylesheet {
+            content,
+            links,
+            sources,
+        })
     }
 
     pub fn links(&self) -> Vec<&StylesheetLink> {
         return self.links.values().collect();
     }
 
-    /// Append stylesheet and discover local referenced resources
-    pub fn append(&mut self, _stylesheet: Stylesheet) -> Result<(), LssgError> {
-        todo!()
+    pub fn content(&self) -> &str {
+        &self.content
     }
 
-    /// Update a resource input path to a new one
+    /// Append stylesheet and discover local referenced resources, links of `stylesheet` are kept
+    /// as is so they should be relative to the same folder
+    pub fn append(&mut self, stylesheet: Stylesheet) -> Result<(), LssgError> {
+        if !self.content.is_empty() && !self.content.ends_with('\n') {
+            self.content.push('\n');
+        }
+        self.content.push_str(&stylesheet.content);
+        self.links.extend(stylesheet.links);
+        self.sources.extend(stylesheet.sources);
+        Ok(())
+    }
+
+    /// Source map (<https://sourcemaps.info/spec.html>) mapping every line to the line of the
+    /// stylesheet it came from
+    pub fn source_map(&self, file: &str) -> String {
+        let mut mappings = String::new();
+        let mut previous = (0, 0);
+        for (i, source) in self.sources.iter().enumerate() {
+            // appending adds a newline to sources without one so every source starts on a new line
+            for line in 0..source.content.lines().count().max(1) {
+                if !mappings.is_empty() {
+                    mappings.push(';');
+                }
+                // generated column, source index, source line and source column relative to the
+                // previous segment
+                vlq(0, &mut mappings);
+                vlq(i as i64 - previous.0, &mut mappings);
+                vlq(line as i64 - previous.1, &mut mappings);
+                vlq(0, &mut mappings);
+                previous = (i as i64, line as i64);
+            }
+        }
+        let names: Vec<String> = self
+            .sources
+            .iter()
+            .enumerate()
+            .map(|(i, s)| s.name.clone().unwrap_or_else(|| format!("source-{i}.css")))
+            .collect();
+        let contents: Vec<&str> = self.sources.iter().map(|s| s.content.as_str()).collect();
+        json!({
+            "version": 3,
+            "file": file,
+            "sources": names,
+            "sourcesContent": contents,
+            "names": [],
+            "mappings": mappings,
+        })
+        .to_string()
+    }
+
+    /// Update a resource input path to a new one, see [`Stylesheet::update_resources`]
     pub fn update_resource(&mut self, raw_path: &str, updated_path: &str) {
-        self.content = self.content.replace(raw_path, updated_path);
+        self.update_resources(&[(raw_path, updated_path.to_string())]);
     }
 
-    pub fn write(&mut self, path: &Path) -> Result<(), LssgError> {
-        info!("Writing stylesheet {path:?}",);
-        write(path, &mut self.content)?;
+    /// Update resource input paths (raw path, updated path) to new ones in a single pass over the
+    /// content, query strings and fragments after the path are kept unless the resource got
+    /// inlined as data uri
+    pub fn update_resources(&mut self, updates: &[(&str, String)]) {
+        let updates: HashMap<&str, &str> = updates
+            .iter()
+            .map(|(raw_path, updated_path)| (*raw_path, updated_path.as_str()))
+            .collect();
+        let mut updated_links = vec![];
+        // (start, end, index into updated_links) of every link in the content
+        let mut replacements = vec![];
+        for (raw, link) in &self.links {
+            let (StylesheetLink::Import(path) | StylesheetLink::Url(path)) = link;
+            let Some(updated_path) = updates.get(path.as_str()) else {
+                continue;
+            };
+            let Some(start) = raw.find(path.as_str()) else {
+                continue;
+            };
+            let mut end = start + path.len();
+            if updated_path.starts_with("data:") {
+                end = raw[end..]
+                    .find(|c: char| matches!(c, '\'' | '"' | ')') || c.is_whitespace())
+                    .map_or(raw.len(), |i| end + i);
+            }
+            let i = updated_links.len();
+            updated_links.push(format!("{}{updated_path}{}", &raw[..start], &raw[end..]));
+            replacements.extend(
+                self.content
+                    .match_indices(raw.as_str())
+                    .map(|(start, raw)| (start, start + raw.len(), i)),
+            );
+        }
+        if replacements.is_empty() {
+            return;
+        }
+
+        replacements.sort_unstable();
+        let mut content = String::with_capacity(self.content.len());
+        let mut last = 0;
+        for (start, end, i) in replacements {
+            // a link inside of another link that already got replaced
+            if start < last {
+                continue;
+            }
+            content.push_str(&self.content[last..start]);
+            content.push_str(&updated_links[i]);
+            last = end;
+        }
+        content.push_str(&self.content[last..]);
+        self.content = content;
+    }
+
+    /// Set the name of the stylesheet shown when debugging, only used for source maps
+    pub fn set_source_name(&mut self, name: impl Into<String>) {
+        if let [source] = self.sources.as_mut_slice() {
+            source.name = Some(name.into());
+        }
+    }
+
+    /// Whether a source map is written next to the stylesheet, only for stylesheets made of
+    /// multiple stylesheets in a debug build
+    fn has_source_map(&self) -> bool {
+        cfg!(debug_assertions) && self.sources.len() > 1
+    }
+
+    /// The exact content written to `file`, including the source map comment if there is one
+    pub fn output(&self, file: &str) -> String {
+        if !self.has_source_map() {
+            return self.content.clone();
+        }
+        let mut out = self.content.clone();
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str(&format!("/*# sourceMappingURL={file}.map */\n"));
+        out
+    }
+
+    /// Write the stylesheet, when made of multiple stylesheets in a debug build a source map is
+    /// written next to it
+    pub fn write(&self, path: &Path) -> Result<(), LssgError> {
+        debug!("Writing stylesheet {path:?}",);
+        let file = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if self.has_source_map() {
+            let map_path = path.with_file_name(format!("{file}.map"));
+            debug!("Writing source map {map_path:?}",);
+            write(&map_path, self.source_map(&file))?;
+        }
+        write(path, self.output(&file))?;
         Ok(())
     }
 }
@@ -81,7 +274,9 @@ impl TryFrom<&Input> for Stylesheet {
     type Error = LssgError;
 
     fn try_from(value: &Input) -> Result<Self, Self::Error> {
-        Self::from_readable(value.readable()?)
+        let mut stylesheet = Self::from_readable(value.readable()?)?;
+        stylesheet.set_source_name(value.filename()?);
+        Ok(stylesheet)
     }
 }
 
@@ -130,4 +325,69 @@ mod tests {
             &StylesheetLink::Url("test.jpg".to_owned())
         );
     }
+
+    #[test]
+    fn test_update_resource() {
+        let mut stylesheet = Stylesheet::from_readable(
+            r#"src: url("fonts/icons.woff2?v=4#iefix") format("woff2"), url(/fonts/icons.ttf);
+fill: url(#gradient);"#
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            stylesheet.links(),
+            vec![
+                &StylesheetLink::Url("fonts/icons.woff2".to_owned()),
+                &StylesheetLink::Url("/fonts/icons.ttf".to_owned()),
+            ]
+        );
+        stylesheet.update_resource("fonts/icons.woff2", "../icons.woff2");
+        stylesheet.update_resource("/fonts/icons.ttf", "../icons.ttf");
+        assert_eq!(
+            stylesheet.content(),
+            r#"src: url("../icons.woff2?v=4#iefix") format("woff2"), url(../icons.ttf);
+fill: url(#gradient);"#
+        );
+
+        let mut stylesheet =
+            Stylesheet::from_readable(r#"src: url("icons.woff2?v=4#iefix");"#.as_bytes()).unwrap();
+        stylesheet.update_resource("icons.woff2", "data:font/woff2;base64,AA==");
+        assert_eq!(
+            stylesheet.content(),
+            r#"src: url("data:font/woff2;base64,AA==");"#
+        );
+    }
+
+    #[test]
+    fn test_source_map() {
+        let mut stylesheet = Stylesheet::from_readable("a {}\nb {}\n".as_bytes()).unwrap();
+        stylesheet.set_source_name("main.css");
+        let mut other = Stylesheet::from_readable("c {}".as_bytes()).unwrap();
+        other.set_source_name("other.css");
+        stylesheet.append(other).unwrap();
+
+        assert_eq!(stylesheet.content(), "a {}\nb {}\nc {}");
+        let map: serde_json::Value =
+            serde_json::from_str(&stylesheet.source_map("style.css")).unwrap();
+        assert_eq!(map["sources"], serde_json::json!(["main.css", "other.css"]));
+        assert_eq!(map["mappings"], "AAAA;AACA;ACDA");
+
+        // the output is exactly what gets written so it can be hashed for integrity
+        let directory =
+            std::env::temp_dir().join(format!("lssg-stylesheet-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("style.css");
+        stylesheet.write(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            stylesheet.output("style.css")
+        );
+        if cfg!(debug_assertions) {
+            assert_eq!(
+                stylesheet.output("style.css"),
+                "a {}\nb {}\nc {}\n/*# sourceMappingURL=style.css.map */\n"
+            );
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
 }
@@ -0,0 +1,226 @@
+use serde_extensions::Overwrite;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Characters with a special meaning in urls or that aren't allowed in filenames on some
+/// systems, removed from every name
+const RESERVED: &[char] = &[
+    '?', '#', '%', '\\', '"', '<', '>', '|', '[', ']', '^', '`', '{', '}',
+];
+
+/// How node names end up in urls, set with a `[urls]` table on the root page
+#[derive(Overwrite, Debug, Clone, Default)]
+pub struct UrlStyle {
+    /// End links to pages with `/`
+    pub trailing_slash: bool,
+    /// Lowercase all names
+    pub lowercase: bool,
+    /// Strip accents, replace whitespace with `-` and remove anything that isn't alphanumeric,
+    /// `-`, `_` or `.` from names
+    pub slugify: bool,
+    /// Write letters without an ASCII form in Latin letters and remove accents (eg. "Straße" ->
+    /// "Strasse", "Привет" -> "Privet")
+    pub transliterate: bool,
+    /// Add a number to the name of pages and files that would be written to the same path as
+    /// another one (eg. "about-2") instead of failing
+    pub disambiguate: bool,
+}
+
+impl UrlStyle {
+    /// Apply the style to a node name, `has_extension` keeps the extension of file names intact
+    ///
+    /// Names are always NFC normalized, so a name typed on one system matches a filename from
+    /// another (macOS writes decomposed filenames), and reserved characters are removed.
+    pub fn name(&self, name: &str, has_extension: bool) -> String {
+        let normalized: String = name
+            .nfc()
+            .filter(|c| !c.is_control() && !RESERVED.contains(c))
+            .collect();
+        let name = match normalized.is_empty() {
+            true => "_",
+            false => normalized.as_str(),
+        };
+        let (stem, extension) = match name.rsplit_once('.') {
+            Some((stem, extension)) if has_extension && !stem.is_empty() => (stem, Some(extension)),
+            _ => (name, None),
+        };
+        let mut stem = match self.transliterate {
+            true => transliterate(stem),
+            false => stem.to_string(),
+        };
+        if self.slugify {
+            stem = slugify(&stem);
+        }
+        if let Some(extension) = extension {
+            stem = format!("{stem}.{extension}");
+        }
+        if self.lowercase {
+            stem = stem.to_lowercase();
+        }
+        stem
+    }
+}
+
+/// Latin spelling of lowercase letters that don't decompose into an ASCII letter with accents
+fn latin(c: char) -> Option<&'static str> {
+    Some(match c {
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'ø' => "o",
+        'đ' | 'ð' => "d",
+        'þ' => "th",
+        'ł' => "l",
+        'ı' => "i",
+        'ŋ' => "ng",
+        'ħ' => "h",
+        // greek
+        'α' => "a",
+        'β' => "v",
+        'γ' => "g",
+        'δ' => "d",
+        'ε' => "e",
+        'ζ' => "z",
+        'η' => "i",
+        'θ' => "th",
+        'ι' => "i",
+        'κ' => "k",
+        'λ' => "l",
+        'μ' => "m",
+        'ν' => "n",
+        'ξ' => "x",
+        'ο' => "o",
+        'π' => "p",
+        'ρ' => "r",
+        'σ' | 'ς' => "s",
+        'τ' => "t",
+        'υ' => "y",
+        'φ' => "f",
+        'χ' => "ch",
+        'ψ' => "ps",
+        'ω' => "o",
+        // cyrillic
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' | 'ґ' => "g",
+        'д' => "d",
+        'е' => "e",
+        'ё' => "yo",
+        'є' => "ye",
+        'ж' => "zh",
+        'з' => "z",
+        'и' | 'і' => "i",
+        'ї' => "yi",
+        'й' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ы' => "y",
+        'э' => "e",
+        'ю' => "yu",
+        'я' => "ya",
+        _ => return None,
+    })
+}
+
+/// Push the Latin spelling of `c` to `result`, capitalized if `c` is uppercase. False if `c`
+/// doesn't have one
+fn transliterate_char(c: char, result: &mut String) -> bool {
+    let lowercase = c.to_lowercase().next().unwrap_or(c);
+    let Some(latin) = latin(lowercase) else {
+        return false;
+    };
+    let mut chars = latin.chars();
+    match (c.is_uppercase(), chars.next()) {
+        (true, Some(first)) => {
+            result.push(first.to_ascii_uppercase());
+            result.extend(chars);
+        }
+        _ => result.push_str(latin),
+    }
+    true
+}
+
+/// Write `name` in Latin letters without accents, letters from other scripts are kept
+fn transliterate(name: &str) -> String {
+    let mut result = String::new();
+    for c in name.chars() {
+        // some letters (eg. "й") decompose into a different letter with an accent
+        if transliterate_char(c, &mut result) {
+            continue;
+        }
+        for c in c.nfkd().filter(|c| !is_combining_mark(*c)) {
+            if !transliterate_char(c, &mut result) {
+                result.push(c);
+            }
+        }
+    }
+    result
+}
+
+fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.nfkd().filter(|c| !is_combining_mark(*c)) {
+        if c.is_alphanumeric() || c == '_' || c == '.' {
+            slug.push(c);
+        } else if (c.is_whitespace() || c == '-') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        return name.to_string();
+    }
+    slug.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name() {
+        let style = UrlStyle {
+            trailing_slash: false,
+            lowercase: true,
+            slugify: true,
+            transliterate: false,
+            disambiguate: false,
+        };
+        assert_eq!(style.name("Über  Café (draft)", false), "uber-cafe-draft");
+        assert_eq!(style.name("My Photo.PNG", true), "my-photo.png");
+        assert_eq!(style.name("v1.2 Notes", false), "v1.2-notes");
+        assert_eq!(UrlStyle::default().name("My Page", false), "My Page");
+        // decomposed and composed forms are the same name
+        assert_eq!(UrlStyle::default().name("Cafe\u{301}", false), "Café");
+        assert_eq!(
+            UrlStyle::default().name("What? C# 100%", false),
+            "What C 100"
+        );
+        assert_eq!(UrlStyle::default().name("???", false), "_");
+
+        let style = UrlStyle {
+            transliterate: true,
+            slugify: true,
+            ..UrlStyle::default()
+        };
+        assert_eq!(style.name("Straße Ærø", false), "Strasse-Aero");
+        assert_eq!(style.name("Привет мир, Щука", false), "Privet-mir-Shchuka");
+        assert_eq!(style.name("Ελληνικά.png", true), "Ellinika.png");
+        assert_eq!(style.name("日本語 Ünïcode", false), "日本語-Unicode");
+    }
+}
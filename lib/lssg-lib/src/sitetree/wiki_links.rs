@@ -0,0 +1,28 @@
+/// Check if wiki link `name` (eg. "Page Name" or "notes/Page Name") refers to the page with
+/// node names `names` from the top folder to the page itself, ignoring case
+pub fn matches_name(names: &[&str], name: &str) -> bool {
+    let name = name.trim_end_matches(".md").to_lowercase();
+    let parts: Vec<String> = names.iter().map(|n| n.to_lowercase()).collect();
+    let name_parts: Vec<String> = name
+        .split('/')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.to_string())
+        .collect();
+    !name_parts.is_empty() && parts.ends_with(&name_parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_name() {
+        let names = ["notes", "Page Name"];
+        assert!(matches_name(&names, "page name"));
+        assert!(matches_name(&names, "notes/Page Name"));
+        assert!(matches_name(&names, "Page Name.md"));
+        assert!(!matches_name(&names, "other/Page Name"));
+        assert!(!matches_name(&names, "Name"));
+        assert!(!matches_name(&names, ""));
+    }
+}
@@ -37,3 +37,23 @@ impl<'n, T: Tree> Iterator for DFS<'n, T> {
         None
     }
 }
+
+/// Same order as [`DFS`] but without holding on to the tree between steps, so nodes can be
+/// changed while walking
+pub struct Walk {
+    stack: Vec<usize>,
+}
+
+impl Walk {
+    pub fn new<T: Tree>(tree: &T) -> Self {
+        Walk {
+            stack: vec![tree.root()],
+        }
+    }
+
+    pub fn next<T: Tree>(&mut self, tree: &T) -> Option<usize> {
+        let id = self.stack.pop()?;
+        self.stack.extend(tree.get(id).children());
+        Some(id)
+    }
+}
@@ -0,0 +1,138 @@
+//! Renders every site in `tests/golden/{name}/input` and compares it to
+//! `tests/golden/{name}/expected`
+//!
+//! Run with `LSSG_BLESS=1 cargo test --test golden` to overwrite the expected output after an
+//! intended change.
+use std::{
+    collections::BTreeSet,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use lssg_lib::{
+    renderer::{BlogModule, CardModule, DefaultModule, ExternalModule, SitemapModule},
+    sitetree::Input,
+    Lssg,
+};
+
+fn golden_directory() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+/// All file paths relative to `directory`
+fn files(directory: &Path) -> BTreeSet<PathBuf> {
+    let mut files = BTreeSet::new();
+    if !directory.exists() {
+        return files;
+    }
+    let mut queue = vec![directory.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                queue.push(path);
+            } else {
+                files.insert(path.strip_prefix(directory).unwrap().to_path_buf());
+            }
+        }
+    }
+    files
+}
+
+fn render(input: &Path, output: &Path) {
+    let input = Input::from_string(&input.join("index.md").to_string_lossy()).unwrap();
+    let mut lssg = Lssg::new(input, output.to_path_buf());
+    lssg.add_module(ExternalModule::new());
+    lssg.add_module(BlogModule::new());
+    lssg.add_module(CardModule::new());
+    lssg.add_module(DefaultModule::new());
+    lssg.add_module(SitemapModule::new());
+    lssg.render().unwrap();
+}
+
+/// Differences between the `expected` and `actual` output trees
+fn compare(expected: &Path, actual: &Path) -> Vec<String> {
+    let expected_files = files(expected);
+    let actual_files = files(actual);
+    let mut differences = vec![];
+    for file in expected_files.difference(&actual_files) {
+        differences.push(format!("missing {file:?}"));
+    }
+    for file in actual_files.difference(&expected_files) {
+        differences.push(format!("unexpected {file:?}"));
+    }
+    for file in expected_files.intersection(&actual_files) {
+        let expected_content = fs::read(expected.join(file)).unwrap();
+        let actual_content = fs::read(actual.join(file)).unwrap();
+        if expected_content == actual_content {
+            continue;
+        }
+        // html is written on a single line so show the surroundings of the first difference
+        let position = expected_content
+            .iter()
+            .zip(actual_content.iter())
+            .position(|(e, a)| e != a)
+            .unwrap_or(expected_content.len().min(actual_content.len()));
+        let excerpt = |content: &[u8]| {
+            let start = position.saturating_sub(80);
+            let end = (position + 80).min(content.len());
+            String::from_utf8_lossy(&content[start..end]).to_string()
+        };
+        differences.push(format!(
+            "{file:?} differs at byte {position}\n  expected: {}\n  actual:   {}",
+            excerpt(&expected_content),
+            excerpt(&actual_content),
+        ));
+    }
+    differences
+}
+
+#[test]
+fn golden() {
+    let bless = env::var("LSSG_BLESS").is_ok_and(|v| v == "1");
+    let mut failures = vec![];
+    for site in fs::read_dir(golden_directory()).unwrap() {
+        let site = site.unwrap().path();
+        if !site.is_dir() {
+            continue;
+        }
+        let name = site.file_name().unwrap().to_string_lossy().to_string();
+        let output = env::temp_dir().join(format!("lssg-golden-{name}-{}", std::process::id()));
+        render(&site.join("input"), &output);
+
+        let expected = site.join("expected");
+        if bless {
+            if expected.exists() {
+                fs::remove_dir_all(&expected).unwrap();
+            }
+            fs::rename(&output, &expected)
+                .or_else(|_| copy_directory(&output, &expected))
+                .unwrap();
+            continue;
+        }
+
+        let differences = compare(&expected, &output);
+        fs::remove_dir_all(&output).unwrap();
+        if !differences.is_empty() {
+            failures.push(format!("{name}:\n{}", differences.join("\n")));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "rendered sites differ from the expected output, run with LSSG_BLESS=1 if this is \
+         intended\n\n{}",
+        failures.join("\n\n")
+    );
+}
+
+/// Fallback for when the temp directory is on another filesystem
+fn copy_directory(from: &Path, to: &Path) -> std::io::Result<()> {
+    for file in files(from) {
+        let to = to.join(&file);
+        fs::create_dir_all(to.parent().unwrap())?;
+        fs::copy(from.join(&file), to)?;
+    }
+    fs::remove_dir_all(from)
+}
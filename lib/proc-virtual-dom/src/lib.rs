@@ -1,20 +1,26 @@
-use std::{collections::HashMap, str::Chars};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    str::Chars,
+};
 
-use proc_macro2::{Span, TokenStream, TokenTree};
+use proc_macro2::{Delimiter, LineColumn, Span, TokenStream, TokenTree};
 use quote::quote;
 use syn::{parse::Parse, parse_macro_input, token::Brace, Block, Expr, Ident, Stmt};
-use virtual_dom::{parse_html, Html};
+use virtual_dom::{is_known_element, parse_html_with_warnings, Html, ParseOptions, ParseWarning};
 
 // using https://github.com/chinedufn/percy/blob/master/crates/html-macro/src/lib.rs as example
 /// Parse a string into virtual_dom::DomNode's with minimal variable interpolation
 ///
-/// Because of the nature of macros whitespace is fairly arbitrary and might spawn spaces or
-/// newlines in between text one way to prevent this it to explicitly add quotes around your text.
+/// Whitespace follows the source like jsx does, spaces in between text and elements on the same
+/// line become a single space and whitespace spanning multiple lines is removed at the start and
+/// end of text. To keep whitespace exactly as written put quotes around your text.
 ///
 /// eg.
 ///
 /// ```
 /// use proc_virtual_dom::dom;
+/// dom!(<p>Read <a href="/more">more</a> here</p>);
 /// dom!(<div>" This is my text with preserved whitespace "</div>);
 /// ```
 ///
@@ -27,12 +33,64 @@ use virtual_dom::{parse_html, Html};
 ///     <div>{title}</div>
 /// };
 /// ```
+///
+/// Braces can also hold any expression that returns something that can be appended as child, like
+/// `Html`, `Vec<Html>` or `DomNode`, so render functions can be used as components
+///
+/// ```
+/// use proc_virtual_dom::dom;
+/// use virtual_dom::Html;
+/// fn card(title: &str) -> Html {
+///     dom!(<div class="card">{title}</div>).into()
+/// }
+/// let content = dom! {
+///     <div>{card("First")}{card("Second")}</div>
+/// };
+/// ```
+///
+/// Unknown elements, duplicate attributes and void elements with children are compile errors
+///
+/// ```compile_fail
+/// use proc_virtual_dom::dom;
+/// dom!(<dvi>Typo</dvi>);
+/// ```
+///
+/// ```compile_fail
+/// use proc_virtual_dom::dom;
+/// dom!(<a href="/" href="/other">Link</a>);
+/// ```
+///
+/// ```compile_fail
+/// use proc_virtual_dom::dom;
+/// dom!(<br>Text</br>);
+/// ```
 #[proc_macro]
 pub fn dom(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let parsed_content = input.to_string();
-    let template = parse_macro_input!(input as Template);
+    let mut expressions = vec![];
+    let mut parsed_content = String::new();
+    stringify(
+        input.clone().into(),
+        &mut expressions,
+        &mut parsed_content,
+        &mut None,
+    );
+    let input_tokens: TokenStream = input.clone().into();
+    let mut template = parse_macro_input!(input as Template);
+    for Expression { ident, .. } in &expressions {
+        template.variables.insert(ident.to_string(), ident.clone());
+    }
+    // evaluate expressions before building the nodes
+    let bindings: Vec<_> = expressions
+        .iter()
+        .map(|Expression { ident, expr }| quote!(let #ident = #expr;))
+        .collect();
 
-    let tokens = match parse_html(parsed_content.to_string().as_bytes()) {
+    // whitespace is already trimmed like jsx, whitespace in between elements is kept
+    let options = ParseOptions {
+        preserve_whitespace: true,
+        ..Default::default()
+    };
+    let (tokens, warnings) = match parse_html_with_warnings(parsed_content.as_bytes(), options) {
         Ok(t) => t,
         Err(e) => {
             let e = syn::Error::new(Span::call_site(), e);
@@ -40,13 +98,18 @@ pub fn dom(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         }
     };
 
+    if let Err(e) = validate(input_tokens, &warnings) {
+        let errors = e.to_compile_error();
+        return quote!({ #errors }).into();
+    }
+
     let html = to_tokens(&tokens, &template, None, 0);
 
     if html.len() > 1 {
         let children = quote!(vec![#({#html},)*]);
         return quote! {
             {
-                use ::std::collections::HashMap;
+                #(#bindings)*
                 use ::virtual_dom::*;
                 #children
             }
@@ -57,7 +120,7 @@ pub fn dom(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let html = html.into_iter().next().expect("no html given");
     return quote! {
         {
-            use ::std::collections::HashMap;
+            #(#bindings)*
             use ::virtual_dom::*;
             #html
         }
@@ -65,6 +128,195 @@ pub fn dom(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     .into();
 }
 
+/// Write `input` as it was written in the source, whitespace in between tokens becomes a single
+/// space or a newline if the tokens are on different lines. Braces holding anything other than
+/// a variable are replaced by a generated variable bound to the expression.
+fn stringify(
+    input: TokenStream,
+    expressions: &mut Vec<Expression>,
+    output: &mut String,
+    end: &mut Option<LineColumn>,
+) {
+    for t in input {
+        let start = match &t {
+            TokenTree::Group(group) => group.span_open().start(),
+            t => t.span().start(),
+        };
+        output.push_str(separator(*end, start));
+        match t {
+            TokenTree::Group(group) => {
+                if group.delimiter() == Delimiter::Brace {
+                    match syn::parse2::<Expr>(group.stream()) {
+                        Ok(Expr::Path(p)) if p.path.get_ident().is_some() => {}
+                        Ok(expr) => {
+                            let ident = expression_ident(expressions.len());
+                            output.push_str(&format!("{{{ident}}}"));
+                            expressions.push(Expression { ident, expr });
+                            *end = Some(group.span_close().end());
+                            continue;
+                        }
+                        Err(_) => {}
+                    }
+                }
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                output.push_str(open);
+                *end = Some(group.span_open().end());
+                stringify(group.stream(), expressions, output, end);
+                output.push_str(separator(*end, group.span_close().start()));
+                output.push_str(close);
+                *end = Some(group.span_close().end());
+            }
+            TokenTree::Punct(p) => {
+                output.push(p.as_char());
+                *end = Some(p.span().end());
+            }
+            t => {
+                output.push_str(&t.to_string());
+                *end = Some(t.span().end());
+            }
+        }
+    }
+}
+
+/// Error on unknown elements, duplicate attributes and badly nested tags (like void elements with
+/// children) so mistakes show up when compiling
+fn validate(input: TokenStream, warnings: &[ParseWarning]) -> Result<(), syn::Error> {
+    let mut tags = vec![];
+    let mut errors = vec![];
+    start_tags(input, &mut tags, &mut errors);
+    // positions of warnings are in the stringified input, point to the tag they are about instead
+    for warning in warnings {
+        let span = tags
+            .iter()
+            .find(|(t, _)| {
+                warning.message.contains(&format!("<{t}>"))
+                    || warning.message.contains(&format!("</{t}>"))
+            })
+            .map(|(_, span)| *span)
+            .unwrap_or_else(Span::call_site);
+        errors.push(syn::Error::new(span, &warning.message));
+    }
+
+    let mut errors = errors.into_iter();
+    let Some(mut error) = errors.next() else {
+        return Ok(());
+    };
+    for e in errors {
+        error.combine(e);
+    }
+    Err(error)
+}
+
+/// Collect the start tags in `input` with the span of their name, checking their name and
+/// attributes
+fn start_tags(input: TokenStream, tags: &mut Vec<(String, Span)>, errors: &mut Vec<syn::Error>) {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i] {
+            TokenTree::Group(group) => start_tags(group.stream(), tags, errors),
+            // a tag name directly follows `<`, otherwise it's text like `a < b`
+            TokenTree::Punct(p) if p.as_char() == '<' => match name(&tokens, i + 1) {
+                Some((tag, span, next)) if span.start() == p.span().end() => {
+                    if !is_known_element(&tag) {
+                        errors.push(syn::Error::new(span, format!("unknown element <{tag}>")));
+                    }
+                    tags.push((tag, span));
+                    i = attributes(&tokens, next, errors);
+                    continue;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// Check the attributes of a start tag for duplicates, returns the index after the start tag
+fn attributes(tokens: &[TokenTree], mut i: usize, errors: &mut Vec<syn::Error>) -> usize {
+    let mut names = HashSet::new();
+    while i < tokens.len() {
+        match &tokens[i] {
+            TokenTree::Punct(p) if p.as_char() == '>' => return i + 1,
+            // skip the value
+            TokenTree::Punct(p) if p.as_char() == '=' => i += 2,
+            TokenTree::Ident(_) => {
+                let Some((name, span, next)) = name(tokens, i) else {
+                    unreachable!()
+                };
+                if !names.insert(name.to_ascii_lowercase()) {
+                    errors.push(syn::Error::new(
+                        span,
+                        format!("duplicate attribute {name:?}"),
+                    ));
+                }
+                i = next;
+            }
+            _ => i += 1,
+        }
+    }
+    i
+}
+
+/// Tag or attribute name starting at `i`, these are idents joined by `-` or `:` (eg.
+/// `aria-label`). Returns the name, the span of its first ident and the index after it
+fn name(tokens: &[TokenTree], mut i: usize) -> Option<(String, Span, usize)> {
+    let TokenTree::Ident(ident) = tokens.get(i)? else {
+        return None;
+    };
+    let mut name = ident.to_string();
+    i += 1;
+    while let (Some(TokenTree::Punct(p)), Some(TokenTree::Ident(next))) =
+        (tokens.get(i), tokens.get(i + 1))
+    {
+        if p.as_char() != '-' && p.as_char() != ':' {
+            break;
+        }
+        name.push(p.as_char());
+        name.push_str(&next.to_string());
+        i += 2;
+    }
+    Some((name, ident.span(), i))
+}
+
+/// Whitespace in between the end of a token and the start of the next
+fn separator(end: Option<LineColumn>, start: LineColumn) -> &'static str {
+    let Some(end) = end else {
+        return "";
+    };
+    match (start.line, start.column).cmp(&(end.line, end.column)) {
+        Ordering::Equal => "",
+        Ordering::Greater if start.line > end.line => "\n",
+        // tokens from different sources, eg. passed through another macro
+        _ => " ",
+    }
+}
+
+/// Name of the variable for the `i`th expression, variables in text can only be alphabetic
+fn expression_ident(mut i: usize) -> Ident {
+    let mut name = String::from("domexpr");
+    loop {
+        name.push((b'a' + (i % 26) as u8) as char);
+        i /= 26;
+        if i == 0 {
+            break;
+        }
+    }
+    Ident::new(&name, Span::call_site())
+}
+
+/// An expression in braces, interpolated through a variable bound to its result
+struct Expression {
+    ident: Ident,
+    expr: Expr,
+}
+
 /// collect all interpolated variables
 #[derive(Clone)]
 struct Template {
@@ -162,6 +414,35 @@ fn parse_braces(chars: &mut Chars) -> Result<String, String> {
     Err(raw)
 }
 
+/// Remove whitespace at the start and end of text if it spans multiple lines
+fn trim_lines(text: &str) -> &str {
+    let start = text.trim_start();
+    let text = match text[..text.len() - start.len()].contains('\n') {
+        true => start,
+        false => text,
+    };
+    let end = text.trim_end();
+    match text[end.len()..].contains('\n') {
+        true => end,
+        false => text,
+    }
+}
+
+/// Replace every run of whitespace with a single space
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_whitespace = false;
+    for c in text.chars() {
+        match c.is_whitespace() {
+            true if in_whitespace => {}
+            true => collapsed.push(' '),
+            false => collapsed.push(c),
+        }
+        in_whitespace = c.is_whitespace();
+    }
+    collapsed
+}
+
 /// check if string has interpolated character if so add it
 fn interpolate_string(text: &str, template: &Template) -> TokenStream {
     let mut chars = text.chars();
@@ -203,15 +484,16 @@ fn to_tokens(
         match t {
             Html::Comment { .. } => {}
             Html::Text { text } => {
+                let text = trim_lines(text);
                 // if text starts with quotes and ends with quotes remove quotes
                 let text = if text.starts_with("\"") && text.ends_with("\"") {
                     let text = text.get(1..text.len() - 1).unwrap_or("".into());
                     if text.len() == 0 {
                         continue;
                     }
-                    text
+                    text.to_owned()
                 } else {
-                    text
+                    collapse_whitespace(text)
                 };
 
                 let mut chars = text.chars();
@@ -259,26 +541,34 @@ fn to_tokens(
                 children,
             } => {
                 let attributes_values = attributes.iter().map(|(key, value)| {
-                    let key = interpolate_string(key, template);
+                    // keys without variables are converted to atoms without allocating
+                    let key = match key.contains('{') {
+                        true => interpolate_string(key, template),
+                        false => {
+                            let key = key.as_str();
+                            quote!(#key)
+                        }
+                    };
                     let value = interpolate_string(value, template);
                     quote! {
-                        attributes.insert(#key, #value);
+                        attributes.insert((#key).into(), #value);
                     }
                 });
+                let tag = tag.as_str();
 
                 let id = Ident::new(&format!("node_{i}"), Span::call_site());
 
                 let el = if children.len() > 0 {
                     let children = to_tokens(children, template, Some(&id), i + tokens.len());
                     quote!(
-                        let mut attributes = HashMap::new();
+                        let mut attributes = Attributes::new();
                         #(#attributes_values)*
                         let #id = DomNode::create_element_with_attributes(#tag, attributes);
                         #({#children})*;
                     )
                 } else {
                     quote!(
-                        let mut attributes = HashMap::new();
+                        let mut attributes = Attributes::new();
                         #(#attributes_values)*
                         let #id = DomNode::create_element_with_attributes(#tag, attributes);
                     )
@@ -1,11 +1,10 @@
 use proc_virtual_dom::dom;
-use std::collections::HashMap;
-use virtual_dom::Html;
+use virtual_dom::{Atom, Attributes, Html};
 
 /// Utility function to convert iteratables into attributes hashmap
-pub fn to_attributes<I: IntoIterator<Item = (impl Into<String>, impl Into<String>)>>(
+pub fn to_attributes<I: IntoIterator<Item = (impl Into<Atom>, impl Into<String>)>>(
     arr: I,
-) -> HashMap<String, String> {
+) -> Attributes {
     arr.into_iter().map(|(k, v)| (k.into(), v.into())).collect()
 }
 
@@ -16,7 +15,7 @@ fn text(text: &str) -> Html {
 fn p(children: Vec<Html>) -> Html {
     Html::Element {
         tag: "p".into(),
-        attributes: HashMap::new(),
+        attributes: Attributes::new(),
         children,
     }
 }
@@ -61,7 +60,7 @@ fn static_html_works() {
 
     let expected = Html::Element {
         tag: "div".into(),
-        attributes: HashMap::new(),
+        attributes: Attributes::new(),
         children: vec![Html::Element {
             tag: "a".into(),
             attributes: to_attributes([("href", "link.com")]),
@@ -99,10 +98,10 @@ fn html_interpolating_html_works() {
     let input = dom!(<div>{html}</div>);
     let expected = Html::Element {
         tag: "div".into(),
-        attributes: HashMap::new(),
+        attributes: Attributes::new(),
         children: vec![Html::Element {
             tag: "h1".into(),
-            attributes: HashMap::new(),
+            attributes: Attributes::new(),
             children: vec![text("Yay")],
         }],
     };
@@ -122,3 +121,61 @@ fn html_interpolation_works_for_self_closing() {
     };
     assert_eq!(expected, input.into());
 }
+
+#[test]
+fn html_interpolating_components_works() {
+    fn card(title: &str) -> Html {
+        dom!(<div class="card">{title}</div>).into()
+    }
+    fn cards(titles: &[&str]) -> Vec<Html> {
+        titles.iter().map(|t| card(t)).collect()
+    }
+    let titles = ["Second", "Third"];
+    let input = dom!(<section>{card("First")}{cards(&titles)}</section>);
+    let card = |title: &str| Html::Element {
+        tag: "div".into(),
+        attributes: to_attributes([("class", "card")]),
+        children: vec![text(title)],
+    };
+    let expected = Html::Element {
+        tag: "section".into(),
+        attributes: Attributes::new(),
+        children: vec![card("First"), card("Second"), card("Third")],
+    };
+    assert_eq!(expected, input.into());
+}
+
+#[test]
+fn whitespace_follows_source() {
+    let href = "/more";
+    let input = dom!(<p>Read <a href="{href}">more</a> here, or   not</p>);
+    let expected = p(vec![
+        text("Read "),
+        Html::Element {
+            tag: "a".into(),
+            attributes: to_attributes([("href", "/more")]),
+            children: vec![text("more")],
+        },
+        text(" here, or not"),
+    ]);
+    assert_eq!(expected, input.into());
+
+    let input = dom! {
+        <p>
+            Text spanning
+            multiple lines
+        </p>
+    };
+    assert_eq!(p(vec![text("Text spanning multiple lines")]), input.into());
+
+    let input = dom!(<p><b>a</b> <i>b</i></p>);
+    let b = |tag: &str, t: &str| Html::Element {
+        tag: tag.into(),
+        attributes: Attributes::new(),
+        children: vec![text(t)],
+    };
+    assert_eq!(p(vec![b("b", "a"), text(" "), b("i", "b")]), input.into());
+
+    let input = dom!(<p>"  quoted  "</p>);
+    assert_eq!(p(vec![text("  quoted  ")]), input.into());
+}
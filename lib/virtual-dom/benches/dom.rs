@@ -0,0 +1,81 @@
+//! Compares the reference counted and the arena backed document, run with `cargo bench -p virtual-dom`
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use virtual_dom::{to_attributes, ArenaDocument, Document, DomBackend, OutputMode};
+
+/// Page with `sections` sections of a heading, paragraphs and links, like a long rendered page
+fn build<D: DomBackend>(sections: usize) -> D {
+    let mut dom = D::new();
+    let body = dom.body();
+    let main = dom.create_element("main", to_attributes([("class", "content")]));
+    dom.append_child(&body, main.clone());
+    for i in 0..sections {
+        let section = dom.create_element("section", to_attributes([("id", format!("s{i}"))]));
+        let heading = dom.create_element("h2", Default::default());
+        let text = dom.create_text(format!("Section {i}"));
+        dom.append_child(&heading, text);
+        dom.append_child(&section, heading);
+        for j in 0..5 {
+            let p = dom.create_element("p", Default::default());
+            let text = dom.create_text("Some text with a ");
+            dom.append_child(&p, text);
+            let a = dom.create_element("a", to_attributes([("href", format!("/page/{i}/{j}"))]));
+            let text = dom.create_text("link");
+            dom.append_child(&a, text);
+            dom.append_child(&p, a);
+            // empty paragraphs are removed by sanitizing
+            let empty = dom.create_element("p", Default::default());
+            dom.append_child(&section, p);
+            dom.append_child(&section, empty);
+        }
+        dom.append_child(&main, section);
+    }
+    dom
+}
+
+/// Build, change, sanitize and serialize a page like the renderer does
+fn run<D: DomBackend>(sections: usize) -> String {
+    let mut dom: D = build(sections);
+    let root = dom.root();
+    let links: Vec<D::Node> = dom
+        .descendants(&root)
+        .filter(|n| dom.tag(n).is_some_and(|t| t == "a"))
+        .collect();
+    for link in links {
+        let icon = dom.create_element("i", to_attributes([("class", "icon")]));
+        dom.prepend(&link, icon);
+    }
+    dom.sanitize();
+    dom.validate();
+    dom.serialize(OutputMode::Html)
+}
+
+fn bench(name: &str, f: impl Fn() -> String) -> Duration {
+    // warm up
+    black_box(f());
+    let iterations = 20;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(f());
+    }
+    let elapsed = start.elapsed() / iterations;
+    println!("{name:<10} {elapsed:>12.2?}");
+    elapsed
+}
+
+fn main() {
+    for sections in [100, 1000, 10000] {
+        println!("{sections} sections");
+        assert_eq!(run::<Document>(sections), run::<ArenaDocument>(sections));
+        let rc = bench("document", || run::<Document>(sections));
+        let arena = bench("arena", || run::<ArenaDocument>(sections));
+        println!(
+            "arena is {:.2}x the speed of document\n",
+            rc.as_secs_f64() / arena.as_secs_f64()
+        );
+    }
+}
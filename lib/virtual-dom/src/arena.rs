@@ -0,0 +1,499 @@
+use crate::{
+    add_class, has_class, remove_class, Atom, Attributes, Document, DomBackend, DomNode,
+    DomNodeKind, DomTree, OutputMode,
+};
+
+/// Handle to a node of an [`ArenaDocument`], only valid for the document that created it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone)]
+struct ArenaNode {
+    kind: DomNodeKind,
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    previous_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+}
+
+/// Document that stores all nodes in a single vector and links them by [`NodeId`] instead of
+/// [`Document`] which links reference counted nodes
+///
+/// Nodes are borrowed through the document so there are no runtime borrow panics, and creating
+/// nodes doesn't allocate each of them separately. Detached nodes stay in the arena until the
+/// document is dropped.
+///
+/// Unlike [`Document`] it is `Send` and `Sync`, so documents can be built and serialized on
+/// worker threads and converted into a [`Document`] where needed.
+#[derive(Debug, Clone)]
+pub struct ArenaDocument {
+    nodes: Vec<ArenaNode>,
+    root: NodeId,
+    pub head: NodeId,
+    pub body: NodeId,
+}
+
+impl ArenaDocument {
+    pub fn new() -> ArenaDocument {
+        let mut document = ArenaDocument {
+            nodes: vec![],
+            root: NodeId(0),
+            head: NodeId(0),
+            body: NodeId(0),
+        };
+        let root = document.create_element("html");
+        let head = document.create_element("head");
+        let body = document.create_element("body");
+        document.append_child(root, head);
+        document.append_child(root, body);
+        document.root = root;
+        document.head = head;
+        document.body = body;
+        document
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    /// Amount of nodes in the arena, including detached ones
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn add(&mut self, kind: DomNodeKind) -> NodeId {
+        self.nodes.push(ArenaNode {
+            kind,
+            parent: None,
+            first_child: None,
+            last_child: None,
+            previous_sibling: None,
+            next_sibling: None,
+        });
+        NodeId(self.nodes.len() - 1)
+    }
+
+    pub fn create_element(&mut self, tag: impl Into<Atom>) -> NodeId {
+        self.create_element_with_attributes(tag, Attributes::new())
+    }
+
+    pub fn create_element_with_attributes(
+        &mut self,
+        tag: impl Into<Atom>,
+        attributes: Attributes,
+    ) -> NodeId {
+        self.add(DomNodeKind::Element {
+            tag: tag.into(),
+            attributes,
+        })
+    }
+
+    pub fn create_text_node(&mut self, text: impl Into<String>) -> NodeId {
+        self.add(DomNodeKind::Text { text: text.into() })
+    }
+
+    pub fn create_comment(&mut self, text: impl Into<String>) -> NodeId {
+        self.add(DomNodeKind::Comment { text: text.into() })
+    }
+
+    pub fn kind(&self, id: NodeId) -> &DomNodeKind {
+        &self.nodes[id.0].kind
+    }
+
+    pub fn kind_mut(&mut self, id: NodeId) -> &mut DomNodeKind {
+        &mut self.nodes[id.0].kind
+    }
+
+    pub fn set_attribute(&mut self, id: NodeId, key: impl Into<Atom>, value: String) {
+        if let DomNodeKind::Element { attributes, .. } = self.kind_mut(id) {
+            attributes.insert(key.into(), value);
+        }
+    }
+
+    /// Add the whitespace separated `classes` after the classes `id` already has, see
+    /// [`crate::add_class`]
+    pub fn add_class(&mut self, id: NodeId, classes: &str) {
+        if let DomNodeKind::Element { attributes, .. } = self.kind_mut(id) {
+            add_class(attributes, classes);
+        }
+    }
+
+    pub fn remove_class(&mut self, id: NodeId, class: &str) {
+        if let DomNodeKind::Element { attributes, .. } = self.kind_mut(id) {
+            remove_class(attributes, class);
+        }
+    }
+
+    pub fn has_class(&self, id: NodeId, class: &str) -> bool {
+        match self.kind(id) {
+            DomNodeKind::Element { attributes, .. } => has_class(attributes, class),
+            DomNodeKind::Text { .. } | DomNodeKind::Comment { .. } => false,
+        }
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    pub fn first_child(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].first_child
+    }
+
+    pub fn last_child(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].last_child
+    }
+
+    pub fn previous_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].previous_sibling
+    }
+
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].next_sibling
+    }
+
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        DomTree::children(self, &id)
+    }
+
+    /// `id` and its descendants in tree order
+    pub fn descendants(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        DomTree::descendants(self, &id)
+    }
+
+    pub fn get_elements_by_tag_name(&self, tag: &str) -> Vec<NodeId> {
+        self.descendants(self.root)
+            .filter(|id| matches!(self.kind(*id), DomNodeKind::Element { tag: t, .. } if t == tag))
+            .collect()
+    }
+
+    /// Detaches a node from its parent and siblings. Children are not affected.
+    pub fn detach(&mut self, id: NodeId) {
+        let node = &mut self.nodes[id.0];
+        let parent = node.parent.take();
+        let previous_sibling = node.previous_sibling.take();
+        let next_sibling = node.next_sibling.take();
+
+        match next_sibling {
+            Some(next) => self.nodes[next.0].previous_sibling = previous_sibling,
+            None => {
+                if let Some(parent) = parent {
+                    self.nodes[parent.0].last_child = previous_sibling;
+                }
+            }
+        }
+        match previous_sibling {
+            Some(previous) => self.nodes[previous.0].next_sibling = next_sibling,
+            None => {
+                if let Some(parent) = parent {
+                    self.nodes[parent.0].first_child = next_sibling;
+                }
+            }
+        }
+    }
+
+    /// Appends a new child to this node, after existing children.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `child` is `parent`
+    pub fn append_child(&mut self, parent: NodeId, child: NodeId) {
+        assert!(parent != child, "a node cannot be appended to itself");
+        self.detach(child);
+        let last_child = self.nodes[parent.0].last_child.replace(child);
+        let node = &mut self.nodes[child.0];
+        node.parent = Some(parent);
+        node.previous_sibling = last_child;
+        match last_child {
+            Some(last_child) => self.nodes[last_child.0].next_sibling = Some(child),
+            None => self.nodes[parent.0].first_child = Some(child),
+        }
+    }
+
+    /// Prepends a new child to this node, before existing children.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `child` is `parent`
+    pub fn prepend(&mut self, parent: NodeId, child: NodeId) {
+        assert!(parent != child, "a node cannot be prepended to itself");
+        self.detach(child);
+        let first_child = self.nodes[parent.0].first_child.replace(child);
+        let node = &mut self.nodes[child.0];
+        node.parent = Some(parent);
+        node.next_sibling = first_child;
+        match first_child {
+            Some(first_child) => self.nodes[first_child.0].previous_sibling = Some(child),
+            None => self.nodes[parent.0].last_child = Some(child),
+        }
+    }
+
+    /// Inserts a new sibling after this node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `new_sibling` is `id`
+    pub fn insert_after(&mut self, id: NodeId, new_sibling: NodeId) {
+        assert!(id != new_sibling, "a node cannot be inserted after itself");
+        self.detach(new_sibling);
+        let parent = self.nodes[id.0].parent;
+        let next_sibling = self.nodes[id.0].next_sibling.replace(new_sibling);
+        let node = &mut self.nodes[new_sibling.0];
+        node.parent = parent;
+        node.previous_sibling = Some(id);
+        node.next_sibling = next_sibling;
+        match next_sibling {
+            Some(next) => self.nodes[next.0].previous_sibling = Some(new_sibling),
+            None => {
+                if let Some(parent) = parent {
+                    self.nodes[parent.0].last_child = Some(new_sibling);
+                }
+            }
+        }
+    }
+
+    /// Inserts a new sibling before this node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `new_sibling` is `id`
+    pub fn insert_before(&mut self, id: NodeId, new_sibling: NodeId) {
+        assert!(id != new_sibling, "a node cannot be inserted before itself");
+        self.detach(new_sibling);
+        let parent = self.nodes[id.0].parent;
+        let previous_sibling = self.nodes[id.0].previous_sibling.replace(new_sibling);
+        let node = &mut self.nodes[new_sibling.0];
+        node.parent = parent;
+        node.next_sibling = Some(id);
+        node.previous_sibling = previous_sibling;
+        match previous_sibling {
+            Some(previous) => self.nodes[previous.0].next_sibling = Some(new_sibling),
+            None => {
+                if let Some(parent) = parent {
+                    self.nodes[parent.0].first_child = Some(new_sibling);
+                }
+            }
+        }
+    }
+
+    /// Copy `node` and its descendants into the arena, returns the id of the copy of `node`
+    pub fn import(&mut self, node: &DomNode) -> NodeId {
+        let root = self.add(node.kind().clone());
+        let mut stack = vec![(node.clone(), root)];
+        while let Some((node, id)) = stack.pop() {
+            for child in node.children() {
+                let child_id = self.add(child.kind().clone());
+                self.append_child(id, child_id);
+                stack.push((child, child_id));
+            }
+        }
+        root
+    }
+
+    /// Copy `id` and its descendants into reference counted nodes
+    pub fn export(&self, id: NodeId) -> DomNode {
+        let root = DomNode::new(self.kind(id).clone());
+        let mut stack = vec![(id, root.clone())];
+        while let Some((id, node)) = stack.pop() {
+            for child in self.children(id) {
+                let child_node = DomNode::new(self.kind(child).clone());
+                node.append_child(child_node.clone());
+                stack.push((child, child_node));
+            }
+        }
+        root
+    }
+
+    pub fn sanitize(&mut self) {
+        DomBackend::sanitize(self)
+    }
+
+    /// Write the document as html or xhtml
+    pub fn serialize(&self, mode: OutputMode) -> String {
+        DomBackend::serialize(self, mode)
+    }
+}
+
+impl Default for ArenaDocument {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<&Document> for ArenaDocument {
+    fn from(document: &Document) -> Self {
+        let mut arena = ArenaDocument {
+            nodes: vec![],
+            root: NodeId(0),
+            head: NodeId(0),
+            body: NodeId(0),
+        };
+        arena.root = arena.import(&document.root());
+        // head and body are the first two elements of the root
+        let children: Vec<NodeId> = arena.children(arena.root).take(2).collect();
+        arena.head = children.first().copied().unwrap_or(arena.root);
+        arena.body = children.get(1).copied().unwrap_or(arena.root);
+        arena
+    }
+}
+
+impl From<&ArenaDocument> for Document {
+    fn from(arena: &ArenaDocument) -> Self {
+        let root = arena.export(arena.root);
+        // head and body are the first two elements of the root
+        let mut children = root.children();
+        let head = children.next().unwrap_or_else(|| root.clone());
+        let body = children.next().unwrap_or_else(|| root.clone());
+        Document::from_nodes(root, head, body)
+    }
+}
+
+// documents are built and serialized on worker threads
+const _: fn() = || {
+    fn send_sync<T: Send + Sync>() {}
+    send_sync::<ArenaDocument>();
+};
+
+impl DomTree for ArenaDocument {
+    type Node = NodeId;
+
+    fn with_kind<R>(&self, node: &NodeId, f: impl FnOnce(&DomNodeKind) -> R) -> R {
+        f(self.kind(*node))
+    }
+
+    fn with_kind_mut<R>(&mut self, node: &NodeId, f: impl FnOnce(&mut DomNodeKind) -> R) -> R {
+        f(self.kind_mut(*node))
+    }
+
+    fn parent(&self, node: &NodeId) -> Option<NodeId> {
+        ArenaDocument::parent(self, *node)
+    }
+
+    fn first_child(&self, node: &NodeId) -> Option<NodeId> {
+        ArenaDocument::first_child(self, *node)
+    }
+
+    fn next_sibling(&self, node: &NodeId) -> Option<NodeId> {
+        ArenaDocument::next_sibling(self, *node)
+    }
+
+    fn append_child(&mut self, parent: &NodeId, child: NodeId) {
+        ArenaDocument::append_child(self, *parent, child)
+    }
+
+    fn prepend(&mut self, parent: &NodeId, child: NodeId) {
+        ArenaDocument::prepend(self, *parent, child)
+    }
+
+    fn insert_before(&mut self, node: &NodeId, new_sibling: NodeId) {
+        ArenaDocument::insert_before(self, *node, new_sibling)
+    }
+
+    fn insert_after(&mut self, node: &NodeId, new_sibling: NodeId) {
+        ArenaDocument::insert_after(self, *node, new_sibling)
+    }
+
+    fn detach(&mut self, node: &NodeId) {
+        ArenaDocument::detach(self, *node)
+    }
+}
+
+impl DomBackend for ArenaDocument {
+    fn new() -> Self {
+        ArenaDocument::new()
+    }
+
+    fn root(&self) -> NodeId {
+        self.root
+    }
+
+    fn head(&self) -> NodeId {
+        self.head
+    }
+
+    fn body(&self) -> NodeId {
+        self.body
+    }
+
+    fn create_element(&mut self, tag: impl Into<Atom>, attributes: Attributes) -> NodeId {
+        self.create_element_with_attributes(tag, attributes)
+    }
+
+    fn create_text(&mut self, text: impl Into<String>) -> NodeId {
+        self.create_text_node(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::{to_attributes, ArenaDocument, Document, DomNode, OutputMode};
+
+    #[test]
+    fn test_same_as_document() {
+        let document = Document::new();
+        let p = DomNode::create_element_with_attributes("p", to_attributes([("id", "a")]));
+        p.append_child(DomNode::create_text("Tom & Jerry"));
+        document.body.append_child(DomNode::create_element("p"));
+        document.body.append_child(p);
+        document.body.prepend(DomNode::create_element("br"));
+
+        let mut arena = ArenaDocument::from(&document);
+        let div = arena.create_element("div");
+        let body = arena.body;
+        arena.insert_before(arena.first_child(body).unwrap(), div);
+        arena.detach(div);
+        let mut document = document;
+        document.sanitize();
+        arena.sanitize();
+
+        for mode in [OutputMode::Html, OutputMode::Xhtml] {
+            assert_eq!(arena.serialize(mode), document.serialize(mode));
+        }
+        assert_eq!(
+            arena.export(arena.root()).serialize(OutputMode::Html),
+            document.root().serialize(OutputMode::Html)
+        );
+    }
+
+    #[test]
+    fn test_build_on_threads() {
+        let pages: Vec<ArenaDocument> = thread::scope(|s| {
+            let workers: Vec<_> = (0..4)
+                .map(|i| {
+                    s.spawn(move || {
+                        let mut arena = ArenaDocument::new();
+                        let h1 = arena.create_element("h1");
+                        let text = arena.create_text_node(format!("Page {i}"));
+                        arena.append_child(h1, text);
+                        arena.append_child(arena.body, h1);
+                        arena
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+        let html: Vec<String> = thread::scope(|s| {
+            let workers: Vec<_> = pages
+                .iter()
+                .map(|page| s.spawn(|| page.serialize(OutputMode::Html)))
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+
+        for (i, (page, html)) in pages.iter().zip(html).enumerate() {
+            assert!(html.contains(&format!("<body><h1>Page {i}</h1></body>")));
+            let document = Document::from(page);
+            assert_eq!(document.serialize(OutputMode::Html), html);
+            assert_eq!(
+                document.body.serialize(OutputMode::Html),
+                format!("<body><h1>Page {i}</h1></body>")
+            );
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use std::iter;
+
+use crate::{
+    dom_node::escape_xml_text, is_raw_text_element, is_void_element, validate::validate_tree, Atom,
+    Attributes, Document, DomNode, DomNodeKind, OutputMode, ValidationError,
+};
+
+/// Navigating and changing the nodes of a dom tree, serializing, sanitizing and validating are
+/// written against this so they work the same for every backend
+pub trait DomTree {
+    /// Handle to a node, cheap to clone
+    type Node: Clone + PartialEq;
+
+    /// Call `f` with the kind of `node`
+    fn with_kind<R>(&self, node: &Self::Node, f: impl FnOnce(&DomNodeKind) -> R) -> R;
+    fn with_kind_mut<R>(&mut self, node: &Self::Node, f: impl FnOnce(&mut DomNodeKind) -> R) -> R;
+    fn parent(&self, node: &Self::Node) -> Option<Self::Node>;
+    fn first_child(&self, node: &Self::Node) -> Option<Self::Node>;
+    fn next_sibling(&self, node: &Self::Node) -> Option<Self::Node>;
+
+    /// Add `child` after the existing children of `parent`, detaching it first
+    fn append_child(&mut self, parent: &Self::Node, child: Self::Node);
+    /// Add `child` before the existing children of `parent`, detaching it first
+    fn prepend(&mut self, parent: &Self::Node, child: Self::Node);
+    fn insert_before(&mut self, node: &Self::Node, new_sibling: Self::Node);
+    fn insert_after(&mut self, node: &Self::Node, new_sibling: Self::Node);
+    /// Remove `node` from its parent and siblings, its children are kept
+    fn detach(&mut self, node: &Self::Node);
+
+    fn has_children(&self, node: &Self::Node) -> bool {
+        self.first_child(node).is_some()
+    }
+
+    fn children<'a>(
+        &'a self,
+        node: &Self::Node,
+    ) -> impl Iterator<Item = Self::Node> + use<'a, Self> {
+        iter::successors(self.first_child(node), move |c| self.next_sibling(c))
+    }
+
+    /// `node` and its ancestors
+    fn ancestors<'a>(
+        &'a self,
+        node: &Self::Node,
+    ) -> impl Iterator<Item = Self::Node> + use<'a, Self> {
+        iter::successors(Some(node.clone()), move |n| self.parent(n))
+    }
+
+    /// `node` and its descendants in tree order
+    fn descendants<'a>(
+        &'a self,
+        node: &Self::Node,
+    ) -> impl Iterator<Item = Self::Node> + use<'a, Self> {
+        let start = node.clone();
+        iter::successors(Some(node.clone()), move |n| {
+            if let Some(child) = self.first_child(n) {
+                return Some(child);
+            }
+            let mut n = n.clone();
+            loop {
+                if n == start {
+                    return None;
+                }
+                if let Some(sibling) = self.next_sibling(&n) {
+                    return Some(sibling);
+                }
+                n = self.parent(&n)?;
+            }
+        })
+    }
+
+    /// Tag of `node`, None for text
+    fn tag(&self, node: &Self::Node) -> Option<Atom> {
+        self.with_kind(node, |kind| match kind {
+            DomNodeKind::Element { tag, .. } => Some(tag.clone()),
+            DomNodeKind::Text { .. } | DomNodeKind::Comment { .. } => None,
+        })
+    }
+}
+
+/// Storage of a whole document, the renderer can build pages on any backend
+pub trait DomBackend: DomTree + Sized {
+    /// Empty document with a `<html>` root containing `<head>` and `<body>`
+    fn new() -> Self;
+    fn root(&self) -> Self::Node;
+    fn head(&self) -> Self::Node;
+    fn body(&self) -> Self::Node;
+    fn create_element(&mut self, tag: impl Into<Atom>, attributes: Attributes) -> Self::Node;
+    fn create_text(&mut self, text: impl Into<String>) -> Self::Node;
+
+    /// Remove empty tags or invalid html in a way that makes sense
+    fn sanitize(&mut self) {
+        let root = self.root();
+        sanitize(self, &root);
+    }
+
+    /// Check for misnested elements, duplicate ids and invalid attribute names
+    fn validate(&self) -> Vec<ValidationError> {
+        validate_tree(self, &self.root())
+    }
+
+    /// Write the document as html or xhtml
+    fn serialize(&self, mode: OutputMode) -> String {
+        let root = self.root();
+        let mut output = String::new();
+        serialize(self, &root, mode, &mut output);
+        match mode {
+            OutputMode::Html => format!(r#"<!DOCTYPE html>{output}"#),
+            OutputMode::Xhtml => {
+                let has_namespace = self.with_kind(&root, |kind| match kind {
+                    DomNodeKind::Element { attributes, .. } => attributes.contains_key("xmlns"),
+                    DomNodeKind::Text { .. } | DomNodeKind::Comment { .. } => false,
+                });
+                let root = match output.strip_prefix("<html") {
+                    Some(rest) if !has_namespace => {
+                        format!(r#"<html xmlns="http://www.w3.org/1999/xhtml"{rest}"#)
+                    }
+                    _ => output,
+                };
+                format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>{root}")
+            }
+        }
+    }
+}
+
+/// Rc nodes carry their own links, so navigating them doesn't need a document
+pub(crate) struct RcTree;
+
+impl DomTree for RcTree {
+    type Node = DomNode;
+
+    fn with_kind<R>(&self, node: &DomNode, f: impl FnOnce(&DomNodeKind) -> R) -> R {
+        f(&node.kind())
+    }
+
+    fn with_kind_mut<R>(&mut self, node: &DomNode, f: impl FnOnce(&mut DomNodeKind) -> R) -> R {
+        f(&mut node.kind_mut())
+    }
+
+    fn parent(&self, node: &DomNode) -> Option<DomNode> {
+        node.parent()
+    }
+
+    fn first_child(&self, node: &DomNode) -> Option<DomNode> {
+        node.first_child()
+    }
+
+    fn next_sibling(&self, node: &DomNode) -> Option<DomNode> {
+        node.next_sibling()
+    }
+
+    fn append_child(&mut self, parent: &DomNode, child: DomNode) {
+        parent.append_child(child)
+    }
+
+    fn prepend(&mut self, parent: &DomNode, child: DomNode) {
+        parent.prepend(child)
+    }
+
+    fn insert_before(&mut self, node: &DomNode, new_sibling: DomNode) {
+        node.insert_before(new_sibling)
+    }
+
+    fn insert_after(&mut self, node: &DomNode, new_sibling: DomNode) {
+        node.insert_after(new_sibling)
+    }
+
+    fn detach(&mut self, node: &DomNode) {
+        node.detach()
+    }
+}
+
+impl DomTree for Document {
+    type Node = DomNode;
+
+    fn with_kind<R>(&self, node: &DomNode, f: impl FnOnce(&DomNodeKind) -> R) -> R {
+        RcTree.with_kind(node, f)
+    }
+
+    fn with_kind_mut<R>(&mut self, node: &DomNode, f: impl FnOnce(&mut DomNodeKind) -> R) -> R {
+        RcTree.with_kind_mut(node, f)
+    }
+
+    fn parent(&self, node: &DomNode) -> Option<DomNode> {
+        node.parent()
+    }
+
+    fn first_child(&self, node: &DomNode) -> Option<DomNode> {
+        node.first_child()
+    }
+
+    fn next_sibling(&self, node: &DomNode) -> Option<DomNode> {
+        node.next_sibling()
+    }
+
+    fn append_child(&mut self, parent: &DomNode, child: DomNode) {
+        parent.append_child(child)
+    }
+
+    fn prepend(&mut self, parent: &DomNode, child: DomNode) {
+        parent.prepend(child)
+    }
+
+    fn insert_before(&mut self, node: &DomNode, new_sibling: DomNode) {
+        node.insert_before(new_sibling)
+    }
+
+    fn insert_after(&mut self, node: &DomNode, new_sibling: DomNode) {
+        node.insert_after(new_sibling)
+    }
+
+    fn detach(&mut self, node: &DomNode) {
+        node.detach()
+    }
+}
+
+impl DomBackend for Document {
+    fn new() -> Self {
+        Document::new()
+    }
+
+    fn root(&self) -> DomNode {
+        Document::root(self)
+    }
+
+    fn head(&self) -> DomNode {
+        self.head.clone()
+    }
+
+    fn body(&self) -> DomNode {
+        self.body.clone()
+    }
+
+    fn create_element(&mut self, tag: impl Into<Atom>, attributes: Attributes) -> DomNode {
+        DomNode::create_element_with_attributes(tag, attributes)
+    }
+
+    fn create_text(&mut self, text: impl Into<String>) -> DomNode {
+        DomNode::create_text(text)
+    }
+}
+
+/// Sanitize the descendants of `node` and then `node` itself
+pub(crate) fn sanitize<T: DomTree>(tree: &mut T, node: &T::Node) {
+    // in reverse tree order children are sanitized before their parent, without recursing
+    let nodes: Vec<T::Node> = tree.descendants(node).collect();
+    for node in nodes.iter().rev() {
+        sanitize_node(tree, node);
+    }
+}
+
+fn sanitize_node<T: DomTree>(tree: &mut T, node: &T::Node) {
+    let should_remove = tree.with_kind(node, |kind| match kind {
+        DomNodeKind::Text { text } => text.is_empty(),
+        // remove paragraph if no children or if text children are only whitespace
+        DomNodeKind::Element { tag, .. } if tag == "p" => tree.children(node).all(|c| {
+            tree.with_kind(&c, |kind| match kind {
+                DomNodeKind::Text { text } => text.chars().all(|c| c == ' '),
+                DomNodeKind::Comment { .. } => true,
+                DomNodeKind::Element { .. } => false,
+            })
+        }),
+        DomNodeKind::Element { .. } | DomNodeKind::Comment { .. } => false,
+    });
+    if should_remove {
+        tree.detach(node);
+        return;
+    }
+
+    tree.with_kind_mut(node, |kind| match kind {
+        // only single whitespace as text
+        DomNodeKind::Text { text } if text.chars().all(|c| c == ' ') => *text = " ".into(),
+        _ => {}
+    });
+}
+
+/// Write `node` and its children to `output`
+pub(crate) fn serialize<T: DomTree>(
+    tree: &T,
+    node: &T::Node,
+    mode: OutputMode,
+    output: &mut String,
+) {
+    // elements that are written up to their children, closed once all children are written
+    let mut open: Vec<(T::Node, Atom)> = vec![];
+    let mut next = Some(node.clone());
+    while let Some(current) = next.take() {
+        if let Some(tag) = serialize_start(tree, &current, mode, output) {
+            next = tree.first_child(&current);
+            open.push((current, tag));
+            continue;
+        }
+        // `current` is written, continue with its next sibling or close its parents
+        let mut done = current;
+        while done != *node {
+            if let Some(sibling) = tree.next_sibling(&done) {
+                next = Some(sibling);
+                break;
+            }
+            let Some((parent, tag)) = open.pop() else {
+                break;
+            };
+            output.push_str(&format!("</{tag}>"));
+            done = parent;
+        }
+    }
+}
+
+/// Write `node` without its children, returns the tag to close if it has children
+fn serialize_start<T: DomTree>(
+    tree: &T,
+    node: &T::Node,
+    mode: OutputMode,
+    output: &mut String,
+) -> Option<Atom> {
+    let xhtml = mode == OutputMode::Xhtml;
+    tree.with_kind(node, |kind| match kind {
+        DomNodeKind::Text { text } => {
+            let is_raw_text = tree
+                .parent(node)
+                .and_then(|p| tree.tag(&p))
+                .is_some_and(|tag| is_raw_text_element(&tag));
+            match is_raw_text {
+                // scripts and styles are not escaped, in xhtml they are wrapped in a commented out
+                // cdata section instead so they work both as xml and html
+                true if xhtml && text.contains(['<', '&']) && !text.contains("]]>") => {
+                    output.push_str("/*<![CDATA[*/");
+                    output.push_str(text);
+                    output.push_str("/*]]>*/");
+                }
+                true => output.push_str(text),
+                false => output.push_str(&escape_xml_text(text, false)),
+            }
+            None
+        }
+        DomNodeKind::Comment { text } => {
+            output.push_str(&format!("<!--{text}-->"));
+            None
+        }
+        DomNodeKind::Element { tag, attributes } => {
+            let tag = match xhtml {
+                true => Atom::from(tag.to_lowercase()),
+                false => tag.clone(),
+            };
+            output.push('<');
+            output.push_str(&tag);
+            for (k, v) in attributes {
+                output.push(' ');
+                if xhtml {
+                    output.push_str(&format!(
+                        r#"{}="{}""#,
+                        k.to_lowercase(),
+                        escape_xml_text(v, true)
+                    ));
+                } else if !v.is_empty() {
+                    output.push_str(&format!(r#"{k}="{}""#, escape_xml_text(v, true)));
+                } else {
+                    output.push_str(k);
+                }
+            }
+
+            if tree.has_children(node) {
+                output.push('>');
+                return Some(tag);
+            }
+            match is_void_element(&tag) {
+                true => output.push_str(if xhtml { " />" } else { "/>" }),
+                false => output.push_str(&format!("></{tag}>")),
+            }
+            None
+        }
+    })
+}
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, error::Error};
 
-use crate::Html;
+use crate::{Atom, Attributes, DomBackend, DomNodeKind, Html, OutputMode};
 
 use super::dom_node::DomNode;
 
@@ -12,6 +12,20 @@ pub struct Document {
     root: DomNode,
     pub head: DomNode,
     pub body: DomNode,
+    /// precedence of the elements added with [`Document::add_to_head`] by their key
+    head_precedence: HashMap<String, HeadPrecedence>,
+}
+
+/// Decides which of two head elements with the same key is kept, the highest one is kept and
+/// the first one added when they are equal
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HeadPrecedence {
+    /// Only added when nothing else is, like a default `og:type`
+    Fallback,
+    #[default]
+    Normal,
+    /// Replaces anything added before, like options set on a page
+    Override,
 }
 
 impl Document {
@@ -22,7 +36,7 @@ impl Document {
         let head = children.next().ok_or("head not found")?;
         let body = children.next().ok_or("body not found")?;
 
-        return Ok(Document { root, head, body });
+        return Ok(Document::from_nodes(root, head, body));
     }
 
     pub fn new() -> Document {
@@ -33,7 +47,16 @@ impl Document {
         root.append_child(head.clone());
         root.append_child(body.clone());
 
-        Document { root, head, body }
+        Document::from_nodes(root, head, body)
+    }
+
+    pub(crate) fn from_nodes(root: DomNode, head: DomNode, body: DomNode) -> Document {
+        Document {
+            root,
+            head,
+            body,
+            head_precedence: HashMap::new(),
+        }
     }
 
     pub fn root(&self) -> DomNode {
@@ -41,21 +64,48 @@ impl Document {
     }
 
     pub fn sanitize(&mut self) {
-        self.root.sanitize_children()
+        DomBackend::sanitize(self)
+    }
+
+    /// Add `node` to head unless an element with the same key (eg. `<meta name="description">`
+    /// or `<link rel="stylesheet" href="main.css">`) with a higher or equal `precedence` is
+    /// already there, replacing it otherwise. Elements are kept in a fixed order: charset,
+    /// http-equiv, viewport, title, other meta tags, links and then everything else.
+    pub fn add_to_head(&mut self, node: DomNode, precedence: HeadPrecedence) {
+        if let Some(key) = head_key(&node) {
+            if let Some(existing) = self
+                .head
+                .children()
+                .find(|c| head_key(c).as_ref() == Some(&key))
+            {
+                let current = self.head_precedence.get(&key).copied().unwrap_or_default();
+                if precedence <= current {
+                    return;
+                }
+                existing.detach();
+            }
+            self.head_precedence.insert(key, precedence);
+        }
+
+        let order = head_order(&node);
+        match self.head.children().find(|c| head_order(c) > order) {
+            Some(next) => next.insert_before(node),
+            None => self.head.append_child(node),
+        }
     }
 
     pub fn get_elements_by_tag_name(&self, tag: &str) -> Vec<DomNode> {
         self.root.get_elements_by_tag_name(tag)
     }
 
-    pub fn create_element(&self, tag: impl Into<String>) -> DomNode {
+    pub fn create_element(&self, tag: impl Into<Atom>) -> DomNode {
         DomNode::create_element(tag)
     }
 
     pub fn create_element_with_attributes(
         &self,
-        tag: impl Into<String>,
-        attributes: HashMap<String, String>,
+        tag: impl Into<Atom>,
+        attributes: Attributes,
     ) -> DomNode {
         DomNode::create_element_with_attributes(tag, attributes)
     }
@@ -63,17 +113,156 @@ impl Document {
     pub fn create_text_node(&self, text: impl Into<String>) -> DomNode {
         DomNode::create_text(text)
     }
+
+    /// Write the document as html or xhtml
+    pub fn serialize(&self, mode: OutputMode) -> String {
+        DomBackend::serialize(self, mode)
+    }
 }
 
 impl ToString for Document {
     fn to_string(&self) -> String {
-        format!(r#"<!DOCTYPE html>{}"#, self.root.to_string())
+        self.serialize(OutputMode::Html)
+    }
+}
+
+/// Meta properties that can be given multiple times, like the tags of an article
+fn is_repeatable_property(property: &str) -> bool {
+    matches!(
+        property,
+        "article:tag" | "article:author" | "og:locale:alternate" | "og:see_also"
+    )
+}
+
+/// What makes a head element unique, None if there can be any number of them
+fn head_key(node: &DomNode) -> Option<String> {
+    let DomNodeKind::Element { tag, attributes } = &*node.kind() else {
+        return None;
+    };
+    let attribute = |key: &str| attributes.get(key).map(|v| v.to_ascii_lowercase());
+    match tag.as_str() {
+        "title" | "base" => Some(tag.to_string()),
+        "meta" => {
+            if attributes.contains_key("charset") {
+                return Some("meta charset".into());
+            }
+            if let Some(name) = attribute("name") {
+                return Some(format!("meta name={name}"));
+            }
+            if let Some(equiv) = attribute("http-equiv") {
+                return Some(format!("meta http-equiv={equiv}"));
+            }
+            let property = attribute("property")?;
+            match is_repeatable_property(&property) {
+                true => Some(format!(
+                    "meta property={property} {}",
+                    attributes.get("content").map(String::as_str).unwrap_or("")
+                )),
+                false => Some(format!("meta property={property}")),
+            }
+        }
+        "link" => match (attribute("rel")?.as_str(), attributes.get("href")) {
+            ("canonical", _) => Some("link canonical".into()),
+            (rel, Some(href)) => Some(format!("link {rel} {href}")),
+            _ => None,
+        },
+        "script" => Some(format!("script {}", attributes.get("src")?)),
+        _ => None,
+    }
+}
+
+/// Position of an element in head relative to others
+fn head_order(node: &DomNode) -> u8 {
+    let DomNodeKind::Element { tag, attributes } = &*node.kind() else {
+        return 7;
+    };
+    match tag.as_str() {
+        // both only apply to what comes after them
+        "meta" if attributes.contains_key("charset") => 0,
+        "meta" if attributes.contains_key("http-equiv") => 1,
+        "meta" if attributes.get("name").is_some_and(|n| n == "viewport") => 2,
+        "title" => 3,
+        "base" => 4,
+        "meta" => 5,
+        "link" => 6,
+        _ => 7,
     }
 }
 
 /// Utility function to convert iteratables into attributes hashmap
-pub fn to_attributes<I: IntoIterator<Item = (impl Into<String>, impl Into<String>)>>(
+pub fn to_attributes<I: IntoIterator<Item = (impl Into<Atom>, impl Into<String>)>>(
     arr: I,
-) -> HashMap<String, String> {
+) -> Attributes {
     arr.into_iter().map(|(k, v)| (k.into(), v.into())).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{to_attributes, Document, DomNode, HeadPrecedence, OutputMode};
+
+    #[test]
+    fn test_serialize_xhtml() {
+        let document = Document::new();
+        document.body.append_child(DomNode::create_element("br"));
+        document
+            .body
+            .append_child(DomNode::create_element_with_attributes(
+                "INPUT",
+                to_attributes([("Checked", ""), ("value", "a \"b\" & c")]),
+            ));
+        document
+            .body
+            .append_child(DomNode::create_text("Tom & Jerry &amp; 1 < 2"));
+        let script = DomNode::create_element("script");
+        script.append_child(DomNode::create_text("if (a < b) {}"));
+        document.body.append_child(script);
+
+        assert_eq!(
+            document.serialize(OutputMode::Html),
+            r#"<!DOCTYPE html><html><head></head><body><br/><INPUT Checked value="a &quot;b&quot; &amp; c"></INPUT>Tom &amp; Jerry &amp; 1 &lt; 2<script>if (a < b) {}</script></body></html>"#
+        );
+        assert_eq!(
+            document.serialize(OutputMode::Xhtml),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html><html xmlns="http://www.w3.org/1999/xhtml"><head></head><body><br /><input checked="" value="a &quot;b&quot; &amp; c" />Tom &amp; Jerry &amp; 1 &lt; 2<script>/*<![CDATA[*/if (a < b) {}/*]]>*/</script></body></html>"#
+        );
+    }
+
+    #[test]
+    fn test_add_to_head() {
+        let mut document = Document::new();
+        let meta = |key: &str, name: &str, content: &str| {
+            DomNode::create_element_with_attributes(
+                "meta",
+                to_attributes([(key, name), ("content", content)]),
+            )
+        };
+        let stylesheet = DomNode::create_element_with_attributes(
+            "link",
+            to_attributes([("rel", "stylesheet"), ("href", "main.css")]),
+        );
+        document.add_to_head(stylesheet.clone(), HeadPrecedence::Normal);
+        document.add_to_head(
+            meta("property", "og:type", "website"),
+            HeadPrecedence::Fallback,
+        );
+        document.add_to_head(
+            meta("property", "og:type", "article"),
+            HeadPrecedence::Normal,
+        );
+        document.add_to_head(meta("name", "description", "a"), HeadPrecedence::Normal);
+        document.add_to_head(meta("name", "description", "b"), HeadPrecedence::Normal);
+        document.add_to_head(meta("property", "article:tag", "a"), HeadPrecedence::Normal);
+        document.add_to_head(meta("property", "article:tag", "b"), HeadPrecedence::Normal);
+        document.add_to_head(stylesheet, HeadPrecedence::Normal);
+        document.add_to_head(
+            DomNode::create_element_with_attributes("meta", to_attributes([("charset", "utf-8")])),
+            HeadPrecedence::Normal,
+        );
+
+        assert_eq!(
+            document.head.to_string(),
+            r#"<head><meta charset="utf-8"/><meta property="og:type" content="article"/><meta name="description" content="a"/><meta property="article:tag" content="a"/><meta property="article:tag" content="b"/><link rel="stylesheet" href="main.css"/></head>"#
+        );
+    }
+}
@@ -1,9 +1,9 @@
 use std::cell::{Ref, RefCell, RefMut};
-use std::collections::HashMap;
 use std::fmt;
 use std::rc::{Rc, Weak};
 
-use crate::{is_void_element, Html, IterableNodes};
+use crate::backend::{sanitize, serialize, RcTree};
+use crate::{add_class, has_class, remove_class, Atom, Attributes, Html, IterableNodes};
 
 /// Strong link
 type Link = Rc<RefCell<DomNodeData>>;
@@ -21,8 +21,12 @@ pub enum DomNodeKind {
         text: String,
     },
     Element {
-        tag: String,
-        attributes: HashMap<String, String>,
+        tag: Atom,
+        attributes: Attributes,
+    },
+    /// Only kept when parsing with [`crate::ParseOptions::preserve_comments`]
+    Comment {
+        text: String,
     },
 }
 
@@ -67,17 +71,14 @@ impl DomNode {
         })))
     }
 
-    pub fn create_element(tag: impl Into<String>) -> DomNode {
+    pub fn create_element(tag: impl Into<Atom>) -> DomNode {
         Self::new(DomNodeKind::Element {
             tag: tag.into(),
-            attributes: HashMap::new(),
+            attributes: Attributes::new(),
         })
     }
 
-    pub fn create_element_with_attributes(
-        tag: impl Into<String>,
-        attributes: HashMap<String, String>,
-    ) -> DomNode {
+    pub fn create_element_with_attributes(tag: impl Into<Atom>, attributes: Attributes) -> DomNode {
         Self::new(DomNodeKind::Element {
             tag: tag.into(),
             attributes,
@@ -88,9 +89,34 @@ impl DomNode {
         Self::new(DomNodeKind::Text { text: text.into() })
     }
 
-    pub fn set_attribute(&mut self, key: String, value: String) {
+    pub fn create_comment(text: impl Into<String>) -> DomNode {
+        Self::new(DomNodeKind::Comment { text: text.into() })
+    }
+
+    pub fn set_attribute(&mut self, key: impl Into<Atom>, value: String) {
+        if let DomNodeKind::Element { attributes, .. } = &mut *self.kind_mut() {
+            attributes.insert(key.into(), value);
+        }
+    }
+
+    /// Add the whitespace separated `classes` after the classes this element already has, see
+    /// [`crate::add_class`]
+    pub fn add_class(&self, classes: &str) {
         if let DomNodeKind::Element { attributes, .. } = &mut *self.kind_mut() {
-            attributes.insert(key, value);
+            add_class(attributes, classes);
+        }
+    }
+
+    pub fn remove_class(&self, class: &str) {
+        if let DomNodeKind::Element { attributes, .. } = &mut *self.kind_mut() {
+            remove_class(attributes, class);
+        }
+    }
+
+    pub fn has_class(&self, class: &str) -> bool {
+        match &*self.kind() {
+            DomNodeKind::Element { attributes, .. } => has_class(attributes, class),
+            DomNodeKind::Text { .. } | DomNodeKind::Comment { .. } => false,
         }
     }
 
@@ -214,35 +240,7 @@ impl DomNode {
 
     /// Remove empty tags or invalid html in a way that makes sense
     pub fn sanitize_children(&mut self) {
-        for mut c in self.children() {
-            c.sanitize_children();
-        }
-
-        // can't remove while borrowing in c.kind() so getting remove flag instead
-        let should_remove = match &*self.kind() {
-            DomNodeKind::Text { text } if text.len() == 0 => true,
-            // remove paragraph if no children
-            DomNodeKind::Element { tag, .. } if tag == "p" && self.first_child().is_none() => true,
-            // remove paragraph if text children are only whitespace
-            DomNodeKind::Element { tag, .. } if tag == "p" => self.children().all(|c| {
-                if let DomNodeKind::Text { text } = &*c.kind() {
-                    text.chars().all(|c| c == ' ')
-                } else {
-                    false
-                }
-            }),
-            _ => false,
-        };
-        if should_remove {
-            self.detach();
-            return;
-        }
-
-        match &mut *self.kind_mut() {
-            // only single whitespace as text
-            DomNodeKind::Text { text } if text.chars().all(|c| c == ' ') => *text = " ".into(),
-            _ => {}
-        };
+        sanitize(&mut RcTree, self);
     }
 
     pub fn get_elements_by_tag_name(&self, tag: &str) -> Vec<DomNode> {
@@ -423,65 +421,86 @@ impl DomNode {
     }
 
     pub fn from_html(html: Html) -> Option<Self> {
-        match html {
-            Html::Comment { .. } => None,
-            Html::Text { text } => Some(DomNode::create_text(text)),
-            Html::Element {
-                tag,
-                attributes,
-                children,
-            } => {
-                let node = DomNode::create_element_with_attributes(tag, attributes);
-                for c in children {
-                    if let Some(n) = DomNode::from_html(c) {
-                        node.append_child(n);
-                    }
+        fn create(html: Html, queue: &mut Vec<(Html, DomNode)>) -> Option<DomNode> {
+            match html {
+                Html::Comment { text } => Some(DomNode::create_comment(text)),
+                Html::Text { text } => Some(DomNode::create_text(text)),
+                Html::Element {
+                    tag,
+                    attributes,
+                    children,
+                } => {
+                    let node = DomNode::create_element_with_attributes(tag, attributes);
+                    // reversed so children are popped and appended in order
+                    queue.extend(children.into_iter().rev().map(|c| (c, node.clone())));
+                    Some(node)
                 }
-                Some(node)
             }
         }
+
+        let mut queue = vec![];
+        let root = create(html, &mut queue)?;
+        while let Some((html, parent)) = queue.pop() {
+            if let Some(node) = create(html, &mut queue) {
+                parent.append_child(node);
+            }
+        }
+        Some(root)
     }
 }
 
-impl ToString for DomNode {
-    fn to_string(&self) -> String {
-        match &*self.kind() {
-            DomNodeKind::Text { text } => text.to_string(),
-            DomNodeKind::Element { tag, attributes } => {
-                let attributes = attributes
-                    .into_iter()
-                    .map(|(k, v)| {
-                        if v.len() > 0 {
-                            format!(r#"{k}="{v}""#)
-                        } else {
-                            k.into()
-                        }
-                    })
-                    .collect::<Vec<String>>()
-                    .join(" ");
-
-                let spacing = if attributes.len() > 0 {
-                    String::from(" ")
-                } else {
-                    String::new()
-                };
+/// How a dom tree is written to text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    #[default]
+    Html,
+    /// Well formed xml as needed by EPUB and strict validators: void elements are written as
+    /// `<br />`, names are lowercase, attribute values are always quoted and bare `&` and `<` are
+    /// escaped
+    Xhtml,
+}
 
-                let children: Vec<DomNode> = self.children().collect();
-                if children.len() == 0 {
-                    if is_void_element(&tag) {
-                        return format!("<{tag}{spacing}{}/>", attributes);
-                    }
-                }
+/// Escape `<` and `&` that does not start a character reference, `"` too if `quote`
+pub(crate) fn escape_xml_text(text: &str, quote: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for (i, c) in text.char_indices() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '"' if quote => escaped.push_str("&quot;"),
+            '&' if !is_character_reference(&text[i..]) => escaped.push_str("&amp;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
 
-                let mut content = String::new();
+/// If `text` starts with a character reference (eg. "&amp;" or "&#39;")
+fn is_character_reference(text: &str) -> bool {
+    let Some(end) = text.find(';') else {
+        return false;
+    };
+    let name = &text[1..end];
+    match name.strip_prefix('#') {
+        Some(number) => match number.strip_prefix(['x', 'X']) {
+            Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+            None => !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()),
+        },
+        None => !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric()),
+    }
+}
 
-                for c in children {
-                    content += &c.to_string();
-                }
+impl DomNode {
+    /// Write the node and its children as html or xhtml
+    pub fn serialize(&self, mode: OutputMode) -> String {
+        let mut output = String::new();
+        serialize(&RcTree, self, mode, &mut output);
+        output
+    }
+}
 
-                format!("<{tag}{spacing}{}>{}</{tag}>", attributes, content)
-            }
-        }
+impl ToString for DomNode {
+    fn to_string(&self) -> String {
+        self.serialize(OutputMode::Html)
     }
 }
 
@@ -540,22 +559,21 @@ impl DomNodeData {
 
 impl Drop for DomNodeData {
     fn drop(&mut self) {
-        // Collect all descendant nodes and detach them to prevent the stack overflow.
-
-        let mut stack = Vec::new();
-        if let Some(first_child) = self.first_child.as_ref() {
-            // Create `Node` from `NodeData`.
-            let first_child = DomNode(first_child.clone());
-            // Iterate `self` children, without creating yet another `Node`.
-            for child1 in first_child.following_siblings() {
-                for child2 in child1.descendants() {
-                    stack.push(child2);
-                }
+        // Dropping a node drops its first child and next sibling, which would recurse as deep as
+        // the tree is deep and its sibling lists are long. Instead take the strong links of nodes
+        // that are only owned by the tree out before they are dropped so every drop is shallow.
+        let mut stack: Vec<Link> = Vec::new();
+        stack.extend(self.first_child.take());
+        stack.extend(self.next_sibling.take());
+        while let Some(link) = stack.pop() {
+            // nodes with other owners stay alive together with their children
+            if Rc::strong_count(&link) > 1 {
+                continue;
+            }
+            if let Ok(mut data) = link.try_borrow_mut() {
+                stack.extend(data.first_child.take());
+                stack.extend(data.next_sibling.take());
             }
-        }
-
-        for node in stack {
-            node.detach();
         }
     }
 }
@@ -799,3 +817,89 @@ impl DoubleEndedIterator for Traverse {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{to_attributes, ArenaDocument, Document, DomNode, DomNodeKind, OutputMode};
+
+    const DEPTH: usize = 100_000;
+
+    /// `<div>` nested `DEPTH` times in the body with a text at the bottom
+    fn deep_document() -> Document {
+        let document = Document::new();
+        let mut parent = document.body.clone();
+        for _ in 0..DEPTH {
+            let div = DomNode::create_element("div");
+            parent.append_child(div.clone());
+            parent = div;
+        }
+        parent.append_child(DomNode::create_text("bottom"));
+        document
+    }
+
+    #[test]
+    fn test_deep_tree() {
+        let mut document = deep_document();
+        assert_eq!(document.root().descendants().count(), DEPTH + 4);
+        document.sanitize();
+        assert!(document.validate().is_empty());
+
+        let html = document.serialize(OutputMode::Html);
+        assert!(html.contains("<div><div>bottom</div></div>"));
+        assert!(html.ends_with("</div></body></html>"));
+        assert_eq!(html.matches("<div>").count(), DEPTH);
+        assert_eq!(html.matches("</div>").count(), DEPTH);
+
+        let arena = ArenaDocument::from(&document);
+        assert_eq!(arena.serialize(OutputMode::Html), html);
+        drop(document);
+        assert_eq!(arena.export(arena.root()).descendants().count(), DEPTH + 4);
+    }
+
+    #[test]
+    fn test_drop_deep_and_wide_trees() {
+        drop(deep_document());
+
+        let list = DomNode::create_element("ul");
+        for _ in 0..DEPTH {
+            list.append_child(DomNode::create_element("li"));
+        }
+        drop(list);
+    }
+
+    #[test]
+    fn test_drop_keeps_referenced_nodes() {
+        let document = deep_document();
+        let bottom = document.body.descendants().last().unwrap();
+        let div = bottom.parent().unwrap();
+        drop(document);
+        assert!(div.parent().is_none());
+        assert_eq!(div.first_child(), Some(bottom.clone()));
+        assert!(matches!(&*bottom.kind(), DomNodeKind::Text { text } if text == "bottom"));
+    }
+
+    #[test]
+    fn test_class() {
+        let mut node = DomNode::create_element_with_attributes(
+            "p",
+            to_attributes([("id", "a"), ("class", "note")]),
+        );
+        node.add_class("wide note");
+        node.add_class("p-name");
+        node.set_attribute("title", "b".into());
+        assert!(node.has_class("wide"));
+        assert_eq!(
+            node.serialize(OutputMode::Html),
+            r#"<p id="a" class="note wide p-name" title="b"></p>"#
+        );
+
+        node.remove_class("note");
+        node.remove_class("wide");
+        node.remove_class("p-name");
+        assert!(!node.has_class("note"));
+        assert_eq!(
+            node.serialize(OutputMode::Html),
+            r#"<p id="a" title="b"></p>"#
+        );
+    }
+}
@@ -1,52 +1,123 @@
-use std::{collections::HashMap, io, io::Read};
+use std::{fmt, io, io::Read};
+
+use crate::{
+    entities::ENTITIES,
+    tokenizer::{Position, Token, Tokenizer},
+    validate::is_flow_only,
+    Atom, Attributes, DomNode,
+};
+
+/// How much of the input [`parse_html_with_options`] keeps, by default comments and text that
+/// is only whitespace are dropped and tags are matched as written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Keep comments, these become comment nodes when converted to a [`DomNode`]
+    pub preserve_comments: bool,
+    /// Keep text that is only whitespace, like the space in `<b>a</b> <i>b</i>`
+    pub preserve_whitespace: bool,
+    /// Match closing tags regardless of case (`<DIV></div>`), tag names and attribute keys are
+    /// lowercased
+    pub case_insensitive_tags: bool,
+}
 
-use char_reader::CharReader;
+/// Something in the input that isn't valid html, the parser recovers from it the way browsers
+/// do (closing unclosed elements, ignoring stray closing tags)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    pub message: String,
+    /// 1 indexed line of the tag causing the warning
+    pub line: usize,
+    /// 1 indexed column of the tag causing the warning
+    pub column: usize,
+}
 
-use crate::DomNode;
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
 
 // TODO: return DomNode directly instead of parsing to intermediary representation
 pub fn parse_html(input: impl Read) -> Result<Vec<Html>, io::Error> {
-    let mut reader = CharReader::new(input);
+    parse_html_with_options(input, ParseOptions::default())
+}
 
-    let mut tokens = vec![];
+pub fn parse_html_with_options(
+    input: impl Read,
+    options: ParseOptions,
+) -> Result<Vec<Html>, io::Error> {
+    parse_html_with_warnings(input, options).map(|(html, _)| html)
+}
 
-    loop {
-        match read_token(&mut reader)? {
-            None => break,
-            Some(t) => tokens.push(t),
-        }
-    }
+/// Parse html and return the warnings for mismatched, stray and unclosed tags
+pub fn parse_html_with_warnings(
+    input: impl Read,
+    options: ParseOptions,
+) -> Result<(Vec<Html>, Vec<ParseWarning>), io::Error> {
+    let mut builder = TreeBuilder::default();
+    let warnings = parse_html_events_with_options(input, options, |e| builder.push(e))?;
+    Ok((builder.root, warnings))
+}
 
-    // add texts together
-    let mut reduced_tokens = vec![];
-    for token in tokens.into_iter() {
-        if let Some(Html::Text { text: a }) = reduced_tokens.last_mut() {
-            if let Html::Text { text: b } = &token {
-                *a += b;
-                continue;
-            }
-        }
-        reduced_tokens.push(token)
-    }
+/// Parse html calling `handler` for every element, text and comment as they are read instead of
+/// building the whole tree, returns the warnings for mismatched, stray and unclosed tags
+///
+/// ```
+/// use virtual_dom::{parse_html_events, HtmlEvent};
+///
+/// let mut links = vec![];
+/// parse_html_events(r#"<p><a href="/a">a</a> <a href="/b">b</a></p>"#.as_bytes(), |e| {
+///     if let HtmlEvent::StartElement { tag, attributes } = e {
+///         if let Some(href) = attributes.get("href").filter(|_| tag == "a") {
+///             links.push(href.clone());
+///         }
+///     }
+/// })
+/// .unwrap();
+/// assert_eq!(links, vec!["/a", "/b"]);
+/// ```
+pub fn parse_html_events(
+    input: impl Read,
+    handler: impl FnMut(HtmlEvent),
+) -> Result<Vec<ParseWarning>, io::Error> {
+    parse_html_events_with_options(input, ParseOptions::default(), handler)
+}
 
-    Ok(reduced_tokens)
+pub fn parse_html_events_with_options(
+    input: impl Read,
+    options: ParseOptions,
+    handler: impl FnMut(HtmlEvent),
+) -> Result<Vec<ParseWarning>, io::Error> {
+    let mut tokenizer = Tokenizer::new(input, options);
+    let mut parser = Parser::new(options, handler);
+    while let Some((token, position)) = tokenizer.next()? {
+        parser.push(token, position);
+    }
+    Ok(parser.finish())
 }
 
-fn attributes(start_tag_content: &str) -> Result<HashMap<String, String>, io::Error> {
+pub(crate) fn attributes(
+    start_tag_content: &str,
+    options: ParseOptions,
+) -> Result<Attributes, io::Error> {
     // remove whitespace before and after text
     let start_tag_content = start_tag_content.trim();
     let chars: Vec<char> = start_tag_content.chars().collect();
-    let mut attributes = HashMap::new();
+    let mut attributes = Attributes::new();
     let mut key = String::new();
     let mut value = String::new();
     let mut in_value = false;
     let mut i = 0;
     while i < chars.len() {
         match chars[i] {
-            ' ' if in_value == false => {
+            // attributes can be separated by any whitespace, including newlines
+            c if c.is_whitespace() && in_value == false => {
+                if options.case_insensitive_tags {
+                    key.make_ascii_lowercase();
+                }
                 if key.len() > 0 {
-                    attributes.insert(key, value);
-                    key = String::new();
+                    attributes.insert(Atom::from(&key), decode_entities(&value));
+                    key.clear();
                     value = String::new();
                     in_value = false;
                 }
@@ -69,68 +140,68 @@ fn attributes(start_tag_content: &str) -> Result<HashMap<String, String>, io::Er
         }
         i += 1;
     }
+    if options.case_insensitive_tags {
+        key.make_ascii_lowercase();
+    }
     if key.len() > 0 {
-        attributes.insert(key, value);
+        attributes.insert(Atom::from(key), decode_entities(&value));
     }
 
     Ok(attributes)
 }
 
-/// parse html from start to end and return (tag, attributes, innerHtml)
-///
-/// seperated to make logic more reusable
-fn element(
-    reader: &mut CharReader<impl Read>,
-) -> Result<Option<(String, HashMap<String, String>, Option<String>)>, io::Error> {
-    if let Some('<') = reader.peek_char(0)? {
-        if let Some(start_tag) = reader.peek_until_exclusive_from(1, |c| c == '>')? {
-            // get html tag
-            let mut tag = String::new();
-            for c in start_tag.chars() {
-                match c {
-                    ' ' => break,
-                    '\n' => break,
-                    _ => tag.push(c),
-                }
-            }
-
-            if start_tag.ends_with("/") && is_void_element(&tag) {
-                // <{start_tag}/>
-                reader.consume(start_tag.len() + 2)?;
-                let attributes = attributes(&start_tag[tag.len()..start_tag.len() - 1])?;
-                return Ok(Some((tag, attributes, None)));
-            }
-
-            let end_tag = format!("</{tag}>");
-            if let Some(html_block) =
-                reader.peek_until_match_exclusive_from(2 + start_tag.len(), &end_tag)?
-            {
-                // <{start_tag}>
-                reader.consume(start_tag.len() + 2)?;
-
-                let attributes = attributes(&start_tag[tag.len()..start_tag.len()])?;
-
-                let content = reader.consume_string(html_block.len())?;
-                reader.consume(end_tag.len())?;
-
-                return Ok(Some((tag, attributes, Some(content))));
-            }
-        }
+/// Replace character references (eg. `&amp;`, `&#39;` or `&#x27;`) with the characters they stand
+/// for, unknown references are kept as written
+pub fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some((reference, characters)) = character_reference(rest) else {
+            decoded.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        decoded.push_str(&characters);
+        rest = &rest[reference.len()..];
     }
-    Ok(None)
+    decoded.push_str(rest);
+    decoded
 }
 
-fn comment(reader: &mut CharReader<impl Read>) -> Result<Option<Html>, io::Error> {
-    if "<!--" == reader.peek_string(4)? {
-        if let Some(text) = reader.peek_until_match_exclusive_from(4, "-->")? {
-            reader.consume(4)?; // skip start
-            let text = reader.consume_string(text.len())?;
-            reader.consume(3)?; // skip end
-            return Ok(Some(Html::Comment { text }));
+/// If `text` starts with a character reference returns the reference and the characters it
+/// stands for
+fn character_reference(text: &str) -> Option<(&str, String)> {
+    // the longest named reference is 32 characters
+    let end = text.get(1..34).unwrap_or(&text[1..]).find(';')? + 1;
+    let name = &text[1..end];
+    let characters = match name.strip_prefix('#') {
+        Some(number) => {
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) if hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+                    u32::from_str_radix(hex, 16).ok()?
+                }
+                None if number.chars().all(|c| c.is_ascii_digit()) => number.parse().ok()?,
+                _ => return None,
+            };
+            // null and invalid code points are replaced, like browsers do
+            let c = char::from_u32(code)
+                .filter(|c| *c != '\0')
+                .unwrap_or(char::REPLACEMENT_CHARACTER);
+            c.to_string()
         }
-    }
+        None => {
+            let i = ENTITIES.binary_search_by_key(&name, |(n, _)| n).ok()?;
+            ENTITIES[i].1.to_string()
+        }
+    };
+    Some((&text[..=end], characters))
+}
 
-    Ok(None)
+/// Check if the content of `tag` is raw text that isn't parsed as html, like a `<script>`
+pub fn is_raw_text_element(tag: &str) -> bool {
+    tag.eq_ignore_ascii_case("script") || tag.eq_ignore_ascii_case("style")
 }
 
 /// check if a html tag is a void tag (it can not have children)
@@ -142,47 +213,225 @@ pub fn is_void_element(tag: &str) -> bool {
     }
 }
 
-/// A "simple" streaming html parser function. This is a fairly simplified way of parsing html
-/// ignoring a lot of edge cases and validation normally seen when parsing html.
-///
-/// **NOTE: Might return multiple Text tokens one after another.**
-fn read_token(reader: &mut CharReader<impl Read>) -> Result<Option<Html>, io::Error> {
-    while let Some(c) = reader.peek_char(0)? {
-        if c == '<' {
-            if let Some(comment) = comment(reader)? {
-                return Ok(Some(comment));
-            }
+/// Check if the closing tag of `tag` may be left out, like `</li>` or `</p>`
+fn is_end_tag_optional(tag: &str) -> bool {
+    matches!(
+        tag,
+        "p" | "li"
+            | "dt"
+            | "dd"
+            | "option"
+            | "optgroup"
+            | "tr"
+            | "td"
+            | "th"
+            | "thead"
+            | "tbody"
+            | "tfoot"
+            | "colgroup"
+            | "caption"
+            | "rp"
+            | "rt"
+            | "html"
+            | "head"
+            | "body"
+    )
+}
 
-            if let Some((tag, attributes, content)) = element(reader)? {
-                let mut children = vec![];
-                if let Some(content) = content {
-                    let mut reader = CharReader::new(content.as_bytes());
-                    while let Some(html) = read_token(&mut reader)? {
-                        children.push(html);
+/// Open elements closed by the start tag of `tag` and the elements they can't be closed across,
+/// eg. a `<li>` closes the previous `<li>` of the same list
+fn implied_end_tags(tag: &str) -> Option<(&'static [&'static str], &'static [&'static str])> {
+    match tag {
+        "li" => Some((&["li"], &["ul", "ol", "menu"])),
+        "dt" | "dd" => Some((&["dt", "dd"], &["dl"])),
+        "option" => Some((&["option"], &["select", "datalist", "optgroup"])),
+        "tr" => Some((&["tr"], &["table", "thead", "tbody", "tfoot"])),
+        "td" | "th" => Some((&["td", "th"], &["tr", "table"])),
+        _ => None,
+    }
+}
+
+/// A step in parsing html, every [`HtmlEvent::StartElement`] is followed by a matching
+/// [`HtmlEvent::EndElement`] even when the input isn't nested correctly
+#[derive(Debug, Clone, PartialEq)]
+pub enum HtmlEvent {
+    StartElement {
+        tag: Atom,
+        attributes: Attributes,
+    },
+    EndElement {
+        tag: Atom,
+    },
+    /// Text with decoded character references, the content of a script or style is kept as is
+    ///
+    /// **NOTE: Might be given multiple times one after another.**
+    Text {
+        text: String,
+    },
+    Comment {
+        text: String,
+    },
+}
+
+/// Nests tokens into events, loosely following the html5 tree construction: elements that
+/// aren't closed are closed by their parent and closing tags without an open element are
+/// ignored
+struct Parser<F: FnMut(HtmlEvent)> {
+    options: ParseOptions,
+    /// open elements with the position of their start tag
+    open: Vec<(Atom, Position)>,
+    warnings: Vec<ParseWarning>,
+    handler: F,
+}
+
+impl<F: FnMut(HtmlEvent)> Parser<F> {
+    fn new(options: ParseOptions, handler: F) -> Parser<F> {
+        Parser {
+            options,
+            open: vec![],
+            warnings: vec![],
+            handler,
+        }
+    }
+
+    fn warn(&mut self, message: String, position: Position) {
+        self.warnings.push(ParseWarning {
+            message,
+            line: position.line,
+            column: position.column,
+        })
+    }
+
+    fn push(&mut self, token: Token, position: Position) {
+        match token {
+            Token::Comment(text) => {
+                if self.options.preserve_comments {
+                    (self.handler)(HtmlEvent::Comment { text })
+                }
+            }
+            Token::RawText(text) => (self.handler)(HtmlEvent::Text { text }),
+            Token::Text(text) => {
+                // only valid text if it contains a non whitespace character
+                if self.options.preserve_whitespace || text.chars().any(|c| c != ' ' && c != '\n') {
+                    let text = decode_entities(&text);
+                    (self.handler)(HtmlEvent::Text { text })
+                }
+            }
+            Token::StartTag {
+                tag,
+                attributes,
+                self_closing,
+            } => {
+                if let Some((closes, scope)) = implied_end_tags(&tag) {
+                    let open = self
+                        .open
+                        .iter()
+                        .rposition(|(t, _)| closes.contains(&&**t) || scope.contains(&&**t));
+                    if let Some(i) = open.filter(|i| closes.contains(&&*self.open[*i].0)) {
+                        self.close(i);
                     }
+                } else if self.open.last().is_some_and(|(t, _)| t == "p") && is_flow_only(&tag) {
+                    self.close(self.open.len() - 1);
                 }
-                return Ok(Some(Html::Element {
-                    tag,
+
+                (self.handler)(HtmlEvent::StartElement {
+                    tag: tag.clone(),
                     attributes,
-                    children,
-                }));
+                });
+                if self_closing || is_void_element(&tag) {
+                    (self.handler)(HtmlEvent::EndElement { tag });
+                } else {
+                    self.open.push((tag, position));
+                }
             }
+            Token::EndTag { tag } => match self.open.iter().rposition(|(t, _)| {
+                // raw text ends at the closing tag in any case
+                *t == tag || (is_raw_text_element(&tag) && t.eq_ignore_ascii_case(&tag))
+            }) {
+                Some(i) => {
+                    let unclosed: Vec<String> = self.open[i + 1..]
+                        .iter()
+                        .filter(|(t, _)| !is_end_tag_optional(t))
+                        .map(|(t, _)| format!("<{t}> closed by </{tag}>"))
+                        .collect();
+                    for message in unclosed {
+                        self.warn(message, position);
+                    }
+                    self.close(i);
+                }
+                None if is_void_element(&tag) => self.warn(
+                    format!("<{tag}> is a void element and can't have children or a closing tag"),
+                    position,
+                ),
+                None => self.warn(format!("stray closing tag </{tag}> ignored"), position),
+            },
+        }
+    }
 
-            // non html opening
-            reader.consume(1)?;
-            let mut text = "<".to_string();
-            text.push_str(&reader.consume_until_exclusive(|c| c == '<')?);
-            return Ok(Some(Html::Text { text }));
+    /// Close the open element at `index` and everything opened after it
+    fn close(&mut self, index: usize) {
+        while self.open.len() > index {
+            let (tag, _) = self.open.pop().expect("open element");
+            (self.handler)(HtmlEvent::EndElement { tag });
         }
+    }
 
-        let text = reader.consume_until_exclusive(|c| c == '<')?;
-        // only valid text if it contains a non whitespace character
-        if text.chars().any(|c| c != ' ' && c != '\n') {
-            return Ok(Some(Html::Text { text }));
+    fn finish(mut self) -> Vec<ParseWarning> {
+        for (tag, position) in &self.open {
+            if !is_end_tag_optional(tag) {
+                self.warnings.push(ParseWarning {
+                    message: format!("<{tag}> is never closed"),
+                    line: position.line,
+                    column: position.column,
+                });
+            }
         }
+        self.close(0);
+        self.warnings
     }
+}
 
-    Ok(None)
+/// Builds [`Html`] from events
+#[derive(Default)]
+struct TreeBuilder {
+    root: Vec<Html>,
+    /// open elements as (tag, attributes, children)
+    open: Vec<(Atom, Attributes, Vec<Html>)>,
+}
+
+impl TreeBuilder {
+    fn children(&mut self) -> &mut Vec<Html> {
+        match self.open.last_mut() {
+            Some((_, _, children)) => children,
+            None => &mut self.root,
+        }
+    }
+
+    fn push(&mut self, event: HtmlEvent) {
+        match event {
+            HtmlEvent::StartElement { tag, attributes } => {
+                self.open.push((tag, attributes, vec![]))
+            }
+            HtmlEvent::EndElement { .. } => {
+                let (tag, attributes, children) = self.open.pop().expect("open element");
+                self.children().push(Html::Element {
+                    tag,
+                    attributes,
+                    children,
+                });
+            }
+            HtmlEvent::Text { text } => {
+                let children = self.children();
+                // add texts together
+                if let Some(Html::Text { text: previous }) = children.last_mut() {
+                    previous.push_str(&text);
+                    return;
+                }
+                children.push(Html::Text { text })
+            }
+            HtmlEvent::Comment { text } => self.children().push(Html::Comment { text }),
+        }
+    }
 }
 
 /// Simple parsed html representation with recursively added children
@@ -195,8 +444,8 @@ pub enum Html {
         text: String,
     },
     Element {
-        tag: String,
-        attributes: HashMap<String, String>,
+        tag: Atom,
+        attributes: Attributes,
         children: Vec<Html>,
     },
 }
@@ -205,6 +454,7 @@ impl From<DomNode> for Html {
     fn from(value: DomNode) -> Self {
         match &*value.kind() {
             crate::DomNodeKind::Text { text } => Html::Text { text: text.clone() },
+            crate::DomNodeKind::Comment { text } => Html::Comment { text: text.clone() },
             crate::DomNodeKind::Element { tag, attributes } => {
                 let children = value.children().into_iter().map(|c| c.into()).collect();
                 Html::Element {
@@ -222,9 +472,9 @@ mod tests {
     use super::*;
 
     /// Utility function to convert iteratables into attributes hashmap
-    pub fn to_attributes<I: IntoIterator<Item = (impl Into<String>, impl Into<String>)>>(
+    pub fn to_attributes<I: IntoIterator<Item = (impl Into<Atom>, impl Into<String>)>>(
         arr: I,
-    ) -> HashMap<String, String> {
+    ) -> Attributes {
         arr.into_iter().map(|(k, v)| (k.into(), v.into())).collect()
     }
 
@@ -262,7 +512,7 @@ mod tests {
 </div>"#;
         let expected = vec![Html::Element {
             tag: "div".into(),
-            attributes: HashMap::new(),
+            attributes: Attributes::new(),
             children: vec![Html::Element {
                 tag: "a".into(),
                 attributes: to_attributes([("href", "link.com")]),
@@ -296,4 +546,160 @@ This should be text
         let tokens = parse_html(input.as_bytes()).unwrap();
         assert_eq!(expected, tokens);
     }
+
+    #[test]
+    fn test_parse_options() {
+        let input = "<DIV Class=\"a\"><!-- note --><b>a</b> <i>b</i></div>";
+        let b = |tag: &str, t: &str| Html::Element {
+            tag: tag.into(),
+            attributes: Attributes::new(),
+            children: vec![Html::Text { text: t.into() }],
+        };
+
+        // `</div>` doesn't close `<DIV>` so it's ignored
+        let (tokens, warnings) =
+            parse_html_with_warnings(input.as_bytes(), ParseOptions::default()).unwrap();
+        assert!(matches!(&tokens[0], Html::Element { tag, .. } if tag == "DIV"));
+        assert_eq!(warnings[0].message, "stray closing tag </div> ignored");
+
+        let options = ParseOptions {
+            preserve_comments: true,
+            preserve_whitespace: true,
+            case_insensitive_tags: true,
+        };
+        let tokens = parse_html_with_options(input.as_bytes(), options).unwrap();
+        let expected = vec![Html::Element {
+            tag: "div".into(),
+            attributes: to_attributes([("class", "a")]),
+            children: vec![
+                Html::Comment {
+                    text: " note ".into(),
+                },
+                b("b", "a"),
+                Html::Text { text: " ".into() },
+                b("i", "b"),
+            ],
+        }];
+        assert_eq!(expected, tokens);
+
+        let node = DomNode::from_html(tokens.into_iter().next().unwrap()).unwrap();
+        assert_eq!(
+            node.to_string(),
+            r#"<div class="a"><!-- note --><b>a</b> <i>b</i></div>"#
+        );
+    }
+
+    #[test]
+    fn test_raw_text() {
+        let script = r#"if (a < b && "<div>" != '</p>') { x = "<!-- no comment -->" }"#;
+        let input = format!("<script>{script}</SCRIPT><style>a > b {{ }}</style>");
+        let expected = vec![
+            Html::Element {
+                tag: "script".into(),
+                attributes: Attributes::new(),
+                children: vec![Html::Text {
+                    text: script.into(),
+                }],
+            },
+            Html::Element {
+                tag: "style".into(),
+                attributes: Attributes::new(),
+                children: vec![Html::Text {
+                    text: "a > b { }".into(),
+                }],
+            },
+        ];
+        let tokens = parse_html(input.as_bytes()).unwrap();
+        assert_eq!(expected, tokens);
+    }
+
+    #[test]
+    fn test_recovery() {
+        let el = |tag: &str, children: Vec<Html>| Html::Element {
+            tag: tag.into(),
+            attributes: Attributes::new(),
+            children,
+        };
+        let text = |text: &str| Html::Text { text: text.into() };
+
+        let input =
+            "<p><b>text</i> more</p>\n</span><ul><li>a<li>b</ul>\n<div><div>c</div>d</div><em>e";
+        let (tokens, warnings) =
+            parse_html_with_warnings(input.as_bytes(), ParseOptions::default()).unwrap();
+        let expected = vec![
+            el("p", vec![el("b", vec![text("text more")])]),
+            el(
+                "ul",
+                vec![el("li", vec![text("a")]), el("li", vec![text("b")])],
+            ),
+            el("div", vec![el("div", vec![text("c")]), text("d")]),
+            el("em", vec![text("e")]),
+        ];
+        assert_eq!(expected, tokens);
+        let warnings: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "1:11: stray closing tag </i> ignored",
+                "1:20: <b> closed by </p>",
+                "2:1: stray closing tag </span> ignored",
+                "3:25: <em> is never closed",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_events() {
+        let mut events = vec![];
+        let warnings =
+            parse_html_events("<ul><li>a<br>b</i></ul>".as_bytes(), |e| events.push(e)).unwrap();
+        let start = |tag: &str| HtmlEvent::StartElement {
+            tag: tag.into(),
+            attributes: Attributes::new(),
+        };
+        let end = |tag: &str| HtmlEvent::EndElement { tag: tag.into() };
+        let text = |text: &str| HtmlEvent::Text { text: text.into() };
+        assert_eq!(
+            events,
+            vec![
+                start("ul"),
+                start("li"),
+                text("a"),
+                start("br"),
+                end("br"),
+                text("b"),
+                end("li"),
+                end("ul"),
+            ]
+        );
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_entities() {
+        assert_eq!(
+            decode_entities(
+                "&lt;a&gt; &amp;amp; &#x27;&#39; &copy;&nbsp;&NotEqualTilde; &unknown; & &#xD800;"
+            ),
+            "<a> &amp; '' \u{a9}\u{a0}\u{2242}\u{338} &unknown; & \u{fffd}"
+        );
+
+        let input = r#"<p title="&quot;quoted&quot; &amp; more">1 &lt; 2 &amp;&amp; 3 &gt; 2</p>"#;
+        let expected = vec![Html::Element {
+            tag: "p".into(),
+            attributes: to_attributes([("title", "\"quoted\" & more")]),
+            children: vec![Html::Text {
+                text: "1 < 2 && 3 > 2".into(),
+            }],
+        }];
+        let tokens = parse_html(input.as_bytes()).unwrap();
+        assert_eq!(expected, tokens);
+
+        // escaped again when written
+        let node = DomNode::from_html(tokens.into_iter().next().unwrap()).unwrap();
+        assert_eq!(
+            node.to_string(),
+            r#"<p title="&quot;quoted&quot; &amp; more">1 &lt; 2 &amp;&amp; 3 > 2</p>"#
+        );
+    }
 }
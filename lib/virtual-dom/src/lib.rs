@@ -1,13 +1,69 @@
+mod arena;
+mod backend;
 mod document;
 mod dom_node;
+mod entities;
 mod html;
+mod tokenizer;
+mod validate;
 
-use std::{collections::VecDeque, iter};
+use std::iter;
 
+pub use arena::{ArenaDocument, NodeId};
+pub use backend::{DomBackend, DomTree};
 pub use document::*;
 pub use dom_node::*;
-use html::*;
-pub use html::{parse_html, Html};
+pub use html::{
+    decode_entities, is_raw_text_element, is_void_element, parse_html, parse_html_events,
+    parse_html_events_with_options, parse_html_with_options, parse_html_with_warnings, Html,
+    HtmlEvent, ParseOptions, ParseWarning,
+};
+pub use validate::{is_known_element, ValidationError};
+
+/// Tag names and attribute keys, these are short so they are stored inline instead of allocating
+pub type Atom = smol_str::SmolStr;
+
+/// Attributes of an element, keeps insertion order so rendered html is deterministic
+pub type Attributes = indexmap::IndexMap<Atom, String>;
+
+/// Add the whitespace separated `classes` to the class attribute, after the classes it already
+/// has and skipping the ones it already has
+pub fn add_class(attributes: &mut Attributes, classes: &str) {
+    let class = attributes.entry("class".into()).or_default();
+    for c in classes.split_whitespace() {
+        if class.split_whitespace().any(|existing| existing == c) {
+            continue;
+        }
+        if !class.is_empty() {
+            class.push(' ');
+        }
+        class.push_str(c);
+    }
+    if class.is_empty() {
+        attributes.shift_remove("class");
+    }
+}
+
+/// Remove `class` from the class attribute, the attribute is removed when no classes are left
+pub fn remove_class(attributes: &mut Attributes, class: &str) {
+    let Some(classes) = attributes.get_mut("class") else {
+        return;
+    };
+    *classes = classes
+        .split_whitespace()
+        .filter(|c| *c != class)
+        .collect::<Vec<&str>>()
+        .join(" ");
+    if classes.is_empty() {
+        attributes.shift_remove("class");
+    }
+}
+
+pub fn has_class(attributes: &Attributes, class: &str) -> bool {
+    attributes
+        .get("class")
+        .is_some_and(|classes| classes.split_whitespace().any(|c| c == class))
+}
 
 /// Used in dom-proc for converting braced variables into domnode and to allow any kind of dom element to be appended
 #[derive(Debug, PartialEq)]
@@ -50,44 +106,7 @@ impl FromIterator<DomNode> for IterableNodes {
 
 impl FromIterator<Html> for IterableNodes {
     fn from_iter<T: IntoIterator<Item = Html>>(iter: T) -> Self {
-        iter.into_iter()
-            .map(|value| match value {
-                Html::Comment { .. } => panic!("root html can't be comment"),
-                Html::Text { text } => DomNode::create_text(text),
-                Html::Element {
-                    tag,
-                    attributes,
-                    children,
-                } => {
-                    let root = DomNode::create_element_with_attributes(tag, attributes);
-                    let mut queue: VecDeque<(Html, DomNode)> = VecDeque::from(
-                        children
-                            .into_iter()
-                            .zip(std::iter::repeat(root.clone()))
-                            .collect::<Vec<(Html, DomNode)>>(),
-                    );
-                    while let Some((c, parent)) = queue.pop_front() {
-                        if let Some(p) = match c {
-                            Html::Text { text } => Some(DomNode::create_text(text)),
-                            Html::Element {
-                                tag,
-                                attributes,
-                                children,
-                            } => {
-                                let p = DomNode::create_element_with_attributes(tag, attributes);
-                                queue
-                                    .extend(children.into_iter().zip(std::iter::repeat(p.clone())));
-                                Some(p)
-                            }
-                            _ => None,
-                        } {
-                            parent.append_child(p)
-                        }
-                    }
-                    root
-                }
-            })
-            .collect()
+        iter.into_iter().filter_map(DomNode::from_html).collect()
     }
 }
 
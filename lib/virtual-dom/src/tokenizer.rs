@@ -0,0 +1,228 @@
+use std::io::{self, Read};
+
+use char_reader::CharReader;
+
+use crate::{html::attributes, is_known_element, is_raw_text_element, is_void_element};
+use crate::{Atom, Attributes, ParseOptions};
+
+/// Line and column (both 1 indexed) of the start of a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    StartTag {
+        tag: Atom,
+        attributes: Attributes,
+        /// written as `<tag />`
+        self_closing: bool,
+    },
+    EndTag {
+        tag: Atom,
+    },
+    Text(String),
+    /// Content of a script or style, it's not decoded
+    RawText(String),
+    Comment(String),
+}
+
+/// Splits html into tags and text without checking if they are nested correctly
+pub(crate) struct Tokenizer<R: Read> {
+    reader: CharReader<R>,
+    options: ParseOptions,
+    position: Position,
+    /// set after the start tag of a raw text element, its content is read up to the closing tag
+    raw_text: Option<Atom>,
+}
+
+impl<R: Read> Tokenizer<R> {
+    pub fn new(input: R, options: ParseOptions) -> Tokenizer<R> {
+        Tokenizer {
+            reader: CharReader::new(input),
+            options,
+            position: Position { line: 1, column: 1 },
+            raw_text: None,
+        }
+    }
+
+    pub fn next(&mut self) -> Result<Option<(Token, Position)>, io::Error> {
+        let position = self.position;
+        if let Some(tag) = self.raw_text.take() {
+            // raw text ends at the first closing tag in any case, whatever comes before it
+            let length = match self.find(0, &format!("</{tag}"), true)? {
+                Some(length) => length,
+                None => self.find(0, "", false)?.unwrap_or(0),
+            };
+            if length > 0 {
+                return Ok(Some((Token::RawText(self.consume(length)?), position)));
+            }
+        }
+
+        if self.reader.peek_char(0)?.is_none() {
+            return Ok(None);
+        }
+        if let Some(token) = self.markup()? {
+            return Ok(Some((token, position)));
+        }
+
+        // a `<` that doesn't start markup is text
+        let mut text = match self.reader.peek_char(0)? {
+            Some('<') => self.consume(1)?,
+            _ => String::new(),
+        };
+        let length = match self.find(0, "<", false)? {
+            Some(length) => length,
+            None => self.find(0, "", false)?.unwrap_or(0),
+        };
+        text.push_str(&self.consume(length)?);
+        Ok(Some((Token::Text(text), position)))
+    }
+
+    /// Read a comment, start tag or end tag
+    fn markup(&mut self) -> Result<Option<Token>, io::Error> {
+        if self.reader.peek_char(0)? != Some('<') {
+            return Ok(None);
+        }
+
+        if self.reader.peek_string(4)? == "<!--" {
+            let Some(end) = self.find(4, "-->", false)? else {
+                return Ok(None);
+            };
+            self.consume(4)?;
+            let text = self.consume(end - 4)?;
+            self.consume(3)?;
+            return Ok(Some(Token::Comment(text)));
+        }
+
+        if self.reader.peek_char(1)? == Some('/') {
+            if !self
+                .reader
+                .peek_char(2)?
+                .is_some_and(|c| c.is_ascii_alphabetic())
+            {
+                return Ok(None);
+            }
+            let Some(end) = self.find(2, ">", false)? else {
+                return Ok(None);
+            };
+            let name = self.reader.peek_string_from(2, end - 2)?;
+            let name = name.trim_end();
+            if name.contains(|c: char| c.is_whitespace() || c == '<') {
+                return Ok(None);
+            }
+            let tag = self.tag(name);
+            self.consume(end + 1)?;
+            return Ok(Some(Token::EndTag { tag }));
+        }
+
+        if !self
+            .reader
+            .peek_char(1)?
+            .is_some_and(|c| c.is_ascii_alphabetic())
+        {
+            return Ok(None);
+        }
+        let Some(end) = self.start_tag_end()? else {
+            return Ok(None);
+        };
+        let content = self.reader.peek_string_from(1, end - 1)?;
+        let name_end = content
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(content.len());
+        let name = &content[..name_end];
+        let self_closing = content.trim_end().ends_with('/');
+        // unknown tags that are never closed are text, like `<this>`
+        if !(self_closing || is_void_element(name) || is_known_element(name))
+            && self.find(end, &format!("</{name}"), false)?.is_none()
+        {
+            return Ok(None);
+        }
+
+        let tag = self.tag(name);
+        let attributes = attributes(
+            content[name_end..].trim_end().trim_end_matches('/'),
+            self.options,
+        )?;
+        self.consume(end + 1)?;
+        if is_raw_text_element(&tag) && !self_closing {
+            self.raw_text = Some(tag.clone());
+        }
+        Ok(Some(Token::StartTag {
+            tag,
+            attributes,
+            self_closing,
+        }))
+    }
+
+    fn tag(&self, name: &str) -> Atom {
+        match self.options.case_insensitive_tags {
+            true => Atom::from(name.to_ascii_lowercase()),
+            false => Atom::from(name),
+        }
+    }
+
+    /// Position of the `>` ending the start tag at the start of the input, skipping the ones in
+    /// quoted attribute values
+    fn start_tag_end(&mut self) -> Result<Option<usize>, io::Error> {
+        let mut quote = None;
+        let mut i = 1;
+        while let Some(c) = self.reader.peek_char(i)? {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '>') => return Ok(Some(i)),
+                (None, '"' | '\'') if self.reader.peek_char(i - 1)? == Some('=') => quote = Some(c),
+                _ => {}
+            }
+            i += 1;
+        }
+        Ok(None)
+    }
+
+    /// Position of the first match of `pattern` from `pos`, an empty pattern matches the end of
+    /// the input
+    fn find(
+        &mut self,
+        pos: usize,
+        pattern: &str,
+        ignore_case: bool,
+    ) -> Result<Option<usize>, io::Error> {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let mut i = pos;
+        'outer: loop {
+            for (pi, pc) in pattern.iter().enumerate() {
+                match self.reader.peek_char(i + pi)? {
+                    Some(c) if c == *pc || (ignore_case && c.eq_ignore_ascii_case(pc)) => {}
+                    Some(_) => {
+                        i += 1;
+                        continue 'outer;
+                    }
+                    None => return Ok(None),
+                }
+            }
+            if pattern.is_empty() && self.reader.peek_char(i)?.is_some() {
+                i += 1;
+                continue;
+            }
+            return Ok(Some(i));
+        }
+    }
+
+    /// Consume `length` characters keeping track of the position
+    fn consume(&mut self, length: usize) -> Result<String, io::Error> {
+        let text = self.reader.consume_string(length)?;
+        for c in text.chars() {
+            match c {
+                '\n' => {
+                    self.position.line += 1;
+                    self.position.column = 1;
+                }
+                _ => self.position.column += 1,
+            }
+        }
+        Ok(text)
+    }
+}
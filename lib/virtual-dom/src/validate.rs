@@ -0,0 +1,407 @@
+use std::{collections::HashSet, fmt};
+
+use crate::{is_void_element, Atom, Document, DomBackend, DomNodeKind, DomTree};
+
+/// Structural problem in a document that browsers would silently repair, often in surprising ways
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// Element that is not allowed inside its parent (eg. `<div>` in `<p>`), often caused by an
+    /// unclosed tag
+    Misnested {
+        tag: Atom,
+        parent: Atom,
+    },
+    /// Void element (eg. `<img>`) with children
+    VoidWithChildren {
+        tag: Atom,
+    },
+    DuplicateId {
+        id: String,
+    },
+    InvalidAttribute {
+        tag: Atom,
+        name: Atom,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::Misnested { tag, parent } => {
+                write!(f, "<{tag}> is not allowed inside <{parent}>")
+            }
+            ValidationError::VoidWithChildren { tag } => {
+                write!(f, "<{tag}> can't have children")
+            }
+            ValidationError::DuplicateId { id } => write!(f, "duplicate id {id:?}"),
+            ValidationError::InvalidAttribute { tag, name } => {
+                write!(f, "invalid attribute name {name:?} on <{tag}>")
+            }
+        }
+    }
+}
+
+/// Elements that can't be inside elements that only allow phrasing content (eg. `<p>`)
+pub(crate) fn is_flow_only(tag: &str) -> bool {
+    matches!(
+        tag,
+        "address"
+            | "article"
+            | "aside"
+            | "blockquote"
+            | "details"
+            | "dialog"
+            | "div"
+            | "dl"
+            | "fieldset"
+            | "figcaption"
+            | "figure"
+            | "footer"
+            | "form"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "header"
+            | "hr"
+            | "li"
+            | "main"
+            | "nav"
+            | "ol"
+            | "p"
+            | "pre"
+            | "section"
+            | "table"
+            | "ul"
+    )
+}
+
+/// Elements that only allow phrasing content
+fn is_phrasing_only(tag: &str) -> bool {
+    matches!(
+        tag,
+        "p" | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "span"
+            | "em"
+            | "strong"
+            | "code"
+            | "b"
+            | "i"
+            | "label"
+            | "pre"
+            | "summary"
+            | "sub"
+            | "sup"
+            | "small"
+            | "mark"
+            | "time"
+    )
+}
+
+fn is_interactive(tag: &str) -> bool {
+    matches!(
+        tag,
+        "a" | "button" | "details" | "iframe" | "label" | "select" | "textarea"
+    )
+}
+
+/// Parents an element must have, None if it can be anywhere
+fn required_parents(tag: &str) -> Option<&'static [&'static str]> {
+    match tag {
+        "li" => Some(&["ul", "ol", "menu"]),
+        "dt" | "dd" => Some(&["dl", "div"]),
+        "tr" => Some(&["table", "thead", "tbody", "tfoot"]),
+        "td" | "th" => Some(&["tr"]),
+        "thead" | "tbody" | "tfoot" | "caption" | "colgroup" => Some(&["table"]),
+        "figcaption" => Some(&["figure"]),
+        "summary" => Some(&["details"]),
+        "head" | "body" => Some(&["html"]),
+        _ => None,
+    }
+}
+
+/// Check if `tag` is a html or svg element, custom elements (eg. `<my-element>`) are known as well
+///
+/// <https://html.spec.whatwg.org/multipage/indices.html#elements-3>
+pub fn is_known_element(tag: &str) -> bool {
+    if tag.contains('-') {
+        return true;
+    }
+    matches!(
+        tag.to_ascii_lowercase().as_str(),
+        "a" | "abbr"
+            | "address"
+            | "area"
+            | "article"
+            | "aside"
+            | "audio"
+            | "b"
+            | "base"
+            | "bdi"
+            | "bdo"
+            | "blockquote"
+            | "body"
+            | "br"
+            | "button"
+            | "canvas"
+            | "caption"
+            | "cite"
+            | "code"
+            | "col"
+            | "colgroup"
+            | "data"
+            | "datalist"
+            | "dd"
+            | "del"
+            | "details"
+            | "dfn"
+            | "dialog"
+            | "div"
+            | "dl"
+            | "dt"
+            | "em"
+            | "embed"
+            | "fieldset"
+            | "figcaption"
+            | "figure"
+            | "footer"
+            | "form"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "head"
+            | "header"
+            | "hgroup"
+            | "hr"
+            | "html"
+            | "i"
+            | "iframe"
+            | "img"
+            | "input"
+            | "ins"
+            | "kbd"
+            | "label"
+            | "legend"
+            | "li"
+            | "link"
+            | "main"
+            | "map"
+            | "mark"
+            | "math"
+            | "menu"
+            | "meta"
+            | "meter"
+            | "nav"
+            | "noscript"
+            | "object"
+            | "ol"
+            | "optgroup"
+            | "option"
+            | "output"
+            | "p"
+            | "param"
+            | "picture"
+            | "pre"
+            | "progress"
+            | "q"
+            | "rp"
+            | "rt"
+            | "ruby"
+            | "s"
+            | "samp"
+            | "script"
+            | "search"
+            | "section"
+            | "select"
+            | "slot"
+            | "small"
+            | "source"
+            | "span"
+            | "strong"
+            | "style"
+            | "sub"
+            | "summary"
+            | "sup"
+            | "table"
+            | "tbody"
+            | "td"
+            | "template"
+            | "textarea"
+            | "tfoot"
+            | "th"
+            | "thead"
+            | "time"
+            | "title"
+            | "tr"
+            | "track"
+            | "u"
+            | "ul"
+            | "var"
+            | "video"
+            | "wbr"
+            // svg
+            | "svg"
+            | "circle"
+            | "clippath"
+            | "defs"
+            | "desc"
+            | "ellipse"
+            | "g"
+            | "image"
+            | "line"
+            | "lineargradient"
+            | "mask"
+            | "path"
+            | "pattern"
+            | "polygon"
+            | "polyline"
+            | "radialgradient"
+            | "rect"
+            | "stop"
+            | "symbol"
+            | "text"
+            | "textpath"
+            | "tspan"
+            | "use"
+    )
+}
+
+/// <https://html.spec.whatwg.org/multipage/syntax.html#attributes-2>
+fn is_valid_attribute_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.chars().any(|c| {
+            c.is_whitespace()
+                || c.is_control()
+                || matches!(
+                    c,
+                    '"' | '\'' | '>' | '/' | '=' | '<' | '\u{FDD0}'..='\u{FDEF}'
+                )
+        })
+}
+
+impl Document {
+    /// Check for misnested elements, duplicate ids and invalid attribute names
+    pub fn validate(&self) -> Vec<ValidationError> {
+        DomBackend::validate(self)
+    }
+}
+
+/// Validate `root` and all nodes below it
+pub(crate) fn validate_tree<T: DomTree>(tree: &T, root: &T::Node) -> Vec<ValidationError> {
+    let mut errors = vec![];
+    let mut ids = HashSet::new();
+    for node in tree.descendants(root) {
+        let Some(node_tag) = tree.tag(&node) else {
+            continue;
+        };
+
+        tree.with_kind(&node, |kind| {
+            let DomNodeKind::Element { attributes, .. } = kind else {
+                return;
+            };
+            for (name, value) in attributes {
+                if !is_valid_attribute_name(name) {
+                    errors.push(ValidationError::InvalidAttribute {
+                        tag: node_tag.clone(),
+                        name: name.clone(),
+                    });
+                }
+                if name == "id" && !ids.insert(value.clone()) {
+                    errors.push(ValidationError::DuplicateId { id: value.clone() });
+                }
+            }
+        });
+
+        if is_void_element(&node_tag) && tree.has_children(&node) {
+            errors.push(ValidationError::VoidWithChildren {
+                tag: node_tag.clone(),
+            });
+        }
+
+        let Some(parent) = tree.parent(&node).and_then(|p| tree.tag(&p)) else {
+            continue;
+        };
+        let misnested = match required_parents(&node_tag) {
+            Some(parents) => !parents.contains(&parent.as_str()),
+            None => false,
+        } || (is_flow_only(&node_tag) && is_phrasing_only(&parent))
+            // links and buttons can't contain other interactive elements
+            || (is_interactive(&node_tag)
+                && tree
+                    .ancestors(&node)
+                    .skip(1)
+                    .filter_map(|a| tree.tag(&a))
+                    .any(|a| a == "a" || a == "button"));
+        if misnested {
+            errors.push(ValidationError::Misnested {
+                tag: node_tag,
+                parent,
+            });
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{to_attributes, Document, DomNode};
+
+    use super::ValidationError;
+
+    #[test]
+    fn test_validate() {
+        let document = Document::new();
+        let p = DomNode::create_element("p");
+        p.append_child(DomNode::create_element("div"));
+        document.body.append_child(p);
+        document
+            .body
+            .append_child(DomNode::create_element_with_attributes(
+                "h1",
+                to_attributes([("id", "a"), ("on click", "")]),
+            ));
+        document
+            .body
+            .append_child(DomNode::create_element_with_attributes(
+                "h2",
+                to_attributes([("id", "a")]),
+            ));
+        let a = DomNode::create_element("a");
+        a.append_child(DomNode::create_element("a"));
+        document.body.append_child(a);
+        document.body.append_child(DomNode::create_element("li"));
+
+        assert_eq!(
+            document.validate(),
+            vec![
+                ValidationError::Misnested {
+                    tag: "div".into(),
+                    parent: "p".into()
+                },
+                ValidationError::InvalidAttribute {
+                    tag: "h1".into(),
+                    name: "on click".into()
+                },
+                ValidationError::DuplicateId { id: "a".into() },
+                ValidationError::Misnested {
+                    tag: "a".into(),
+                    parent: "a".into()
+                },
+                ValidationError::Misnested {
+                    tag: "li".into(),
+                    parent: "body".into()
+                },
+            ]
+        );
+    }
+}
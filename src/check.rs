@@ -0,0 +1,29 @@
+use clap::Args;
+use log::info;
+use lssg_lib::{
+    lssg_error::LssgError,
+    renderer::{ProseModule, RendererModule},
+    sitetree::{Input, SiteTree},
+};
+
+#[derive(Args, Debug)]
+pub struct CheckArgs {
+    /// a reference to the first markdown input file (eg. ./my_blog/index.md)
+    #[clap(value_parser = Input::from_string)]
+    input: Input,
+}
+
+/// Check the spelling and style of all pages, returns false if anything was found
+pub fn check(args: CheckArgs) -> Result<bool, LssgError> {
+    let mut site_tree = SiteTree::from_input(args.input)?;
+    let mut prose = ProseModule::new();
+    prose.init(&mut site_tree)?;
+
+    let findings = prose.findings().len();
+    if findings > 0 {
+        info!("Found {findings} problem(s)");
+        return Ok(false);
+    }
+    info!("No problems found");
+    Ok(true)
+}
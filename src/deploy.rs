@@ -0,0 +1,222 @@
+use std::{
+    env,
+    fs::{self, remove_dir_all},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use clap::Args;
+use log::{info, warn};
+use lssg_lib::{lssg_error::LssgError, sitetree::Input};
+
+use crate::lssg;
+
+#[derive(Args, Debug)]
+pub struct DeployArgs {
+    /// a reference to the first markdown input file
+    #[clap(value_parser = Input::from_string)]
+    input: Input,
+
+    /// Publish to GitHub Pages by committing the site to a `gh-pages` branch
+    #[clap(long, required = true)]
+    github_pages: bool,
+
+    /// Commit the site to the `docs/` folder of the current branch instead
+    #[clap(long)]
+    docs: bool,
+
+    /// Custom domain to write to `CNAME`, an existing `CNAME` is kept otherwise
+    #[clap(long)]
+    cname: Option<String>,
+
+    /// Branch to publish to
+    #[clap(long, default_value = "gh-pages")]
+    branch: String,
+
+    /// Remote to push to
+    #[clap(long, default_value = "origin")]
+    remote: String,
+
+    /// Only commit, don't push
+    #[clap(long)]
+    no_push: bool,
+}
+
+/// Run git in `dir` returning stdout
+fn git(dir: &Path, args: &[&str]) -> Result<String, LssgError> {
+    let output = Command::new("git").current_dir(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(LssgError::io(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub fn deploy(args: DeployArgs) -> Result<(), LssgError> {
+    let repository = PathBuf::from(git(
+        &env::current_dir()?,
+        &["rev-parse", "--show-toplevel"],
+    )?);
+    if args.docs {
+        deploy_docs(&args, &repository)
+    } else {
+        deploy_branch(&args, &repository)
+    }
+}
+
+/// Files GitHub Pages needs next to the site
+fn write_pages_files(directory: &Path, cname: Option<&str>) -> Result<(), LssgError> {
+    // don't process the site with jekyll
+    fs::write(directory.join(".nojekyll"), "")?;
+    if let Some(cname) = cname {
+        fs::write(directory.join("CNAME"), format!("{cname}\n"))?;
+    }
+    Ok(())
+}
+
+/// Commit everything in `pathspec` if anything changed, returns false if nothing changed
+fn commit(directory: &Path, pathspec: &str, repository: &Path) -> Result<bool, LssgError> {
+    git(directory, &["add", "--all", pathspec])?;
+    if git(directory, &["status", "--porcelain", pathspec])?.is_empty() {
+        return Ok(false);
+    }
+    let message = match git(repository, &["rev-parse", "--short", "HEAD"]) {
+        Ok(head) => format!("Deploy site from {head}"),
+        Err(_) => "Deploy site".to_string(),
+    };
+    git(
+        directory,
+        &["commit", "--quiet", "-m", &message, "--", pathspec],
+    )?;
+    Ok(true)
+}
+
+fn deploy_docs(args: &DeployArgs, repository: &Path) -> Result<(), LssgError> {
+    let docs = repository.join("docs");
+    let cname = match &args.cname {
+        Some(cname) => Some(cname.clone()),
+        None => fs::read_to_string(docs.join("CNAME")).ok(),
+    };
+    lssg(args.input.clone(), docs.clone()).render()?;
+    write_pages_files(&docs, cname.as_deref().map(str::trim))?;
+
+    if !commit(repository, "docs", repository)? {
+        info!("Site did not change, nothing to deploy");
+        return Ok(());
+    }
+    if !args.no_push {
+        info!("Pushing to {}", args.remote);
+        git(repository, &["push", "--quiet", &args.remote, "HEAD"])?;
+    }
+    Ok(())
+}
+
+fn deploy_branch(args: &DeployArgs, repository: &Path) -> Result<(), LssgError> {
+    let temp = env::temp_dir().join(format!("lssg-deploy-{}", std::process::id()));
+    let worktree = temp.join("worktree");
+    let site = temp.join("site");
+    if temp.exists() {
+        remove_dir_all(&temp)?;
+    }
+
+    let result = publish_branch(args, repository, &worktree, &site);
+
+    if let Err(e) = git(
+        repository,
+        &["worktree", "remove", "--force", &worktree.to_string_lossy()],
+    ) {
+        warn!("Failed to remove worktree: {e}");
+    }
+    if temp.exists() {
+        remove_dir_all(&temp)?;
+    }
+    result
+}
+
+fn publish_branch(
+    args: &DeployArgs,
+    repository: &Path,
+    worktree: &Path,
+    site: &Path,
+) -> Result<(), LssgError> {
+    let branch = args.branch.as_str();
+    let remote_branch = format!("{}/{branch}", args.remote);
+    // get the latest published site, fails if nothing is published yet
+    if git(repository, &["fetch", "--quiet", &args.remote, branch]).is_err() {
+        info!("{remote_branch} not found");
+    }
+
+    let worktree_path = worktree.to_string_lossy();
+    let exists =
+        |reference: &str| git(repository, &["rev-parse", "--verify", "--quiet", reference]).is_ok();
+    if exists(&format!("refs/heads/{branch}")) {
+        git(
+            repository,
+            &["worktree", "add", "--quiet", &worktree_path, branch],
+        )?;
+        if exists(&format!("refs/remotes/{remote_branch}")) {
+            git(worktree, &["merge", "--quiet", "--ff-only", &remote_branch])?;
+        }
+    } else if exists(&format!("refs/remotes/{remote_branch}")) {
+        git(
+            repository,
+            &[
+                "worktree",
+                "add",
+                "--quiet",
+                "-b",
+                branch,
+                &worktree_path,
+                &remote_branch,
+            ],
+        )?;
+    } else {
+        info!("Creating branch {branch}");
+        git(
+            repository,
+            &["worktree", "add", "--quiet", "--detach", &worktree_path],
+        )?;
+        git(worktree, &["checkout", "--quiet", "--orphan", branch])?;
+        git(
+            worktree,
+            &["rm", "-r", "--quiet", "--force", "--ignore-unmatch", "."],
+        )?;
+    }
+
+    lssg(args.input.clone(), site.to_path_buf()).render()?;
+
+    // replace the previous site, keeping its CNAME if no other is given
+    let cname = match &args.cname {
+        Some(cname) => Some(cname.clone()),
+        None => fs::read_to_string(worktree.join("CNAME")).ok(),
+    };
+    for entry in fs::read_dir(worktree)? {
+        let path = entry?.path();
+        if path.file_name().is_some_and(|name| name == ".git") {
+            continue;
+        }
+        if path.is_dir() && !path.is_symlink() {
+            remove_dir_all(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+    }
+    for entry in fs::read_dir(site)? {
+        let entry = entry?;
+        fs::rename(entry.path(), worktree.join(entry.file_name()))?;
+    }
+    write_pages_files(worktree, cname.as_deref().map(str::trim))?;
+
+    if !commit(worktree, ".", repository)? {
+        info!("Site did not change, nothing to deploy");
+        return Ok(());
+    }
+    if !args.no_push {
+        info!("Pushing to {remote_branch}");
+        git(worktree, &["push", "--quiet", &args.remote, branch])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use log::{error, info};
+use lssg_lib::{
+    lmarkdown::{is_equivalent, parse_lmarkdown, to_markdown_wrapped},
+    lssg_error::LssgError,
+};
+
+#[derive(Args, Debug)]
+pub struct FmtArgs {
+    /// markdown files or folders containing markdown files to format
+    #[clap(required = true)]
+    paths: Vec<PathBuf>,
+
+    /// Don't write anything, fail if any file is not formatted
+    #[clap(long)]
+    check: bool,
+
+    /// Wrap paragraphs at this many characters
+    #[clap(long)]
+    width: Option<usize>,
+}
+
+/// All markdown files in `paths`, searching folders recursively
+fn markdown_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, LssgError> {
+    let mut files = vec![];
+    let mut queue = paths.to_vec();
+    while let Some(path) = queue.pop() {
+        if path.is_dir() {
+            for entry in fs::read_dir(&path)? {
+                let path = entry?.path();
+                if path.is_dir() || path.extension().is_some_and(|e| e == "md") {
+                    queue.push(path);
+                }
+            }
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Formatted content of `path`, errors if formatting would change the meaning of the file
+fn format(path: &Path, width: Option<usize>) -> Result<(String, String), LssgError> {
+    let content = fs::read_to_string(path)?;
+    let tokens = parse_lmarkdown(content.as_bytes())?;
+    let formatted = to_markdown_wrapped(&tokens, width);
+    if !is_equivalent(&tokens, &parse_lmarkdown(formatted.as_bytes())?) {
+        return Err(LssgError::io(format!(
+            "formatting {path:?} would change its content"
+        )));
+    }
+    Ok((content, formatted))
+}
+
+/// Format all files, returns false if `--check` is given and a file is not formatted or if a file
+/// failed to format
+pub fn fmt(args: FmtArgs) -> Result<bool, LssgError> {
+    let mut success = true;
+    for path in markdown_files(&args.paths)? {
+        let (content, formatted) = match format(&path, args.width) {
+            Ok(result) => result,
+            Err(e) => {
+                error!("{e}");
+                success = false;
+                continue;
+            }
+        };
+        if content == formatted {
+            continue;
+        }
+        if args.check {
+            println!("{}", path.display());
+            success = false;
+        } else {
+            info!("Formatting {path:?}");
+            fs::write(&path, formatted)?;
+        }
+    }
+    Ok(success)
+}
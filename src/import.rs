@@ -0,0 +1,788 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::LazyLock,
+};
+
+use clap::Args;
+use log::{info, warn};
+use lssg_lib::{
+    lmarkdown::{to_markdown, Token},
+    lssg_error::LssgError,
+};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// Hugo content folder or Jekyll site folder
+    input: PathBuf,
+
+    /// folder to write the lssg content to
+    output: PathBuf,
+
+    /// Folder with static files to copy into the root of the output (eg. Hugo's `static/`)
+    #[clap(long = "static")]
+    static_folder: Option<PathBuf>,
+
+    /// Also import pages marked as draft
+    #[clap(long)]
+    drafts: bool,
+}
+
+/// A value that can be given as a single item or a list
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+impl OneOrMany {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            // jekyll allows space separated lists
+            OneOrMany::One(s) => s.split_whitespace().map(str::to_string).collect(),
+            OneOrMany::Many(v) => v,
+        }
+    }
+}
+
+static DAY_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap());
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum Date {
+    Text(String),
+    Toml(toml::value::Datetime),
+}
+impl Date {
+    /// Date in %Y-%m-%d
+    fn day(&self) -> Option<String> {
+        let text = match self {
+            Date::Text(text) => text.clone(),
+            Date::Toml(datetime) => datetime.to_string(),
+        };
+        let day = text.get(..10)?;
+        DAY_REGEX.is_match(day).then(|| day.to_string())
+    }
+}
+
+/// Front matter fields used by Hugo and Jekyll that have an lssg equivalent
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct FrontMatter {
+    title: Option<String>,
+    description: Option<String>,
+    date: Option<Date>,
+    lastmod: Option<Date>,
+    tags: Option<OneOrMany>,
+    categories: Option<OneOrMany>,
+    author: Option<OneOrMany>,
+    authors: Option<OneOrMany>,
+    draft: bool,
+    published: Option<bool>,
+    slug: Option<String>,
+}
+
+impl FrontMatter {
+    /// Split `content` into its front matter and the markdown after it
+    fn parse(content: &str, path: &Path) -> Result<(FrontMatter, String), LssgError> {
+        let (delimiter, yaml) = if content.starts_with("---") {
+            ("---", true)
+        } else if content.starts_with("+++") {
+            ("+++", false)
+        } else {
+            return Ok((FrontMatter::default(), content.to_string()));
+        };
+        let rest = &content[3..];
+        let Some(end) = rest.find(&format!("\n{delimiter}")) else {
+            return Ok((FrontMatter::default(), content.to_string()));
+        };
+        let front = &rest[..end];
+        let body = rest[end + 4..].trim_start_matches(['\r', '\n']).to_string();
+        let front_matter = if yaml {
+            // empty front matter is used by jekyll to mark files for processing
+            if front.trim().is_empty() {
+                FrontMatter::default()
+            } else {
+                serde_yaml::from_str(front)
+                    .map_err(|e| LssgError::io(format!("invalid front matter in {path:?}: {e}")))?
+            }
+        } else {
+            toml::from_str(front)
+                .map_err(|e| LssgError::io(format!("invalid front matter in {path:?}: {e}")))?
+        };
+        Ok((front_matter, body))
+    }
+
+    /// lssg attributes of a page
+    fn attributes(self, date: Option<String>) -> toml::Table {
+        let mut table = toml::Table::new();
+        if let Some(title) = self.title {
+            table.insert("title".into(), title.into());
+        }
+        if let Some(description) = self.description {
+            let mut meta = toml::Table::new();
+            meta.insert("description".into(), description.into());
+            table.insert("meta".into(), meta.into());
+        }
+
+        let mut blog = toml::Table::new();
+        if let Some(published_on) = self.date.as_ref().and_then(|d| d.day()) {
+            blog.insert("published_on".into(), published_on.into());
+        }
+        let modified_on = self.lastmod.or(self.date).and_then(|d| d.day()).or(date);
+        if let Some(modified_on) = modified_on {
+            blog.insert("modified_on".into(), modified_on.into());
+        }
+        let mut tags = vec![];
+        for list in [self.tags, self.categories].into_iter().flatten() {
+            for tag in list.into_vec() {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+        }
+        if !tags.is_empty() {
+            blog.insert("tags".into(), tags.into());
+        }
+        if let Some(author) = self.author.or(self.authors) {
+            blog.insert("author".into(), author.into_vec().join(", ").into());
+        }
+        if !blog.is_empty() {
+            table.insert("blog".into(), blog.into());
+        }
+        table
+    }
+}
+
+struct ImportedPage {
+    source: PathBuf,
+    /// Output path relative to the output folder
+    output: PathBuf,
+    title: String,
+    attributes: toml::Table,
+    body: String,
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e == "md" || e == "markdown")
+}
+
+/// Files to import relative to `input`, skipping hidden files and jekyll's special folders
+fn source_files(input: &Path) -> Result<Vec<PathBuf>, LssgError> {
+    let mut files = vec![];
+    let mut queue = vec![PathBuf::new()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(input.join(&dir))? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.')
+                || (name.starts_with('_') && name != "_posts" && name != "_index.md")
+            {
+                continue;
+            }
+            let path = dir.join(&name);
+            if entry.file_type()?.is_dir() {
+                queue.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Jekyll post file name (eg. "2024-01-31-title")
+static POST_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d{4}-\d{2}-\d{2})-(.+)$").unwrap());
+
+/// Output location of a markdown file and the date in its name for jekyll posts
+fn page_output(source: &Path) -> (PathBuf, Option<String>) {
+    let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+    let parent = source.parent().unwrap_or(Path::new(""));
+    if stem == "_index" {
+        return (parent.join("index.md"), None);
+    }
+    if parent.ends_with("_posts") {
+        if let Some(captures) = POST_REGEX.captures(&stem) {
+            let parent = parent.with_file_name("posts");
+            return (
+                parent.join(format!("{}.md", &captures[2])),
+                Some(captures[1].to_string()),
+            );
+        }
+        return (
+            parent.with_file_name("posts").join(format!("{stem}.md")),
+            None,
+        );
+    }
+    (parent.join(format!("{stem}.md")), None)
+}
+
+/// Path as url without extension, using `/` as separator
+fn url_key(path: &Path) -> String {
+    path.with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// Resolve `.` and `..` in a relative path
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir | Component::RootDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            c => normalized.push(c),
+        }
+    }
+    Some(normalized)
+}
+
+/// Relative link from the folder of `from` to `to`
+fn relative_link(from: &Path, to: &Path) -> String {
+    let from: Vec<Component> = from
+        .parent()
+        .unwrap_or(Path::new(""))
+        .components()
+        .collect();
+    let to_components: Vec<Component> = to.components().collect();
+    let common = from
+        .iter()
+        .zip(&to_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut link = "../".repeat(from.len() - common);
+    if link.is_empty() {
+        link.push_str("./");
+    }
+    let rest: Vec<String> = to_components[common..]
+        .iter()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    link + &rest.join("/")
+}
+
+/// Urls by which hugo and jekyll pages and files can be linked to, mapped to their output path
+struct Urls {
+    urls: HashMap<String, PathBuf>,
+}
+impl Urls {
+    fn new() -> Urls {
+        Urls {
+            urls: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, url: String, output: &Path) {
+        self.urls.entry(url).or_insert_with(|| output.to_path_buf());
+    }
+
+    fn add_page(&mut self, source: &Path, page: &ImportedPage, slug: Option<&str>) {
+        let key = url_key(source);
+        self.add(key.clone(), &page.output);
+        self.add(url_key(&page.output), &page.output);
+        if key == "_index" || key == "index" {
+            self.add(String::new(), &page.output);
+        } else if let Some(folder) = key.strip_suffix("/_index").or(key.strip_suffix("/index")) {
+            self.add(folder.to_string(), &page.output);
+        }
+        if let Some(slug) = slug {
+            let parent = page.output.parent().unwrap_or(Path::new(""));
+            self.add(url_key(&parent.join(slug)), &page.output);
+        }
+        // jekyll's default permalink /year/month/day/title.html
+        if let Some(Ok(date)) = page
+            .attributes
+            .get("blog")
+            .and_then(|b| b.get("modified_on"))
+            .map(|d| d.clone().try_into::<String>())
+        {
+            if source.starts_with("_posts") {
+                let title = page
+                    .output
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                self.add(format!("{}/{title}", date.replace('-', "/")), &page.output);
+            }
+        }
+    }
+
+    /// Find the output path `href` in `page` points to
+    fn resolve(&self, page: &ImportedPage, href: &str) -> Option<(PathBuf, String)> {
+        if href.is_empty()
+            || href.starts_with('#')
+            || href.contains("://")
+            || href.starts_with("mailto:")
+        {
+            return None;
+        }
+        let (path, fragment) = match href.find('#') {
+            Some(i) => (&href[..i], &href[i..]),
+            None => (href, ""),
+        };
+        let (path, absolute) = match path.strip_prefix('/') {
+            Some(path) => (path, true),
+            None => (path, false),
+        };
+        let path = path.trim_end_matches('/');
+        let path = path.strip_suffix(".html").unwrap_or(path);
+        let candidates = if absolute {
+            vec![PathBuf::from(path)]
+        } else {
+            // hugo resolves relative links from the url of the page, `posts/a.md` is `/posts/a/`
+            let file_folder = page.source.parent().unwrap_or(Path::new(""));
+            vec![
+                file_folder.join(path),
+                page.source.with_extension("").join(path),
+            ]
+        };
+        for candidate in candidates {
+            let Some(candidate) = normalize(&candidate) else {
+                continue;
+            };
+            let key = url_key(&candidate);
+            let output = self
+                .urls
+                .get(&key)
+                // files with their extension
+                .or_else(|| self.urls.get(&candidate.to_string_lossy().to_string()));
+            if let Some(output) = output {
+                return Some((output.clone(), fragment.to_string()));
+            }
+        }
+        None
+    }
+}
+
+static ARGUMENT_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(\w+)=(?:"([^"]*)"|(\S+))|"([^"]*)"|(\S+)"#).unwrap());
+
+/// Named and positional arguments of a shortcode
+fn shortcode_arguments(arguments: &str) -> (HashMap<String, String>, Vec<String>) {
+    let mut named = HashMap::new();
+    let mut positional = vec![];
+    for captures in ARGUMENT_REGEX.captures_iter(arguments) {
+        if let Some(key) = captures.get(1) {
+            let value = captures.get(2).or(captures.get(3)).unwrap();
+            named.insert(key.as_str().to_string(), value.as_str().to_string());
+        } else {
+            let value = captures.get(4).or(captures.get(5)).unwrap();
+            positional.push(value.as_str().to_string());
+        }
+    }
+    (named, positional)
+}
+
+static SHORTCODE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{[<%]\s*(/?)(\w+)\s*(.*?)\s*[>%]\}\}").unwrap());
+static URL_FILTER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\{\{\s*["']([^"']*)["']\s*\|\s*(?:relative|absolute)_url\s*\}\}"#).unwrap()
+});
+static BASE_URL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*site\.baseurl\s*\}\}").unwrap());
+static LIQUID_TAG_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{%-?\s*(\w+)\s*(.*?)\s*-?%\}").unwrap());
+
+/// Replace hugo shortcodes and jekyll liquid tags with markdown, unknown ones are kept
+fn convert_shortcodes(body: &str, source: &Path) -> String {
+    let body = SHORTCODE_REGEX.replace_all(body, |c: &Captures| {
+        let (named, positional) = shortcode_arguments(&c[3]);
+        let argument = |key: &str, position: usize| {
+            named
+                .get(key)
+                .or(positional.get(position))
+                .cloned()
+                .unwrap_or_default()
+        };
+        match (&c[1], &c[2]) {
+            ("", "figure") => {
+                let alt = named
+                    .get("alt")
+                    .or(named.get("caption"))
+                    .cloned()
+                    .unwrap_or_default();
+                match named.get("title") {
+                    Some(title) => format!("![{alt}]({} \"{title}\")", argument("src", 0)),
+                    None => format!("![{alt}]({})", argument("src", 0)),
+                }
+            }
+            ("", "ref" | "relref") => argument("path", 0),
+            ("", "highlight") => format!("```{}", argument("lang", 0)),
+            ("/", "highlight") => "```".to_string(),
+            ("", "youtube") => format!(
+                "<iframe src=\"https://www.youtube-nocookie.com/embed/{}\" allowfullscreen></iframe>",
+                argument("id", 0)
+            ),
+            ("", "vimeo") => format!(
+                "<iframe src=\"https://player.vimeo.com/video/{}\" allowfullscreen></iframe>",
+                argument("id", 0)
+            ),
+            ("", "gist") => format!(
+                "[Gist](https://gist.github.com/{}/{})",
+                argument("user", 0),
+                argument("id", 1)
+            ),
+            _ => {
+                warn!("Unknown shortcode {:?} in {source:?}", &c[0]);
+                c[0].to_string()
+            }
+        }
+    });
+
+    let body = URL_FILTER_REGEX.replace_all(&body, "$1");
+    let body = BASE_URL_REGEX.replace_all(&body, "");
+
+    LIQUID_TAG_REGEX
+        .replace_all(&body, |c: &Captures| {
+            let (_, positional) = shortcode_arguments(&c[2]);
+            let first = positional.first().cloned().unwrap_or_default();
+            match &c[1] {
+                "highlight" => format!("```{first}"),
+                "endhighlight" => "```".to_string(),
+                "link" => format!("/{}", first.trim_start_matches('/')),
+                "post_url" => format!("/_posts/{first}"),
+                "raw" | "endraw" => String::new(),
+                _ => {
+                    warn!("Unknown liquid tag {:?} in {source:?}", &c[0]);
+                    c[0].to_string()
+                }
+            }
+        })
+        .to_string()
+}
+
+static MARKDOWN_LINK_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(!?\[[^\]]*\]\()([^)\s]+)"#).unwrap());
+static HTML_LINK_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"((?:href|src)=")([^"]*)"#).unwrap());
+
+/// Point links to pages and files to their imported markdown and file paths
+fn rewrite_links(page: &ImportedPage, urls: &Urls) -> String {
+    let rewrite = |c: &Captures| match urls.resolve(page, &c[2]) {
+        Some((output, fragment)) => {
+            format!(
+                "{}{}{fragment}",
+                &c[1],
+                relative_link(&page.output, &output)
+            )
+        }
+        None => c[0].to_string(),
+    };
+    let body = MARKDOWN_LINK_REGEX.replace_all(&page.body, rewrite);
+    HTML_LINK_REGEX.replace_all(&body, rewrite).to_string()
+}
+
+/// Make sure every folder has an index linking to all pages and folders in it, hugo and jekyll
+/// generate these listings while lssg only follows links
+fn index_pages(pages: &mut Vec<ImportedPage>) {
+    let mut folders: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for page in pages.iter() {
+        let folder = page.output.parent().unwrap_or(Path::new("")).to_path_buf();
+        let children = folders.entry(folder).or_default();
+        if page.output.file_name().is_some_and(|n| n != "index.md") {
+            children.push(page.output.clone());
+        }
+    }
+    // link the index of every folder from the index of its parent
+    for folder in folders.keys().cloned().collect::<Vec<PathBuf>>() {
+        let mut child = folder;
+        while let Some(parent) = child.parent() {
+            let children = folders.entry(parent.to_path_buf()).or_default();
+            let index = child.join("index.md");
+            if !children.contains(&index) {
+                children.push(index);
+            }
+            child = parent.to_path_buf();
+        }
+    }
+
+    for folder in folders.keys() {
+        let index = folder.join("index.md");
+        if pages.iter().any(|p| p.output == index) {
+            continue;
+        }
+        let title = folder
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Home".to_string());
+        pages.push(ImportedPage {
+            source: folder.join("_index.md"),
+            output: index,
+            body: format!("# {title}\n"),
+            title,
+            attributes: toml::Table::new(),
+        });
+    }
+
+    let titles: HashMap<PathBuf, String> = pages
+        .iter()
+        .map(|p| (p.output.clone(), p.title.clone()))
+        .collect();
+    for (folder, children) in folders {
+        let index = folder.join("index.md");
+        let page = pages.iter_mut().find(|p| p.output == index).unwrap();
+        let missing: Vec<String> = children
+            .iter()
+            .map(|output| (&titles[output], relative_link(&index, output)))
+            .filter(|(_, link)| !page.body.contains(&format!("]({link}")))
+            .map(|(title, link)| format!("- [{title}]({link})"))
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+        let body = page.body.trim_end();
+        page.body = match body.is_empty() {
+            true => format!("{}\n", missing.join("\n")),
+            false => format!("{body}\n\n{}\n", missing.join("\n")),
+        };
+    }
+}
+
+fn copy_folder(from: &Path, to: &Path) -> Result<(), LssgError> {
+    for file in source_files(from)? {
+        let to = to.join(&file);
+        fs::create_dir_all(to.parent().unwrap())?;
+        fs::copy(from.join(&file), to)?;
+    }
+    Ok(())
+}
+
+pub fn import(args: ImportArgs) -> Result<(), LssgError> {
+    let mut pages = vec![];
+    let mut files = vec![];
+    let mut urls = Urls::new();
+    for source in source_files(&args.input)? {
+        if !is_markdown(&source) {
+            urls.add(source.to_string_lossy().to_string(), &source);
+            files.push(source);
+            continue;
+        }
+        let content = fs::read_to_string(args.input.join(&source))?;
+        let (front_matter, body) = FrontMatter::parse(&content, &source)?;
+        if (front_matter.draft || front_matter.published == Some(false)) && !args.drafts {
+            info!("Skipping draft {source:?}");
+            continue;
+        }
+        let (output, date) = page_output(&source);
+        let slug = front_matter.slug.clone();
+        let title = front_matter
+            .title
+            .clone()
+            .unwrap_or_else(|| output.file_stem().unwrap().to_string_lossy().to_string());
+        let page = ImportedPage {
+            body: convert_shortcodes(&body, &source),
+            source,
+            output,
+            title,
+            attributes: front_matter.attributes(date),
+        };
+        urls.add_page(&page.source.clone(), &page, slug.as_deref());
+        pages.push(page);
+    }
+    if let Some(static_folder) = &args.static_folder {
+        for file in source_files(static_folder)? {
+            urls.add(file.to_string_lossy().to_string(), &file);
+        }
+    }
+
+    for page in pages.iter_mut() {
+        page.body = rewrite_links(page, &urls);
+    }
+    index_pages(&mut pages);
+
+    for page in pages {
+        let path = args.output.join(&page.output);
+        info!("Writing {path:?}");
+        fs::create_dir_all(path.parent().unwrap())?;
+        let mut content = String::new();
+        if !page.attributes.is_empty() {
+            content.push_str(&to_markdown(&[Token::Attributes {
+                table: page.attributes,
+            }]));
+        }
+        content.push_str(&page.body);
+        fs::write(path, content)?;
+    }
+    for file in files {
+        let path = args.output.join(&file);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::copy(args.input.join(&file), path)?;
+    }
+    if let Some(static_folder) = &args.static_folder {
+        copy_folder(static_folder, &args.output)?;
+    }
+    info!(
+        "Imported into {:?}, build it with `lssg {:?} <output>`",
+        args.output,
+        args.output.join("index.md")
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(source: &str) -> ImportedPage {
+        let (output, _) = page_output(Path::new(source));
+        ImportedPage {
+            source: source.into(),
+            output,
+            title: String::new(),
+            attributes: toml::Table::new(),
+            body: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_front_matter() {
+        let path = Path::new("post.md");
+        let (front_matter, body) = FrontMatter::parse(
+            "---\ntitle: Post\ndate: 2024-01-31T10:00:00Z\ntags: a b\ndraft: true\n---\n\n# Body\n",
+            path,
+        )
+        .unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Post"));
+        assert!(front_matter.draft);
+        assert_eq!(body, "# Body\n");
+        let attributes = front_matter.attributes(None);
+        let blog = attributes["blog"].as_table().unwrap();
+        assert_eq!(blog["published_on"].as_str(), Some("2024-01-31"));
+        assert_eq!(
+            blog["tags"],
+            toml::Value::from(vec!["a".to_string(), "b".to_string()])
+        );
+
+        let (front_matter, body) =
+            FrontMatter::parse("+++\ntitle = \"Toml\"\ndate = 2024-02-01\n+++\nText", path)
+                .unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Toml"));
+        assert_eq!(
+            front_matter.date.and_then(|d| d.day()).as_deref(),
+            Some("2024-02-01")
+        );
+        assert_eq!(body, "Text");
+
+        // no or unterminated front matter is kept as body
+        let (front_matter, body) = FrontMatter::parse("# Title\n---\n", path).unwrap();
+        assert!(front_matter.title.is_none());
+        assert_eq!(body, "# Title\n---\n");
+        let (_, body) = FrontMatter::parse("---\ntitle: Post\n", path).unwrap();
+        assert_eq!(body, "---\ntitle: Post\n");
+
+        assert!(FrontMatter::parse("---\n: :\n---\n", path).is_err());
+    }
+
+    #[test]
+    fn test_convert_shortcodes() {
+        let source = Path::new("post.md");
+        assert_eq!(
+            convert_shortcodes(r#"{{< figure src="/a.png" alt="A" title="T" >}}"#, source),
+            r#"![A](/a.png "T")"#
+        );
+        assert_eq!(
+            convert_shortcodes(
+                "{{< highlight rust >}}\nfn main() {}\n{{< /highlight >}}",
+                source
+            ),
+            "```rust\nfn main() {}\n```"
+        );
+        assert_eq!(
+            convert_shortcodes(r#"[Other]({{< ref "other.md" >}})"#, source),
+            "[Other](other.md)"
+        );
+        assert_eq!(
+            convert_shortcodes(
+                r#"[Post]({% post_url 2024-01-31-post %}) ![]({{ "/a.png" | relative_url }})"#,
+                source
+            ),
+            "[Post](/_posts/2024-01-31-post) ![](/a.png)"
+        );
+        assert_eq!(
+            convert_shortcodes("{{ site.baseurl }}/about {% raw %}x{% endraw %}", source),
+            "/about x"
+        );
+        // unknown shortcodes are kept
+        assert_eq!(
+            convert_shortcodes("{{< unknown 1 >}} {% unknown %}", source),
+            "{{< unknown 1 >}} {% unknown %}"
+        );
+    }
+
+    #[test]
+    fn test_page_output() {
+        assert_eq!(
+            page_output(Path::new("_posts/2024-01-31-first-post.md")),
+            (
+                PathBuf::from("posts/first-post.md"),
+                Some("2024-01-31".to_string())
+            )
+        );
+        assert_eq!(
+            page_output(Path::new("_posts/undated.markdown")),
+            (PathBuf::from("posts/undated.md"), None)
+        );
+        assert_eq!(
+            page_output(Path::new("blog/_index.md")),
+            (PathBuf::from("blog/index.md"), None)
+        );
+        assert_eq!(
+            page_output(Path::new("about.md")),
+            (PathBuf::from("about.md"), None)
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut urls = Urls::new();
+        let about = page("about.md");
+        urls.add_page(&about.source, &about, None);
+        let post = page("blog/post.md");
+        urls.add_page(&post.source, &post, Some("pretty"));
+        let index = page("blog/_index.md");
+        urls.add_page(&index.source, &index, None);
+        urls.add("images/a.png".into(), Path::new("images/a.png"));
+
+        let resolve = |href: &str| urls.resolve(&post, href);
+        assert_eq!(
+            resolve("/about/#team"),
+            Some((PathBuf::from("about.md"), "#team".to_string()))
+        );
+        assert_eq!(
+            resolve("../about.html"),
+            Some((PathBuf::from("about.md"), String::new()))
+        );
+        // hugo resolves relative links from the url of the page
+        assert_eq!(
+            resolve("../../about"),
+            Some((PathBuf::from("about.md"), String::new()))
+        );
+        assert_eq!(
+            resolve("/blog/pretty"),
+            Some((PathBuf::from("blog/post.md"), String::new()))
+        );
+        assert_eq!(
+            resolve("/blog/"),
+            Some((PathBuf::from("blog/index.md"), String::new()))
+        );
+        assert_eq!(
+            resolve("/images/a.png"),
+            Some((PathBuf::from("images/a.png"), String::new()))
+        );
+        assert_eq!(resolve("https://example.com/about"), None);
+        assert_eq!(resolve("#heading"), None);
+        assert_eq!(resolve("/missing"), None);
+    }
+}
@@ -1,83 +1,245 @@
-use log::LevelFilter;
+use log::{error, LevelFilter};
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use lssg_lib::{
+    cache,
     lmarkdown::parse_lmarkdown,
-    renderer::{BlogModule, DefaultModule, ExternalModule, Renderer},
-    sitetree::{Input, SiteTree},
-    Lssg,
+    lssg_error::{LssgError, LssgErrorKind},
+    renderer::OutputMode,
+    sitetree::Input,
+    Lssg, LssgBuilder,
 };
 use simple_logger::SimpleLogger;
 
+mod check;
+use check::CheckArgs;
+mod deploy;
+use deploy::DeployArgs;
+mod fmt;
+use fmt::FmtArgs;
+mod import;
+use import::ImportArgs;
+mod watch;
+
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
 #[command(
     author = "Lyr",
     version = "0.1.0",
     about = "Lyr's Static Site Generator - Command Line Interface",
-    long_about = "Generate static websites using the command line"
+    long_about = "Generate static websites using the command line",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
 )]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// a reference to the first markdown input file
     /// this can either be a path (eg. ./my_blog/index.md)
     /// or an url (eg. http://github.com/project/readme.md)
-    #[clap(value_parser = Input::from_string)]
-    input: Input,
+    #[clap(value_parser = Input::from_string, required = true)]
+    input: Option<Input>,
 
     /// path to put the static files into, any needed parent folders are automatically created
-    output: PathBuf,
+    #[clap(required_unless_present = "tree")]
+    output: Option<PathBuf>,
 
     /// Print output of a single page
     #[clap(long, short, global = true)]
     single_page: bool,
 
+    /// Print all pages and resources with their output paths without rendering
+    #[clap(long)]
+    tree: bool,
+
     /// Print ast tokens of a single page
     #[clap(long, short, global = true)]
     ast: bool,
 
+    /// Write pages as xhtml, for EPUB pipelines and strict validators
+    #[clap(long)]
+    xhtml: bool,
+
+    /// Only replace the files in the output directory that changed since the last build
+    #[clap(long)]
+    incremental: bool,
+
+    /// Render again every time a page or resource of the site changes
+    #[clap(long, conflicts_with_all = ["single_page", "tree", "ast"])]
+    watch: bool,
+
     /// "TRACE", "DEBUG", "INFO", "WARN", "ERROR"
-    #[clap(long, short)]
+    #[clap(long, short, global = true)]
     log: Option<LevelFilter>,
+
+    /// Only log errors
+    #[clap(long, short, global = true, conflicts_with = "log")]
+    quiet: bool,
+
+    /// directory to keep data between builds in
+    #[clap(long, global = true, default_value = cache::DEFAULT_CACHE_DIRECTORY)]
+    cache_dir: PathBuf,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Render the site and publish it (eg. `lssg deploy --github-pages ./index.md`)
+    Deploy(DeployArgs),
+    /// Check the spelling and style of all pages (eg. `lssg check ./index.md`)
+    Check(CheckArgs),
+    /// Format markdown files in place (eg. `lssg fmt ./content --check`)
+    Fmt(FmtArgs),
+    /// Convert a Hugo or Jekyll site to lssg content (eg. `lssg import ./hugo/content ./content`)
+    Import(ImportArgs),
+    /// Remove generated data
+    Clean {
+        /// Remove the build cache
+        #[clap(long, required = true)]
+        cache: bool,
+    },
+}
+
+/// Lssg with all default modules
+fn lssg(input: Input, output: PathBuf) -> Lssg {
+    let builder = LssgBuilder::new(input)
+        .output(output)
+        .enable_external()
+        .enable_blog()
+        .enable_blogroll()
+        .enable_cards()
+        .enable_epub()
+        .enable_events()
+        .enable_notebooks()
+        .enable_updates();
+    #[cfg(feature = "pdf")]
+    let builder = builder.enable_pdf();
+    builder
+        .enable_docs()
+        .enable_headers()
+        .enable_sitemap()
+        .build()
+        .expect("output directory is given")
+}
+
+/// Exit code for errors of `kind`, so scripts can tell failures apart
+///
+/// 1 for other errors and failed checks, 2 for invalid arguments, 3 for parse errors, 4 for io and
+/// request errors and 5 when strict mode turned warnings into errors
+fn exit_code(kind: LssgErrorKind) -> i32 {
+    match kind {
+        LssgErrorKind::ParseError => 3,
+        LssgErrorKind::Io | LssgErrorKind::Request => 4,
+        LssgErrorKind::Strict => 5,
+        LssgErrorKind::Render | LssgErrorKind::SiteTree => 1,
+    }
+}
+
+fn exit_with(error: LssgError, message: &str) -> ! {
+    error!("{message}: {error}");
+    std::process::exit(exit_code(error.kind()))
 }
 
 fn main() {
     let args: Args = Args::parse();
-    SimpleLogger::new()
-        .with_level(args.log.unwrap_or(LevelFilter::Info))
-        .init()
-        .unwrap();
+    let level = match args.quiet {
+        true => LevelFilter::Error,
+        false => args.log.unwrap_or(LevelFilter::Info),
+    };
+    SimpleLogger::new().with_level(level).init().unwrap();
+
+    cache::set_directory(args.cache_dir);
 
-    let input = args.input;
+    match args.command {
+        Some(Commands::Deploy(deploy_args)) => {
+            deploy::deploy(deploy_args).unwrap_or_else(|e| exit_with(e, "failed to deploy"));
+            return;
+        }
+        Some(Commands::Check(check_args)) => {
+            if !check::check(check_args).unwrap_or_else(|e| exit_with(e, "failed to check")) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Commands::Fmt(fmt_args)) => {
+            if !fmt::fmt(fmt_args).unwrap_or_else(|e| exit_with(e, "failed to format")) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Commands::Import(import_args)) => {
+            import::import(import_args).unwrap_or_else(|e| exit_with(e, "failed to import"));
+            return;
+        }
+        Some(Commands::Clean { .. }) => {
+            cache::clean().unwrap_or_else(|e| exit_with(e, "failed to remove cache"));
+            return;
+        }
+        None => {}
+    }
+
+    let input = args.input.expect("input is required");
+    let output_mode = match args.xhtml {
+        true => OutputMode::Xhtml,
+        false => OutputMode::Html,
+    };
+
+    if args.tree {
+        let mut lssg = lssg(input, args.output.unwrap_or_default());
+        let site_tree = lssg
+            .site_tree()
+            .unwrap_or_else(|e| exit_with(e, "failed to generate site tree"));
+        print!("{site_tree}");
+        return;
+    }
 
     if args.single_page {
-        let mut site_tree =
-            SiteTree::from_input(input.clone()).expect("Failed to generate site tree");
-
-        let mut renderer = Renderer::new();
-        renderer.add_module(ExternalModule::new());
-        renderer.add_module(BlogModule::new());
-        renderer.add_module(DefaultModule::new());
-        renderer.init(&mut site_tree);
-        renderer.after_init(&site_tree);
-        let html = renderer
-            .render(&site_tree, site_tree.root())
-            .expect("failed to render");
+        let mut lssg = lssg(input, args.output.unwrap_or_default());
+        lssg.set_output_mode(output_mode);
+        let root = lssg
+            .site_tree()
+            .unwrap_or_else(|e| exit_with(e, "failed to generate site tree"))
+            .root();
+        let html = lssg
+            .render_page(root)
+            .unwrap_or_else(|e| exit_with(e, "failed to render"));
         println!("{html}");
-        // fs::write(args.output, html).expect("failed to write to file");
         return;
     }
 
     if args.ast {
-        let read = input.readable().expect("failed to fetch input");
-        let out = parse_lmarkdown(read).expect("failed to parse input");
+        let read = input
+            .readable()
+            .unwrap_or_else(|e| exit_with(e, "failed to fetch input"));
+        let out =
+            parse_lmarkdown(read).unwrap_or_else(|e| exit_with(e.into(), "failed to parse input"));
         println!("{out:#?}");
         return;
     }
 
-    let mut lssg = Lssg::new(input, args.output);
-    lssg.add_module(ExternalModule::new());
-    lssg.add_module(BlogModule::new());
-    lssg.add_module(DefaultModule::new());
-    lssg.render().unwrap()
+    let output = args.output.expect("output is required");
+    if args.watch {
+        let Input::Local { path: root } = input.clone() else {
+            exit_with(
+                LssgError::io("Only local inputs can be watched"),
+                "failed to watch",
+            )
+        };
+        watch::watch(
+            || {
+                let mut lssg = lssg(input.clone(), output.clone());
+                lssg.set_output_mode(output_mode);
+                lssg.set_incremental(args.incremental);
+                lssg
+            },
+            root,
+        )
+    }
+    let mut lssg = lssg(input, output);
+    lssg.set_output_mode(output_mode);
+    lssg.set_incremental(args.incremental);
+    if let Err(e) = lssg.render() {
+        exit_with(e, "failed to render")
+    }
 }
@@ -0,0 +1,74 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use log::{error, info};
+use lssg_lib::{renderer::FOLDER_OPTIONS, Lssg};
+
+/// How often the source files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Modification time of every file, None if it can't be read (eg. because it got removed)
+fn modified(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// `_options.toml` files of the folders containing `paths`, up to the folder of `root`, these
+/// are watched even when they don't exist yet so adding one renders the site again
+fn folder_options(paths: &[PathBuf], root: &Path) -> Vec<PathBuf> {
+    let Some(root) = root.parent() else {
+        return vec![];
+    };
+    let mut options: Vec<PathBuf> = paths
+        .iter()
+        .flat_map(|path| {
+            path.ancestors()
+                .skip(1)
+                .take_while(|folder| folder.starts_with(root))
+        })
+        .map(|folder| folder.join(FOLDER_OPTIONS))
+        .collect();
+    options.sort();
+    options.dedup();
+    options
+}
+
+/// Render the site made by `lssg` and render it again every time one of its source files
+/// changes. When the site can't be generated the files of the last generated site and `root`
+/// keep being watched.
+pub fn watch(mut lssg: impl FnMut() -> Lssg, root: PathBuf) -> ! {
+    let mut paths = vec![root.clone()];
+    loop {
+        let mut site = lssg();
+        match site.site_tree() {
+            Ok(site_tree) => {
+                paths = site_tree.source_paths();
+                paths.extend(folder_options(&paths, &root));
+                if let Err(e) = site.render() {
+                    error!("failed to render: {e}");
+                }
+            }
+            Err(e) => {
+                error!("failed to generate site tree: {e}");
+                if !paths.contains(&root) {
+                    paths.push(root.clone());
+                }
+            }
+        }
+
+        info!("Watching {} files for changes", paths.len());
+        let last = modified(&paths);
+        while modified(&paths) == last {
+            thread::sleep(POLL_INTERVAL);
+        }
+        // editors often save in multiple writes
+        thread::sleep(POLL_INTERVAL);
+        info!("Change detected, rendering again");
+    }
+}